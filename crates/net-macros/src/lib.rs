@@ -0,0 +1,318 @@
+//! Procedural macros of [gloo-net](https://docs.rs/gloo-net), re-exported from there. See
+//! `gloo_net::http::endpoint` for the documentation.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    parenthesized, Attribute, Error, FnArg, GenericArgument, Ident, LitStr, Pat, PathArguments,
+    ReturnType, Signature, Token, Type, Visibility,
+};
+
+// Documented on the re-export, `gloo_net::http::endpoint`, where the examples can run.
+#[proc_macro_attribute]
+pub fn endpoint(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let endpoint = syn::parse_macro_input!(attr as Endpoint);
+    let function = syn::parse_macro_input!(item as Declaration);
+    expand(endpoint, function)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The arguments of the attribute: `GET "/users/{id}", query(page), body = user`.
+struct Endpoint {
+    method: Ident,
+    path: LitStr,
+    query: Vec<Ident>,
+    body: Option<Ident>,
+}
+
+impl Parse for Endpoint {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let method: Ident = input.parse()?;
+        const METHODS: [&str; 7] = ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+        if !METHODS.iter().any(|name| method == name) {
+            return Err(Error::new(
+                method.span(),
+                format!("expected one of {}", METHODS.join(", ")),
+            ));
+        }
+        let mut endpoint = Endpoint {
+            method,
+            path: input.parse()?,
+            query: Vec::new(),
+            body: None,
+        };
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            if key == "query" {
+                let content;
+                parenthesized!(content in input);
+                let names = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+                endpoint.query.extend(names);
+            } else if key == "body" {
+                input.parse::<Token![=]>()?;
+                endpoint.body = Some(input.parse()?);
+            } else {
+                return Err(Error::new(
+                    key.span(),
+                    "expected `query(...)` or `body = ...`",
+                ));
+            }
+        }
+        Ok(endpoint)
+    }
+}
+
+/// A function declaration without a body: `pub async fn get_user(client: &Client, id: u64) ->
+/// Result<User, Error>;`.
+struct Declaration {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    sig: Signature,
+}
+
+impl Parse for Declaration {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let declaration = Declaration {
+            attrs: input.call(Attribute::parse_outer)?,
+            vis: input.parse()?,
+            sig: input.parse()?,
+        };
+        input.parse::<Token![;]>()?;
+        Ok(declaration)
+    }
+}
+
+/// A part of a path template.
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+/// Splits `/users/{id}` into its literal parts and parameters.
+fn parse_path(path: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| format!("unclosed `{{` in `{}`", path))?;
+        if start > 0 {
+            segments.push(Segment::Literal(rest[..start].to_string()));
+        }
+        let name = &rest[start + 1..end];
+        if name.is_empty() || name.contains('{') {
+            return Err(format!("invalid parameter `{{{}}}` in `{}`", name, path));
+        }
+        segments.push(Segment::Param(name.to_string()));
+        rest = &rest[end + 1..];
+    }
+    if rest.contains('}') {
+        return Err(format!("unopened `}}` in `{}`", path));
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+/// The name of the last segment of a type path, like `Option` for `std::option::Option<u32>`.
+fn type_name(ty: &Type) -> Option<&Ident> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last().map(|s| &s.ident),
+        _ => None,
+    }
+}
+
+/// Whether `ty` is `Vec<u8>`.
+fn is_bytes(ty: &Type) -> bool {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last(),
+        _ => None,
+    };
+    match segment.map(|segment| &segment.arguments) {
+        Some(PathArguments::AngleBracketed(args)) => matches!(
+            args.args.first(),
+            Some(GenericArgument::Type(item)) if type_name(item).is_some_and(|name| name == "u8")
+        ),
+        _ => false,
+    }
+}
+
+/// The type of successful results of `Result<T, E>`.
+fn ok_type(output: &ReturnType) -> Option<&Type> {
+    let ty = match output {
+        ReturnType::Type(_, ty) => ty,
+        ReturnType::Default => return None,
+    };
+    let segment = match &**ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn expand(endpoint: Endpoint, function: Declaration) -> syn::Result<TokenStream2> {
+    let Declaration { attrs, vis, sig } = function;
+    if sig.asyncness.is_none() {
+        return Err(Error::new_spanned(
+            sig.fn_token,
+            "endpoints must be `async`",
+        ));
+    }
+    let ok = ok_type(&sig.output)
+        .ok_or_else(|| Error::new_spanned(&sig.output, "endpoints must return a `Result<T, E>`"))?;
+
+    // The first argument is the client, the others feed the path, query and body.
+    let mut inputs = sig.inputs.iter().map(|input| match input {
+        FnArg::Typed(arg) => match &*arg.pat {
+            Pat::Ident(pat) => Ok((pat.ident.clone(), &*arg.ty)),
+            _ => Err(Error::new_spanned(&arg.pat, "expected an argument name")),
+        },
+        FnArg::Receiver(receiver) => Err(Error::new_spanned(
+            receiver,
+            "endpoints take a client as their first argument, not `self`",
+        )),
+    });
+    let client = match inputs.next() {
+        Some(client) => client?.0,
+        None => {
+            return Err(Error::new_spanned(
+                &sig,
+                "endpoints take a client as their first argument",
+            ))
+        }
+    };
+    let args = inputs.collect::<syn::Result<Vec<_>>>()?;
+    let arg = |name: &str, span: Span| {
+        args.iter()
+            .find(|(ident, _)| ident == name)
+            .ok_or_else(|| Error::new(span, format!("no argument named `{}`", name)))
+    };
+
+    let segments =
+        parse_path(&endpoint.path.value()).map_err(|e| Error::new(endpoint.path.span(), e))?;
+    let mut used = Vec::new();
+    let url = segments.iter().map(|segment| match segment {
+        Segment::Literal(literal) => Ok(quote!(__url.push_str(#literal);)),
+        Segment::Param(name) => {
+            let (ident, _) = arg(name, endpoint.path.span())?;
+            used.push(ident.to_string());
+            Ok(quote! {
+                __url.push_str(&::gloo_net::http::__private::encode_path_segment(
+                    &::std::string::ToString::to_string(&#ident),
+                ));
+            })
+        }
+    });
+    let url = url.collect::<syn::Result<Vec<_>>>()?;
+
+    let query = endpoint.query.iter().map(|name| {
+        let (ident, ty) = arg(&name.to_string(), name.span())?;
+        used.push(ident.to_string());
+        let key = ident.to_string();
+        Ok(match type_name(ty) {
+            Some(option) if option == "Option" => quote! {
+                if let ::std::option::Option::Some(value) = &#ident {
+                    __query.push((#key, ::std::string::ToString::to_string(value)));
+                }
+            },
+            _ => quote!(__query.push((#key, ::std::string::ToString::to_string(&#ident)));),
+        })
+    });
+    let query = query.collect::<syn::Result<Vec<_>>>()?;
+
+    let build = match &endpoint.body {
+        Some(name) => {
+            let (ident, _) = arg(&name.to_string(), name.span())?;
+            used.push(ident.to_string());
+            quote!(.json(&#ident)?)
+        }
+        None => quote!(.build()?),
+    };
+    if let Some((ident, _)) = args
+        .iter()
+        .find(|(ident, _)| !used.contains(&ident.to_string()))
+    {
+        return Err(Error::new(
+            ident.span(),
+            format!(
+                "`{}` isn't used by the path, and isn't listed in `query(...)` or as the `body`",
+                ident
+            ),
+        ));
+    }
+
+    let decode = match type_name(ok).map(ToString::to_string).as_deref() {
+        _ if matches!(ok, Type::Tuple(tuple) if tuple.elems.is_empty()) => {
+            quote!(::std::result::Result::Ok(()))
+        }
+        Some("Response") => quote!(::std::result::Result::Ok(__response)),
+        Some("String") => quote!(::std::result::Result::Ok(__response.text().await?)),
+        Some("Vec") if is_bytes(ok) => {
+            quote!(::std::result::Result::Ok(__response.binary().await?))
+        }
+        _ => quote!(::std::result::Result::Ok(__response.json::<#ok>().await?)),
+    };
+
+    let method = format_ident!("{}", endpoint.method);
+    let sig = sig.to_token_stream();
+    Ok(quote! {
+        #(#attrs)*
+        // `?` converts the error of decoding into the error of the function
+        #[allow(clippy::needless_question_mark)]
+        #vis #sig {
+            let mut __url = ::std::string::String::new();
+            #(#url)*
+            #[allow(unused_mut)]
+            let mut __query: ::std::vec::Vec<(&str, ::std::string::String)> =
+                ::std::vec::Vec::new();
+            #(#query)*
+            let __request = #client
+                .request(::gloo_net::http::Method::#method, &__url)
+                .query(__query.iter().map(|(key, value)| (*key, value)))
+                #build;
+            let __response = __request.send().await?.error_for_status()?;
+            #decode
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_paths() {
+        let literal = |s: &str| Segment::Literal(s.to_string());
+        let param = |s: &str| Segment::Param(s.to_string());
+        assert_eq!(parse_path("/users").unwrap(), [literal("/users")]);
+        assert_eq!(
+            parse_path("/users/{id}/posts/{post}").unwrap(),
+            [
+                literal("/users/"),
+                param("id"),
+                literal("/posts/"),
+                param("post")
+            ]
+        );
+        assert!(parse_path("/users/{id").is_err());
+        assert!(parse_path("/users/{}").is_err());
+        assert!(parse_path("/users/id}").is_err());
+    }
+}
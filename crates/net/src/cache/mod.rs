@@ -0,0 +1,108 @@
+//! Wrapper around the [Cache API](https://developer.mozilla.org/en-US/docs/Web/API/Cache), to
+//! store responses for offline use.
+//!
+//! # Example
+//!
+//! ```
+//! # use gloo_net::cache::Cache;
+//! # use gloo_net::http::Request;
+//! # async fn no_run() -> Result<(), gloo_net::Error> {
+//! let cache = Cache::open("v1").await?;
+//! let request = Request::get("/data.json").build()?;
+//!
+//! let resp = match cache.match_request(&request).await? {
+//!     Some(resp) => resp,
+//!     None => {
+//!         let resp = Request::get("/data.json").send().await?;
+//!         cache.put(&request, &resp).await?;
+//!         resp
+//!     }
+//! };
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::{Request, Response};
+use crate::{js_to_error, Error};
+use js_sys::{Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::CacheStorage;
+
+/// A named cache of [`Request`]/[`Response`] pairs, shared with the service worker and every
+/// other page of the origin.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    raw: web_sys::Cache,
+}
+
+impl Cache {
+    /// Opens the cache called `name`, creating it if it doesn't exist yet.
+    pub async fn open(name: &str) -> Result<Self, Error> {
+        let raw = await_promise(caches()?.open(name)).await?;
+        Ok(Self {
+            raw: raw.unchecked_into(),
+        })
+    }
+
+    /// Whether a cache called `name` exists.
+    pub async fn exists(name: &str) -> Result<bool, Error> {
+        let exists = await_promise(caches()?.has(name)).await?;
+        Ok(exists.is_truthy())
+    }
+
+    /// Deletes the cache called `name`, returning whether it existed.
+    pub async fn delete_cache(name: &str) -> Result<bool, Error> {
+        let deleted = await_promise(caches()?.delete(name)).await?;
+        Ok(deleted.is_truthy())
+    }
+
+    /// Finds the response stored for `request`, if any.
+    pub async fn match_request(&self, request: &Request) -> Result<Option<Response>, Error> {
+        let response = await_promise(self.raw.match_with_request(request.as_raw())).await?;
+        Ok(response
+            .dyn_into::<web_sys::Response>()
+            .ok()
+            .map(Response::from))
+    }
+
+    /// Stores a copy of `response` for `request`, replacing any response previously stored for
+    /// it.
+    ///
+    /// The body of `response` must not have been read yet; it can still be read afterwards.
+    pub async fn put(&self, request: &Request, response: &Response) -> Result<(), Error> {
+        let response = response.clone_raw()?;
+        await_promise(self.raw.put_with_request(request.as_raw(), &response)).await?;
+        Ok(())
+    }
+
+    /// Deletes the response stored for `request`, returning whether there was one.
+    pub async fn delete(&self, request: &Request) -> Result<bool, Error> {
+        let deleted = await_promise(self.raw.delete_with_request(request.as_raw())).await?;
+        Ok(deleted.is_truthy())
+    }
+}
+
+impl From<web_sys::Cache> for Cache {
+    fn from(raw: web_sys::Cache) -> Self {
+        Self { raw }
+    }
+}
+
+impl From<Cache> for web_sys::Cache {
+    fn from(cache: Cache) -> Self {
+        cache.raw
+    }
+}
+
+/// The `caches` of the global scope, which is only available in secure contexts.
+fn caches() -> Result<CacheStorage, Error> {
+    Reflect::get(&js_sys::global(), &JsValue::from_str("caches"))
+        .map_err(js_to_error)?
+        .dyn_into::<CacheStorage>()
+        .map_err(|_| Error::GlooError("the Cache API is not available".to_string()))
+}
+
+async fn await_promise(promise: Promise) -> Result<JsValue, Error> {
+    JsFuture::from(promise).await.map_err(js_to_error)
+}
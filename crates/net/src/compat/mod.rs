@@ -0,0 +1,4 @@
+//! APIs mirroring those of other HTTP crates, so that code sending requests can be shared
+//! between native targets and the browser.
+
+pub mod reqwest;
@@ -0,0 +1,912 @@
+//! A [`Client`] with the API of [reqwest](https://docs.rs/reqwest), for code which is compiled
+//! against reqwest on native targets and against gloo-net in the browser.
+//!
+//! The most used methods of reqwest's `Client`, `RequestBuilder`, `Response` and `Error` are
+//! mirrored, with the same names and semantics, along with the [`header`] module and the
+//! [`IntoUrl`] trait. Shared code can then pick its client with
+//! `#[cfg(target_arch = "wasm32")] use gloo_net::compat::reqwest;`.
+//!
+//! The differences with reqwest are:
+//! - [`Response::bytes`] resolves to a `Vec<u8>`, rather than to `bytes::Bytes`.
+//! - Relative URLs are accepted, and resolved against the location of the page or of the worker.
+//! - The query parameters and form fields of [`RequestBuilder::query`] and
+//!   [`RequestBuilder::form`] are serialized through JSON, which sorts the fields of maps and
+//!   structs.
+//!
+//! # Example
+//!
+//! ```
+//! use gloo_net::compat::reqwest;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Item {
+//!     name: String,
+//! }
+//!
+//! # async fn no_run() -> Result<(), reqwest::Error> {
+//! let client = reqwest::Client::new();
+//! let items: Vec<Item> = client
+//!     .get("https://example.com/items")
+//!     .query(&[("page", 2)])
+//!     .bearer_auth("token")
+//!     .send()
+//!     .await?
+//!     .error_for_status()?
+//!     .json()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::Duration;
+
+use js_sys::{Reflect, Uint8Array};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use wasm_bindgen::JsValue;
+use web_sys::{AbortSignal, RequestMode};
+
+use crate::http::btoa;
+
+pub use http::header;
+#[doc(inline)]
+pub use http::{Method, StatusCode};
+pub use url::Url;
+
+use header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+
+/// A `Result` with the [`Error`] of this module.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A value which can be parsed as a [`Url`].
+///
+/// Unlike with reqwest, relative URLs are accepted, and resolved against the location of the page
+/// or of the worker.
+pub trait IntoUrl {
+    /// Parses the URL.
+    fn into_url(self) -> Result<Url>;
+}
+
+impl IntoUrl for Url {
+    fn into_url(self) -> Result<Url> {
+        Ok(self)
+    }
+}
+
+impl IntoUrl for &str {
+    fn into_url(self) -> Result<Url> {
+        let url = match Url::parse(self) {
+            Err(url::ParseError::RelativeUrlWithoutBase) => match location() {
+                Some(base) => base.join(self),
+                None => Err(url::ParseError::RelativeUrlWithoutBase),
+            },
+            url => url,
+        };
+        url.map_err(|e| {
+            Error::builder(crate::Error::GlooError(format!(
+                "invalid URL `{}`: {}",
+                self, e
+            )))
+        })
+    }
+}
+
+impl IntoUrl for &String {
+    fn into_url(self) -> Result<Url> {
+        self.as_str().into_url()
+    }
+}
+
+impl IntoUrl for String {
+    fn into_url(self) -> Result<Url> {
+        self.as_str().into_url()
+    }
+}
+
+/// The location of the page or of the worker, which relative URLs are resolved against.
+fn location() -> Option<Url> {
+    let location = Reflect::get(&js_sys::global(), &JsValue::from_str("location")).ok()?;
+    let href = Reflect::get(&location, &JsValue::from_str("href")).ok()?;
+    Url::parse(&href.as_string()?).ok()
+}
+
+/// A client to send requests with, like `reqwest::Client`.
+///
+/// Requests are sent through an HTTP [`Client`](crate::http::Client), which can be given its
+/// own fetcher and middleware, and turned into this client with [`From`].
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    inner: crate::http::Client,
+    default_headers: HeaderMap,
+    timeout: Option<Duration>,
+}
+
+impl Client {
+    /// Creates a client with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts configuring a client.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Starts building a [`GET`][Method::GET] request to `url`.
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// Starts building a [`POST`][Method::POST] request to `url`.
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// Starts building a [`PUT`][Method::PUT] request to `url`.
+    pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    /// Starts building a [`PATCH`][Method::PATCH] request to `url`.
+    pub fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    /// Starts building a [`DELETE`][Method::DELETE] request to `url`.
+    pub fn delete<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Starts building a [`HEAD`][Method::HEAD] request to `url`.
+    pub fn head<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    /// Starts building a request to `url`.
+    ///
+    /// An invalid URL is reported when the request is sent.
+    pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        RequestBuilder {
+            client: self.clone(),
+            request: url.into_url().map(|url| Request::new(method, url)),
+        }
+    }
+
+    /// Sends `request`, with the default headers and timeout of this client.
+    pub async fn execute(&self, mut request: Request) -> Result<Response> {
+        for (name, value) in &self.default_headers {
+            if let header::Entry::Vacant(entry) = request.headers.entry(name) {
+                entry.insert(value.clone());
+            }
+        }
+        if request.timeout.is_none() {
+            request.timeout = self.timeout;
+        }
+        let url = request.url.clone();
+        let response = match request.into_gloo(&self.inner) {
+            Ok(request) => request.send().await.map_err(Error::request),
+            Err(e) => Err(Error::builder(e)),
+        };
+        response
+            .and_then(|response| Response::new(response, url.clone()))
+            .map_err(|e| e.with_url(url))
+    }
+}
+
+impl From<crate::http::Client> for Client {
+    fn from(inner: crate::http::Client) -> Self {
+        Self {
+            inner,
+            ..Self::default()
+        }
+    }
+}
+
+/// Configures a [`Client`], like `reqwest::ClientBuilder`.
+#[derive(Debug, Default)]
+#[must_use = "builders do nothing unless built"]
+pub struct ClientBuilder {
+    client: Client,
+}
+
+impl ClientBuilder {
+    /// Starts with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets headers sent with every request, unless the request has its own header with the same
+    /// name.
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        for (name, value) in &headers {
+            self.client.default_headers.insert(name, value.clone());
+        }
+        self
+    }
+
+    /// Sets the timeout of every request, unless the request has its own. The timeout covers the
+    /// whole request, until the response body is read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client.timeout = Some(timeout);
+        self
+    }
+
+    /// Builds the client.
+    pub fn build(self) -> Result<Client> {
+        Ok(self.client)
+    }
+}
+
+/// The body of a [`Request`].
+#[derive(Debug, Clone)]
+pub struct Body {
+    bytes: Vec<u8>,
+}
+
+impl Body {
+    /// The bytes of the body.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        Some(&self.bytes)
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+}
+
+impl From<&'static [u8]> for Body {
+    fn from(bytes: &'static [u8]) -> Self {
+        Self::from(bytes.to_vec())
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        Self::from(text.into_bytes())
+    }
+}
+
+impl From<&'static str> for Body {
+    fn from(text: &'static str) -> Self {
+        Self::from(text.as_bytes())
+    }
+}
+
+/// A request, like `reqwest::Request`.
+#[derive(Debug)]
+pub struct Request {
+    method: Method,
+    url: Url,
+    headers: HeaderMap,
+    body: Option<Body>,
+    timeout: Option<Duration>,
+    no_cors: bool,
+}
+
+impl Request {
+    /// Creates a request without headers or body.
+    pub fn new(method: Method, url: Url) -> Self {
+        Self {
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: None,
+            timeout: None,
+            no_cors: false,
+        }
+    }
+
+    /// The method.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The method, to change it.
+    pub fn method_mut(&mut self) -> &mut Method {
+        &mut self.method
+    }
+
+    /// The URL.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The URL, to change it.
+    pub fn url_mut(&mut self) -> &mut Url {
+        &mut self.url
+    }
+
+    /// The headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The headers, to change them.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// The body.
+    pub fn body(&self) -> Option<&Body> {
+        self.body.as_ref()
+    }
+
+    /// The body, to change it.
+    pub fn body_mut(&mut self) -> &mut Option<Body> {
+        &mut self.body
+    }
+
+    /// The timeout.
+    pub fn timeout(&self) -> Option<&Duration> {
+        self.timeout.as_ref()
+    }
+
+    /// The timeout, to change it.
+    pub fn timeout_mut(&mut self) -> &mut Option<Duration> {
+        &mut self.timeout
+    }
+
+    /// Copies the request. This always succeeds, as bodies are held in memory.
+    pub fn try_clone(&self) -> Option<Request> {
+        Some(Self {
+            method: self.method.clone(),
+            url: self.url.clone(),
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+            timeout: self.timeout,
+            no_cors: self.no_cors,
+        })
+    }
+
+    /// Builds the request to send through `client`.
+    fn into_gloo(
+        self,
+        client: &crate::http::Client,
+    ) -> std::result::Result<crate::http::Request, crate::Error> {
+        let mut builder = client.request(self.method, self.url.as_str());
+        for (name, value) in &self.headers {
+            // Header values are byte strings, with one character per byte.
+            let value: String = value.as_bytes().iter().map(|&b| char::from(b)).collect();
+            builder = builder.append_header(name.as_str(), &value);
+        }
+        if let Some(timeout) = self.timeout {
+            let signal = AbortSignal::timeout_with_f64(timeout.as_millis() as f64);
+            builder = builder.abort_signal(Some(&signal));
+        }
+        if self.no_cors {
+            builder = builder.mode(RequestMode::NoCors);
+        }
+        match self.body {
+            Some(body) => builder.body(Uint8Array::from(body.bytes.as_slice())),
+            None => builder.build(),
+        }
+    }
+}
+
+/// Builds a [`Request`], like `reqwest::RequestBuilder`.
+///
+/// Errors, like invalid headers, are reported when the request is built or sent.
+#[derive(Debug)]
+#[must_use = "requests do nothing unless sent"]
+pub struct RequestBuilder {
+    client: Client,
+    request: Result<Request>,
+}
+
+impl RequestBuilder {
+    /// Applies `f` to the request, unless building it already failed.
+    fn and_then(mut self, f: impl FnOnce(&mut Request) -> Result<()>) -> Self {
+        if let Ok(request) = &mut self.request {
+            if let Err(e) = f(request) {
+                self.request = Err(e);
+            }
+        }
+        self
+    }
+
+    /// Appends a header.
+    pub fn header<K, V>(self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.and_then(|request| {
+            let name = HeaderName::try_from(key).map_err(invalid_header)?;
+            let value = HeaderValue::try_from(value).map_err(invalid_header)?;
+            request.headers.append(name, value);
+            Ok(())
+        })
+    }
+
+    /// Adds `headers`, replacing the headers of the request with the same names.
+    pub fn headers(self, headers: HeaderMap) -> Self {
+        self.and_then(|request| {
+            replace_headers(&mut request.headers, headers);
+            Ok(())
+        })
+    }
+
+    /// Sets the `Authorization` header for HTTP basic authentication.
+    pub fn basic_auth<U: fmt::Display, P: fmt::Display>(
+        self,
+        username: U,
+        password: Option<P>,
+    ) -> Self {
+        let credentials = match password {
+            Some(password) => format!("{}:{}", username, password),
+            None => format!("{}:", username),
+        };
+        // `btoa` takes a "binary string", with one character per byte.
+        let credentials: String = credentials.bytes().map(char::from).collect();
+        self.authorization(format!("Basic {}", btoa(&credentials)))
+    }
+
+    /// Sets the `Authorization` header for bearer token authentication.
+    pub fn bearer_auth<T: fmt::Display>(self, token: T) -> Self {
+        self.authorization(format!("Bearer {}", token))
+    }
+
+    fn authorization(self, value: String) -> Self {
+        self.and_then(|request| {
+            let mut value = HeaderValue::try_from(value).map_err(invalid_header)?;
+            value.set_sensitive(true);
+            request.headers.insert(AUTHORIZATION, value);
+            Ok(())
+        })
+    }
+
+    /// Sets the body.
+    pub fn body<T: Into<Body>>(self, body: T) -> Self {
+        self.and_then(|request| {
+            request.body = Some(body.into());
+            Ok(())
+        })
+    }
+
+    /// Sets the timeout of the request, which covers the whole request, until the response body
+    /// is read.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.and_then(|request| {
+            request.timeout = Some(timeout);
+            Ok(())
+        })
+    }
+
+    /// Appends query parameters to the URL, serialized from a map, a struct, or a sequence of
+    /// pairs.
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        self.and_then(|request| {
+            let pairs = pairs(query).map_err(Error::builder)?;
+            if !pairs.is_empty() {
+                request.url.query_pairs_mut().extend_pairs(pairs);
+            }
+            Ok(())
+        })
+    }
+
+    /// Sets a URL-encoded form body, serialized from a map, a struct, or a sequence of pairs,
+    /// along with its `Content-Type`.
+    pub fn form<T: Serialize + ?Sized>(self, form: &T) -> Self {
+        self.and_then(|request| {
+            let pairs = pairs(form).map_err(Error::builder)?;
+            let body = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(pairs)
+                .finish();
+            request.headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/x-www-form-urlencoded"),
+            );
+            request.body = Some(body.into());
+            Ok(())
+        })
+    }
+
+    /// Sets a JSON body, along with its `Content-Type` unless the request already has one.
+    pub fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        self.and_then(|request| {
+            let body = serde_json::to_vec(json).map_err(|e| Error::builder(e.into()))?;
+            if !request.headers.contains_key(CONTENT_TYPE) {
+                request
+                    .headers
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            }
+            request.body = Some(body.into());
+            Ok(())
+        })
+    }
+
+    /// Sends the request with the `no-cors` mode, which allows cross-origin requests without
+    /// CORS, whose responses are opaque. Such responses have no status, and fail to be read.
+    pub fn fetch_mode_no_cors(self) -> Self {
+        self.and_then(|request| {
+            request.no_cors = true;
+            Ok(())
+        })
+    }
+
+    /// Builds the request.
+    pub fn build(self) -> Result<Request> {
+        self.request
+    }
+
+    /// Copies the builder, unless building the request already failed.
+    pub fn try_clone(&self) -> Option<RequestBuilder> {
+        let request = self.request.as_ref().ok()?.try_clone()?;
+        Some(Self {
+            client: self.client.clone(),
+            request: Ok(request),
+        })
+    }
+
+    /// Sends the request through its client.
+    pub async fn send(self) -> Result<Response> {
+        self.client.execute(self.request?).await
+    }
+}
+
+fn invalid_header(error: impl Into<http::Error>) -> Error {
+    Error::builder(crate::Error::GlooError(format!(
+        "invalid header: {}",
+        error.into()
+    )))
+}
+
+/// Adds `src` to `dst`, replacing the headers of `dst` with the same names, like reqwest.
+fn replace_headers(dst: &mut HeaderMap, src: HeaderMap) {
+    let mut previous = None;
+    for (name, value) in src {
+        match name {
+            Some(name) => {
+                dst.insert(name.clone(), value);
+                previous = Some(name);
+            }
+            // further values of the previous name
+            None => {
+                if let Some(name) = &previous {
+                    dst.append(name.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+/// The pairs serialized from a map, a struct, or a sequence of pairs of scalars, like
+/// `serde_urlencoded` does, skipping `None` values.
+fn pairs<T: Serialize + ?Sized>(
+    value: &T,
+) -> std::result::Result<Vec<(String, String)>, crate::Error> {
+    let entries = match serde_json::to_value(value)? {
+        Value::Object(map) => map.into_iter().collect(),
+        Value::Array(pairs) => pairs
+            .into_iter()
+            .map(|pair| match pair {
+                Value::Array(pair) => match <[Value; 2]>::try_from(pair) {
+                    Ok([key, value]) => Ok((scalar(key)?.unwrap_or_default(), value)),
+                    Err(_) => Err(unsupported()),
+                },
+                _ => Err(unsupported()),
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?,
+        Value::Null => Vec::new(),
+        _ => return Err(unsupported()),
+    };
+    let mut pairs = Vec::new();
+    for (key, value) in entries {
+        if let Some(value) = scalar(value)? {
+            pairs.push((key, value));
+        }
+    }
+    Ok(pairs)
+}
+
+fn scalar(value: Value) -> std::result::Result<Option<String>, crate::Error> {
+    match value {
+        Value::String(value) => Ok(Some(value)),
+        Value::Number(value) => Ok(Some(value.to_string())),
+        Value::Bool(value) => Ok(Some(value.to_string())),
+        Value::Null => Ok(None),
+        _ => Err(unsupported()),
+    }
+}
+
+fn unsupported() -> crate::Error {
+    crate::Error::GlooError(
+        "only maps, structs and sequences of pairs of scalars can be URL-encoded".to_string(),
+    )
+}
+
+/// A response, like `reqwest::Response`.
+#[derive(Debug)]
+pub struct Response {
+    inner: crate::http::Response,
+    status: StatusCode,
+    headers: HeaderMap,
+    url: Url,
+}
+
+impl Response {
+    fn new(inner: crate::http::Response, request_url: Url) -> Result<Self> {
+        let status = StatusCode::from_u16(inner.status()).map_err(|_| {
+            Error::request(crate::Error::GlooError(
+                "the response is opaque, as the request was sent with `no-cors`".to_string(),
+            ))
+        })?;
+        let headers = inner
+            .headers()
+            .entries()
+            .filter_map(|(name, value)| {
+                let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+                Some((name, HeaderValue::from_str(&value).ok()?))
+            })
+            .collect();
+        // Constructed responses have no URL.
+        let url = Url::parse(&inner.url()).unwrap_or(request_url);
+        Ok(Self {
+            inner,
+            status,
+            headers,
+            url,
+        })
+    }
+
+    /// The status.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The headers, to change them.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// The URL of the response, after redirects.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The size of the body, from the `Content-Length` header.
+    pub fn content_length(&self) -> Option<u64> {
+        self.inner.content_length()
+    }
+
+    /// Reads the body as text.
+    pub async fn text(self) -> Result<String> {
+        let url = self.url;
+        self.inner
+            .text()
+            .await
+            .map_err(|e| Error::body(e).with_url(url))
+    }
+
+    /// Reads the body as JSON.
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T> {
+        let url = self.url.clone();
+        let bytes = self.bytes().await?;
+        serde_json::from_slice(&bytes).map_err(|e| Error::decode(e.into()).with_url(url))
+    }
+
+    /// Reads the body as bytes.
+    pub async fn bytes(self) -> Result<Vec<u8>> {
+        let url = self.url;
+        self.inner
+            .binary()
+            .await
+            .map_err(|e| Error::body(e).with_url(url))
+    }
+
+    /// Turns a response with a client (`4xx`) or server (`5xx`) error status into an error.
+    pub fn error_for_status(self) -> Result<Self> {
+        match self.status_error() {
+            Some(e) => Err(e),
+            None => Ok(self),
+        }
+    }
+
+    /// Like [`error_for_status`](Self::error_for_status), without consuming the response.
+    pub fn error_for_status_ref(&self) -> Result<&Self> {
+        match self.status_error() {
+            Some(e) => Err(e),
+            None => Ok(self),
+        }
+    }
+
+    fn status_error(&self) -> Option<Error> {
+        match self.status.is_client_error() || self.status.is_server_error() {
+            true => Some(Error::new(Kind::Status(self.status), None).with_url(self.url.clone())),
+            false => None,
+        }
+    }
+}
+
+/// The errors of this module, like `reqwest::Error`.
+#[derive(Debug)]
+pub struct Error {
+    inner: Box<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    kind: Kind,
+    url: Option<Url>,
+    source: Option<crate::Error>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Builder,
+    Request,
+    Status(StatusCode),
+    Body,
+    Decode,
+}
+
+impl Error {
+    fn new(kind: Kind, source: Option<crate::Error>) -> Self {
+        Self {
+            inner: Box::new(Inner {
+                kind,
+                url: None,
+                source,
+            }),
+        }
+    }
+
+    fn builder(source: crate::Error) -> Self {
+        Self::new(Kind::Builder, Some(source))
+    }
+
+    fn request(source: crate::Error) -> Self {
+        Self::new(Kind::Request, Some(source))
+    }
+
+    fn body(source: crate::Error) -> Self {
+        Self::new(Kind::Body, Some(source))
+    }
+
+    fn decode(source: crate::Error) -> Self {
+        Self::new(Kind::Decode, Some(source))
+    }
+
+    /// The URL of the request which failed, if any.
+    pub fn url(&self) -> Option<&Url> {
+        self.inner.url.as_ref()
+    }
+
+    /// The URL of the request which failed, to change it, e.g. to remove secrets.
+    pub fn url_mut(&mut self) -> Option<&mut Url> {
+        self.inner.url.as_mut()
+    }
+
+    /// Sets the URL of the request which failed.
+    pub fn with_url(mut self, url: Url) -> Self {
+        self.inner.url = Some(url);
+        self
+    }
+
+    /// Removes the URL of the request which failed, e.g. because it has secrets.
+    pub fn without_url(mut self) -> Self {
+        self.inner.url = None;
+        self
+    }
+
+    /// The status of the response, if it had an error status.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self.inner.kind {
+            Kind::Status(status) => Some(status),
+            _ => None,
+        }
+    }
+
+    /// Whether building the request failed.
+    pub fn is_builder(&self) -> bool {
+        self.inner.kind == Kind::Builder
+    }
+
+    /// Whether sending the request failed.
+    pub fn is_request(&self) -> bool {
+        self.inner.kind == Kind::Request
+    }
+
+    /// Whether the response had an error status, see [`Response::error_for_status`].
+    pub fn is_status(&self) -> bool {
+        self.status().is_some()
+    }
+
+    /// Whether the request timed out.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.inner.source, Some(crate::Error::Timeout))
+    }
+
+    /// Whether the server couldn't be reached. Browsers don't tell this apart from other
+    /// network errors, e.g. CORS failures.
+    pub fn is_connect(&self) -> bool {
+        matches!(self.inner.source, Some(crate::Error::Network(_)))
+    }
+
+    /// Whether reading the body failed.
+    pub fn is_body(&self) -> bool {
+        self.inner.kind == Kind::Body
+    }
+
+    /// Whether decoding the body failed.
+    pub fn is_decode(&self) -> bool {
+        self.inner.kind == Kind::Decode
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner.kind {
+            Kind::Builder => f.write_str("builder error")?,
+            Kind::Request => f.write_str("error sending request")?,
+            Kind::Body => f.write_str("request or response body error")?,
+            Kind::Decode => f.write_str("error decoding response body")?,
+            Kind::Status(status) if status.is_client_error() => {
+                write!(f, "HTTP status client error ({})", status)?
+            }
+            Kind::Status(status) => write!(f, "HTTP status server error ({})", status)?,
+        }
+        if let Some(url) = &self.inner.url {
+            write!(f, " for url ({})", url)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.inner
+            .source
+            .as_ref()
+            .map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn serializes_pairs() {
+        let pair = |key: &str, value: &str| (key.to_string(), value.to_string());
+        assert_eq!(
+            pairs(&[("page", "2"), ("sort", "name")]).unwrap(),
+            [pair("page", "2"), pair("sort", "name")]
+        );
+        assert_eq!(pairs(&[("page", 2)]).unwrap(), [pair("page", "2")]);
+        let map: BTreeMap<_, _> = vec![("a", Some(true)), ("b", None)].into_iter().collect();
+        assert_eq!(pairs(&map).unwrap(), [pair("a", "true")]);
+        assert!(pairs(&[("nested", [1, 2])]).is_err());
+        assert!(pairs("text").is_err());
+    }
+
+    #[test]
+    fn replaces_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("accept", HeaderValue::from_static("text/html"));
+        headers.insert("x-kept", HeaderValue::from_static("yes"));
+        let mut replacement = HeaderMap::new();
+        replacement.append("accept", HeaderValue::from_static("application/json"));
+        replacement.append("accept", HeaderValue::from_static("text/plain"));
+        replace_headers(&mut headers, replacement);
+        assert_eq!(
+            headers.get_all("accept").iter().collect::<Vec<_>>(),
+            ["application/json", "text/plain"]
+        );
+        assert_eq!(headers["x-kept"], "yes");
+    }
+}
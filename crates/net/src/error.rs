@@ -8,7 +8,12 @@ pub enum Error {
     #[error("{0}")]
     JsError(JsError),
     /// Error returned by `serde` during deserialization.
+    ///
+    /// This crate reports the errors of `serde_json` as [`Error::BodyError`] and doesn't return
+    /// this variant anymore. It is kept for the `From<serde_json::Error>` conversion, which lets
+    /// `?` turn JSON errors into this error in code using the crate.
     #[cfg(feature = "json")]
+    #[deprecated(note = "JSON errors are reported as `Error::BodyError`")]
     #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
     #[error("{0}")]
     SerdeError(
@@ -16,9 +21,179 @@ pub enum Error {
         #[from]
         serde_json::Error,
     ),
+    /// Encoding or decoding a body failed, e.g. because it isn't valid JSON.
+    ///
+    /// `operation` tells what was being done, like ``decode the JSON body of `/api/users` ``, and
+    /// `source` is the error of the format, like a `serde_json::Error`.
+    #[error("failed to {operation}: {source}")]
+    BodyError {
+        /// What was being done.
+        operation: String,
+        /// The error of the format.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
     /// Error returned by this crate
     #[error("{0}")]
     GlooError(String),
+    /// The request got no response: the browser is offline, the server can't be reached, or
+    /// the response was blocked by CORS. Browsers don't tell these apart, but log the reason to
+    /// the developer console.
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("network error: {0}")]
+    Network(JsError),
+    /// The request got no response because of a network error, and was stored in an
+    /// [`OfflineQueue`](crate::http::OfflineQueue) to be sent again later.
+    #[cfg(feature = "offline-queue")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "offline-queue")))]
+    #[error("network error, the request was queued: {0}")]
+    Queued(JsError),
+    /// The request, or the reading of its response, was aborted with its
+    /// [`AbortSignal`](web_sys::AbortSignal).
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("the request was aborted")]
+    Aborted,
+    /// The request timed out, like when it was aborted with an `AbortSignal.timeout()`.
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("the request timed out")]
+    Timeout,
+    /// A [circuit breaker](crate::http::CircuitBreaker) refused to send a request to this
+    /// origin, because the previous ones kept failing.
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("circuit breaker for `{0}` is open")]
+    CircuitOpen(String),
+    /// The server responded with an error status, see
+    /// [`Response::error_for_status`](crate::http::Response::error_for_status).
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("{0}")]
+    StatusError(
+        #[source]
+        #[from]
+        StatusError,
+    ),
+    /// The response has a `Content-Type` which can't be decoded, see
+    /// [`Response::decode`](crate::http::Response::decode). This is empty when the response has
+    /// no `Content-Type`.
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("unsupported media type `{0}`")]
+    UnsupportedMediaType(String),
+    /// The response has a `Content-Type` other than the one of the format it is read as, like a
+    /// HTML error page read with `Response::cbor`.
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("expected a `{expected}` response, got `{actual}`")]
+    UnexpectedMediaType {
+        /// The media type of the format, like `application/cbor`.
+        expected: String,
+        /// The media type of the response, lowercased and without its parameters.
+        actual: String,
+    },
+    /// A GraphQL server answered with errors, see [`GraphQlClient::query`].
+    ///
+    /// [`GraphQlClient::query`]: crate::graphql::GraphQlClient::query
+    #[cfg(feature = "graphql")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "graphql")))]
+    #[error(
+        "GraphQL request failed: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    GraphQlErrors(Vec<crate::graphql::GraphQlError>),
+}
+
+impl Error {
+    /// An [`Error::BodyError`] for `operation`.
+    #[cfg(all(
+        feature = "http",
+        any(
+            feature = "json",
+            feature = "cbor",
+            feature = "msgpack",
+            feature = "bincode",
+            feature = "prost"
+        )
+    ))]
+    pub(crate) fn body(
+        operation: impl Into<String>,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self::BodyError {
+            operation: operation.into(),
+            source: source.into(),
+        }
+    }
+}
+
+/// A response with a client (`4xx`) or server (`5xx`) error status.
+///
+/// It holds on to the response, whose headers and body can still be read with
+/// [`response`](Self::response).
+#[cfg(feature = "http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[derive(Debug, Clone, PartialEq, Eq, ThisError)]
+#[error("HTTP status {status} {status_text} for `{url}`")]
+pub struct StatusError {
+    pub(crate) status: u16,
+    pub(crate) status_text: String,
+    pub(crate) url: String,
+    pub(crate) body: Option<String>,
+    pub(crate) response: web_sys::Response,
+    #[cfg(feature = "json")]
+    pub(crate) problem: Option<Box<crate::http::ProblemDetails>>,
+}
+
+#[cfg(feature = "http")]
+impl StatusError {
+    /// The HTTP status code of the response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// The status message of the response, e.g. `Not Found`.
+    pub fn status_text(&self) -> &str {
+        &self.status_text
+    }
+
+    /// The URL of the response.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The headers of the response.
+    pub fn headers(&self) -> crate::http::Headers {
+        crate::http::Headers::from_raw(self.response.headers())
+    }
+
+    /// The response, whose body can be read unless it was read already.
+    ///
+    /// This is the same response each time, so its body can only be read once.
+    pub fn response(&self) -> crate::http::Response {
+        crate::http::Response::from(Clone::clone(&self.response))
+    }
+
+    /// Turns the error back into the response.
+    pub fn into_response(self) -> crate::http::Response {
+        crate::http::Response::from(self.response)
+    }
+
+    /// The body of the response, if it was read with
+    /// [`Response::error_for_status_with_body`](crate::http::Response::error_for_status_with_body).
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+
+    /// The problem details in the body of the response, if it was read with
+    /// [`Response::error_for_status_with_body`](crate::http::Response::error_for_status_with_body)
+    /// and is `application/problem+json`.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn problem_details(&self) -> Option<&crate::http::ProblemDetails> {
+        self.problem.as_deref()
+    }
 }
 
 #[cfg(any(feature = "http", feature = "websocket", feature = "eventsource"))]
@@ -29,9 +204,26 @@ mod conversion {
     use std::convert::TryFrom;
     use wasm_bindgen::JsValue;
 
+    /// Converts a JavaScript error, telling aborts and timeouts apart.
     #[cfg(feature = "http")]
     pub(crate) fn js_to_error(js_value: JsValue) -> super::Error {
-        super::Error::JsError(js_to_js_error(js_value))
+        let error = js_to_js_error(js_value);
+        match error.name.as_str() {
+            "AbortError" => super::Error::Aborted,
+            "TimeoutError" => super::Error::Timeout,
+            _ => super::Error::JsError(error),
+        }
+    }
+
+    /// Converts the rejection of `fetch`, which is a `TypeError` for network errors.
+    #[cfg(feature = "http")]
+    pub(crate) fn fetch_to_error(js_value: JsValue) -> super::Error {
+        match js_to_error(js_value) {
+            super::Error::JsError(error) if error.name == "TypeError" => {
+                super::Error::Network(error)
+            }
+            error => error,
+        }
     }
 
     pub(crate) fn js_to_js_error(js_value: JsValue) -> JsError {
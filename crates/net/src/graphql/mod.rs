@@ -0,0 +1,241 @@
+//! A small [GraphQL](https://graphql.org/learn/serving-over-http/) client, sending queries over
+//! HTTP.
+//!
+//! # Example
+//!
+//! ```
+//! # use serde::{Deserialize, Serialize};
+//! #[derive(Serialize)]
+//! struct Variables {
+//!     id: u32,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Data {
+//!     user: User,
+//! }
+//!
+//! # async fn no_run() -> Result<(), gloo_net::Error> {
+//! let data: Data = gloo_net::graphql::query(
+//!     "/graphql",
+//!     "query User($id: ID!) { user(id: $id) { name } }",
+//!     &Variables { id: 1 },
+//! )
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With the `graphql-ws` feature, subscriptions are sent over WebSocket, see the [`ws`] module.
+//!
+//! Queries generated by `graphql_client` can be sent with [`GraphQlClient::execute`], passing it
+//! the `QueryBody` returned by `GraphQLQuery::build_query`.
+
+#[cfg(feature = "graphql-ws")]
+#[cfg_attr(docsrs, doc(cfg(feature = "graphql-ws")))]
+pub mod ws;
+
+use std::fmt;
+
+use crate::http::Client;
+use crate::Error;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The media types of GraphQL responses, the standard one being preferred.
+const ACCEPT: &str = "application/graphql-response+json, application/json;q=0.9";
+
+/// Sends `query` with `variables` to the GraphQL endpoint at `url`, and returns its `data`.
+///
+/// See [`GraphQlClient::query`].
+pub async fn query<V, R>(url: &str, query: &str, variables: &V) -> Result<R, Error>
+where
+    V: Serialize + ?Sized,
+    R: DeserializeOwned,
+{
+    GraphQlClient::new(url).query(query, variables).await
+}
+
+/// A client for the GraphQL endpoint at a given URL.
+#[derive(Debug, Clone)]
+pub struct GraphQlClient {
+    client: Client,
+    url: String,
+    #[cfg(feature = "graphql-ws")]
+    subscriptions: Option<ws::GraphQlWsClient>,
+}
+
+impl GraphQlClient {
+    /// Creates a client for the endpoint at `url`, sending requests with the `fetch` of the global
+    /// scope.
+    pub fn new(url: &str) -> Self {
+        Self::with_client(Client::new(), url)
+    }
+
+    /// Creates a client for the endpoint at `url`, sending requests through `client`.
+    pub fn with_client(client: Client, url: &str) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+            #[cfg(feature = "graphql-ws")]
+            subscriptions: None,
+        }
+    }
+
+    /// Sends `query` with `variables`, and returns the `data` of the response.
+    ///
+    /// If the response carries any errors, they are returned as [`Error::GraphQlErrors`], even
+    /// when the response also has partial data. Use [`execute`](Self::execute) to get both.
+    pub async fn query<V, R>(&self, query: &str, variables: &V) -> Result<R, Error>
+    where
+        V: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        self.execute(&QueryBody { query, variables })
+            .await?
+            .into_result()
+    }
+
+    /// POSTs `body` to the endpoint as JSON, and returns the whole response.
+    ///
+    /// `body` is usually an object with `query`, `variables` and `operationName` fields, like the
+    /// `QueryBody` of `graphql_client`.
+    pub async fn execute<B, R>(&self, body: &B) -> Result<GraphQlResponse<R>, Error>
+    where
+        B: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Accept", ACCEPT)
+            .json(body)?
+            .send()
+            .await?;
+        match response.json().await {
+            Ok(parsed) => Ok(parsed),
+            // A server failing before executing the query may not answer with a GraphQL response.
+            Err(e) => Err(response.error_for_status().err().map_or(e, Error::from)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct QueryBody<'a, V: ?Sized> {
+    query: &'a str,
+    variables: &'a V,
+}
+
+/// The response to a GraphQL request.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GraphQlResponse<T> {
+    /// The result of the query, which may be partial when there are errors.
+    pub data: Option<T>,
+    /// The errors raised while executing the query.
+    #[serde(default)]
+    pub errors: Vec<GraphQlError>,
+    /// Additional information added by the server.
+    pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl<T> GraphQlResponse<T> {
+    /// Returns the data of this response, or its errors if it has any.
+    pub fn into_result(self) -> Result<T, Error> {
+        if !self.errors.is_empty() {
+            return Err(Error::GraphQlErrors(self.errors));
+        }
+        self.data
+            .ok_or_else(|| Error::GlooError("the GraphQL response has no data".to_string()))
+    }
+}
+
+/// An error raised while executing a GraphQL request.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GraphQlError {
+    /// The description of the error.
+    pub message: String,
+    /// The locations in the query the error relates to.
+    #[serde(default)]
+    pub locations: Vec<Location>,
+    /// The path to the field of the response the error relates to.
+    #[serde(default)]
+    pub path: Vec<PathSegment>,
+    /// Additional information added by the server, like an error code.
+    pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl fmt::Display for GraphQlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if !self.path.is_empty() {
+            let path: Vec<_> = self.path.iter().map(ToString::to_string).collect();
+            write!(f, " at `{}`", path.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// A location in a GraphQL query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Location {
+    /// The line, starting at 1.
+    pub line: u32,
+    /// The column, starting at 1.
+    pub column: u32,
+}
+
+/// A segment of the path to a field of a GraphQL response.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum PathSegment {
+    /// The name or alias of a field.
+    Field(String),
+    /// The index of an element of a list.
+    Index(u64),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Field(field) => f.write_str(field),
+            Self::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_take_precedence_over_partial_data() {
+        let response: GraphQlResponse<serde_json::Value> = serde_json::from_str(
+            r#"{
+                "data": { "user": null },
+                "errors": [{
+                    "message": "not found",
+                    "locations": [{ "line": 1, "column": 3 }],
+                    "path": ["user", 0, "name"]
+                }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            response.errors[0].locations,
+            [Location { line: 1, column: 3 }]
+        );
+        assert_eq!(response.errors[0].to_string(), "not found at `user.0.name`");
+        match response.into_result() {
+            Err(Error::GraphQlErrors(errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let response: GraphQlResponse<u32> = serde_json::from_str(r#"{ "data": 4 }"#).unwrap();
+        assert_eq!(response.into_result().unwrap(), 4);
+    }
+}
@@ -0,0 +1,553 @@
+//! GraphQL subscriptions over WebSocket, with the
+//! [`graphql-transport-ws`](https://github.com/enisdenjo/graphql-ws/blob/master/PROTOCOL.md)
+//! protocol of `graphql-ws`, which Apollo Server, Hasura and async-graphql speak.
+//!
+//! A [`GraphQlWsClient`] opens one connection, over which any number of subscriptions run,
+//! each being a stream of the results the server pushes. Queries and mutations can be sent over
+//! it too, as subscriptions with a single result. A [`GraphQlClient`] can
+//! [hand its subscriptions](GraphQlClient::with_subscriptions) to one, sending everything else
+//! over HTTP.
+//!
+//! # Example
+//!
+//! ```
+//! use futures::StreamExt;
+//! use gloo_net::graphql::ws::GraphQlWsClient;
+//! use gloo_net::graphql::GraphQlClient;
+//! # use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Message {
+//!     text: String,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Data {
+//!     message_added: Message,
+//! }
+//!
+//! # async fn no_run() -> Result<(), gloo_net::graphql::ws::GraphQlWsError> {
+//! let subscriptions = GraphQlWsClient::connect("https://example.com/graphql").await?;
+//! let client = GraphQlClient::new("https://example.com/graphql").with_subscriptions(subscriptions);
+//!
+//! let mut messages = client.subscribe::<_, Data>(
+//!     "subscription { message_added { text } }",
+//!     &serde_json::json!({}),
+//! )?;
+//! while let Some(data) = messages.next().await {
+//!     println!("{}", data?.message_added.text);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_core::{ready, Stream};
+use futures_sink::Sink;
+use gloo_utils::errors::JsError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error as ThisError;
+
+use crate::graphql::{GraphQlClient, GraphQlError, GraphQlResponse};
+use crate::websocket::events::{CloseEvent, ErrorEvent};
+use crate::websocket::futures::{websocket_url, WebSocket, WebSocketReceiver, WebSocketSender};
+use crate::websocket::{Message, WebSocketError};
+
+/// The sub-protocol of `graphql-ws`, not to be confused with the `graphql-ws` sub-protocol of
+/// the older `subscriptions-transport-ws`.
+const PROTOCOL: &str = "graphql-transport-ws";
+
+/// The error of a GraphQL WebSocket client, or of a subscription.
+#[derive(Debug, ThisError)]
+#[non_exhaustive]
+pub enum GraphQlWsError {
+    /// The WebSocket couldn't be opened.
+    #[error("{0}")]
+    Open(JsError),
+    /// The server answered with errors, after which the subscription ends.
+    #[error(
+        "GraphQL subscription failed: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    GraphQl(Vec<GraphQlError>),
+    /// The server closed the connection, like with code `4403` when it refused the
+    /// `connection_init` payload, or `4500` when it failed.
+    #[error("the server closed the connection with code {}: {}", .0.code, .0.reason)]
+    Closed(CloseEvent),
+    /// The [`GraphQlClient`] has no connection for subscriptions, see
+    /// [`GraphQlClient::with_subscriptions`].
+    #[error("the GraphQL client has no WebSocket connection for subscriptions")]
+    NotConnected,
+    /// The connection failed.
+    #[error("{0}")]
+    WebSocket(WebSocketError),
+    /// The server sent something the protocol doesn't allow, like a result with neither data
+    /// nor errors.
+    #[error("GraphQL protocol error: {0}")]
+    Protocol(String),
+    /// A message couldn't be encoded or decoded.
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A connection to a GraphQL server, see the [module documentation](self).
+///
+/// Clones share the same connection, which is closed once every clone is dropped.
+#[derive(Clone)]
+pub struct GraphQlWsClient {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    sender: RefCell<Option<WebSocketSender>>,
+    routes: Rc<Routes>,
+    next_id: Cell<u64>,
+}
+
+/// Where the results of the server go, shared with the task reading the connection.
+#[derive(Default)]
+struct Routes {
+    url: String,
+    subscriptions: RefCell<HashMap<String, mpsc::UnboundedSender<Result<Value, GraphQlWsError>>>>,
+    closed: Cell<bool>,
+}
+
+impl Routes {
+    /// The error of the connection failing, or closed, without an event telling more.
+    fn failed(&self) -> GraphQlWsError {
+        GraphQlWsError::WebSocket(WebSocketError::ConnectionError(ErrorEvent::unknown(
+            self.url.clone(),
+        )))
+    }
+}
+
+impl GraphQlWsClient {
+    /// Connects to the GraphQL server at `url`, without a `connection_init` payload.
+    ///
+    /// See [`connect_with`](Self::connect_with).
+    pub async fn connect(url: &str) -> Result<Self, GraphQlWsError> {
+        Self::connect_with(url, &Value::Null).await
+    }
+
+    /// Connects to the GraphQL server at `url`, sending `payload` with the `connection_init`
+    /// message, which is where servers usually expect credentials, like
+    /// `{ "authToken": "..." }`.
+    ///
+    /// The `http` and `https` schemes of `url` become `ws` and `wss`, so the client can share the
+    /// URL of a [`GraphQlClient`]. This waits for the server to acknowledge the connection, then
+    /// a task reads the connection until it closes. This must be called in a
+    /// wasm-bindgen-futures context.
+    pub async fn connect_with<P>(url: &str, payload: &P) -> Result<Self, GraphQlWsError>
+    where
+        P: Serialize + ?Sized,
+    {
+        let ws = WebSocket::open_with_protocol(&websocket_url(url), PROTOCOL)
+            .map_err(GraphQlWsError::Open)?;
+        let (mut sender, mut receiver) = ws.into_halves();
+        let payload = serde_json::to_value(payload)?;
+        let init = match payload {
+            Value::Null => json!({ "type": "connection_init" }),
+            payload => json!({ "type": "connection_init", "payload": payload }),
+        };
+        std::future::poll_fn(|cx| Pin::new(&mut sender).poll_ready(cx))
+            .await
+            .map_err(GraphQlWsError::WebSocket)?;
+        send(&mut sender, &init)?;
+
+        loop {
+            match serde_json::from_value(next(&mut receiver).await?)? {
+                ServerMessage::ConnectionAck => break,
+                ServerMessage::Ping => send(&mut sender, &json!({ "type": "pong" }))?,
+                // nothing else is sent before the acknowledgement
+                _ => {}
+            }
+        }
+
+        let inner = Rc::new(Inner {
+            sender: RefCell::new(Some(sender)),
+            routes: Rc::new(Routes {
+                url: receiver.url(),
+                ..Routes::default()
+            }),
+            next_id: Cell::new(1),
+        });
+        wasm_bindgen_futures::spawn_local(read(
+            receiver,
+            Rc::clone(&inner.routes),
+            Rc::downgrade(&inner),
+        ));
+        Ok(Self { inner })
+    }
+
+    /// Subscribes to `query` with `variables`, and returns the stream of the `data` of its
+    /// results.
+    ///
+    /// Results carrying errors are returned as [`GraphQlWsError::GraphQl`], like
+    /// [`GraphQlClient::query`] does. Use [`execute`](Self::execute) to get both.
+    pub fn subscribe<V, R>(
+        &self,
+        query: &str,
+        variables: &V,
+    ) -> Result<Subscription<R>, GraphQlWsError>
+    where
+        V: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let body = json!({ "query": query, "variables": serde_json::to_value(variables)? });
+        self.start(body, data)
+    }
+
+    /// Subscribes with `body`, and returns the stream of its whole results.
+    ///
+    /// `body` is usually an object with `query`, `variables` and `operationName` fields, like the
+    /// `QueryBody` of `graphql_client`.
+    pub fn execute<B, R>(
+        &self,
+        body: &B,
+    ) -> Result<Subscription<GraphQlResponse<R>>, GraphQlWsError>
+    where
+        B: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        self.start(serde_json::to_value(body)?, |result| {
+            Ok(serde_json::from_value(result)?)
+        })
+    }
+
+    fn start<R>(
+        &self,
+        payload: Value,
+        decode: fn(Value) -> Result<R, GraphQlWsError>,
+    ) -> Result<Subscription<R>, GraphQlWsError> {
+        let id = self.inner.next_id.get();
+        self.inner.next_id.set(id + 1);
+        let id = id.to_string();
+
+        let (sender, receiver) = mpsc::unbounded();
+        self.inner
+            .routes
+            .subscriptions
+            .borrow_mut()
+            .insert(id.clone(), sender);
+        let message = json!({ "id": id, "type": "subscribe", "payload": payload });
+        if let Err(error) = self.inner.send(&message) {
+            self.inner.routes.subscriptions.borrow_mut().remove(&id);
+            return Err(error);
+        }
+        Ok(Subscription {
+            id,
+            receiver,
+            client: Rc::downgrade(&self.inner),
+            decode,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl Inner {
+    fn send(&self, message: &Value) -> Result<(), GraphQlWsError> {
+        if self.routes.closed.get() {
+            return Err(self.routes.failed());
+        }
+        match &mut *self.sender.borrow_mut() {
+            Some(sender) => send(sender, message),
+            None => Err(self.routes.failed()),
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.borrow_mut().take() {
+            let _ = sender.close(Some(1000), None);
+        }
+    }
+}
+
+impl fmt::Debug for GraphQlWsClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let routes = &self.inner.routes;
+        f.debug_struct("GraphQlWsClient")
+            .field("subscriptions", &routes.subscriptions.borrow().len())
+            .field("closed", &routes.closed.get())
+            .finish_non_exhaustive()
+    }
+}
+
+impl GraphQlClient {
+    /// Sends the subscriptions of this client over `connection`, see
+    /// [`subscribe`](Self::subscribe).
+    pub fn with_subscriptions(mut self, connection: GraphQlWsClient) -> Self {
+        self.subscriptions = Some(connection);
+        self
+    }
+
+    /// Subscribes to `query` with `variables` over the connection given to
+    /// [`with_subscriptions`](Self::with_subscriptions), see [`GraphQlWsClient::subscribe`].
+    ///
+    /// This fails with [`GraphQlWsError::NotConnected`] when the client has no connection.
+    pub fn subscribe<V, R>(
+        &self,
+        query: &str,
+        variables: &V,
+    ) -> Result<Subscription<R>, GraphQlWsError>
+    where
+        V: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        self.subscriptions
+            .as_ref()
+            .ok_or(GraphQlWsError::NotConnected)?
+            .subscribe(query, variables)
+    }
+}
+
+/// The results of a subscription, see [`GraphQlWsClient::subscribe`].
+///
+/// The stream ends when the server completes the subscription, after reporting an error, or
+/// once the connection closes. A message about it which can't be decoded is yielded as a
+/// [`GraphQlWsError::Json`], and the stream goes on. Dropping it stops the subscription on the
+/// server.
+#[must_use = "streams do nothing unless polled"]
+pub struct Subscription<R> {
+    id: String,
+    receiver: mpsc::UnboundedReceiver<Result<Value, GraphQlWsError>>,
+    client: Weak<Inner>,
+    decode: fn(Value) -> Result<R, GraphQlWsError>,
+    _marker: PhantomData<fn() -> R>,
+}
+
+impl<R> Subscription<R> {
+    /// The id of the subscription on the connection.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl<R> Stream for Subscription<R> {
+    type Item = Result<R, GraphQlWsError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = ready!(Pin::new(&mut self.receiver).poll_next(cx));
+        Poll::Ready(item.map(|result| result.and_then(self.decode)))
+    }
+}
+
+impl<R> Drop for Subscription<R> {
+    fn drop(&mut self) {
+        let Some(client) = self.client.upgrade() else {
+            return;
+        };
+        // the subscriptions the server ended are already gone
+        let removed = client.routes.subscriptions.borrow_mut().remove(&self.id);
+        if removed.is_some() {
+            let _ = client.send(&json!({ "id": self.id, "type": "complete" }));
+        }
+    }
+}
+
+impl<R> fmt::Debug for Subscription<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message of the server.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    ConnectionAck,
+    Ping,
+    Pong,
+    Next {
+        id: String,
+        payload: Value,
+    },
+    Error {
+        id: String,
+        payload: Vec<GraphQlError>,
+    },
+    Complete {
+        id: String,
+    },
+}
+
+fn send(sender: &mut WebSocketSender, message: &Value) -> Result<(), GraphQlWsError> {
+    // the connection is open, which is all sending needs
+    Pin::new(sender)
+        .start_send(Message::Text(message.to_string()))
+        .map_err(GraphQlWsError::WebSocket)
+}
+
+/// Receives the next message of the server, as JSON.
+async fn next(receiver: &mut WebSocketReceiver) -> Result<Value, GraphQlWsError> {
+    let message = std::future::poll_fn(|cx| Pin::new(&mut *receiver).poll_next(cx)).await;
+    match message {
+        Some(Ok(Message::Text(text))) => Ok(serde_json::from_str(&text)?),
+        Some(Ok(Message::Bytes(bytes))) => Ok(serde_json::from_slice(&bytes)?),
+        Some(Err(WebSocketError::ConnectionClose(event))) => Err(GraphQlWsError::Closed(event)),
+        Some(Err(error)) => Err(GraphQlWsError::WebSocket(error)),
+        None => Err(GraphQlWsError::WebSocket(WebSocketError::ConnectionError(
+            ErrorEvent::unknown(receiver.url()),
+        ))),
+    }
+}
+
+/// Reads the connection until it closes, passing the results on to their subscriptions.
+async fn read(mut receiver: WebSocketReceiver, routes: Rc<Routes>, client: Weak<Inner>) {
+    let closed = loop {
+        let message = match next(&mut receiver).await {
+            Ok(message) => message,
+            // without an id, a message which isn't JSON can't be blamed on a subscription
+            Err(GraphQlWsError::Json(_)) => continue,
+            Err(GraphQlWsError::Closed(event)) => break Some(event),
+            Err(_) => break None,
+        };
+        let message = match serde_json::from_value(message.clone()) {
+            Ok(message) => message,
+            Err(error) => {
+                fail_subscription(&routes, &message, error);
+                continue;
+            }
+        };
+        match message {
+            ServerMessage::Next { id, payload } => {
+                if let Some(route) = routes.subscriptions.borrow().get(&id) {
+                    let _ = route.unbounded_send(Ok(payload));
+                }
+            }
+            ServerMessage::Error { id, payload } => {
+                if let Some(route) = routes.subscriptions.borrow_mut().remove(&id) {
+                    let _ = route.unbounded_send(Err(GraphQlWsError::GraphQl(payload)));
+                }
+            }
+            ServerMessage::Complete { id } => {
+                routes.subscriptions.borrow_mut().remove(&id);
+            }
+            ServerMessage::Ping => {
+                if let Some(client) = client.upgrade() {
+                    let _ = client.send(&json!({ "type": "pong" }));
+                }
+            }
+            ServerMessage::ConnectionAck | ServerMessage::Pong => {}
+        }
+    };
+    routes.closed.set(true);
+    for (_, route) in routes.subscriptions.borrow_mut().drain() {
+        let error = match &closed {
+            Some(event) => GraphQlWsError::Closed(event.clone()),
+            None => routes.failed(),
+        };
+        let _ = route.unbounded_send(Err(error));
+    }
+}
+
+/// Decodes the `data` of a result, or its errors.
+fn data<R: DeserializeOwned>(result: Value) -> Result<R, GraphQlWsError> {
+    serde_json::from_value::<GraphQlResponse<R>>(result)?
+        .into_result()
+        .map_err(|error| match error {
+            crate::Error::GraphQlErrors(errors) => GraphQlWsError::GraphQl(errors),
+            // a result has either data or errors
+            error => GraphQlWsError::Protocol(error.to_string()),
+        })
+}
+
+/// Reports a message the client couldn't decode to the subscription it is about. The messages
+/// about no subscription, like those of later versions of the protocol, are skipped.
+fn fail_subscription(routes: &Routes, message: &Value, error: serde_json::Error) {
+    let id = message.get("id").and_then(Value::as_str);
+    if let Some(route) = id.and_then(|id| routes.subscriptions.borrow().get(id).cloned()) {
+        let _ = route.unbounded_send(Err(GraphQlWsError::Json(error)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undecodable_messages_go_to_their_subscription() {
+        let routes = Routes::default();
+        let (sender, mut receiver) = mpsc::unbounded();
+        routes
+            .subscriptions
+            .borrow_mut()
+            .insert("1".to_string(), sender);
+        let fail = |message: Value| {
+            let error = serde_json::from_value::<ServerMessage>(message.clone()).unwrap_err();
+            fail_subscription(&routes, &message, error);
+        };
+
+        // a result without payload
+        fail(json!({ "id": "1", "type": "next" }));
+        assert!(matches!(
+            receiver.try_next(),
+            Ok(Some(Err(GraphQlWsError::Json(_))))
+        ));
+        // a message of another subscription, or of a later version of the protocol
+        fail(json!({ "id": "2", "type": "next" }));
+        fail(json!({ "type": "goodbye" }));
+        assert!(receiver.try_next().is_err());
+        assert_eq!(routes.subscriptions.borrow().len(), 1);
+    }
+
+    #[test]
+    fn decodes_the_data_of_results() {
+        assert_eq!(data::<u32>(json!({ "data": 1 })).unwrap(), 1);
+        assert!(matches!(
+            data::<u32>(json!({ "errors": [{ "message": "denied" }] })),
+            Err(GraphQlWsError::GraphQl(errors)) if errors[0].message == "denied"
+        ));
+        assert!(matches!(
+            data::<u32>(json!({})),
+            Err(GraphQlWsError::Protocol(_))
+        ));
+        assert!(matches!(
+            data::<u32>(json!({ "data": "one" })),
+            Err(GraphQlWsError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn parses_server_messages() {
+        let parse = |text: &str| serde_json::from_str::<ServerMessage>(text).unwrap();
+        assert_eq!(
+            parse(r#"{ "type": "connection_ack", "payload": { "v": 1 } }"#),
+            ServerMessage::ConnectionAck
+        );
+        assert_eq!(parse(r#"{ "type": "ping" }"#), ServerMessage::Ping);
+        assert_eq!(
+            parse(r#"{ "id": "1", "type": "next", "payload": { "data": { "n": 1 } } }"#),
+            ServerMessage::Next {
+                id: "1".to_string(),
+                payload: json!({ "data": { "n": 1 } }),
+            }
+        );
+        match parse(r#"{ "id": "2", "type": "error", "payload": [{ "message": "denied" }] }"#) {
+            ServerMessage::Error { id, payload } => {
+                assert_eq!(id, "2");
+                assert_eq!(payload[0].message, "denied");
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert_eq!(
+            parse(r#"{ "id": "3", "type": "complete" }"#),
+            ServerMessage::Complete {
+                id: "3".to_string()
+            }
+        );
+    }
+}
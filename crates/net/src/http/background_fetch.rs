@@ -0,0 +1,515 @@
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+use js_sys::{Array, Object, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{EventTarget, ServiceWorkerRegistration};
+
+use crate::http::{Progress, Request, Response};
+use crate::registration::{self, await_promise};
+use crate::{js_to_error, Error};
+
+#[wasm_bindgen]
+extern "C" {
+    /// The `BackgroundFetchManager` of a service worker registration.
+    #[derive(Debug, Clone)]
+    type BackgroundFetchManager;
+
+    #[wasm_bindgen(method, catch)]
+    fn fetch(
+        this: &BackgroundFetchManager,
+        id: &str,
+        requests: &Array,
+        options: &Object,
+    ) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(method, catch)]
+    fn get(this: &BackgroundFetchManager, id: &str) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = getIds)]
+    fn get_ids(this: &BackgroundFetchManager) -> Result<Promise, JsValue>;
+
+    /// A `BackgroundFetchRegistration`.
+    #[wasm_bindgen(extends = EventTarget)]
+    #[derive(Debug, Clone)]
+    type RawRegistration;
+
+    #[wasm_bindgen(method, getter)]
+    fn id(this: &RawRegistration) -> String;
+
+    #[wasm_bindgen(method, getter, js_name = uploadTotal)]
+    fn upload_total(this: &RawRegistration) -> f64;
+
+    #[wasm_bindgen(method, getter)]
+    fn uploaded(this: &RawRegistration) -> f64;
+
+    #[wasm_bindgen(method, getter, js_name = downloadTotal)]
+    fn download_total(this: &RawRegistration) -> f64;
+
+    #[wasm_bindgen(method, getter)]
+    fn downloaded(this: &RawRegistration) -> f64;
+
+    #[wasm_bindgen(method, getter)]
+    fn result(this: &RawRegistration) -> String;
+
+    #[wasm_bindgen(method, getter, js_name = failureReason)]
+    fn failure_reason(this: &RawRegistration) -> String;
+
+    #[wasm_bindgen(method, getter, js_name = recordsAvailable)]
+    fn records_available(this: &RawRegistration) -> bool;
+
+    #[wasm_bindgen(method, catch)]
+    fn abort(this: &RawRegistration) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = match)]
+    fn match_(this: &RawRegistration, request: &web_sys::Request) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = matchAll)]
+    fn match_all(this: &RawRegistration) -> Result<Promise, JsValue>;
+
+    /// A `BackgroundFetchRecord`.
+    #[derive(Debug, Clone)]
+    type RawRecord;
+
+    #[wasm_bindgen(method, getter)]
+    fn request(this: &RawRecord) -> web_sys::Request;
+
+    #[wasm_bindgen(method, getter, js_name = responseReady)]
+    fn response_ready(this: &RawRecord) -> Promise;
+}
+
+/// The [Background Fetch API](https://developer.mozilla.org/en-US/docs/Web/API/Background_Fetch_API)
+/// of a service worker registration, which downloads large files in the background, with a UI
+/// of the browser showing their progress, and goes on after the page was closed.
+///
+/// The API is only available in Chromium-based browsers.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{BackgroundFetch, BackgroundFetchIcon, BackgroundFetchOptions, Request};
+/// # async fn no_run() -> Result<(), gloo_net::Error> {
+/// let fetch = BackgroundFetch::ready().await?;
+/// let episode = fetch
+///     .fetch(
+///         "episode-42",
+///         vec![Request::get("/episodes/42.mp4").build()?],
+///         BackgroundFetchOptions::new()
+///             .title("Episode 42")
+///             .icon(BackgroundFetchIcon::new("/icon.png").sizes("192x192"))
+///             .download_total(60_000_000),
+///     )
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BackgroundFetch {
+    manager: BackgroundFetchManager,
+}
+
+impl BackgroundFetch {
+    /// The Background Fetch of the active service worker registration: the one controlling the
+    /// page in a window, or the registration of the service worker itself.
+    pub async fn ready() -> Result<Self, Error> {
+        Self::from_registration(&registration::ready(unsupported).await?)
+    }
+
+    /// The Background Fetch of `registration`.
+    pub fn from_registration(registration: &ServiceWorkerRegistration) -> Result<Self, Error> {
+        Ok(Self {
+            manager: registration::manager(registration, "backgroundFetch", unsupported)?,
+        })
+    }
+
+    /// Starts downloading the responses to `requests` in the background, under `id`.
+    ///
+    /// This fails if a fetch with the same `id` is still running.
+    pub async fn fetch(
+        &self,
+        id: &str,
+        requests: impl IntoIterator<Item = Request>,
+        options: BackgroundFetchOptions,
+    ) -> Result<BackgroundFetchRegistration, Error> {
+        let requests = requests
+            .into_iter()
+            .map(web_sys::Request::from)
+            .collect::<Array>();
+        let options = options.to_js()?;
+        let promise = self
+            .manager
+            .fetch(id, &requests, &options)
+            .map_err(js_to_error)?;
+        let raw = await_promise(promise).await?;
+        Ok(BackgroundFetchRegistration {
+            raw: raw.unchecked_into(),
+        })
+    }
+
+    /// The fetch with `id`, unless it is unknown or finished.
+    pub async fn get(&self, id: &str) -> Result<Option<BackgroundFetchRegistration>, Error> {
+        let raw = await_promise(self.manager.get(id).map_err(js_to_error)?).await?;
+        if raw.is_undefined() {
+            return Ok(None);
+        }
+        Ok(Some(BackgroundFetchRegistration {
+            raw: raw.unchecked_into(),
+        }))
+    }
+
+    /// The ids of the running fetches.
+    pub async fn ids(&self) -> Result<Vec<String>, Error> {
+        let ids = await_promise(self.manager.get_ids().map_err(js_to_error)?).await?;
+        Ok(ids
+            .unchecked_into::<Array>()
+            .iter()
+            .filter_map(|id| id.as_string())
+            .collect())
+    }
+}
+
+/// How the browser presents a [`BackgroundFetch`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackgroundFetchOptions {
+    title: Option<String>,
+    icons: Vec<BackgroundFetchIcon>,
+    download_total: Option<u64>,
+}
+
+impl BackgroundFetchOptions {
+    /// Options without a title or icons.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the title shown in the download UI.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Adds an icon to pick from for the download UI.
+    pub fn icon(mut self, icon: BackgroundFetchIcon) -> Self {
+        self.icons.push(icon);
+        self
+    }
+
+    /// Sets the total size of the responses, in bytes, to show the progress in the download
+    /// UI. The fetch fails if it downloads more than this.
+    pub fn download_total(mut self, bytes: u64) -> Self {
+        self.download_total = Some(bytes);
+        self
+    }
+
+    fn to_js(&self) -> Result<Object, Error> {
+        let options = Object::new();
+        if let Some(title) = &self.title {
+            set(&options, "title", &JsValue::from_str(title))?;
+        }
+        if !self.icons.is_empty() {
+            let icons = self
+                .icons
+                .iter()
+                .map(BackgroundFetchIcon::to_js)
+                .collect::<Result<Array, Error>>()?;
+            set(&options, "icons", &icons)?;
+        }
+        if let Some(total) = self.download_total {
+            set(&options, "downloadTotal", &JsValue::from_f64(total as f64))?;
+        }
+        Ok(options)
+    }
+}
+
+/// An icon of the download UI of a [`BackgroundFetch`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackgroundFetchIcon {
+    src: String,
+    sizes: Option<String>,
+    mime_type: Option<String>,
+    label: Option<String>,
+}
+
+impl BackgroundFetchIcon {
+    /// An icon at the URL `src`.
+    pub fn new(src: &str) -> Self {
+        Self {
+            src: src.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the sizes of the icon, like `"192x192"`, or `"16x16 32x32"`.
+    pub fn sizes(mut self, sizes: &str) -> Self {
+        self.sizes = Some(sizes.to_string());
+        self
+    }
+
+    /// Sets the MIME type of the icon, so the browser can skip the types it doesn't support.
+    pub fn mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    /// Sets the accessible name of the icon.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    fn to_js(&self) -> Result<Object, Error> {
+        let icon = Object::new();
+        set(&icon, "src", &JsValue::from_str(&self.src))?;
+        let fields = [
+            ("sizes", &self.sizes),
+            ("type", &self.mime_type),
+            ("label", &self.label),
+        ];
+        for (name, value) in fields.iter() {
+            if let Some(value) = value {
+                set(&icon, name, &JsValue::from_str(value))?;
+            }
+        }
+        Ok(icon)
+    }
+}
+
+/// The outcome of a [`BackgroundFetchRegistration`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackgroundFetchResult {
+    /// The fetch is still running.
+    Pending,
+    /// Every response was downloaded.
+    Success,
+    /// The fetch failed, for the given reason: `aborted`, `bad-status`, `fetch-error`,
+    /// `quota-exceeded` or `download-total-exceeded`.
+    Failure(String),
+}
+
+/// A fetch started by [`BackgroundFetch::fetch`].
+///
+/// In the service worker, the registration of a finished fetch is handed to the
+/// `backgroundfetchsuccess`, `backgroundfetchfail` and `backgroundfetchabort` events, see
+/// [`from_event`](Self::from_event).
+#[derive(Debug, Clone)]
+pub struct BackgroundFetchRegistration {
+    raw: RawRegistration,
+}
+
+impl BackgroundFetchRegistration {
+    /// The registration of `event`, if it is one of the Background Fetch events of a service
+    /// worker.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::BackgroundFetchRegistration;
+    /// # async fn no_run(event: web_sys::Event) -> Result<(), gloo_net::Error> {
+    /// // in the `backgroundfetchsuccess` event listener of the service worker
+    /// if let Some(fetch) = BackgroundFetchRegistration::from_event(&event) {
+    ///     for record in fetch.records().await? {
+    ///         let response = record.response().await?;
+    ///         let bytes = response.binary().await?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_event(event: &web_sys::Event) -> Option<Self> {
+        if !event.type_().starts_with("backgroundfetch") {
+            return None;
+        }
+        let raw = Reflect::get(event, &JsValue::from_str("registration")).ok()?;
+        if raw.is_undefined() {
+            return None;
+        }
+        Some(Self {
+            raw: raw.unchecked_into(),
+        })
+    }
+
+    /// The id the fetch was started with.
+    pub fn id(&self) -> String {
+        self.raw.id()
+    }
+
+    /// The number of bytes downloaded so far.
+    pub fn downloaded(&self) -> u64 {
+        self.raw.downloaded() as u64
+    }
+
+    /// The total number of bytes to download, if it was given in the
+    /// [options](BackgroundFetchOptions::download_total).
+    pub fn download_total(&self) -> Option<u64> {
+        match self.raw.download_total() as u64 {
+            0 => None,
+            total => Some(total),
+        }
+    }
+
+    /// The number of bytes of request bodies uploaded so far.
+    pub fn uploaded(&self) -> u64 {
+        self.raw.uploaded() as u64
+    }
+
+    /// The total number of bytes of the request bodies.
+    pub fn upload_total(&self) -> u64 {
+        self.raw.upload_total() as u64
+    }
+
+    /// The download progress.
+    pub fn progress(&self) -> Progress {
+        Progress {
+            loaded: self.downloaded(),
+            total: self.download_total(),
+        }
+    }
+
+    /// Whether the fetch is still running, succeeded or failed.
+    pub fn result(&self) -> BackgroundFetchResult {
+        match self.raw.result().as_str() {
+            "" => BackgroundFetchResult::Pending,
+            "success" => BackgroundFetchResult::Success,
+            _ => BackgroundFetchResult::Failure(self.raw.failure_reason()),
+        }
+    }
+
+    /// Whether the records can still be read. They can't anymore once the service worker
+    /// event of the finished fetch was handled.
+    pub fn records_available(&self) -> bool {
+        self.raw.records_available()
+    }
+
+    /// Follows the download progress of the fetch, until it finishes or the returned stream is
+    /// dropped.
+    ///
+    /// The stream starts with the current progress, and ends once the
+    /// [result](Self::result) is known.
+    pub fn watch(&self) -> BackgroundFetchProgress {
+        let (sender, receiver) = mpsc::unbounded();
+        let _ = sender.unbounded_send(self.progress());
+        let target: EventTarget = Clone::clone(&self.raw).unchecked_into();
+        if self.result() != BackgroundFetchResult::Pending {
+            sender.close_channel();
+            return BackgroundFetchProgress {
+                target,
+                listener: None,
+                receiver,
+            };
+        }
+        let registration = self.clone();
+        let listener = Closure::<dyn FnMut()>::new(move || {
+            let _ = sender.unbounded_send(registration.progress());
+            if registration.result() != BackgroundFetchResult::Pending {
+                sender.close_channel();
+            }
+        });
+        let _ =
+            target.add_event_listener_with_callback("progress", listener.as_ref().unchecked_ref());
+        BackgroundFetchProgress {
+            target,
+            listener: Some(listener),
+            receiver,
+        }
+    }
+
+    /// Aborts the fetch, returning whether it was still running.
+    pub async fn abort(&self) -> Result<bool, Error> {
+        let aborted = await_promise(self.raw.abort().map_err(js_to_error)?).await?;
+        Ok(aborted.is_truthy())
+    }
+
+    /// The records of the requests of the fetch, whose responses may still be downloading.
+    pub async fn records(&self) -> Result<Vec<BackgroundFetchRecord>, Error> {
+        let records = await_promise(self.raw.match_all().map_err(js_to_error)?).await?;
+        Ok(records
+            .unchecked_into::<Array>()
+            .iter()
+            .map(|raw| BackgroundFetchRecord {
+                raw: raw.unchecked_into(),
+            })
+            .collect())
+    }
+
+    /// The record of the request matching `request`, if the fetch has one.
+    pub async fn record(&self, request: &Request) -> Result<Option<BackgroundFetchRecord>, Error> {
+        let raw = await_promise(self.raw.match_(request.as_raw()).map_err(js_to_error)?).await?;
+        if raw.is_undefined() {
+            return Ok(None);
+        }
+        Ok(Some(BackgroundFetchRecord {
+            raw: raw.unchecked_into(),
+        }))
+    }
+}
+
+/// A request of a [`BackgroundFetchRegistration`], along with its response.
+#[derive(Debug, Clone)]
+pub struct BackgroundFetchRecord {
+    raw: RawRecord,
+}
+
+impl BackgroundFetchRecord {
+    /// The request.
+    pub fn request(&self) -> Request {
+        Request::from(self.raw.request())
+    }
+
+    /// Waits for the response to be downloaded.
+    ///
+    /// This fails if the request failed, or the fetch was aborted.
+    pub async fn response(&self) -> Result<Response, Error> {
+        let response = await_promise(self.raw.response_ready()).await?;
+        Ok(Response::from(
+            response.unchecked_into::<web_sys::Response>(),
+        ))
+    }
+}
+
+/// A [`Stream`] of the download [`Progress`] of a [`BackgroundFetchRegistration`], see
+/// [`BackgroundFetchRegistration::watch`].
+#[must_use = "streams do nothing unless polled"]
+pub struct BackgroundFetchProgress {
+    target: EventTarget,
+    listener: Option<Closure<dyn FnMut()>>,
+    receiver: mpsc::UnboundedReceiver<Progress>,
+}
+
+impl Stream for BackgroundFetchProgress {
+    type Item = Progress;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for BackgroundFetchProgress {
+    fn drop(&mut self) {
+        if let Some(listener) = &self.listener {
+            let _ = self
+                .target
+                .remove_event_listener_with_callback("progress", listener.as_ref().unchecked_ref());
+        }
+    }
+}
+
+impl fmt::Debug for BackgroundFetchProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackgroundFetchProgress")
+            .finish_non_exhaustive()
+    }
+}
+
+fn set(object: &Object, name: &str, value: &JsValue) -> Result<(), Error> {
+    Reflect::set(object, &JsValue::from_str(name), value)
+        .map(drop)
+        .map_err(js_to_error)
+}
+
+fn unsupported() -> Error {
+    Error::GlooError("the Background Fetch API is not available".to_string())
+}
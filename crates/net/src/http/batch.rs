@@ -0,0 +1,278 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures_channel::oneshot;
+
+use crate::http::{Client, DecodeFuture, Request, Response};
+use crate::Error;
+
+/// The replies to a batch of calls, each of which may have failed on its own.
+pub type Replies<T> = Vec<Result<T, Error>>;
+
+/// How the calls collected by a [`Batcher`] are packed into a single request, and their replies
+/// unpacked from its response.
+pub trait Envelope {
+    /// A single call, like the arguments of a remote procedure.
+    type Call;
+    /// The reply to a single call.
+    type Reply;
+
+    /// Builds the request sending all of `calls` at once.
+    fn encode(&self, calls: &[Self::Call]) -> Result<Request, Error>;
+
+    /// Splits the response to a batch of `count` calls into their replies, in the same order as
+    /// the calls.
+    fn decode(&self, response: Response, count: usize) -> DecodeFuture<'_, Replies<Self::Reply>>;
+}
+
+/// Collects the calls made within a short time window and sends them as a single request.
+///
+/// Each call made with [`call`](Self::call) waits for the [`window`](Self::window) started by
+/// the first call of its batch to close, or for the batch to be full. The batch is then sent as
+/// one request, packed by the [`Envelope`], and each caller gets its own reply back. If the batch
+/// request fails as a whole, every caller gets an error.
+///
+/// # Example
+///
+/// Batching lookups into one `POST` of a JSON array, answered by a JSON array of the same
+/// length:
+///
+/// ```
+/// # use gloo_net::http::{Batcher, JsonBatch};
+/// # use serde::Deserialize;
+/// # #[derive(Deserialize)]
+/// # struct User;
+/// # async fn no_run() -> Result<(), gloo_net::Error> {
+/// let batcher = Batcher::new(JsonBatch::<u32, User>::new("/api/users/batch"));
+///
+/// // sent together, in one request
+/// let (ferris, corro) = futures::join!(batcher.call(1), batcher.call(2));
+/// # Ok(())
+/// # }
+/// ```
+pub struct Batcher<E: Envelope> {
+    inner: Rc<Inner<E>>,
+}
+
+struct Inner<E: Envelope> {
+    envelope: E,
+    client: Client,
+    window: Duration,
+    max_size: usize,
+    pending: RefCell<Pending<E>>,
+}
+
+type Sender<E> = oneshot::Sender<Result<<E as Envelope>::Reply, Error>>;
+
+struct Pending<E: Envelope> {
+    /// Incremented whenever a batch is sent, so that the timer of a batch which was sent because
+    /// it was full doesn't send the next one early.
+    id: u64,
+    calls: Vec<E::Call>,
+    senders: Vec<Sender<E>>,
+}
+
+impl<E: Envelope + 'static> Batcher<E> {
+    /// Creates a batcher packing calls with `envelope`, waiting 10 ms for more calls, with up to
+    /// 50 calls per batch.
+    pub fn new(envelope: E) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                envelope,
+                client: Client::new(),
+                window: Duration::from_millis(10),
+                max_size: 50,
+                pending: RefCell::new(Pending {
+                    id: 0,
+                    calls: Vec::new(),
+                    senders: Vec::new(),
+                }),
+            }),
+        }
+    }
+
+    /// Sets how long after the first call of a batch the batch is sent.
+    pub fn window(self, window: Duration) -> Self {
+        self.configure(|inner| inner.window = window)
+    }
+
+    /// Sets how many calls a batch holds at most, at least 1. A full batch is sent right away.
+    pub fn max_size(self, max_size: usize) -> Self {
+        self.configure(|inner| inner.max_size = max_size.max(1))
+    }
+
+    /// Sends the batches through `client` instead of a default [`Client`].
+    pub fn client(self, client: Client) -> Self {
+        self.configure(|inner| inner.client = client)
+    }
+
+    fn configure(mut self, f: impl FnOnce(&mut Inner<E>)) -> Self {
+        f(Rc::get_mut(&mut self.inner)
+            .expect("a `Batcher` must be configured before it is cloned"));
+        self
+    }
+
+    /// Adds `call` to the current batch, resolving to its reply once the batch was sent.
+    pub async fn call(&self, call: E::Call) -> Result<E::Reply, Error> {
+        let (sender, receiver) = oneshot::channel();
+        let (id, len) = {
+            let mut pending = self.inner.pending.borrow_mut();
+            pending.calls.push(call);
+            pending.senders.push(sender);
+            (pending.id, pending.calls.len())
+        };
+        self.inner.schedule(id, len);
+        receiver
+            .await
+            .unwrap_or_else(|_| Err(Error::GlooError("the batch was dropped".to_string())))
+    }
+
+    /// Sends the calls collected so far right away.
+    pub fn flush(&self) {
+        wasm_bindgen_futures::spawn_local(self.inner.clone().flush(None));
+    }
+}
+
+impl<E: Envelope + 'static> Inner<E> {
+    /// Sends batch `id` right away once it holds `len` calls and is full, or starts its window
+    /// when `len` is its first call.
+    fn schedule(self: &Rc<Self>, id: u64, len: usize) {
+        if len >= self.max_size {
+            wasm_bindgen_futures::spawn_local(self.clone().flush(None));
+        } else if len == 1 {
+            self.start_window(id);
+        }
+    }
+
+    /// Sends batch `id` once the window elapsed, unless it was sent before because it was full.
+    fn start_window(self: &Rc<Self>, id: u64) {
+        let inner = self.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            gloo_timers::future::sleep(inner.window).await;
+            inner.flush(Some(id)).await;
+        });
+    }
+
+    /// Sends up to `max_size` of the pending calls, if they are batch `id` when given. The calls
+    /// left over make the next batch.
+    async fn flush(self: Rc<Self>, id: Option<u64>) {
+        let (calls, senders) = {
+            let mut pending = self.pending.borrow_mut();
+            if pending.calls.is_empty() || id.is_some_and(|id| id != pending.id) {
+                return;
+            }
+            pending.id += 1;
+            let count = pending.calls.len().min(self.max_size);
+            let batch = (
+                pending.calls.drain(..count).collect::<Vec<_>>(),
+                pending.senders.drain(..count).collect::<Vec<_>>(),
+            );
+            // the calls left over wait for a window of their own, unless they fill a batch
+            let left = pending.calls.len();
+            if left >= self.max_size {
+                wasm_bindgen_futures::spawn_local(self.clone().flush(None));
+            } else if left > 0 {
+                self.start_window(pending.id);
+            }
+            batch
+        };
+        match self.send(&calls).await {
+            Ok(replies) => {
+                for (sender, reply) in senders.into_iter().zip(replies) {
+                    let _ = sender.send(reply);
+                }
+            }
+            Err(e) => {
+                let message = e.to_string();
+                for sender in senders {
+                    let _ = sender.send(Err(Error::GlooError(format!(
+                        "batch request failed: {message}"
+                    ))));
+                }
+            }
+        }
+    }
+
+    async fn send(&self, calls: &[E::Call]) -> Result<Replies<E::Reply>, Error> {
+        let request = self.envelope.encode(calls)?;
+        let response = self.client.send(request).await?;
+        let replies = self.envelope.decode(response, calls.len()).await?;
+        if replies.len() != calls.len() {
+            return Err(Error::GlooError(format!(
+                "expected {} replies to the batch, got {}",
+                calls.len(),
+                replies.len()
+            )));
+        }
+        Ok(replies)
+    }
+}
+
+impl<E: Envelope> Clone for Batcher<E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E: Envelope> fmt::Debug for Batcher<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batcher")
+            .field("window", &self.inner.window)
+            .field("max_size", &self.inner.max_size)
+            .field("pending", &self.inner.pending.borrow().calls.len())
+            .finish()
+    }
+}
+
+/// An [`Envelope`] `POST`ing the calls as a JSON array to a URL, which answers with a JSON
+/// array of the replies, in the same order.
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub struct JsonBatch<C, R> {
+    url: String,
+    marker: std::marker::PhantomData<fn(&C) -> R>,
+}
+
+#[cfg(feature = "json")]
+impl<C, R> JsonBatch<C, R> {
+    /// Sends the batches to `url`.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl<C, R> Envelope for JsonBatch<C, R>
+where
+    C: serde::Serialize,
+    R: serde::de::DeserializeOwned,
+{
+    type Call = C;
+    type Reply = R;
+
+    fn encode(&self, calls: &[C]) -> Result<Request, Error> {
+        Request::post(&self.url).json(calls)
+    }
+
+    fn decode(&self, response: Response, _count: usize) -> DecodeFuture<'_, Replies<R>> {
+        Box::pin(async move {
+            let response = response.error_for_status()?;
+            let replies: Vec<R> = response.json().await?;
+            Ok(replies.into_iter().map(Ok).collect())
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+impl<C, R> fmt::Debug for JsonBatch<C, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonBatch").field("url", &self.url).finish()
+    }
+}
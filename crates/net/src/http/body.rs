@@ -0,0 +1,218 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::http::progress::ProgressTracker;
+use crate::{js_to_error, Error};
+use futures_core::{ready, Stream};
+use js_sys::Uint8Array;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{ReadableStream, ReadableStreamDefaultReader, ReadableStreamReadResult};
+
+/// A [`Stream`] of the chunks of a body, read from a [`web_sys::ReadableStream`].
+///
+/// Each item is one chunk as delivered by the browser. A body which is `null` (for example the
+/// body of a `204 No Content` response) yields no chunks.
+pub struct BodyStream {
+    reader: Option<ReadableStreamDefaultReader>,
+    pending: Option<JsFuture>,
+    progress: Option<ProgressTracker>,
+}
+
+impl BodyStream {
+    /// Locks `stream` and reads it chunk by chunk.
+    ///
+    /// This errors if the stream is already locked, e.g. because the body was already consumed.
+    pub(crate) fn new(stream: Option<ReadableStream>) -> Result<Self, Error> {
+        let reader = match stream {
+            Some(stream) => Some(ReadableStreamDefaultReader::new(&stream).map_err(js_to_error)?),
+            None => None,
+        };
+        Ok(Self {
+            reader,
+            pending: None,
+            progress: None,
+        })
+    }
+
+    /// Reports every chunk read to `tracker`.
+    pub(crate) fn track(mut self, tracker: Option<ProgressTracker>) -> Self {
+        self.progress = tracker;
+        self
+    }
+
+    /// Reads the next chunk.
+    pub(crate) async fn next_chunk(&mut self) -> Option<Result<Vec<u8>, Error>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Reads the stream to completion.
+    pub(crate) async fn collect(mut self) -> Result<Vec<u8>, Error> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.next_chunk().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(body)
+    }
+}
+
+impl Stream for BodyStream {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let reader = match &self.reader {
+            Some(reader) => reader.clone(),
+            None => return Poll::Ready(None),
+        };
+        let read = self
+            .pending
+            .get_or_insert_with(|| JsFuture::from(reader.read()));
+        let result = ready!(Pin::new(read).poll(cx));
+        self.pending = None;
+
+        match result {
+            Ok(result) => {
+                let result: ReadableStreamReadResult = result.unchecked_into();
+                if result.get_done().unwrap_or(false) {
+                    self.reader = None;
+                    return Poll::Ready(None);
+                }
+                let chunk = Uint8Array::new(&result.get_value()).to_vec();
+                if let Some(progress) = &mut self.progress {
+                    progress.advance(chunk.len());
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Err(e) => {
+                self.reader = None;
+                Poll::Ready(Some(Err(js_to_error(e))))
+            }
+        }
+    }
+}
+
+/// Lets the utilities of the `hyper` ecosystem, like body collectors and length limiters, read
+/// the body. Bodies have no trailers.
+#[cfg(feature = "http-body")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http-body")))]
+impl http_body::Body for BodyStream {
+    type Data = bytes::Bytes;
+    type Error = Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.poll_next(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(bytes::Bytes::from)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.reader.is_none()
+    }
+}
+
+impl Drop for BodyStream {
+    fn drop(&mut self) {
+        // Tell the browser we are no longer interested in the rest of the body.
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.cancel();
+        }
+    }
+}
+
+impl fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyStream")
+            .field("done", &self.reader.is_none())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "io")]
+pub use reader::BodyReader;
+
+#[cfg(feature = "io")]
+mod reader {
+    use super::BodyStream;
+    use futures_core::{ready, Stream};
+    use futures_io::{AsyncBufRead, AsyncRead};
+    use std::fmt;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// An [`AsyncRead`] over a body, obtained from
+    /// [`Response::into_async_read`](crate::http::Response::into_async_read).
+    ///
+    /// This also implements [`AsyncBufRead`], handing out the chunks as the browser delivers
+    /// them without copying them into an intermediate buffer.
+    pub struct BodyReader {
+        stream: BodyStream,
+        chunk: Vec<u8>,
+        pos: usize,
+    }
+
+    impl BodyReader {
+        pub(crate) fn new(stream: BodyStream) -> Self {
+            Self {
+                stream,
+                chunk: Vec::new(),
+                pos: 0,
+            }
+        }
+    }
+
+    impl AsyncBufRead for BodyReader {
+        fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+            let this = self.get_mut();
+            while this.pos >= this.chunk.len() {
+                match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                    Some(Ok(chunk)) => {
+                        this.chunk = chunk;
+                        this.pos = 0;
+                    }
+                    Some(Err(e)) => return Poll::Ready(Err(io::Error::other(e))),
+                    None => return Poll::Ready(Ok(&[])),
+                }
+            }
+            Poll::Ready(Ok(&this.chunk[this.pos..]))
+        }
+
+        fn consume(mut self: Pin<&mut Self>, amt: usize) {
+            self.pos = usize::min(self.pos + amt, self.chunk.len());
+        }
+    }
+
+    impl AsyncRead for BodyReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+            let len = usize::min(available.len(), buf.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            self.consume(len);
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    impl fmt::Debug for BodyReader {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("BodyReader")
+                .field("stream", &self.stream)
+                .field("buffered", &(self.chunk.len() - self.pos))
+                .finish()
+        }
+    }
+}
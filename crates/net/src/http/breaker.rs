@@ -0,0 +1,281 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::http::retry::is_network_error;
+use crate::http::{FetchFuture, Fetcher, Request, Response};
+use crate::Error;
+
+/// The state of the circuit of a [`CircuitBreaker`] for one origin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent as usual.
+    Closed,
+    /// Requests fail right away with [`Error::CircuitOpen`], until the cooldown is over.
+    Open,
+    /// The cooldown is over and a single trial request is let through. If it succeeds, the
+    /// circuit closes again; if it fails, it opens for another cooldown.
+    HalfOpen,
+}
+
+type StateCallback = Rc<dyn Fn(&str, CircuitState)>;
+
+/// Stops sending requests to an origin after too many consecutive failures.
+///
+/// A request fails when sending it fails with a network error, or when the server responds with
+/// a `5xx` status. After [`failure_threshold`](Self::failure_threshold) consecutive failures
+/// the circuit for that origin opens, and requests to it fail right away with
+/// [`Error::CircuitOpen`] until the [`cooldown`](Self::cooldown) is over. Then, one trial
+/// request decides whether the circuit closes again.
+///
+/// Each origin has its own circuit. Relative URLs all share the circuit of the current origin.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{CircuitBreaker, CircuitState, Client};
+/// use std::time::Duration;
+///
+/// # fn no_run() {
+/// let client = Client::new().with_circuit_breaker(
+///     CircuitBreaker::new()
+///         .failure_threshold(3)
+///         .cooldown(Duration::from_secs(10))
+///         .on_state_change(|origin, state| {
+///             if state == CircuitState::Open {
+///                 // show a banner that `origin` is unavailable
+///             }
+///         }),
+/// );
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    on_state_change: Option<StateCallback>,
+}
+
+impl CircuitBreaker {
+    /// Creates a circuit breaker opening after 5 consecutive failures, for 30s.
+    pub fn new() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+            on_state_change: None,
+        }
+    }
+
+    /// Sets how many consecutive failures open the circuit.
+    pub fn failure_threshold(mut self, failures: u32) -> Self {
+        self.failure_threshold = failures;
+        self
+    }
+
+    /// Sets how long the circuit stays open before a trial request is let through.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Calls `callback` with the origin and the new state whenever the circuit of an origin
+    /// changes state.
+    pub fn on_state_change(mut self, callback: impl Fn(&str, CircuitState) + 'static) -> Self {
+        self.on_state_change = Some(Rc::new(callback));
+        self
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("failure_threshold", &self.failure_threshold)
+            .field("cooldown", &self.cooldown)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The circuit for one origin.
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    failures: u32,
+    /// When the circuit last changed state, in milliseconds since the epoch.
+    since: f64,
+}
+
+impl Circuit {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            failures: 0,
+            since: 0.0,
+        }
+    }
+
+    /// Whether a request may be sent at `now`, and the state the circuit changed to, if any.
+    fn admit(&mut self, config: &CircuitBreaker, now: f64) -> (bool, Option<CircuitState>) {
+        match self.state {
+            CircuitState::Closed => (true, None),
+            // Also let another trial through when the previous one never completed.
+            CircuitState::Open | CircuitState::HalfOpen
+                if now - self.since >= config.cooldown.as_millis() as f64 =>
+            {
+                let changed = self.state != CircuitState::HalfOpen;
+                self.set_state(CircuitState::HalfOpen, now);
+                (true, changed.then_some(CircuitState::HalfOpen))
+            }
+            CircuitState::Open | CircuitState::HalfOpen => (false, None),
+        }
+    }
+
+    /// Records the outcome of a request, returning the state the circuit changed to, if any.
+    fn record(&mut self, config: &CircuitBreaker, failed: bool, now: f64) -> Option<CircuitState> {
+        let state = if failed {
+            self.failures = self.failures.saturating_add(1);
+            match self.state {
+                CircuitState::Closed if self.failures < config.failure_threshold => return None,
+                CircuitState::Open => return None,
+                _ => CircuitState::Open,
+            }
+        } else {
+            self.failures = 0;
+            CircuitState::Closed
+        };
+        if state == self.state {
+            return None;
+        }
+        self.set_state(state, now);
+        Some(state)
+    }
+
+    fn set_state(&mut self, state: CircuitState, now: f64) {
+        self.state = state;
+        self.since = now;
+    }
+}
+
+/// The origin `url` is sent to, or an empty string for relative URLs.
+pub(crate) fn origin(url: &str) -> String {
+    web_sys::Url::new(url)
+        .map(|url| url.origin())
+        .unwrap_or_default()
+}
+
+/// A [`Fetcher`] sending requests through `inner` guarded by a [`CircuitBreaker`].
+pub(crate) struct Breaker {
+    config: CircuitBreaker,
+    inner: Rc<dyn Fetcher>,
+    circuits: RefCell<HashMap<String, Circuit>>,
+}
+
+impl Breaker {
+    pub(crate) fn new(config: CircuitBreaker, inner: Rc<dyn Fetcher>) -> Self {
+        Self {
+            config,
+            inner,
+            circuits: RefCell::default(),
+        }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let origin = origin(&request.url());
+        let (admitted, changed) = self
+            .circuits
+            .borrow_mut()
+            .entry(origin.clone())
+            .or_insert_with(Circuit::new)
+            .admit(&self.config, js_sys::Date::now());
+        self.notify(&origin, changed);
+        if !admitted {
+            return Err(Error::CircuitOpen(origin));
+        }
+
+        let result = self.inner.fetch(request).await;
+        let failed = match &result {
+            Ok(response) => response.status() >= 500,
+            Err(e) if is_network_error(e) => true,
+            // e.g. aborted, which says nothing about the server
+            Err(_) => return result,
+        };
+        let changed = self
+            .circuits
+            .borrow_mut()
+            .entry(origin.clone())
+            .or_insert_with(Circuit::new)
+            .record(&self.config, failed, js_sys::Date::now());
+        self.notify(&origin, changed);
+        result
+    }
+
+    /// Calls the callback, if any, without holding on to `circuits`, so that it can send requests.
+    fn notify(&self, origin: &str, changed: Option<CircuitState>) {
+        if let (Some(callback), Some(state)) = (&self.config.on_state_change, changed) {
+            callback(origin, state);
+        }
+    }
+}
+
+impl Fetcher for Breaker {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let config = CircuitBreaker::new().failure_threshold(2);
+        let mut circuit = Circuit::new();
+        assert_eq!(circuit.record(&config, true, 0.0), None);
+        assert_eq!(circuit.record(&config, false, 0.0), None);
+        assert_eq!(circuit.record(&config, true, 0.0), None);
+        assert_eq!(
+            circuit.record(&config, true, 10.0),
+            Some(CircuitState::Open)
+        );
+        assert_eq!(circuit.admit(&config, 20.0), (false, None));
+    }
+
+    #[test]
+    fn trial_request_after_cooldown() {
+        let config = CircuitBreaker::new()
+            .failure_threshold(1)
+            .cooldown(Duration::from_millis(100));
+        let mut circuit = Circuit::new();
+        circuit.record(&config, true, 0.0);
+
+        assert_eq!(
+            circuit.admit(&config, 100.0),
+            (true, Some(CircuitState::HalfOpen))
+        );
+        // only one trial at a time
+        assert_eq!(circuit.admit(&config, 150.0), (false, None));
+        assert_eq!(
+            circuit.record(&config, true, 150.0),
+            Some(CircuitState::Open)
+        );
+        assert_eq!(circuit.admit(&config, 200.0), (false, None));
+
+        assert_eq!(
+            circuit.admit(&config, 250.0),
+            (true, Some(CircuitState::HalfOpen))
+        );
+        assert_eq!(
+            circuit.record(&config, false, 260.0),
+            Some(CircuitState::Closed)
+        );
+        assert_eq!(circuit.admit(&config, 260.0), (true, None));
+    }
+}
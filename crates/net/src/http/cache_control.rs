@@ -0,0 +1,351 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::http::conditional::parse_http_date;
+use crate::http::headers::split_list;
+use crate::http::{Headers, Response};
+
+/// The directives of a `Cache-Control` header.
+///
+/// Unknown directives are ignored, as are directives whose value is invalid, like a negative
+/// `max-age`.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::CacheControl;
+/// use std::time::Duration;
+///
+/// let cache_control = CacheControl::parse("public, max-age=600, stale-while-revalidate=30");
+/// assert_eq!(cache_control.max_age(), Some(Duration::from_secs(600)));
+/// assert!(!cache_control.no_store());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    max_age: Option<Duration>,
+    s_maxage: Option<Duration>,
+    max_stale: Option<Duration>,
+    min_fresh: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    stale_if_error: Option<Duration>,
+    no_cache: bool,
+    no_store: bool,
+    no_transform: bool,
+    must_revalidate: bool,
+    proxy_revalidate: bool,
+    public: bool,
+    private: bool,
+    immutable: bool,
+    only_if_cached: bool,
+}
+
+impl CacheControl {
+    /// Parses the value of a `Cache-Control` header, like `max-age=60, must-revalidate`.
+    pub fn parse(value: &str) -> Self {
+        Self::from_directives(split_list(value))
+    }
+
+    /// Parses all the `Cache-Control` headers in `headers`.
+    pub fn from_headers(headers: &Headers) -> Self {
+        Self::from_directives(headers.get_all("Cache-Control"))
+    }
+
+    fn from_directives(directives: Vec<String>) -> Self {
+        let mut cache_control = Self::default();
+        for directive in directives {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            let seconds = || {
+                value
+                    .and_then(|value| value.parse().ok())
+                    .map(Duration::from_secs)
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "max-age" => cache_control.max_age = seconds(),
+                "s-maxage" => cache_control.s_maxage = seconds(),
+                // a `max-stale` without a value accepts responses however stale they are
+                "max-stale" => {
+                    cache_control.max_stale = match value {
+                        Some(_) => seconds(),
+                        None => Some(Duration::MAX),
+                    }
+                }
+                "min-fresh" => cache_control.min_fresh = seconds(),
+                "stale-while-revalidate" => cache_control.stale_while_revalidate = seconds(),
+                "stale-if-error" => cache_control.stale_if_error = seconds(),
+                "no-cache" => cache_control.no_cache = true,
+                "no-store" => cache_control.no_store = true,
+                "no-transform" => cache_control.no_transform = true,
+                "must-revalidate" => cache_control.must_revalidate = true,
+                "proxy-revalidate" => cache_control.proxy_revalidate = true,
+                "public" => cache_control.public = true,
+                "private" => cache_control.private = true,
+                "immutable" => cache_control.immutable = true,
+                "only-if-cached" => cache_control.only_if_cached = true,
+                _ => {}
+            }
+        }
+        cache_control
+    }
+
+    /// The `max-age` directive: how long a response stays fresh, or how old a response a
+    /// request accepts.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// The `s-maxage` directive, which overrides `max-age` for shared caches only.
+    pub fn s_maxage(&self) -> Option<Duration> {
+        self.s_maxage
+    }
+
+    /// The `max-stale` directive of a request: how long past its freshness a response is
+    /// accepted. This is [`Duration::MAX`] when the directive has no value.
+    pub fn max_stale(&self) -> Option<Duration> {
+        self.max_stale
+    }
+
+    /// The `min-fresh` directive of a request: how long a response must still stay fresh.
+    pub fn min_fresh(&self) -> Option<Duration> {
+        self.min_fresh
+    }
+
+    /// The `stale-while-revalidate` directive: how long a stale response may be used while it
+    /// is revalidated in the background.
+    pub fn stale_while_revalidate(&self) -> Option<Duration> {
+        self.stale_while_revalidate
+    }
+
+    /// The `stale-if-error` directive: how long a stale response may be used when revalidating
+    /// it fails.
+    pub fn stale_if_error(&self) -> Option<Duration> {
+        self.stale_if_error
+    }
+
+    /// The `no-cache` directive: a stored response must be revalidated before each use.
+    pub fn no_cache(&self) -> bool {
+        self.no_cache
+    }
+
+    /// The `no-store` directive: the response must not be stored at all.
+    pub fn no_store(&self) -> bool {
+        self.no_store
+    }
+
+    /// The `no-transform` directive: intermediaries must not transform the body.
+    pub fn no_transform(&self) -> bool {
+        self.no_transform
+    }
+
+    /// The `must-revalidate` directive: a stale response must not be used without revalidating
+    /// it first.
+    pub fn must_revalidate(&self) -> bool {
+        self.must_revalidate
+    }
+
+    /// The `proxy-revalidate` directive, `must-revalidate` for shared caches only.
+    pub fn proxy_revalidate(&self) -> bool {
+        self.proxy_revalidate
+    }
+
+    /// The `public` directive: the response may be stored by shared caches.
+    pub fn public(&self) -> bool {
+        self.public
+    }
+
+    /// The `private` directive: the response must only be stored by the browser.
+    pub fn private(&self) -> bool {
+        self.private
+    }
+
+    /// The `immutable` directive: the response won't change while it is fresh.
+    pub fn immutable(&self) -> bool {
+        self.immutable
+    }
+
+    /// The `only-if-cached` directive of a request: only a stored response is wanted.
+    pub fn only_if_cached(&self) -> bool {
+        self.only_if_cached
+    }
+}
+
+impl Response {
+    /// Parses the `Cache-Control` headers of the response.
+    pub fn cache_control(&self) -> CacheControl {
+        CacheControl::from_headers(&self.headers())
+    }
+
+    /// Parses the `Age` header, how long the response was stored in caches on its way.
+    pub fn age(&self) -> Option<Duration> {
+        let age = self.headers().get("Age")?;
+        age.trim().parse().ok().map(Duration::from_secs)
+    }
+
+    /// Parses the `Date` header, the time the server generated the response.
+    pub fn date(&self) -> Option<SystemTime> {
+        parse_http_date(&self.headers().get("Date")?)
+    }
+
+    /// Parses the `Expires` header, the time the response becomes stale.
+    ///
+    /// An invalid date, like the commonly used `0`, means the response is already stale, and is
+    /// returned as the Unix epoch.
+    pub fn expires(&self) -> Option<SystemTime> {
+        let expires = self.headers().get("Expires")?;
+        Some(parse_http_date(&expires).unwrap_or(UNIX_EPOCH))
+    }
+
+    /// How long the response stays fresh after it was generated, following
+    /// [RFC 9111](https://www.rfc-editor.org/rfc/rfc9111#section-4.2.1) for a private cache.
+    ///
+    /// This is the `max-age` directive, or the time between the `Date` and `Expires` headers.
+    /// Without either, it is guessed as 10% of the time between the `Last-Modified` and `Date`
+    /// headers. `None` means the headers don't tell how long the response stays fresh.
+    pub fn freshness_lifetime(&self) -> Option<Duration> {
+        freshness_lifetime(
+            &self.cache_control(),
+            self.status(),
+            self.date(),
+            self.expires(),
+            self.last_modified(),
+        )
+    }
+
+    /// How old the response is at `now`, from its `Age` and `Date` headers.
+    pub fn current_age(&self, now: SystemTime) -> Duration {
+        current_age(self.age(), self.date(), now)
+    }
+
+    /// Whether the response can still be used at `now` without revalidating it.
+    ///
+    /// `SystemTime::now` panics on `wasm32-unknown-unknown`, so take the current time from
+    /// `js_sys::Date::now` instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Response;
+    /// # fn no_run(cached: Response) {
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let now = UNIX_EPOCH + Duration::from_millis(js_sys::Date::now() as u64);
+    /// if !cached.is_fresh(now) {
+    ///     // revalidate or fetch again
+    /// }
+    /// # }
+    /// ```
+    pub fn is_fresh(&self, now: SystemTime) -> bool {
+        let cache_control = self.cache_control();
+        if cache_control.no_cache() || cache_control.no_store() {
+            return false;
+        }
+        match self.freshness_lifetime() {
+            Some(lifetime) => self.current_age(now) < lifetime,
+            // without a `Date`, compare the `Expires` to the local clock instead
+            None => self.expires().is_some_and(|expires| now < expires),
+        }
+    }
+}
+
+/// The statuses whose responses may be cached with a heuristic freshness lifetime.
+const HEURISTICALLY_CACHEABLE: &[u16] =
+    &[200, 203, 204, 206, 300, 301, 308, 404, 405, 410, 414, 501];
+
+fn freshness_lifetime(
+    cache_control: &CacheControl,
+    status: u16,
+    date: Option<SystemTime>,
+    expires: Option<SystemTime>,
+    last_modified: Option<SystemTime>,
+) -> Option<Duration> {
+    if let Some(max_age) = cache_control.max_age() {
+        return Some(max_age);
+    }
+    if let Some(expires) = expires {
+        return Some(expires.duration_since(date?).unwrap_or_default());
+    }
+    if !cache_control.public() && !HEURISTICALLY_CACHEABLE.contains(&status) {
+        return None;
+    }
+    let since_modified = date?.duration_since(last_modified?).ok()?;
+    Some(since_modified / 10)
+}
+
+fn current_age(age: Option<Duration>, date: Option<SystemTime>, now: SystemTime) -> Duration {
+    let apparent_age = date
+        .and_then(|date| now.duration_since(date).ok())
+        .unwrap_or_default();
+    apparent_age.max(age.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn parses_directives() {
+        let cache_control = CacheControl::parse(
+            r#"Max-Age="60", no-cache="Set-Cookie", private, max-stale, s-maxage=-1"#,
+        );
+        assert_eq!(cache_control.max_age(), Some(Duration::from_secs(60)));
+        assert!(cache_control.no_cache());
+        assert!(cache_control.private());
+        assert_eq!(cache_control.max_stale(), Some(Duration::MAX));
+        assert_eq!(cache_control.s_maxage(), None);
+        assert!(!cache_control.public());
+        assert_eq!(CacheControl::parse(""), CacheControl::default());
+    }
+
+    #[test]
+    fn computes_freshness_lifetime() {
+        let max_age = CacheControl::parse("max-age=60");
+        let none = CacheControl::default();
+        // max-age wins over Expires
+        assert_eq!(
+            freshness_lifetime(&max_age, 200, Some(at(1000)), Some(at(2000)), None),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            freshness_lifetime(&none, 200, Some(at(1000)), Some(at(1300)), None),
+            Some(Duration::from_secs(300))
+        );
+        // an Expires in the past means stale
+        assert_eq!(
+            freshness_lifetime(&none, 200, Some(at(1000)), Some(UNIX_EPOCH), None),
+            Some(Duration::ZERO)
+        );
+        // heuristic
+        assert_eq!(
+            freshness_lifetime(&none, 200, Some(at(1000)), None, Some(at(0))),
+            Some(Duration::from_secs(100))
+        );
+        assert_eq!(
+            freshness_lifetime(&none, 302, Some(at(1000)), None, Some(at(0))),
+            None
+        );
+        assert_eq!(
+            freshness_lifetime(&none, 200, None, Some(at(1300)), None),
+            None
+        );
+        assert_eq!(freshness_lifetime(&none, 200, None, None, None), None);
+    }
+
+    #[test]
+    fn computes_current_age() {
+        assert_eq!(
+            current_age(Some(Duration::from_secs(30)), Some(at(1000)), at(1010)),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            current_age(Some(Duration::from_secs(5)), Some(at(1000)), at(1010)),
+            Duration::from_secs(10)
+        );
+        // clocks out of sync
+        assert_eq!(current_age(None, Some(at(1000)), at(900)), Duration::ZERO);
+    }
+}
@@ -0,0 +1,435 @@
+use std::fmt;
+use std::future::Future;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::http::breaker::Breaker;
+use crate::http::dedup::Dedup;
+#[cfg(feature = "har")]
+use crate::http::har::Record;
+#[cfg(feature = "network-information")]
+use crate::http::hints::NetworkHints;
+use crate::http::interceptor::Intercept;
+use crate::http::limit::Limit;
+#[cfg(feature = "offline-queue")]
+use crate::http::offline::Queue;
+#[cfg(feature = "persistent-cache")]
+use crate::http::persist::Persist;
+use crate::http::rate::Throttle;
+use crate::http::retry::Retry;
+use crate::http::revalidate::Revalidate;
+use crate::http::trace;
+#[cfg(feature = "har")]
+use crate::http::HarRecorder;
+#[cfg(feature = "offline-queue")]
+use crate::http::OfflineQueue;
+#[cfg(feature = "persistent-cache")]
+use crate::http::PersistentCache;
+use crate::http::{
+    CircuitBreaker, Decode, FetchFuture, Fetcher, GlobalFetch, LongPoll, Method, Next, Pages,
+    Polling, RateLimit, Request, RequestBuilder, Response, RetryPolicy,
+};
+use crate::Error;
+
+/// A client to send requests through a [`Fetcher`].
+///
+/// By default, requests are sent with the `fetch` of the global scope. Code which takes a
+/// `Client` rather than sending requests directly can be handed a client built with
+/// [`Client::with_fetcher`] in tests, answering requests without a network.
+///
+/// Cloning a `Client` is cheap; the clones share the same [`Fetcher`].
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::Client;
+/// # async fn no_run() {
+/// let client = Client::new();
+/// let resp = client.get("/path").send().await.unwrap();
+/// assert_eq!(resp.status(), 200);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Client {
+    fetcher: Rc<dyn Fetcher>,
+    base_url: Option<Rc<str>>,
+}
+
+impl Client {
+    /// Creates a client which sends requests with the `fetch` of the global scope.
+    pub fn new() -> Self {
+        Self::with_fetcher(GlobalFetch)
+    }
+
+    /// Creates a client which sends requests through `fetcher`.
+    pub fn with_fetcher(fetcher: impl Fetcher + 'static) -> Self {
+        Self {
+            fetcher: Rc::new(fetcher),
+            base_url: None,
+        }
+    }
+
+    /// Wraps the fetcher of this client in another one.
+    fn layer<F: Fetcher + 'static>(self, layer: impl FnOnce(Rc<dyn Fetcher>) -> F) -> Self {
+        Self {
+            fetcher: Rc::new(layer(self.fetcher)),
+            ..self
+        }
+    }
+
+    /// Resolves the relative URLs of requests built from this client against `base`, see
+    /// [`RequestBuilder::base_url`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Client;
+    /// # fn no_run() {
+    /// let api = Client::new().with_base_url("/app/api/");
+    /// // sent to `/app/api/users`
+    /// let request = api.get("users");
+    /// # }
+    /// ```
+    pub fn with_base_url(self, base: &str) -> Self {
+        Self {
+            base_url: Some(base.into()),
+            ..self
+        }
+    }
+
+    /// Adds an interceptor which every request sent through this client passes through.
+    ///
+    /// The interceptor receives the request and the [`Next`] step of the chain. It can modify
+    /// the request before passing it on with [`Next::run`], inspect or replace the response, or
+    /// answer the request itself without calling `next` at all. Interceptors run in the reverse
+    /// order they were added in: the last one added sees the request first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Client;
+    /// # fn no_run(token: &'static str) {
+    /// let client = Client::new().with_interceptor(move |request, next| async move {
+    ///     request
+    ///         .headers()
+    ///         .set("Authorization", &format!("Bearer {}", token));
+    ///     let response = next.run(request).await?;
+    ///     if response.status() == 401 {
+    ///         // redirect to the login page
+    ///     }
+    ///     Ok(response)
+    /// });
+    /// # }
+    /// ```
+    pub fn with_interceptor<F, Fut>(self, interceptor: F) -> Self
+    where
+        F: Fn(Request, Next) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.layer(|next| Intercept::new(interceptor, next))
+    }
+
+    /// Retries the requests sent through this client according to `policy`.
+    ///
+    /// Interceptors added before this one see every attempt, the ones added after it only see
+    /// the request once. See [`RetryPolicy`] for which requests are retried.
+    pub fn with_retry(self, policy: RetryPolicy) -> Self {
+        self.layer(|next| Retry::new(policy, next))
+    }
+
+    /// Stops sending requests to an origin whose previous requests kept failing.
+    ///
+    /// See [`CircuitBreaker`] for when the circuit opens and closes again.
+    pub fn with_circuit_breaker(self, breaker: CircuitBreaker) -> Self {
+        self.layer(|next| Breaker::new(breaker, next))
+    }
+
+    /// Limits the rate of requests sent through this client to each origin, and backs off when
+    /// an origin answers with `429 Too Many Requests`.
+    ///
+    /// Requests beyond the limit wait rather than fail. See [`RateLimit`] for how the limit
+    /// works.
+    pub fn with_rate_limit(self, limit: RateLimit) -> Self {
+        self.layer(|next| Throttle::new(limit, next))
+    }
+
+    /// Coalesces concurrent identical `GET` and `HEAD` requests sent through this client into one.
+    ///
+    /// Requests are identical when their method, URL and headers are. While such a request is in
+    /// flight, sending another one doesn't hit the network but waits for the first one, and
+    /// each caller gets its own copy of the response, whose body it can read independently.
+    /// Other methods are never coalesced.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Client;
+    /// # async fn no_run() {
+    /// let client = Client::new().with_deduplication();
+    /// // only one request for `/config` goes out
+    /// let (a, b) = futures::join!(client.get("/config").send(), client.get("/config").send());
+    /// # }
+    /// ```
+    pub fn with_deduplication(self) -> Self {
+        self.layer(Dedup::new)
+    }
+
+    /// Limits the number of requests sent through this client which are in flight at once.
+    ///
+    /// Requests beyond the limit are queued and sent in order as earlier ones complete; a request
+    /// counts as in flight until its response arrives. Browsers limit the connections per origin
+    /// themselves, but without any notion of which requests matter more. Sending bulk traffic,
+    /// like prefetches, through a limited client keeps room for the requests sent through other
+    /// clients.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Client;
+    /// # async fn no_run(urls: Vec<String>) {
+    /// let prefetch = Client::new().max_concurrent(4);
+    /// let responses = futures::future::join_all(urls.iter().map(|url| prefetch.get(url).send())).await;
+    /// # }
+    /// ```
+    pub fn max_concurrent(self, max: usize) -> Self {
+        assert!(max > 0, "`max_concurrent` must be at least 1");
+        self.layer(|next| Limit::new(max, next))
+    }
+
+    /// Revalidates `GET` responses carrying an `ETag` or `Last-Modified` header.
+    ///
+    /// The client keeps such responses in memory, by URL, and sends the next request for the
+    /// same URL with the matching `If-None-Match` or `If-Modified-Since` header. When the server
+    /// answers with `304 Not Modified`, the stored response is returned instead, so callers
+    /// always see the full body. Requests which already carry one of these headers are sent
+    /// as is.
+    ///
+    /// Note that these headers make cross-origin requests require a CORS preflight.
+    pub fn with_revalidation(self) -> Self {
+        self.layer(Revalidate::new)
+    }
+
+    /// Stores the `GET` responses of this client in IndexedDB, and answers requests with them
+    /// while they are fresh, even after a reload.
+    ///
+    /// See [`PersistentCache`] for which responses are stored and when they are used.
+    #[cfg(feature = "persistent-cache")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "persistent-cache")))]
+    pub fn with_persistent_cache(self, cache: PersistentCache) -> Self {
+        self.layer(|next| Persist::new(cache, next))
+    }
+
+    /// Stores the `POST`, `PUT`, `PATCH` and `DELETE` requests of this client which fail with a
+    /// network error in `queue`, and fails them with [`Error::Queued`] instead.
+    ///
+    /// The body of these requests is read into memory before they are sent, to be able to store
+    /// them. See [`OfflineQueue`] for how they are sent again.
+    #[cfg(feature = "offline-queue")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "offline-queue")))]
+    pub fn with_offline_queue(self, queue: OfflineQueue) -> Self {
+        self.layer(|next| Queue::new(queue, next))
+    }
+
+    /// Tells the server about the quality of the connection, with the `ECT`, `Downlink`, `RTT`
+    /// and `Save-Data` headers of the
+    /// [Client Hints](https://wicg.github.io/netinfo/#client-hints), so that it can send lighter
+    /// responses on slow connections.
+    ///
+    /// Headers already set on a request are kept. Browsers only send these hints themselves to
+    /// the servers asking for them, and not to other origins. Note that they make cross-origin
+    /// requests require a CORS preflight. See [`NetworkInformation`] for what they mean.
+    ///
+    /// [`NetworkInformation`]: crate::network::NetworkInformation
+    #[cfg(feature = "network-information")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "network-information")))]
+    pub fn with_network_hints(self) -> Self {
+        self.layer(NetworkHints::new)
+    }
+
+    /// Records the requests sent through this client and their responses with `recorder`.
+    ///
+    /// Interceptors added before this one see the requests as recorded, the ones added after it
+    /// can still change them. See [`HarRecorder`] for what is recorded.
+    #[cfg(feature = "har")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "har")))]
+    pub fn with_recorder(self, recorder: HarRecorder) -> Self {
+        self.layer(|next| Record::new(recorder, next))
+    }
+
+    /// Starts building a request to `url`, which will be sent through this client.
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let builder = RequestBuilder::new(url).method(method).client(self.clone());
+        match &self.base_url {
+            Some(base) => builder.base_url(base),
+            None => builder,
+        }
+    }
+
+    /// Starts building a [`GET`][Method::GET] request to `url`.
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// Starts building a [`POST`][Method::POST] request to `url`.
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// Starts building a [`PUT`][Method::PUT] request to `url`.
+    pub fn put(&self, url: &str) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    /// Starts building a [`DELETE`][Method::DELETE] request to `url`.
+    pub fn delete(&self, url: &str) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Starts building a [`PATCH`][Method::PATCH] request to `url`.
+    pub fn patch(&self, url: &str) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    /// Sends `request` and then the requests for the following pages, as given by the
+    /// `rel="next"` link of each response's `Link` header, like GitHub's API does.
+    ///
+    /// The next pages are requested with `GET` and the headers of `request`, minus its
+    /// `Authorization` header if they are on another origin. The stream ends after the first
+    /// response without a next link, or after the first error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Client;
+    /// use futures::TryStreamExt;
+    ///
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let client = Client::new();
+    /// let request = client.get("/repos/rustwasm/gloo/issues").build()?;
+    /// let mut pages = client.paginate(request);
+    /// while let Some(page) = pages.try_next().await? {
+    ///     let issues = page.text().await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn paginate(&self, request: Request) -> Pages {
+        Pages::new(self.clone(), request)
+    }
+
+    /// Sends `request` again and again, waiting `interval` after each response, and streams the
+    /// responses which changed.
+    ///
+    /// The requests are conditional: after a response with an `ETag` or `Last-Modified` header,
+    /// the server can answer `304 Not Modified`, and such responses are skipped. Errors are
+    /// yielded without ending the stream, and polling stops when the stream is dropped. `request`
+    /// is copied for each attempt, so it should have no body, or one which can be copied, see
+    /// [`Request::try_clone`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Client;
+    /// use futures::StreamExt;
+    /// use std::time::Duration;
+    ///
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let client = Client::new();
+    /// let request = client.get("/api/stats").build()?;
+    /// let mut updates = client.poll(request, Duration::from_secs(30));
+    /// while let Some(update) = updates.next().await {
+    ///     if let Ok(response) = update {
+    ///         let stats = response.text().await?;
+    ///         // update the dashboard
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn poll(&self, request: Request, interval: Duration) -> Polling {
+        Polling::new(self.clone(), request, interval)
+    }
+
+    /// Long-polls `request`, streaming the payloads of its responses decoded with `D`.
+    ///
+    /// See [`LongPoll`] for how the requests are sent and retried. `request` is copied for each
+    /// attempt, so it should have no body, or one which can be copied, see
+    /// [`Request::try_clone`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Client;
+    /// use futures::StreamExt;
+    ///
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let client = Client::new();
+    /// let request = client.get("/chat/messages?wait=30").build()?;
+    /// let mut messages = client.long_poll::<String>(request);
+    /// while let Some(message) = messages.next().await {
+    ///     match message {
+    ///         Ok(message) => { /* show it */ }
+    ///         Err(e) => { /* show that the chat is disconnected */ }
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn long_poll<D: Decode + 'static>(&self, request: Request) -> LongPoll<D> {
+        LongPoll::new(self.clone(), request)
+    }
+
+    /// Sends `request` through this client.
+    pub async fn send(&self, request: Request) -> Result<Response, Error> {
+        trace::send(&*self.fetcher, request).await
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fetcher for Client {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        self.fetcher.fetch(request)
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sends [`http::Request`]s through this client, so that `tower` middleware can be layered on top
+/// of it.
+///
+/// See the `TryFrom<http::Request<B>>` implementation of [`Request`] for the accepted body types.
+#[cfg(feature = "tower")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tower")))]
+impl<B: Into<wasm_bindgen::JsValue>> tower_service::Service<http::Request<B>> for Client {
+    type Response = Response;
+    type Error = Error;
+    type Future = FetchFuture<'static>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let client = self.clone();
+        let request: Result<Request, Error> = std::convert::TryFrom::try_from(request);
+        Box::pin(async move { client.send(request?).await })
+    }
+}
@@ -0,0 +1,186 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use js_sys::Uint8Array;
+use wasm_bindgen::JsValue;
+
+use crate::http::{Request, RequestBuilder, Response};
+use crate::Error;
+
+/// The future returned by [`Decode::decode`].
+pub type DecodeFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + 'a>>;
+
+/// A wire format responses can be decoded from, with [`Response::decode_as`].
+///
+/// Implementing it plugs formats this crate doesn't support, like XML or FlatBuffers, into
+/// responses without a feature of their own. The implementing type can be the decoded type
+/// itself, or a marker type for the format, with the decoded type as its
+/// [`Output`](Self::Output).
+///
+/// # Example
+///
+/// ```
+/// use gloo_net::http::{Decode, DecodeFuture, Response};
+///
+/// /// A list of lines.
+/// struct Lines(Vec<String>);
+///
+/// impl Decode for Lines {
+///     type Output = Self;
+///
+///     fn decode(response: Response) -> DecodeFuture<'static, Self> {
+///         Box::pin(async move {
+///             let text = response.text().await?;
+///             Ok(Lines(text.lines().map(String::from).collect()))
+///         })
+///     }
+/// }
+///
+/// # async fn no_run(response: Response) -> Result<(), gloo_net::Error> {
+/// let Lines(lines) = response.decode_as::<Lines>().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Decode {
+    /// The decoded value.
+    type Output;
+
+    /// Reads and decodes the body of `response`.
+    fn decode(response: Response) -> DecodeFuture<'static, Self::Output>;
+}
+
+/// Decodes the body as text, like [`Response::text`].
+impl Decode for String {
+    type Output = Self;
+
+    fn decode(response: Response) -> DecodeFuture<'static, Self> {
+        Box::pin(async move { response.text().await })
+    }
+}
+
+/// Reads the raw bytes of the body, like [`Response::binary`].
+impl Decode for Vec<u8> {
+    type Output = Self;
+
+    fn decode(response: Response) -> DecodeFuture<'static, Self> {
+        Box::pin(async move { response.binary().await })
+    }
+}
+
+impl Response {
+    /// Reads the response to completion, decoding it with the [`Decode`] implementation of `D`.
+    pub async fn decode_as<D: Decode>(self) -> Result<D::Output, Error> {
+        D::decode(self).await
+    }
+}
+
+/// A request body produced by [`Encode`], along with its `Content-Type`.
+#[derive(Debug, Clone)]
+pub struct EncodedBody {
+    body: JsValue,
+    content_type: Option<String>,
+}
+
+impl EncodedBody {
+    /// A body of the given `Content-Type`, like `application/xml`.
+    pub fn new(body: impl Into<JsValue>, content_type: &str) -> Self {
+        Self {
+            body: body.into(),
+            content_type: Some(content_type.to_string()),
+        }
+    }
+
+    /// A body whose `Content-Type` is left to the browser, like `FormData`, which is sent as
+    /// `multipart/form-data` with a boundary of the browser's choosing.
+    pub fn untyped(body: impl Into<JsValue>) -> Self {
+        Self {
+            body: body.into(),
+            content_type: None,
+        }
+    }
+
+    /// The body, as passed to [`RequestBuilder::body`].
+    pub fn body(&self) -> &JsValue {
+        &self.body
+    }
+
+    /// The `Content-Type` of the body.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+}
+
+/// A value which can be sent as a request body, with [`RequestBuilder::encoded`].
+///
+/// This is the counterpart of [`Decode`], plugging serializers this crate doesn't support into
+/// the request builder the same way [`RequestBuilder::json`] works.
+///
+/// # Example
+///
+/// ```
+/// use gloo_net::http::{Encode, EncodedBody, Request};
+///
+/// /// A list of lines.
+/// struct Lines(Vec<String>);
+///
+/// impl Encode for Lines {
+///     fn encode(&self) -> Result<EncodedBody, gloo_net::Error> {
+///         Ok(EncodedBody::new(self.0.join("\n"), "text/plain;charset=UTF-8"))
+///     }
+/// }
+///
+/// # async fn no_run() -> Result<(), gloo_net::Error> {
+/// let lines = Lines(vec!["one".to_string(), "two".to_string()]);
+/// let resp = Request::post("/lines").encoded(&lines)?.send().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Encode {
+    /// Encodes this value into a request body.
+    fn encode(&self) -> Result<EncodedBody, Error>;
+}
+
+/// Sends the string as `text/plain`, like [`RequestBuilder::text`].
+impl Encode for str {
+    fn encode(&self) -> Result<EncodedBody, Error> {
+        Ok(EncodedBody::new(self, "text/plain;charset=UTF-8"))
+    }
+}
+
+/// Sends the string as `text/plain`, like [`RequestBuilder::text`].
+impl Encode for String {
+    fn encode(&self) -> Result<EncodedBody, Error> {
+        self.as_str().encode()
+    }
+}
+
+/// Sends the bytes as `application/octet-stream`.
+impl Encode for [u8] {
+    fn encode(&self) -> Result<EncodedBody, Error> {
+        Ok(EncodedBody::new(
+            Uint8Array::from(self),
+            "application/octet-stream",
+        ))
+    }
+}
+
+/// Sends the bytes as `application/octet-stream`.
+impl Encode for Vec<u8> {
+    fn encode(&self) -> Result<EncodedBody, Error> {
+        self.as_slice().encode()
+    }
+}
+
+impl RequestBuilder {
+    /// Sets `value`, encoded with its [`Encode`] implementation, as the request body.
+    ///
+    /// This also sets the `Content-Type` header to the one of the encoded body, unless a content
+    /// type was set before.
+    pub fn encoded<E: Encode + ?Sized>(self, value: &E) -> Result<Request, Error> {
+        let EncodedBody { body, content_type } = value.encode()?;
+        match content_type {
+            Some(content_type) => self.default_content_type(&content_type).body(body),
+            None => self.body(body),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// An entity tag, as found in the `ETag` header of a response and sent back in the `If-Match` and
+/// `If-None-Match` headers of conditional requests.
+///
+/// # Example
+///
+/// Updating a resource only if nobody else changed it since it was read:
+///
+/// ```
+/// # use gloo_net::http::Request;
+/// # async fn no_run() -> Result<(), gloo_net::Error> {
+/// let resp = Request::get("/documents/1").send().await?;
+/// let etag = resp.etag().expect("the server sends an ETag");
+/// let document = resp.text().await?;
+///
+/// let resp = Request::put("/documents/1")
+///     .if_match(&etag)
+///     .text(&format!("{}!", document))?
+///     .send()
+///     .await?;
+/// if resp.status() == 412 {
+///     // someone else changed the document in the meantime
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ETag {
+    tag: String,
+    weak: bool,
+}
+
+impl ETag {
+    /// Creates a strong entity tag, which changes whenever the bytes of the resource do.
+    pub fn strong(tag: &str) -> Self {
+        Self {
+            tag: tag.to_string(),
+            weak: false,
+        }
+    }
+
+    /// Creates a weak entity tag, which only changes when the meaning of the resource does.
+    pub fn weak(tag: &str) -> Self {
+        Self {
+            tag: tag.to_string(),
+            weak: true,
+        }
+    }
+
+    /// Parses an entity tag like `"xyzzy"` or `W/"xyzzy"`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, value),
+        };
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        if tag.contains('"') {
+            return None;
+        }
+        Some(Self {
+            tag: tag.to_string(),
+            weak,
+        })
+    }
+
+    /// The opaque tag, without the quotes and the weakness indicator.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Whether this is a weak entity tag.
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Whether both tags are strong and equal, as required by `If-Match`.
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Whether both tags are equal, regardless of their weakness, as used by `If-None-Match`.
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// Formats `time` as an HTTP date, like `Wed, 21 Oct 2015 07:28:00 GMT`.
+pub(crate) fn format_http_date(time: SystemTime) -> String {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as f64;
+    String::from(js_sys::Date::new(&millis.into()).to_utc_string())
+}
+
+/// Parses an HTTP date, like the value of a `Last-Modified` header.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let millis = js_sys::Date::parse(value.trim());
+    if millis.is_nan() || millis < 0.0 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::from_millis(millis as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_etags() {
+        assert_eq!(ETag::parse(r#""xyzzy""#), Some(ETag::strong("xyzzy")));
+        assert_eq!(ETag::parse(r#" W/"xyzzy" "#), Some(ETag::weak("xyzzy")));
+        assert_eq!(ETag::parse(r#""""#), Some(ETag::strong("")));
+        assert_eq!(ETag::parse("xyzzy"), None);
+        assert_eq!(ETag::parse(r#"w/"xyzzy""#), None);
+        assert_eq!(ETag::parse(r#""xy"zzy""#), None);
+        assert_eq!(ETag::weak("1").to_string(), r#"W/"1""#);
+    }
+
+    #[test]
+    fn compares_etags() {
+        let (strong, weak) = (ETag::strong("1"), ETag::weak("1"));
+        assert!(strong.strong_eq(&ETag::strong("1")));
+        assert!(!strong.strong_eq(&weak));
+        assert!(strong.weak_eq(&weak));
+        assert!(!weak.weak_eq(&ETag::weak("2")));
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt::{self, Write};
+
+use crate::http::{Headers, Method, Request};
+use crate::Error;
+
+/// What redacted header values are replaced with.
+const REDACTED: &str = "[REDACTED]";
+
+impl Request {
+    /// Renders the request as a `curl` command, e.g. to reproduce it from a terminal or in a bug
+    /// report.
+    ///
+    /// The command has the method, the URL, the headers and the body of the request. The values
+    /// of [sensitive](Headers::is_sensitive) headers are redacted, see
+    /// [`to_curl_with`](Self::to_curl_with) to choose which ones. Text bodies are included as
+    /// is, while binary ones are left out with a note.
+    ///
+    /// The body is read from a copy of the request, which can still be sent afterwards. This
+    /// errors if the body has already been used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let request = Request::post("https://example.com/items")
+    ///     .header("Authorization", "Bearer hunter2")
+    ///     .json(&[1, 2, 3])?;
+    /// let curl = request.to_curl().await?;
+    /// // curl 'https://example.com/items' \
+    /// //   -H 'authorization: [REDACTED]' \
+    /// //   -H 'content-type: application/json' \
+    /// //   --data-raw '[1,2,3]'
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn to_curl(&self) -> Result<String, Error> {
+        self.to_curl_with(Headers::is_sensitive).await
+    }
+
+    /// Renders the request as a `curl` command like [`to_curl`](Self::to_curl), redacting the
+    /// values of the headers for which `redact` returns `true`, given their name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::{Headers, Request};
+    /// # async fn no_run(request: Request) -> Result<(), gloo_net::Error> {
+    /// let curl = request
+    ///     .to_curl_with(|name| Headers::is_sensitive(name) || name == "x-session")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn to_curl_with(&self, redact: impl Fn(&str) -> bool) -> Result<String, Error> {
+        let body = match self.body() {
+            Some(_) => Some(self.try_clone()?.binary().await?),
+            None => None,
+        };
+        Ok(render(
+            &self.method(),
+            &self.url(),
+            &headers(self, redact),
+            body.as_deref(),
+        ))
+    }
+}
+
+/// Renders the request as a `curl` command, without its body, which can't be read
+/// synchronously; see [`Request::to_curl`] for the full command. The values of
+/// [sensitive](Headers::is_sensitive) headers are redacted.
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers = headers(self, Headers::is_sensitive);
+        f.write_str(&render(&self.method(), &self.url(), &headers, None))
+    }
+}
+
+/// The headers of `request`, with the values of those matching `redact` replaced.
+fn headers(request: &Request, redact: impl Fn(&str) -> bool) -> Vec<(String, String)> {
+    request
+        .headers()
+        .entries()
+        .map(|(name, value)| match redact(&name) {
+            true => (name, REDACTED.to_string()),
+            false => (name, value),
+        })
+        .collect()
+}
+
+fn render(method: &Method, url: &str, headers: &[(String, String)], body: Option<&[u8]>) -> String {
+    let mut command = format!("curl {}", quote(url));
+    let mut arg = |arg: &str| {
+        let _ = write!(command, " \\\n  {}", arg);
+    };
+    let text = body.map(std::str::from_utf8);
+    // `curl` sends a `GET` by default, and a `POST` when given data.
+    match (method, matches!(text, Some(Ok(_)))) {
+        (&Method::GET, false) | (&Method::POST, true) => {}
+        (&Method::HEAD, _) => arg("--head"),
+        (method, _) => arg(&format!("-X {}", method)),
+    }
+    for (name, value) in headers {
+        arg(&format!("-H {}", quote(&format!("{}: {}", name, value))));
+    }
+    match text {
+        Some(Ok(text)) => arg(&format!("--data-raw {}", quote(text))),
+        Some(Err(_)) => {
+            let _ = write!(
+                command,
+                " # {} bytes of binary body left out",
+                body.map_or(0, <[u8]>::len)
+            );
+        }
+        None => {}
+    }
+    command
+}
+
+/// Quotes `arg` for a POSIX shell.
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_curl_commands() {
+        let headers = [
+            ("authorization".to_string(), REDACTED.to_string()),
+            ("content-type".to_string(), "text/plain".to_string()),
+        ];
+        assert_eq!(
+            render(&Method::GET, "https://example.com/", &[], None),
+            "curl 'https://example.com/'"
+        );
+        assert_eq!(
+            render(
+                &Method::PUT,
+                "https://example.com/notes/1",
+                &headers,
+                Some(b"it's done")
+            ),
+            "curl 'https://example.com/notes/1' \\\n  -X PUT \\\n  \
+             -H 'authorization: [REDACTED]' \\\n  -H 'content-type: text/plain' \\\n  \
+             --data-raw 'it'\\''s done'"
+        );
+        assert_eq!(
+            render(&Method::POST, "https://example.com/", &[], Some(&[0xff, 0])),
+            "curl 'https://example.com/' \\\n  -X POST # 2 bytes of binary body left out"
+        );
+        assert_eq!(
+            render(&Method::HEAD, "https://example.com/", &[], None),
+            "curl 'https://example.com/' \\\n  --head"
+        );
+    }
+
+    #[test]
+    fn sensitive_headers() {
+        assert!(Headers::is_sensitive("Authorization"));
+        assert!(Headers::is_sensitive("cookie"));
+        assert!(Headers::is_sensitive("X-CSRF-Token"));
+        assert!(Headers::is_sensitive("x-api-key"));
+        assert!(!Headers::is_sensitive("Content-Type"));
+        assert!(!Headers::is_sensitive("Accept"));
+    }
+}
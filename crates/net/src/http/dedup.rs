@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::http::{FetchFuture, Fetcher, Method, Request, Response};
+use crate::{fetch_to_error, js_to_error, Error};
+use futures_channel::oneshot;
+
+/// Identifies identical requests: the method, URL and headers.
+type Key = (Method, String, Vec<(String, String)>);
+
+type Waiters = Vec<oneshot::Sender<Result<web_sys::Response, Error>>>;
+
+/// A [`Fetcher`] coalescing concurrent identical `GET` and `HEAD` requests into one.
+///
+/// The first request is sent through `inner`; the identical requests made while it is in flight
+/// wait for it and each get a clone of its response.
+pub(crate) struct Dedup {
+    inner: Rc<dyn Fetcher>,
+    in_flight: Rc<RefCell<HashMap<Key, Waiters>>>,
+}
+
+impl Dedup {
+    pub(crate) fn new(inner: Rc<dyn Fetcher>) -> Self {
+        Self {
+            inner,
+            in_flight: Rc::default(),
+        }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let method = request.method();
+        if method != Method::GET && method != Method::HEAD {
+            return self.inner.fetch(request).await;
+        }
+
+        let mut headers: Vec<_> = request.headers().entries().collect();
+        headers.sort();
+        let key = (method, request.url(), headers);
+
+        let waiting = self.in_flight.borrow_mut().get_mut(&key).map(|waiters| {
+            let (sender, receiver) = oneshot::channel();
+            waiters.push(sender);
+            receiver
+        });
+        if let Some(receiver) = waiting {
+            let download_progress = request.download_progress();
+            return match receiver.await {
+                Ok(result) => {
+                    result.map(|raw| Response::from(raw).with_download_progress(download_progress))
+                }
+                // The first request was dropped before it completed, so send this one instead.
+                Err(oneshot::Canceled) => self.inner.fetch(request).await,
+            };
+        }
+
+        self.in_flight.borrow_mut().insert(key.clone(), Vec::new());
+        let guard = InFlight {
+            in_flight: &self.in_flight,
+            key,
+        };
+        let result = self.inner.fetch(request).await;
+        for waiter in guard.finish() {
+            let shared = match &result {
+                Ok(response) => response.clone_raw(),
+                Err(e) => Err(duplicate_error(e)),
+            };
+            let _ = waiter.send(shared);
+        }
+        result
+    }
+}
+
+impl Fetcher for Dedup {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+/// Removes an in-flight request when it completes or is dropped.
+struct InFlight<'a> {
+    in_flight: &'a RefCell<HashMap<Key, Waiters>>,
+    key: Key,
+}
+
+impl InFlight<'_> {
+    fn finish(self) -> Waiters {
+        self.in_flight
+            .borrow_mut()
+            .remove(&self.key)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.in_flight.borrow_mut().remove(&self.key);
+    }
+}
+
+/// Recreates an error to hand out to every waiting caller, since [`Error`] can't be cloned.
+fn duplicate_error(error: &Error) -> Error {
+    match error {
+        Error::JsError(e) | Error::Network(e) => {
+            let js_error = js_sys::Error::new(&e.message);
+            js_error.set_name(&e.name);
+            match error {
+                Error::Network(_) => fetch_to_error(js_error.into()),
+                _ => js_to_error(js_error.into()),
+            }
+        }
+        Error::Aborted => Error::Aborted,
+        Error::Timeout => Error::Timeout,
+        Error::StatusError(e) => Error::StatusError(e.clone()),
+        other => Error::GlooError(other.to_string()),
+    }
+}
@@ -0,0 +1,145 @@
+use crate::http::headers::{split_unquoted, unquote};
+use crate::http::Response;
+
+impl Response {
+    /// The file name the server suggests saving the body as, from the `filename*` or `filename`
+    /// parameter of the `Content-Disposition` header.
+    ///
+    /// The [RFC 5987](https://www.rfc-editor.org/rfc/rfc5987) encoded `filename*` is preferred,
+    /// as it can hold any character. Any directory part is stripped from the name, so it is safe
+    /// to use as is. This is also returned for `inline` responses, which browsers save under the
+    /// same name.
+    ///
+    /// The header of a cross-origin response is only visible if the server lists it in
+    /// `Access-Control-Expose-Headers`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let resp = Request::get("/reports/latest").send().await?;
+    /// let file_name = resp
+    ///     .attachment_filename()
+    ///     .unwrap_or_else(|| "report.pdf".to_string());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn attachment_filename(&self) -> Option<String> {
+        filename(&self.headers().get("Content-Disposition")?)
+    }
+}
+
+/// Extracts the file name of a `Content-Disposition` header value.
+fn filename(value: &str) -> Option<String> {
+    let mut filename = None;
+    let mut encoded = None;
+    // the first part is the disposition type
+    for param in split_unquoted(value, ';').into_iter().skip(1) {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => continue,
+        };
+        if name.eq_ignore_ascii_case("filename*") {
+            encoded = encoded.or_else(|| decode_ext_value(value));
+        } else if name.eq_ignore_ascii_case("filename") {
+            filename = filename.or_else(|| Some(unquote(value)));
+        }
+    }
+    encoded
+        .into_iter()
+        .chain(filename)
+        .find_map(|name| sanitize(&name))
+}
+
+/// Decodes an RFC 5987 `ext-value`, like `UTF-8''%e2%82%ac%20rates.pdf`.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let (charset, _language, encoded) = (parts.next()?, parts.next()?, parts.next()?);
+    let bytes = percent_decode(encoded)?;
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        None
+    }
+}
+
+fn percent_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    Some(bytes)
+}
+
+/// Strips any directory from `name`, rejecting names which are empty or refer to a directory.
+fn sanitize(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    match name {
+        "" | "." | ".." => None,
+        name => Some(name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_filenames() {
+        assert_eq!(
+            filename(r#"attachment; filename="annual report.pdf""#).as_deref(),
+            Some("annual report.pdf")
+        );
+        assert_eq!(
+            filename("attachment; filename=plain.txt").as_deref(),
+            Some("plain.txt")
+        );
+        // `filename*` wins, wherever it is
+        assert_eq!(
+            filename(
+                r#"attachment; filename*=UTF-8''%e2%82%ac%20rates.pdf; filename="EUR rates.pdf""#
+            )
+            .as_deref(),
+            Some("€ rates.pdf")
+        );
+        assert_eq!(
+            filename("attachment; FILENAME*=iso-8859-1'en'%A3%20rates.pdf").as_deref(),
+            Some("£ rates.pdf")
+        );
+        // an invalid `filename*` falls back to `filename`
+        assert_eq!(
+            filename(r#"attachment; filename*=UTF-8''%zz; filename="ok.txt""#).as_deref(),
+            Some("ok.txt")
+        );
+        assert_eq!(
+            filename(r#"inline; filename="a;b.txt""#).as_deref(),
+            Some("a;b.txt")
+        );
+        assert_eq!(filename("attachment"), None);
+    }
+
+    #[test]
+    fn strips_directories() {
+        assert_eq!(
+            filename(r#"attachment; filename="../../etc/passwd""#).as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(
+            filename(r#"attachment; filename="C:\\Windows\\evil.exe""#).as_deref(),
+            Some("evil.exe")
+        );
+        assert_eq!(filename(r#"attachment; filename="..""#), None);
+        assert_eq!(filename(r#"attachment; filename="dir/""#), None);
+    }
+}
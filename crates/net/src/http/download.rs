@@ -0,0 +1,641 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Poll, Waker};
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::Stream;
+use js_sys::{Array, Uint8Array};
+use serde::{Deserialize, Serialize};
+
+use crate::http::progress::progress_channel;
+use crate::http::{Client, Priority, Progress, ProgressStream, Response};
+use crate::{js_to_error, Error};
+
+/// Runs downloads in the background, a few at a time, with pause and resume.
+///
+/// Downloads are started in order of [`Priority`], then in the order they were added, with at
+/// most [`max_concurrent`](Self::max_concurrent) of them running at once. A paused download is
+/// resumed where it stopped, with a `Range` request, as long as the server supports them and the
+/// resource didn't change in the meantime; otherwise it starts over. The state of a paused or
+/// failed download can be saved as a [`PartialDownload`], e.g. in IndexedDB, to resume it after a
+/// reload.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{DownloadManager, Priority};
+/// use futures::StreamExt;
+///
+/// # async fn no_run() -> Result<(), gloo_net::Error> {
+/// let manager = DownloadManager::new().max_concurrent(2);
+/// let video = manager.download("/media/video.mp4", Priority::Low);
+/// let subtitles = manager.download("/media/video.vtt", Priority::High);
+///
+/// let mut progress = video.progress_stream();
+/// while let Some(progress) = progress.next().await {
+///     // update a progress bar, e.g. with `progress.fraction()`
+/// }
+/// let blob = video.finish().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DownloadManager {
+    inner: Rc<RefCell<Manager>>,
+}
+
+struct Manager {
+    client: Client,
+    max_concurrent: usize,
+    running: usize,
+    downloads: Vec<Rc<RefCell<State>>>,
+    listeners: Vec<mpsc::UnboundedSender<Progress>>,
+}
+
+impl DownloadManager {
+    /// Creates a manager running up to 3 downloads at once, with a default [`Client`].
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(Manager {
+                client: Client::new(),
+                max_concurrent: 3,
+                running: 0,
+                downloads: Vec::new(),
+                listeners: Vec::new(),
+            })),
+        }
+    }
+
+    /// Sets the client the downloads are sent through.
+    pub fn client(self, client: Client) -> Self {
+        self.inner.borrow_mut().client = client;
+        self
+    }
+
+    /// Sets how many downloads may run at once.
+    pub fn max_concurrent(self, max_concurrent: usize) -> Self {
+        self.inner.borrow_mut().max_concurrent = max_concurrent.max(1);
+        self.pump();
+        self
+    }
+
+    /// Downloads `url` once the downloads with a higher priority have started.
+    pub fn download(&self, url: &str, priority: Priority) -> Download {
+        self.add(State::new(url, priority))
+    }
+
+    /// Resumes a download saved with [`Download::partial`].
+    pub fn resume(&self, partial: PartialDownload, priority: Priority) -> Download {
+        let mut state = State::new(&partial.url, priority);
+        state.received = partial.bytes;
+        state.total = partial.total;
+        state.etag = partial.etag;
+        self.add(state)
+    }
+
+    fn add(&self, state: State) -> Download {
+        let state = Rc::new(RefCell::new(state));
+        self.inner.borrow_mut().downloads.push(state.clone());
+        self.pump();
+        Download {
+            state,
+            manager: self.clone(),
+        }
+    }
+
+    /// The downloads added to this manager, which were not removed yet.
+    pub fn downloads(&self) -> Vec<Download> {
+        self.inner
+            .borrow()
+            .downloads
+            .iter()
+            .map(|state| Download {
+                state: state.clone(),
+                manager: self.clone(),
+            })
+            .collect()
+    }
+
+    /// Forgets the downloads which completed, failed or were cancelled, so that they no longer
+    /// count in the [`progress`](Self::progress).
+    pub fn remove_finished(&self) {
+        self.inner
+            .borrow_mut()
+            .downloads
+            .retain(|state| !state.borrow().status.is_finished());
+    }
+
+    /// The combined progress of all the downloads, except the cancelled ones.
+    ///
+    /// The total is only known when it is known for every download.
+    pub fn progress(&self) -> Progress {
+        let manager = self.inner.borrow();
+        let mut progress = Progress {
+            loaded: 0,
+            total: Some(0),
+        };
+        for state in &manager.downloads {
+            let state = state.borrow();
+            if state.status == DownloadStatus::Cancelled {
+                continue;
+            }
+            let own = state.progress();
+            progress.loaded += own.loaded;
+            progress.total = progress.total.zip(own.total).map(|(a, b)| a + b);
+        }
+        progress
+    }
+
+    /// A stream of the combined [`progress`](Self::progress), updated whenever a download
+    /// receives data.
+    pub fn progress_stream(&self) -> ProgressStream {
+        let (sender, stream) = progress_channel();
+        self.inner.borrow_mut().listeners.push(sender);
+        stream
+    }
+
+    /// Starts the next queued downloads while there are free slots.
+    fn pump(&self) {
+        loop {
+            let (next, generation) = {
+                let mut manager = self.inner.borrow_mut();
+                if manager.running >= manager.max_concurrent {
+                    return;
+                }
+                let next = manager
+                    .downloads
+                    .iter()
+                    .filter(|state| state.borrow().status == DownloadStatus::Queued)
+                    .min_by_key(|state| rank(state.borrow().priority))
+                    .cloned();
+                match next {
+                    Some(next) => {
+                        manager.running += 1;
+                        let generation = {
+                            let mut state = next.borrow_mut();
+                            state.status = DownloadStatus::Running;
+                            state.generation += 1;
+                            state.generation
+                        };
+                        (next, generation)
+                    }
+                    None => return,
+                }
+            };
+            let manager = self.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                manager.run(next, generation).await;
+            });
+        }
+    }
+
+    /// Runs the download, as its run `generation`.
+    async fn run(self, state: Rc<RefCell<State>>, generation: u64) {
+        let client = self.inner.borrow().client.clone();
+        let result = self.transfer(&client, &state, generation).await;
+        {
+            let mut state = state.borrow_mut();
+            match result {
+                // paused and resumed before this run stopped, the new run takes over
+                _ if state.generation != generation => {}
+                Ok(true) => state.complete(),
+                // paused or cancelled
+                Ok(false) => {}
+                Err(e) if state.status == DownloadStatus::Running => {
+                    let error = format!("download of `{}` failed: {}", state.url, e);
+                    state.fail(error);
+                }
+                Err(_) => {}
+            }
+        }
+        self.inner.borrow_mut().running -= 1;
+        self.pump();
+    }
+
+    /// Receives the rest of the download, resolving to whether it completed rather than being
+    /// interrupted.
+    async fn transfer(
+        &self,
+        client: &Client,
+        state: &Rc<RefCell<State>>,
+        generation: u64,
+    ) -> Result<bool, Error> {
+        let request = {
+            let state = state.borrow();
+            let mut builder = client.get(&state.url).priority(state.priority);
+            if !state.received.is_empty() {
+                builder = builder.header("Range", &format!("bytes={}-", state.received.len()));
+                if let Some(etag) = &state.etag {
+                    builder = builder.header("If-Range", etag);
+                }
+            }
+            builder.build()?
+        };
+        let response = match request.send().await? {
+            response if response.status() == 416 => response,
+            response => response.error_for_status()?,
+        };
+        if !state.borrow().is_running(generation) {
+            return Ok(false);
+        }
+        if !self.start(state, &response)? {
+            return Ok(true);
+        }
+
+        let mut body = response.into_stream()?;
+        loop {
+            let chunk = std::future::poll_fn(|cx| {
+                // Stop reading as soon as the download is paused or cancelled.
+                if !state.borrow().is_running(generation) {
+                    return Poll::Ready(None);
+                }
+                state.borrow_mut().waker = Some(cx.waker().clone());
+                Pin::new(&mut body).poll_next(cx).map(Some)
+            })
+            .await;
+            match chunk {
+                None => return Ok(false),
+                Some(None) => return Ok(true),
+                Some(Some(chunk)) => {
+                    state.borrow_mut().received.extend_from_slice(&chunk?);
+                    self.notify(state);
+                }
+            }
+        }
+    }
+
+    /// Handles the headers of the response to a download, resolving to whether its body must be
+    /// read.
+    fn start(&self, state: &Rc<RefCell<State>>, response: &Response) -> Result<bool, Error> {
+        let mut state = state.borrow_mut();
+        let offset = state.received.len() as u64;
+        let length = response.content_length();
+        match response.status() {
+            206 => match response
+                .headers()
+                .get("Content-Range")
+                .as_deref()
+                .and_then(content_range)
+            {
+                Some((start, total)) if start == offset => {
+                    state.total = total.or_else(|| length.map(|len| offset + len));
+                }
+                _ => {
+                    return Err(Error::GlooError(
+                        "the server sent another range than the one requested".to_string(),
+                    ))
+                }
+            },
+            // the resource is already complete
+            416 if offset > 0 && state.total == Some(offset) => return Ok(false),
+            416 => {
+                return Err(Error::GlooError(
+                    "the server can't send the rest of the download".to_string(),
+                ))
+            }
+            // the server ignored the range, or the resource changed: start over
+            _ => {
+                state.received.clear();
+                state.total = length;
+            }
+        }
+        state.etag = response.headers().get("ETag");
+        Ok(true)
+    }
+
+    /// Reports the progress of `state` to its listeners and the ones of this manager.
+    fn notify(&self, state: &Rc<RefCell<State>>) {
+        {
+            let mut state = state.borrow_mut();
+            let progress = state.progress();
+            state
+                .listeners
+                .retain(|listener| listener.unbounded_send(progress).is_ok());
+        }
+        let total = self.progress();
+        self.inner
+            .borrow_mut()
+            .listeners
+            .retain(|listener| listener.unbounded_send(total).is_ok());
+    }
+}
+
+impl Default for DownloadManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DownloadManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let manager = self.inner.borrow();
+        f.debug_struct("DownloadManager")
+            .field("max_concurrent", &manager.max_concurrent)
+            .field("running", &manager.running)
+            .field("downloads", &manager.downloads.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Orders priorities from the most urgent.
+fn rank(priority: Priority) -> u8 {
+    match priority {
+        Priority::High => 0,
+        Priority::Auto => 1,
+        Priority::Low => 2,
+    }
+}
+
+/// Parses a `Content-Range` header like `bytes 100-199/1000` into its start and total length.
+fn content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (range, total) = range.split_once('/')?;
+    let (start, _end) = range.split_once('-')?;
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+    Some((start.trim().parse().ok()?, total))
+}
+
+/// Where a [`Download`] is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadStatus {
+    /// Waiting for a free slot.
+    Queued,
+    /// Receiving data.
+    Running,
+    /// Paused with [`Download::pause`].
+    Paused,
+    /// All the data was received.
+    Completed,
+    /// Failed, see [`Download::finish`] for the error.
+    Failed,
+    /// Cancelled with [`Download::cancel`].
+    Cancelled,
+}
+
+impl DownloadStatus {
+    /// Whether the download is over, whether it completed or not.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+struct State {
+    url: String,
+    priority: Priority,
+    status: DownloadStatus,
+    /// Incremented whenever the download starts running, so that a run which was paused
+    /// doesn't keep receiving data once the download was resumed.
+    generation: u64,
+    received: Vec<u8>,
+    total: Option<u64>,
+    etag: Option<String>,
+    blob: Option<web_sys::Blob>,
+    error: Option<String>,
+    waker: Option<Waker>,
+    listeners: Vec<mpsc::UnboundedSender<Progress>>,
+    waiting: Vec<oneshot::Sender<()>>,
+}
+
+impl State {
+    fn new(url: &str, priority: Priority) -> Self {
+        Self {
+            url: url.to_string(),
+            priority,
+            status: DownloadStatus::Queued,
+            generation: 0,
+            received: Vec::new(),
+            total: None,
+            etag: None,
+            blob: None,
+            error: None,
+            waker: None,
+            listeners: Vec::new(),
+            waiting: Vec::new(),
+        }
+    }
+
+    fn progress(&self) -> Progress {
+        match &self.blob {
+            Some(blob) => {
+                let size = blob.size() as u64;
+                Progress {
+                    loaded: size,
+                    total: Some(size),
+                }
+            }
+            None => Progress {
+                loaded: self.received.len() as u64,
+                total: self.total,
+            },
+        }
+    }
+
+    /// Whether run `generation` is still the one receiving the download.
+    fn is_running(&self, generation: u64) -> bool {
+        self.status == DownloadStatus::Running && self.generation == generation
+    }
+
+    /// Moves to `status`, and stops receiving data if the download was running.
+    fn interrupt(&mut self, status: DownloadStatus) {
+        self.status = status;
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn complete(&mut self) {
+        let chunk = Uint8Array::from(self.received.as_slice());
+        match web_sys::Blob::new_with_u8_array_sequence(&Array::of1(&chunk)) {
+            Ok(blob) => {
+                self.blob = Some(blob);
+                self.received = Vec::new();
+                self.finish(DownloadStatus::Completed);
+            }
+            Err(e) => self.fail(js_to_error(e).to_string()),
+        }
+    }
+
+    fn fail(&mut self, error: String) {
+        self.error = Some(error);
+        self.finish(DownloadStatus::Failed);
+    }
+
+    fn finish(&mut self, status: DownloadStatus) {
+        self.interrupt(status);
+        // ends the progress streams
+        self.listeners.clear();
+        for waiting in self.waiting.drain(..) {
+            let _ = waiting.send(());
+        }
+    }
+}
+
+/// A handle to a download of a [`DownloadManager`].
+#[derive(Clone)]
+pub struct Download {
+    state: Rc<RefCell<State>>,
+    manager: DownloadManager,
+}
+
+impl Download {
+    /// The URL being downloaded.
+    pub fn url(&self) -> String {
+        self.state.borrow().url.clone()
+    }
+
+    /// Where the download is at.
+    pub fn status(&self) -> DownloadStatus {
+        self.state.borrow().status
+    }
+
+    /// How much was received so far.
+    pub fn progress(&self) -> Progress {
+        self.state.borrow().progress()
+    }
+
+    /// A stream of the [`progress`](Self::progress), updated whenever data is received, which
+    /// ends when the download is finished.
+    pub fn progress_stream(&self) -> ProgressStream {
+        let (sender, stream) = progress_channel();
+        let mut state = self.state.borrow_mut();
+        if !state.status.is_finished() {
+            state.listeners.push(sender);
+        }
+        stream
+    }
+
+    /// Pauses the download, keeping what was received so far.
+    pub fn pause(&self) {
+        let mut state = self.state.borrow_mut();
+        if matches!(
+            state.status,
+            DownloadStatus::Queued | DownloadStatus::Running
+        ) {
+            state.interrupt(DownloadStatus::Paused);
+        }
+    }
+
+    /// Resumes a paused or failed download, once there is a free slot.
+    pub fn resume(&self) {
+        {
+            let mut state = self.state.borrow_mut();
+            if !matches!(
+                state.status,
+                DownloadStatus::Paused | DownloadStatus::Failed
+            ) {
+                return;
+            }
+            state.status = DownloadStatus::Queued;
+            state.error = None;
+        }
+        self.manager.pump();
+    }
+
+    /// Cancels the download, and drops what was received so far.
+    pub fn cancel(&self) {
+        let mut state = self.state.borrow_mut();
+        if !state.status.is_finished() || state.status == DownloadStatus::Failed {
+            state.received = Vec::new();
+            state.finish(DownloadStatus::Cancelled);
+        }
+    }
+
+    /// Changes the priority of the download.
+    ///
+    /// A queued download is started according to its new priority. A running download keeps
+    /// receiving with the priority it was requested with, and uses the new one once it is paused
+    /// and resumed.
+    pub fn set_priority(&self, priority: Priority) {
+        self.state.borrow_mut().priority = priority;
+    }
+
+    /// What was received so far, to resume the download later with
+    /// [`DownloadManager::resume`]. `None` once the download completed or was cancelled.
+    pub fn partial(&self) -> Option<PartialDownload> {
+        let state = self.state.borrow();
+        if matches!(
+            state.status,
+            DownloadStatus::Completed | DownloadStatus::Cancelled
+        ) {
+            return None;
+        }
+        Some(PartialDownload {
+            url: state.url.clone(),
+            etag: state.etag.clone(),
+            total: state.total,
+            bytes: state.received.clone(),
+        })
+    }
+
+    /// Waits for the download to complete, resolving to what was received.
+    ///
+    /// This fails if the download failed or was cancelled. A paused download must be resumed for
+    /// this to resolve.
+    pub async fn finish(&self) -> Result<web_sys::Blob, Error> {
+        let waiting = {
+            let mut state = self.state.borrow_mut();
+            if state.status.is_finished() {
+                None
+            } else {
+                let (sender, receiver) = oneshot::channel();
+                state.waiting.push(sender);
+                Some(receiver)
+            }
+        };
+        if let Some(waiting) = waiting {
+            let _ = waiting.await;
+        }
+        let state = self.state.borrow();
+        match (&state.blob, &state.error) {
+            (Some(blob), _) => Ok(blob.clone()),
+            (None, Some(error)) => Err(Error::GlooError(error.clone())),
+            (None, None) => Err(Error::GlooError(format!(
+                "download of `{}` was cancelled",
+                state.url
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for Download {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Download")
+            .field("url", &state.url)
+            .field("status", &state.status)
+            .field("progress", &state.progress())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The saved state of an unfinished [`Download`], to resume it later, see
+/// [`Download::partial`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialDownload {
+    /// The URL being downloaded.
+    pub url: String,
+    /// The entity tag of the resource, to check it didn't change when resuming.
+    pub etag: Option<String>,
+    /// The size of the resource, if known.
+    pub total: Option<u64>,
+    /// The data received so far.
+    pub bytes: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_content_range() {
+        assert_eq!(content_range("bytes 100-199/1000"), Some((100, Some(1000))));
+        assert_eq!(content_range("bytes 0-9/*"), Some((0, None)));
+        assert_eq!(content_range("bytes */1000"), None);
+        assert_eq!(content_range("items 0-9/10"), None);
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::http::BodyStream;
+use crate::Error;
+use futures_core::{ready, Stream};
+
+/// A single event of a `text/event-stream` body.
+///
+/// See the [HTML Standard](https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation)
+/// for how the fields are interpreted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerSentEvent {
+    /// The event type, `message` if the server didn't specify one.
+    pub event: String,
+    /// The event data. Multiple `data` lines are joined with `\n`.
+    pub data: String,
+    /// The last event ID set by the server, if any.
+    pub id: Option<String>,
+    /// The reconnection time requested by the server alongside this event, if any.
+    pub retry: Option<Duration>,
+}
+
+/// A [`Stream`] of [`ServerSentEvent`]s parsed from a response body, obtained from
+/// [`Response::events`](crate::http::Response::events).
+///
+/// Unlike [`EventSource`](web_sys::EventSource), this works with any request, including ones
+/// with a body, custom headers or a method other than `GET`. It does not reconnect once the
+/// body ends.
+pub struct EventStream {
+    body: BodyStream,
+    parser: EventParser,
+    events: VecDeque<ServerSentEvent>,
+}
+
+impl EventStream {
+    pub(crate) fn new(body: BodyStream) -> Self {
+        Self {
+            body,
+            parser: EventParser::default(),
+            events: VecDeque::new(),
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<ServerSentEvent, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(event) = this.events.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            match ready!(Pin::new(&mut this.body).poll_next(cx)) {
+                Some(Ok(chunk)) => this.parser.feed(&chunk, &mut this.events),
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                // An event which wasn't terminated by a blank line is discarded.
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("body", &self.body)
+            .field("last_event_id", &self.parser.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Incremental `text/event-stream` parser.
+#[derive(Debug, Default)]
+pub(crate) struct EventParser {
+    line: Vec<u8>,
+    started: bool,
+    skip_lf: bool,
+    event: String,
+    data: String,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl EventParser {
+    /// Feeds a chunk of the body, pushing every event completed by it onto `events`.
+    pub(crate) fn feed(&mut self, mut chunk: &[u8], events: &mut VecDeque<ServerSentEvent>) {
+        if !self.started && !chunk.is_empty() {
+            self.started = true;
+            chunk = chunk.strip_prefix("\u{feff}".as_bytes()).unwrap_or(chunk);
+        }
+        for &byte in chunk {
+            match byte {
+                b'\n' if self.skip_lf => self.skip_lf = false,
+                b'\r' | b'\n' => {
+                    self.skip_lf = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                        events.push_back(event);
+                    }
+                }
+                _ => {
+                    self.skip_lf = false;
+                    self.line.push(byte);
+                }
+            }
+        }
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<ServerSentEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok().map(Duration::from_millis);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<ServerSentEvent> {
+        let event = std::mem::take(&mut self.event);
+        let retry = self.retry.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(ServerSentEvent {
+            event: if event.is_empty() {
+                "message".to_string()
+            } else {
+                event
+            },
+            data,
+            id: self.id.clone(),
+            retry,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&str]) -> Vec<ServerSentEvent> {
+        let mut parser = EventParser::default();
+        let mut events = VecDeque::new();
+        for chunk in chunks {
+            parser.feed(chunk.as_bytes(), &mut events);
+        }
+        events.into()
+    }
+
+    fn message(data: &str) -> ServerSentEvent {
+        ServerSentEvent {
+            event: "message".to_string(),
+            data: data.to_string(),
+            id: None,
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn parses_fields() {
+        let events =
+            parse(&["\u{feff}: comment\nevent: update\nid: 7\nretry: 1500\ndata: a\ndata:b\n\n"]);
+        assert_eq!(
+            events,
+            vec![ServerSentEvent {
+                event: "update".to_string(),
+                data: "a\nb".to_string(),
+                id: Some("7".to_string()),
+                retry: Some(Duration::from_millis(1500)),
+            }]
+        );
+    }
+
+    #[test]
+    fn handles_line_endings_across_chunks() {
+        let events = parse(&["data: one\r", "\n\r", "\ndata", ": two\r\r"]);
+        assert_eq!(events, vec![message("one"), message("two")]);
+    }
+
+    #[test]
+    fn keeps_last_event_id() {
+        let events = parse(&["id: 1\ndata: a\n\ndata: b\n\nid\ndata: c\n\n"]);
+        let ids: Vec<_> = events.into_iter().map(|e| e.id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                Some("1".to_string()),
+                Some("1".to_string()),
+                Some(String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_events_without_data() {
+        let events = parse(&["event: ping\n\ndata: x\n\ndata: unterminated\n"]);
+        assert_eq!(events, vec![message("x")]);
+    }
+}
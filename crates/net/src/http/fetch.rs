@@ -0,0 +1,122 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use crate::http::{Request, Response};
+use crate::{js_to_error, Error};
+use js_sys::{Function, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// A `fetch` implementation which requests can be sent with, using [`Request::send_with`].
+///
+/// This allows routing requests through something other than the `fetch` of the global scope,
+/// e.g. an instrumented `fetch`, a polyfill such as `node-fetch`, or a service worker client.
+///
+/// [`Request::send_with`]: crate::http::Request::send_with
+pub trait Fetch {
+    /// Starts fetching `request`, returning a promise which resolves to a
+    /// [`web_sys::Response`].
+    fn fetch(&self, request: &web_sys::Request) -> Result<Promise, Error>;
+}
+
+/// Calls the JavaScript function with the request as its only argument, like `fetch(request)`.
+impl Fetch for Function {
+    fn fetch(&self, request: &web_sys::Request) -> Result<Promise, Error> {
+        let promise = self
+            .call1(&JsValue::UNDEFINED, request)
+            .map_err(js_to_error)?;
+        promise.dyn_into().map_err(|value| {
+            Error::GlooError(format!(
+                "fetch function returned {value:?}, not a `Promise`"
+            ))
+        })
+    }
+}
+
+/// The `fetch` function of the current global scope, which requests are sent with by default.
+///
+/// This works on the main thread (`Window`), in dedicated, shared and service workers
+/// (`WorkerGlobalScope`), and in any other runtime which exposes a global `fetch` function, such
+/// as Node.js or Deno.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlobalFetch;
+
+impl Fetch for GlobalFetch {
+    fn fetch(&self, request: &web_sys::Request) -> Result<Promise, Error> {
+        let global = js_sys::global();
+        let maybe_window =
+            Reflect::get(&global, &JsValue::from_str("Window")).map_err(js_to_error)?;
+        if !maybe_window.is_undefined() {
+            let window = global.unchecked_into::<web_sys::Window>();
+            return Ok(window.fetch_with_request(request));
+        }
+
+        let maybe_worker =
+            Reflect::get(&global, &JsValue::from_str("WorkerGlobalScope")).map_err(js_to_error)?;
+        if !maybe_worker.is_undefined() {
+            let worker = global.unchecked_into::<web_sys::WorkerGlobalScope>();
+            return Ok(worker.fetch_with_request(request));
+        }
+
+        let fetch = Reflect::get(&global, &JsValue::from_str("fetch")).map_err(js_to_error)?;
+        match fetch.dyn_into::<Function>() {
+            Ok(fetch) => {
+                let promise = fetch.call1(&global, request).map_err(js_to_error)?;
+                Ok(promise.unchecked_into())
+            }
+            Err(_) => Err(Error::GlooError(
+                "Unsupported JavaScript global context: no `fetch` function found".to_string(),
+            )),
+        }
+    }
+}
+
+/// The future returned by [`Fetcher::fetch`].
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output = Result<Response, Error>> + 'a>>;
+
+/// Something which turns a [`Request`] into a [`Response`].
+///
+/// This is the abstraction a [`Client`](crate::http::Client) sends its requests through. Every
+/// [`Fetch`] implementation is a `Fetcher`, sending requests with that `fetch`. Implementing it
+/// directly allows answering requests without a network at all, e.g. to unit test code making
+/// requests, or wrapping another `Fetcher` to add behavior around it.
+///
+/// # Example
+///
+/// ```
+/// use gloo_net::http::{Client, FetchFuture, Fetcher, Request, Response};
+///
+/// struct Mock;
+///
+/// impl Fetcher for Mock {
+///     fn fetch(&self, request: Request) -> FetchFuture<'_> {
+///         Box::pin(async move {
+///             Response::builder()
+///                 .status(200)
+///                 .body(Some(format!("hello from {}", request.url()).as_str()))
+///         })
+///     }
+/// }
+///
+/// # async fn no_run() {
+/// let client = Client::with_fetcher(Mock);
+/// let resp = client.get("/greeting").send().await.unwrap();
+/// assert_eq!(resp.status(), 200);
+/// # }
+/// ```
+pub trait Fetcher {
+    /// Sends `request`, resolving to its response.
+    fn fetch(&self, request: Request) -> FetchFuture<'_>;
+}
+
+impl<F: Fetch + ?Sized> Fetcher for F {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(request.fetch_with(self))
+    }
+}
+
+impl Fetcher for Rc<dyn Fetcher> {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        (**self).fetch(request)
+    }
+}
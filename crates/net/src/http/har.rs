@@ -0,0 +1,311 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+
+use serde_json::{json, Value};
+use wasm_bindgen::JsCast;
+
+use crate::http::{FetchFuture, Fetcher, Headers, Request, Response};
+use crate::Error;
+
+/// What the values of sensitive headers are replaced with.
+const REDACTED: &str = "[REDACTED]";
+
+/// Records the requests sent through a [`Client`](crate::http::Client) and their responses, as
+/// an [HAR 1.2](http://www.softwareishard.com/blog/har-12-spec/) log.
+///
+/// The log can be exported with [`to_json`](Self::to_json), e.g. to attach it to a bug report,
+/// and opened in the network panel of the browser's developer tools. It holds the headers and
+/// timings of each request, along with the beginning of the request and response bodies, up to
+/// [`max_body_size`](Self::max_body_size) bytes each. The values of
+/// [sensitive](Headers::is_sensitive) headers, like `Authorization` and `Cookie`, are redacted.
+///
+/// Recording bodies means buffering copies of them, so avoid recording requests which upload
+/// or download large or endless bodies, or set `max_body_size` to 0.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{Client, HarRecorder};
+/// # async fn no_run() {
+/// let recorder = HarRecorder::new().max_entries(100);
+/// let client = Client::new().with_recorder(recorder.clone());
+/// client.get("/api/status").send().await.unwrap();
+///
+/// // when the user reports a bug
+/// let har = recorder.to_json();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct HarRecorder {
+    log: Rc<RefCell<Log>>,
+}
+
+struct Log {
+    entries: VecDeque<Rc<RefCell<Value>>>,
+    max_entries: usize,
+    max_body_size: usize,
+}
+
+impl HarRecorder {
+    /// Creates a recorder keeping the last 1000 entries, with up to 64 KiB of each body.
+    pub fn new() -> Self {
+        Self {
+            log: Rc::new(RefCell::new(Log {
+                entries: VecDeque::new(),
+                max_entries: 1000,
+                max_body_size: 64 * 1024,
+            })),
+        }
+    }
+
+    /// Sets how many entries are kept, the oldest ones being dropped first.
+    pub fn max_entries(self, max_entries: usize) -> Self {
+        self.log.borrow_mut().max_entries = max_entries;
+        self
+    }
+
+    /// Sets how many bytes of each request and response body are recorded.
+    pub fn max_body_size(self, bytes: usize) -> Self {
+        self.log.borrow_mut().max_body_size = bytes;
+        self
+    }
+
+    /// The number of entries recorded.
+    pub fn len(&self) -> usize {
+        self.log.borrow().entries.len()
+    }
+
+    /// Whether no entries are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops all the recorded entries.
+    pub fn clear(&self) {
+        self.log.borrow_mut().entries.clear();
+    }
+
+    /// The HAR document, as a JSON value.
+    pub fn to_value(&self) -> Value {
+        let entries: Vec<Value> = self
+            .log
+            .borrow()
+            .entries
+            .iter()
+            .map(|entry| entry.borrow().clone())
+            .collect();
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "gloo-net", "version": env!("CARGO_PKG_VERSION") },
+                "pages": [],
+                "entries": entries,
+            }
+        })
+    }
+
+    /// The HAR document, serialized as JSON.
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+
+    fn push(&self, entry: Value) -> Rc<RefCell<Value>> {
+        let entry = Rc::new(RefCell::new(entry));
+        let mut log = self.log.borrow_mut();
+        log.entries.push_back(entry.clone());
+        while log.entries.len() > log.max_entries {
+            log.entries.pop_front();
+        }
+        entry
+    }
+
+    fn body_limit(&self) -> usize {
+        self.log.borrow().max_body_size
+    }
+}
+
+impl Default for HarRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for HarRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let log = self.log.borrow();
+        f.debug_struct("HarRecorder")
+            .field("entries", &log.entries.len())
+            .field("max_entries", &log.max_entries)
+            .field("max_body_size", &log.max_body_size)
+            .finish()
+    }
+}
+
+/// A [`Fetcher`] recording the requests sent through `inner` with a [`HarRecorder`].
+pub(crate) struct Record {
+    recorder: HarRecorder,
+    inner: Rc<dyn Fetcher>,
+}
+
+impl Record {
+    pub(crate) fn new(recorder: HarRecorder, inner: Rc<dyn Fetcher>) -> Self {
+        Self { recorder, inner }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let max_body_size = self.recorder.body_limit();
+        let body = match (request.body(), max_body_size) {
+            (Some(_), 1..) => match request.try_clone() {
+                Ok(copy) => copy.text().await.ok(),
+                Err(_) => None,
+            },
+            _ => None,
+        };
+        let started = js_sys::Date::new_0();
+        let start = js_sys::Date::now();
+        let entry = self.recorder.push(json!({
+            "startedDateTime": String::from(started.to_iso_string()),
+            "time": 0,
+            "request": request_json(&request, body.as_deref(), max_body_size),
+            "response": {
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "cookies": [],
+                "headers": [],
+                "content": { "size": 0, "mimeType": "" },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+            },
+            "cache": {},
+            "timings": { "send": 0, "wait": 0, "receive": 0 },
+        }));
+
+        let result = self.inner.fetch(request).await;
+        let wait = js_sys::Date::now() - start;
+        let mut recorded = entry.borrow_mut();
+        recorded["time"] = json!(wait);
+        recorded["timings"]["wait"] = json!(wait);
+        let response = match &result {
+            Ok(response) => response,
+            Err(e) => {
+                recorded["response"]["comment"] = json!(e.to_string());
+                return result;
+            }
+        };
+        recorded["response"]["status"] = json!(response.status());
+        recorded["response"]["statusText"] = json!(response.status_text());
+        recorded["response"]["headers"] = headers_json(&response.headers());
+        recorded["response"]["redirectURL"] = json!(response.location().unwrap_or_default());
+        recorded["response"]["content"]["mimeType"] =
+            json!(response.headers().get("Content-Type").unwrap_or_default());
+        drop(recorded);
+
+        // Read a copy of the body in the background, so that the response is returned as soon as
+        // its headers arrived.
+        if let Ok(copy) = response.clone_raw() {
+            wasm_bindgen_futures::spawn_local(async move {
+                let body = Response::from(copy).binary().await;
+                let receive = js_sys::Date::now() - start - wait;
+                let mut recorded = entry.borrow_mut();
+                recorded["time"] = json!(wait + receive);
+                recorded["timings"]["receive"] = json!(receive);
+                if let Ok(body) = body {
+                    let content = &mut recorded["response"]["content"];
+                    content["size"] = json!(body.len());
+                    if max_body_size > 0 {
+                        let text = String::from_utf8_lossy(&body);
+                        let (text, truncated) = truncate(&text, max_body_size);
+                        content["text"] = json!(text);
+                        if truncated {
+                            content["comment"] = json!("truncated");
+                        }
+                    }
+                    recorded["response"]["bodySize"] = json!(body.len());
+                }
+            });
+        }
+        result
+    }
+}
+
+impl Fetcher for Record {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+fn request_json(request: &Request, body: Option<&str>, max_body_size: usize) -> Value {
+    let url = request.url();
+    let query: Vec<Value> = web_sys::Url::new(&url)
+        .ok()
+        .and_then(|url| js_sys::try_iter(&url.search_params()).ok().flatten())
+        .into_iter()
+        .flatten()
+        .filter_map(|pair| {
+            let pair: js_sys::Array = pair.ok()?.dyn_into().ok()?;
+            Some(json!({ "name": pair.get(0).as_string()?, "value": pair.get(1).as_string()? }))
+        })
+        .collect();
+    let headers = request.headers();
+    let mut json = json!({
+        "method": request.method().as_str(),
+        "url": url,
+        "httpVersion": "",
+        "cookies": [],
+        "headers": headers_json(&headers),
+        "queryString": query,
+        "headersSize": -1,
+        "bodySize": body.map_or(0, str::len),
+    });
+    if let Some(body) = body {
+        let (text, truncated) = truncate(body, max_body_size);
+        json["postData"] = json!({
+            "mimeType": headers.get("Content-Type").unwrap_or_default(),
+            "text": text,
+        });
+        if truncated {
+            json["postData"]["comment"] = json!("truncated");
+        }
+    }
+    json
+}
+
+/// The headers as HAR name/value pairs, with the values of the sensitive ones redacted.
+fn headers_json(headers: &Headers) -> Value {
+    headers
+        .entries()
+        .map(|(name, value)| match Headers::is_sensitive(&name) {
+            true => json!({ "name": name, "value": REDACTED }),
+            false => json!({ "name": name, "value": value }),
+        })
+        .collect()
+}
+
+/// Cuts `text` down to at most `max` bytes, on a character boundary.
+fn truncate(text: &str, max: usize) -> (&str, bool) {
+    if text.len() <= max {
+        return (text, false);
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&text[..end], true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_on_char_boundaries() {
+        assert_eq!(truncate("hello", 10), ("hello", false));
+        assert_eq!(truncate("hello", 4), ("hell", true));
+        assert_eq!(truncate("héllo", 2), ("h", true));
+        assert_eq!(truncate("héllo", 0), ("", true));
+    }
+}
@@ -1,7 +1,10 @@
+use crate::http::Link;
+use crate::Error;
 use gloo_utils::iter::UncheckedIter;
-use js_sys::{Array, Map};
+use js_sys::{Array, Function, Map, Reflect};
+use std::convert::TryFrom;
 use std::fmt;
-use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use wasm_bindgen::{JsCast, JsValue, UnwrapThrowExt};
 
 // I experimented with using `js_sys::Object` for the headers, since this object is marked
 // experimental in MDN. However it's in the fetch spec, and it's necessary for appending headers.
@@ -53,6 +56,45 @@ impl Headers {
         self.raw.get(name).unwrap_throw()
     }
 
+    /// Gets all the values of a header.
+    ///
+    /// The browser combines the values of a header into one, separated by commas, which this
+    /// splits up again (except for commas inside quoted strings). `Set-Cookie` values, which
+    /// can't be combined, are returned as they were received, where the browser supports it.
+    pub fn get_all(&self, name: &str) -> Vec<String> {
+        if name.eq_ignore_ascii_case("set-cookie") {
+            if let Some(cookies) = self.set_cookies() {
+                return cookies;
+            }
+            return self.get(name).into_iter().collect();
+        }
+        self.get(name)
+            .map(|value| split_list(&value))
+            .unwrap_or_default()
+    }
+
+    /// Parses the links of the `Link` header.
+    pub fn links(&self) -> Vec<Link> {
+        self.get("Link")
+            .map(|value| Link::parse_all(&value))
+            .unwrap_or_default()
+    }
+
+    /// The `Set-Cookie` values, if the browser supports `Headers.getSetCookie()`.
+    fn set_cookies(&self) -> Option<Vec<String>> {
+        let get_set_cookie = Reflect::get(&self.raw, &JsValue::from_str("getSetCookie"))
+            .ok()?
+            .dyn_into::<Function>()
+            .ok()?;
+        let cookies: Array = get_set_cookie.call0(&self.raw).ok()?.dyn_into().ok()?;
+        Some(
+            cookies
+                .iter()
+                .filter_map(|cookie| cookie.as_string())
+                .collect(),
+        )
+    }
+
     /// Whether a header with the given name exists.
     pub fn has(&self, name: &str) -> bool {
         self.raw.has(name).unwrap_throw()
@@ -63,6 +105,32 @@ impl Headers {
         self.raw.set(name, value).unwrap_throw()
     }
 
+    /// Overwrites a header with the given name, failing if the name or the value is invalid.
+    pub(crate) fn try_set(&self, name: &str, value: &str) -> Result<(), Error> {
+        self.raw.set(name, value).map_err(|_| {
+            Error::GlooError(format!(
+                "invalid header `{}: {}`",
+                name,
+                value.escape_debug()
+            ))
+        })
+    }
+
+    /// Whether a header with the given name usually carries a secret, like a credential or a
+    /// session, which shouldn't end up in logs or bug reports.
+    ///
+    /// This matches `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie`, and names
+    /// mentioning a token, a secret, a password or an API key, like `X-Auth-Token`.
+    pub fn is_sensitive(name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        matches!(
+            name.as_str(),
+            "authorization" | "proxy-authorization" | "cookie" | "set-cookie"
+        ) || ["token", "secret", "password", "api-key", "apikey"]
+            .iter()
+            .any(|word| name.contains(word))
+    }
+
     /// Iterate over (header name, header value) pairs.
     pub fn entries(&self) -> impl Iterator<Item = (String, String)> {
         // Here we cheat and cast to a map even though `self` isn't, because the method names match
@@ -93,6 +161,89 @@ impl Headers {
     }
 }
 
+/// Splits a comma-separated header list into its elements, leaving quoted strings intact.
+pub(crate) fn split_list(value: &str) -> Vec<String> {
+    split_unquoted(value, ',')
+        .into_iter()
+        .map(str::trim)
+        .filter(|element| !element.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Splits `value` on each `separator` which is not inside a quoted string.
+pub(crate) fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if c == separator && !quoted => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Removes the quotes and escapes of a quoted string, leaving tokens as they are.
+pub(crate) fn unquote(value: &str) -> String {
+    match value.strip_prefix('"') {
+        Some(quoted) => {
+            let mut unquoted = String::new();
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => unquoted.extend(chars.next()),
+                    c => unquoted.push(c),
+                }
+            }
+            unquoted
+        }
+        None => value.to_string(),
+    }
+}
+
+impl TryFrom<&http::HeaderMap> for Headers {
+    type Error = Error;
+
+    /// Converts the headers, keeping every value of headers with multiple values.
+    fn try_from(map: &http::HeaderMap) -> Result<Self, Self::Error> {
+        let headers = Headers::new();
+        for (name, value) in map {
+            let value = value
+                .to_str()
+                .map_err(|e| Error::GlooError(format!("invalid value for header `{name}`: {e}")))?;
+            headers.append(name.as_str(), value);
+        }
+        Ok(headers)
+    }
+}
+
+impl TryFrom<&Headers> for http::HeaderMap {
+    type Error = Error;
+
+    /// Converts the headers, keeping every `Set-Cookie` value as a separate entry where the
+    /// browser supports it.
+    fn try_from(headers: &Headers) -> Result<Self, Self::Error> {
+        let mut map = http::HeaderMap::new();
+        for (name, value) in headers.entries() {
+            let name = http::header::HeaderName::try_from(name)
+                .map_err(|e| Error::GlooError(e.to_string()))?;
+            let value =
+                http::HeaderValue::try_from(value).map_err(|e| Error::GlooError(e.to_string()))?;
+            map.append(name, value);
+        }
+        Ok(map)
+    }
+}
+
 impl fmt::Debug for Headers {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut dbg = f.debug_struct("Headers");
@@ -102,3 +253,18 @@ impl fmt::Debug for Headers {
         dbg.finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_lists_outside_quotes() {
+        assert_eq!(split_list("Accept, Origin"), vec!["Accept", "Origin"]);
+        assert_eq!(
+            split_list(r#"W/"a,b", "c\"," , "#),
+            vec![r#"W/"a,b""#, r#""c\",""#]
+        );
+        assert!(split_list("").is_empty());
+    }
+}
@@ -0,0 +1,30 @@
+use std::rc::Rc;
+
+use crate::http::{FetchFuture, Fetcher, Request};
+use crate::network::NetworkInformation;
+
+/// A [`Fetcher`] adding the Client Hints of the [`NetworkInformation`] to the requests sent
+/// through `inner`.
+pub(crate) struct NetworkHints {
+    inner: Rc<dyn Fetcher>,
+}
+
+impl NetworkHints {
+    pub(crate) fn new(inner: Rc<dyn Fetcher>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Fetcher for NetworkHints {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        if let Some(network) = NetworkInformation::current() {
+            let headers = request.headers();
+            for (name, value) in network.client_hints() {
+                if !headers.has(name) {
+                    headers.set(name, &value);
+                }
+            }
+        }
+        self.inner.fetch(request)
+    }
+}
@@ -0,0 +1,82 @@
+//! Helpers for the layers storing data in IndexedDB.
+
+use js_sys::{Array, Promise, Reflect};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbFactory, IdbRequest, IdbTransaction, IdbTransactionMode};
+
+use crate::{js_to_error, Error};
+
+/// Opens the database called `name`, calling `upgrade` to create its stores when it is created.
+pub(crate) async fn open(
+    name: &str,
+    upgrade: impl FnOnce(&IdbDatabase) + 'static,
+) -> Result<IdbDatabase, Error> {
+    let factory: IdbFactory = Reflect::get(&js_sys::global(), &JsValue::from_str("indexedDB"))
+        .ok()
+        .and_then(|factory| factory.dyn_into().ok())
+        .ok_or_else(|| Error::GlooError("IndexedDB is not available".to_string()))?;
+    let open = factory.open_with_u32(name, 1).map_err(js_to_error)?;
+    let upgrade = Closure::once(move |event: web_sys::Event| {
+        let db: IdbDatabase = event
+            .target()
+            .and_then(|target| target.unchecked_into::<IdbRequest>().result().ok())
+            .map(JsCast::unchecked_into)
+            .expect("the open request has a database");
+        upgrade(&db);
+    });
+    open.set_onupgradeneeded(Some(upgrade.as_ref().unchecked_ref()));
+    let db = request(Ok(open.into())).await;
+    Ok(db?.unchecked_into())
+}
+
+/// Starts a transaction over `stores`.
+pub(crate) fn transaction(
+    db: &IdbDatabase,
+    stores: &[&str],
+    mode: IdbTransactionMode,
+) -> Result<IdbTransaction, Error> {
+    let stores: Array = stores
+        .iter()
+        .map(|store| JsValue::from_str(store))
+        .collect();
+    db.transaction_with_str_sequence_and_mode(&stores, mode)
+        .map_err(js_to_error)
+}
+
+/// Waits for the result of an IndexedDB request.
+pub(crate) async fn request(request: Result<IdbRequest, JsValue>) -> Result<JsValue, Error> {
+    let request = request.map_err(js_to_error)?;
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    let outcome = JsFuture::from(promise).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    match outcome {
+        Ok(_) => request.result().map_err(js_to_error),
+        Err(_) => Err(idb_error(request.error().ok().flatten())),
+    }
+}
+
+/// Waits for a transaction to be committed.
+pub(crate) async fn complete(tx: &IdbTransaction) -> Result<(), Error> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        tx.set_oncomplete(Some(&resolve));
+        tx.set_onerror(Some(&reject));
+        tx.set_onabort(Some(&reject));
+    });
+    match JsFuture::from(promise).await {
+        Ok(_) => Ok(()),
+        Err(_) => Err(idb_error(tx.error())),
+    }
+}
+
+fn idb_error(error: Option<web_sys::DomException>) -> Error {
+    match error {
+        Some(error) => js_to_error(error.into()),
+        None => Error::GlooError("the IndexedDB transaction was aborted".to_string()),
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+use std::future::Future;
+use std::rc::Rc;
+
+use crate::http::{FetchFuture, Fetcher, Request, Response};
+use crate::Error;
+
+/// The rest of a [`Client`](crate::http::Client)'s chain, handed to an interceptor.
+///
+/// See [`Client::with_interceptor`](crate::http::Client::with_interceptor).
+pub struct Next {
+    fetcher: Rc<dyn Fetcher>,
+}
+
+impl Next {
+    /// Passes `request` on to the rest of the chain, resolving to its response.
+    pub async fn run(self, request: Request) -> Result<Response, Error> {
+        self.fetcher.fetch(request).await
+    }
+}
+
+impl fmt::Debug for Next {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Next").finish_non_exhaustive()
+    }
+}
+
+/// A [`Fetcher`] running an interceptor before `next`.
+pub(crate) struct Intercept<F> {
+    interceptor: F,
+    next: Rc<dyn Fetcher>,
+}
+
+impl<F> Intercept<F> {
+    pub(crate) fn new(interceptor: F, next: Rc<dyn Fetcher>) -> Self {
+        Self { interceptor, next }
+    }
+}
+
+impl<F, Fut> Fetcher for Intercept<F>
+where
+    F: Fn(Request, Next) -> Fut,
+    Fut: Future<Output = Result<Response, Error>> + 'static,
+{
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        let next = Next {
+            fetcher: Rc::clone(&self.next),
+        };
+        Box::pin((self.interceptor)(request, next))
+    }
+}
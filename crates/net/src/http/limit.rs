@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use crate::http::{FetchFuture, Fetcher, Request, Response};
+use crate::Error;
+use futures_channel::oneshot;
+
+/// A [`Fetcher`] limiting how many requests sent through `inner` are in flight at once.
+///
+/// A request holds one of the permits from sending it until its response arrives. Requests
+/// which can't get one wait in line, first come first served.
+pub(crate) struct Limit {
+    inner: Rc<dyn Fetcher>,
+    semaphore: Rc<RefCell<Semaphore>>,
+}
+
+impl Limit {
+    pub(crate) fn new(max_concurrent: usize, inner: Rc<dyn Fetcher>) -> Self {
+        Self {
+            inner,
+            semaphore: Rc::new(RefCell::new(Semaphore {
+                available: max_concurrent,
+                waiters: VecDeque::new(),
+            })),
+        }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let _permit = acquire(&self.semaphore).await;
+        self.inner.fetch(request).await
+    }
+}
+
+impl Fetcher for Limit {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+struct Semaphore {
+    available: usize,
+    waiters: VecDeque<oneshot::Sender<Permit>>,
+}
+
+/// Allows one request to be in flight; dropping it hands it to the next waiting request.
+struct Permit {
+    semaphore: Option<Rc<RefCell<Semaphore>>>,
+}
+
+async fn acquire(semaphore: &Rc<RefCell<Semaphore>>) -> Permit {
+    let receiver = {
+        let mut state = semaphore.borrow_mut();
+        if state.available > 0 {
+            state.available -= 1;
+            return Permit {
+                semaphore: Some(semaphore.clone()),
+            };
+        }
+        let (sender, receiver) = oneshot::channel();
+        state.waiters.push_back(sender);
+        receiver
+    };
+    // The senders are only dropped after handing out a permit, or with the semaphore, which we
+    // hold on to.
+    receiver.await.expect("the semaphore outlives its waiters")
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let semaphore = match self.semaphore.take() {
+            Some(semaphore) => semaphore,
+            None => return,
+        };
+        loop {
+            let waiter = semaphore.borrow_mut().waiters.pop_front();
+            let waiter = match waiter {
+                Some(waiter) => waiter,
+                None => {
+                    semaphore.borrow_mut().available += 1;
+                    return;
+                }
+            };
+            let permit = Permit {
+                semaphore: Some(semaphore.clone()),
+            };
+            match waiter.send(permit) {
+                Ok(()) => return,
+                // The waiting request was dropped; try the next one.
+                Err(mut permit) => permit.semaphore = None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::FutureExt;
+
+    fn semaphore(available: usize) -> Rc<RefCell<Semaphore>> {
+        Rc::new(RefCell::new(Semaphore {
+            available,
+            waiters: VecDeque::new(),
+        }))
+    }
+
+    #[test]
+    fn permits_are_handed_to_waiters_in_order() {
+        let semaphore = semaphore(1);
+        let first = block_on(acquire(&semaphore));
+
+        let mut second = Box::pin(acquire(&semaphore));
+        let third = Box::pin(acquire(&semaphore));
+        let mut fourth = Box::pin(acquire(&semaphore));
+        assert!((&mut second).now_or_never().is_none());
+        assert!(third.now_or_never().is_none()); // dropped while waiting
+        assert!((&mut fourth).now_or_never().is_none());
+
+        drop(first);
+        let second = block_on(second);
+        assert!((&mut fourth).now_or_never().is_none());
+        drop(second);
+        let fourth = block_on(fourth);
+        assert_eq!(semaphore.borrow().available, 0);
+        drop(fourth);
+        assert_eq!(semaphore.borrow().available, 1);
+    }
+}
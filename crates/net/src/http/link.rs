@@ -0,0 +1,264 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::http::{Client, Request, Response};
+use crate::{js_to_error, Error};
+
+/// A link of a [`Link`](https://www.rfc-editor.org/rfc/rfc8288) header, see [`Headers::links`].
+///
+/// [`Headers::links`]: crate::http::Headers::links
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    target: String,
+    params: Vec<(String, String)>,
+}
+
+impl Link {
+    /// Parses the links of a `Link` header value, skipping malformed ones.
+    pub fn parse_all(value: &str) -> Vec<Link> {
+        let mut parser = Parser { rest: value };
+        let mut links = Vec::new();
+        while let Some(link) = parser.next_link() {
+            links.extend(link);
+        }
+        links
+    }
+
+    /// The URI the link points to, as written in the header, which may be relative.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// The `rel` parameter: one or more relation types, separated by spaces.
+    pub fn rel(&self) -> Option<&str> {
+        self.param("rel")
+    }
+
+    /// Whether `rel` is one of the relation types of this link, ignoring case.
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.rel().is_some_and(|rels| {
+            rels.split_ascii_whitespace()
+                .any(|r| r.eq_ignore_ascii_case(rel))
+        })
+    }
+
+    /// Gets a parameter of the link, like `title` or `type`, by its case-insensitive name.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The parameters of the link, with lowercased names, in order.
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    /// Parses the next link, returning `Some(None)` for a malformed one and `None` at the end.
+    fn next_link(&mut self) -> Option<Option<Link>> {
+        self.rest = self
+            .rest
+            .trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if self.rest.is_empty() {
+            return None;
+        }
+        let link = self.link();
+        if link.is_none() {
+            // Skip to the next link.
+            self.params();
+        }
+        Some(link)
+    }
+
+    fn link(&mut self) -> Option<Link> {
+        let rest = self.rest.strip_prefix('<')?;
+        let end = rest.find('>')?;
+        let target = rest[..end].trim().to_string();
+        self.rest = &rest[end + 1..];
+        let params = self.params();
+        Some(Link { target, params })
+    }
+
+    /// Parses `; name=value` parameters up to the end of the current link.
+    fn params(&mut self) -> Vec<(String, String)> {
+        let mut params = Vec::new();
+        loop {
+            self.rest = self.rest.trim_start();
+            match self.rest.chars().next() {
+                None | Some(',') => return params,
+                Some(';') => self.rest = self.rest[1..].trim_start(),
+                // Skip stray characters.
+                Some(c) => {
+                    self.rest = &self.rest[c.len_utf8()..];
+                    continue;
+                }
+            }
+            let name = self.token().to_ascii_lowercase();
+            self.rest = self.rest.trim_start();
+            let value = match self.rest.strip_prefix('=') {
+                Some(rest) => {
+                    self.rest = rest.trim_start();
+                    self.value()
+                }
+                None => String::new(),
+            };
+            if !name.is_empty() {
+                params.push((name, value));
+            }
+        }
+    }
+
+    fn token(&mut self) -> &str {
+        let end = self
+            .rest
+            .find(|c: char| c == '=' || c == ';' || c == ',' || c == '"' || c.is_whitespace())
+            .unwrap_or(self.rest.len());
+        let (token, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        token
+    }
+
+    fn value(&mut self) -> String {
+        let quoted = match self.rest.strip_prefix('"') {
+            Some(quoted) => quoted,
+            None => return self.token().to_string(),
+        };
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &quoted[i + 1..];
+                    return value;
+                }
+                '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                c => value.push(c),
+            }
+        }
+        // Unterminated quoted string.
+        self.rest = "";
+        value
+    }
+}
+
+/// A [`Stream`] of the pages of a paginated resource, see [`Client::paginate`].
+#[must_use = "streams do nothing unless polled"]
+pub struct Pages {
+    client: Client,
+    page: Option<PageFuture>,
+}
+
+type PageFuture = Pin<Box<dyn Future<Output = Result<Page, Error>>>>;
+
+struct Page {
+    response: Response,
+    next: Option<Request>,
+}
+
+impl Pages {
+    pub(crate) fn new(client: Client, request: Request) -> Self {
+        Self {
+            page: Some(Box::pin(fetch_page(client.clone(), request))),
+            client,
+        }
+    }
+}
+
+async fn fetch_page(client: Client, request: Request) -> Result<Page, Error> {
+    let template = request.try_clone()?;
+    let response = client.send(request).await?;
+    let next = match response
+        .headers()
+        .links()
+        .iter()
+        .find(|l| l.has_rel("next"))
+    {
+        Some(link) => {
+            let url = web_sys::Url::new_with_base(link.target(), &response.url())
+                .map_err(js_to_error)?
+                .href();
+            // The next page is always fetched with `GET`, as after a `303 See Other`.
+            Some(template.redirect(&url, 303).await?)
+        }
+        None => None,
+    };
+    Ok(Page { response, next })
+}
+
+impl Stream for Pages {
+    type Item = Result<Response, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let page = match self.page.as_mut() {
+            Some(page) => page,
+            None => return Poll::Ready(None),
+        };
+        let result = match page.as_mut().poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.page = None;
+        let page = match result {
+            Ok(page) => page,
+            Err(e) => return Poll::Ready(Some(Err(e))),
+        };
+        if let Some(next) = page.next {
+            self.page = Some(Box::pin(fetch_page(self.client.clone(), next)));
+        }
+        Poll::Ready(Some(Ok(page.response)))
+    }
+}
+
+impl std::fmt::Debug for Pages {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pages")
+            .field("done", &self.page.is_none())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_links() {
+        let links = Link::parse_all(
+            r#"<https://api.example.com/items?page=2&per_page=50>; rel="next",
+               <https://api.example.com/items?page=1,2>; rel="first prev"; title="First, \"1\"",
+               </items?page=9>;REL=last;type=text/html"#,
+        );
+        assert_eq!(links.len(), 3);
+        assert_eq!(
+            links[0].target(),
+            "https://api.example.com/items?page=2&per_page=50"
+        );
+        assert!(links[0].has_rel("next"));
+        assert_eq!(links[1].target(), "https://api.example.com/items?page=1,2");
+        assert!(links[1].has_rel("PREV"));
+        assert_eq!(links[1].param("title"), Some(r#"First, "1""#));
+        assert_eq!(links[2].rel(), Some("last"));
+        assert_eq!(
+            links[2].params().collect::<Vec<_>>(),
+            [("rel", "last"), ("type", "text/html")]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_links() {
+        let links = Link::parse_all(r#"garbage; rel=next, <a>; rel=prev, <c>, <b; rel="x""#);
+        let targets: Vec<_> = links.iter().map(Link::target).collect();
+        assert_eq!(targets, ["a", "c"]);
+        assert!(Link::parse_all("").is_empty());
+    }
+}
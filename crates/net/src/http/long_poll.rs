@@ -0,0 +1,162 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+
+use crate::http::retry::{is_network_error, is_retryable_status, retry_after};
+use crate::http::{Client, Decode, Request, RetryPolicy};
+use crate::Error;
+
+/// Empty answers arriving sooner than this weren't held by the server, e.g. because it doesn't
+/// support long-polling, and the request is only sent again after a backoff.
+const MIN_HOLD: Duration = Duration::from_secs(1);
+
+/// The shortest wait before sending the request again after an empty answer which wasn't held.
+const MIN_REPOLL_DELAY: Duration = Duration::from_millis(100);
+
+/// A [`Stream`] of the payloads of a long-polled resource, see [`Client::long_poll`].
+///
+/// Each request is held by the server until it has something to send, or until its own timeout,
+/// after which it answers `204 No Content` or `304 Not Modified`. The request is sent again as
+/// soon as it completes, unless the server answered right away, in which case it is sent again
+/// after the backoff of the [`RetryPolicy`], so that a server not holding requests isn't
+/// flooded with them. The bodies of the other successful responses are decoded with `D` and
+/// yielded.
+///
+/// Network errors and `429` or `5xx` responses are retried after a backoff, according to the
+/// [`RetryPolicy`] set with [`retry`](Self::retry), honoring their `Retry-After` header. Once
+/// the retries are exhausted, or on any other error, the error is yielded, and polling resumes
+/// after the maximum backoff. Polling stops when the stream is dropped.
+#[must_use = "streams do nothing unless polled"]
+pub struct LongPoll<D: Decode> {
+    client: Client,
+    template: Request,
+    policy: RetryPolicy,
+    next: Option<PayloadFuture<D::Output>>,
+    failed: bool,
+    done: bool,
+    format: PhantomData<fn() -> D>,
+}
+
+type PayloadFuture<T> = Pin<Box<dyn Future<Output = Result<T, Error>>>>;
+
+impl<D: Decode + 'static> LongPoll<D> {
+    pub(crate) fn new(client: Client, request: Request) -> Self {
+        Self {
+            client,
+            template: request,
+            policy: RetryPolicy::new().max_backoff(Duration::from_secs(30)),
+            next: None,
+            failed: false,
+            done: false,
+            format: PhantomData,
+        }
+    }
+
+    /// Sets how failed requests are retried. By default, they are retried 3 times, with a
+    /// backoff from 100ms up to 30s.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Starts waiting for the next payload.
+    fn schedule(&mut self) -> Result<PayloadFuture<D::Output>, Error> {
+        let template = self.template.try_clone()?;
+        let delay = match std::mem::replace(&mut self.failed, false) {
+            true => Some(self.policy.max_backoff),
+            false => None,
+        };
+        Ok(Box::pin(next_payload::<D>(
+            self.client.clone(),
+            template,
+            self.policy,
+            delay,
+        )))
+    }
+}
+
+/// Sends `template`, after `delay`, until a response has a payload.
+async fn next_payload<D: Decode>(
+    client: Client,
+    template: Request,
+    policy: RetryPolicy,
+    delay: Option<Duration>,
+) -> Result<D::Output, Error> {
+    if let Some(delay) = delay {
+        gloo_timers::future::sleep(delay).await;
+    }
+    let mut attempt = 0;
+    // the empty answers in a row which the server didn't hold
+    let mut unheld = 0;
+    loop {
+        let sent = js_sys::Date::now();
+        let delay = match client.send(template.try_clone()?).await {
+            Ok(response) if matches!(response.status(), 204 | 304) => {
+                attempt = 0;
+                if js_sys::Date::now() - sent >= MIN_HOLD.as_millis() as f64 {
+                    unheld = 0;
+                    continue;
+                }
+                unheld += 1;
+                let delay = policy.backoff(unheld - 1, js_sys::Math::random());
+                gloo_timers::future::sleep(delay.max(MIN_REPOLL_DELAY)).await;
+                continue;
+            }
+            Ok(response)
+                if is_retryable_status(response.status())
+                    && attempt < policy.max_retries
+                    && retry_after(&response).is_none_or(|delay| delay <= policy.max_backoff) =>
+            {
+                retry_after(&response)
+                    .unwrap_or_else(|| policy.backoff(attempt, js_sys::Math::random()))
+            }
+            Ok(response) => return D::decode(response.error_for_status()?).await,
+            Err(e) if is_network_error(&e) && attempt < policy.max_retries => {
+                policy.backoff(attempt, js_sys::Math::random())
+            }
+            Err(e) => return Err(e),
+        };
+        gloo_timers::future::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+impl<D: Decode + 'static> Stream for LongPoll<D> {
+    type Item = Result<D::Output, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        if self.next.is_none() {
+            match self.schedule() {
+                Ok(next) => self.next = Some(next),
+                // The request can't be copied to be sent again, e.g. its body was used.
+                Err(e) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+        let result = match self.next.as_mut().map(|next| next.as_mut().poll(cx)) {
+            Some(Poll::Ready(result)) => result,
+            _ => return Poll::Pending,
+        };
+        self.next = None;
+        self.failed = result.is_err();
+        Poll::Ready(Some(result))
+    }
+}
+
+impl<D: Decode> std::fmt::Debug for LongPoll<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LongPoll")
+            .field("url", &self.template.url())
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
@@ -13,15 +13,182 @@
 //! # }
 //! ```
 
+#[cfg(feature = "background-fetch")]
+mod background_fetch;
+mod batch;
+mod body;
+mod breaker;
+mod cache_control;
+mod client;
+mod codec;
+mod conditional;
+mod curl;
+mod dedup;
+mod disposition;
+#[cfg(feature = "download")]
+mod download;
+mod events;
+mod fetch;
+#[cfg(feature = "har")]
+mod har;
 mod headers;
+#[cfg(feature = "network-information")]
+mod hints;
+#[cfg(any(feature = "persistent-cache", feature = "offline-queue"))]
+mod idb;
+mod interceptor;
+mod limit;
+mod link;
+mod long_poll;
+#[cfg(feature = "offline-queue")]
+mod offline;
+#[cfg(feature = "persistent-cache")]
+mod persist;
+mod poll;
+#[cfg(feature = "json")]
+mod problem;
+mod progress;
 mod query;
+mod rate;
 mod request;
 mod response;
+mod retry;
+mod revalidate;
+#[cfg(feature = "snapshot")]
+mod snapshot;
+#[cfg(feature = "background-sync")]
+mod sync;
+mod timing;
+mod trace;
+#[cfg(feature = "upload")]
+mod upload;
+mod xhr;
 
+#[cfg(feature = "background-fetch")]
+#[cfg_attr(docsrs, doc(cfg(feature = "background-fetch")))]
+pub use background_fetch::{
+    BackgroundFetch, BackgroundFetchIcon, BackgroundFetchOptions, BackgroundFetchProgress,
+    BackgroundFetchRecord, BackgroundFetchRegistration, BackgroundFetchResult,
+};
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub use batch::JsonBatch;
+pub use batch::{Batcher, Envelope, Replies};
+#[cfg(feature = "io")]
+#[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+pub use body::BodyReader;
+pub use body::BodyStream;
+pub use breaker::{CircuitBreaker, CircuitState};
+pub use cache_control::CacheControl;
+pub use client::Client;
+pub use codec::{Decode, DecodeFuture, Encode, EncodedBody};
+pub use conditional::ETag;
+#[cfg(feature = "download")]
+#[cfg_attr(docsrs, doc(cfg(feature = "download")))]
+pub use download::{Download, DownloadManager, DownloadStatus, PartialDownload};
+pub use events::{EventStream, ServerSentEvent};
+pub use fetch::{Fetch, FetchFuture, Fetcher, GlobalFetch};
+#[cfg(feature = "har")]
+#[cfg_attr(docsrs, doc(cfg(feature = "har")))]
+pub use har::HarRecorder;
 pub use headers::Headers;
 #[doc(inline)]
 pub use http::Method;
+pub use interceptor::Next;
+pub use link::{Link, Pages};
+pub use long_poll::LongPoll;
+#[cfg(feature = "offline-queue")]
+#[cfg_attr(docsrs, doc(cfg(feature = "offline-queue")))]
+pub use offline::{Conflict, OfflineQueue, ReplayOnReconnect};
+#[cfg(feature = "persistent-cache")]
+#[cfg_attr(docsrs, doc(cfg(feature = "persistent-cache")))]
+pub use persist::PersistentCache;
+pub use poll::Polling;
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub use problem::ProblemDetails;
+pub use progress::{Progress, ProgressStream};
 pub use query::QueryParams;
+pub use rate::RateLimit;
 
-pub use request::Request;
-pub use response::{IntoRawResponse, Response};
+#[cfg(feature = "reqwest-compat")]
+pub(crate) use request::btoa;
+pub use request::{Duplex, Priority, Request, RequestBuilder};
+pub use response::{IntoRawResponse, Response, ResponseBuilder};
+pub use retry::RetryPolicy;
+#[cfg(feature = "snapshot")]
+#[cfg_attr(docsrs, doc(cfg(feature = "snapshot")))]
+pub use snapshot::{RequestSnapshot, ResponseSnapshot};
+#[cfg(feature = "background-sync")]
+#[cfg_attr(docsrs, doc(cfg(feature = "background-sync")))]
+pub use sync::{BackgroundSync, SyncEvent, SyncOutcome};
+pub use timing::{ResourceTiming, ServerTiming};
+
+/// Generates a function sending a request to an endpoint through a [`Client`].
+///
+/// The attribute takes the method and the URL of the endpoint, which is resolved against the
+/// [base URL](Client::with_base_url) of the client. It is applied to an `async fn` without a
+/// body, whose first argument is the client, and whose other arguments each fill either:
+///
+/// - a parameter of the path, like `{id}` in `"users/{id}"`, which is percent-encoded,
+/// - a parameter of the query string, when listed in `query(...)`, which is left out when it
+///   is an `Option` set to `None`,
+/// - the JSON body of the request, when named by `body = ...`.
+///
+/// Path and query parameters are formatted with [`ToString`]. The function returns a
+/// `Result<T, E>`, where `E` converts from [`Error`](crate::Error), and fails on responses with
+/// an error status. Its successful responses are decoded according to `T`: `()` ignores the
+/// body, [`Response`] returns the response itself, `String` its text, `Vec<u8>` its bytes, and
+/// any other type is deserialized from JSON.
+///
+/// # Example
+///
+/// ```
+/// use gloo_net::http::{endpoint, Client};
+/// use gloo_net::Error;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// pub struct User {
+///     pub name: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// pub struct NewUser<'a> {
+///     pub name: &'a str,
+/// }
+///
+/// #[endpoint(GET "users/{id}")]
+/// pub async fn user(client: &Client, id: u64) -> Result<User, Error>;
+///
+/// #[endpoint(GET "users", query(page, search))]
+/// pub async fn users(client: &Client, page: u32, search: Option<&str>) -> Result<Vec<User>, Error>;
+///
+/// #[endpoint(POST "users", body = user)]
+/// pub async fn create_user(client: &Client, user: &NewUser<'_>) -> Result<User, Error>;
+///
+/// #[endpoint(DELETE "users/{id}")]
+/// pub async fn delete_user(client: &Client, id: u64) -> Result<(), Error>;
+///
+/// # async fn no_run() -> Result<(), Error> {
+/// let client = Client::new().with_base_url("https://api.example.com/v1/");
+/// let created = create_user(&client, &NewUser { name: "ferris" }).await?;
+/// let found = users(&client, 1, Some(&created.name)).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+pub use gloo_net_macros::endpoint;
+
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod __private {
+    /// Percent-encodes a parameter of a path, for `#[endpoint]`.
+    pub fn encode_path_segment(segment: &str) -> String {
+        js_sys::encode_uri_component(segment).into()
+    }
+}
+#[cfg(feature = "upload")]
+#[cfg_attr(docsrs, doc(cfg(feature = "upload")))]
+pub use upload::{Tus, Upload, UploadFuture, UploadManager, UploadProtocol, UploadStatus};
@@ -0,0 +1,382 @@
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use js_sys::Array;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{EventTarget, IdbDatabase, IdbObjectStoreParameters, IdbTransactionMode};
+
+use crate::http::idb::{self, complete, request};
+use crate::http::{Client, FetchFuture, Fetcher, Method, Request, RequestSnapshot, Response};
+use crate::Error;
+
+/// The store of the queued requests, as JSON snapshots, keyed by an increasing number.
+const REQUESTS: &str = "requests";
+
+/// What to do with a queued request the server answered with `409 Conflict` or
+/// `412 Precondition Failed` when it was replayed, see [`OfflineQueue::on_conflict`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// Removes the request from the queue, and goes on with the next one.
+    Drop,
+    /// Keeps the request at the front of the queue, and stops replaying until the next time.
+    Keep,
+    /// Replaces the request with another one, like the same change based on the latest version
+    /// of the resource, and sends it right away.
+    ///
+    /// A request is replaced up to [`max_conflict_retries`](OfflineQueue::max_conflict_retries)
+    /// times per replay, after which it is kept as with [`Keep`](Self::Keep).
+    Retry(RequestSnapshot),
+}
+
+type ConflictHook = dyn Fn(RequestSnapshot, Response) -> Pin<Box<dyn Future<Output = Conflict>>>;
+
+/// Stores the mutating requests which failed because the browser was offline in IndexedDB, and
+/// sends them again, in order, once it is back online.
+///
+/// Requests are queued by a [`Client`] with [`Client::with_offline_queue`], or by hand with
+/// [`enqueue`](Self::enqueue), and replayed with [`replay`](Self::replay), e.g. automatically
+/// with [`replay_on_reconnect`](Self::replay_on_reconnect). They are stored as
+/// [`RequestSnapshot`]s, so they survive reloads, and they can be replayed from a service worker.
+///
+/// While replaying, each request is removed from the queue once the server answered it. A
+/// network error, or a `429` or `5xx` status, stops the replay and leaves the request at the front
+/// of the queue until the next replay. A `409 Conflict` or `412 Precondition Failed` status is
+/// handed to the [`on_conflict`](Self::on_conflict) hook, and any other response, including other
+/// client errors, counts as delivered.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{Client, Conflict, OfflineQueue};
+/// # async fn no_run() {
+/// let queue = OfflineQueue::new("outbox").on_conflict(|request, response| async move {
+///     // the server has a newer version: let the user merge the changes instead
+///     Conflict::Drop
+/// });
+/// let client = Client::new().with_offline_queue(queue.clone());
+/// let _replaying = queue.replay_on_reconnect();
+///
+/// let result = client.post("/notes").body("buy milk").unwrap().send().await;
+/// if let Err(gloo_net::Error::Queued(_)) = result {
+///     // offline, the note will be sent later
+/// }
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct OfflineQueue {
+    pub(crate) name: String,
+    client: Client,
+    on_conflict: Option<Rc<ConflictHook>>,
+    max_conflict_retries: u32,
+    replaying: Rc<Cell<bool>>,
+}
+
+impl OfflineQueue {
+    /// Creates a queue stored in the IndexedDB database called `name`, replaying requests with a
+    /// default [`Client`].
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            client: Client::new(),
+            on_conflict: None,
+            max_conflict_retries: 3,
+            replaying: Rc::default(),
+        }
+    }
+
+    /// Sets the client the requests are replayed with.
+    ///
+    /// This must not be a client queueing its requests in this queue, or requests failing again
+    /// would be queued twice.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sets the hook deciding what to do with a request the server answered with
+    /// `409 Conflict` or `412 Precondition Failed` when it was replayed. By default, such
+    /// requests are dropped.
+    pub fn on_conflict<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(RequestSnapshot, Response) -> Fut + 'static,
+        Fut: Future<Output = Conflict> + 'static,
+    {
+        self.on_conflict = Some(Rc::new(move |request, response| {
+            Box::pin(hook(request, response))
+        }));
+        self
+    }
+
+    /// Sets how many times in a row a request can be replaced with [`Conflict::Retry`] during a
+    /// replay, 3 by default. A request still conflicting after that stays at the front of the
+    /// queue, and the replay stops until the next time.
+    pub fn max_conflict_retries(mut self, retries: u32) -> Self {
+        self.max_conflict_retries = retries;
+        self
+    }
+
+    /// Adds a request to the back of the queue.
+    pub async fn enqueue(&self, request: &RequestSnapshot) -> Result<(), Error> {
+        let json = serde_json::to_string(request)?;
+        let db = self.db().await?;
+        let tx = idb::transaction(&db, &[REQUESTS], IdbTransactionMode::Readwrite)?;
+        let store = tx.object_store(REQUESTS).map_err(crate::js_to_error)?;
+        request_ok(store.add(&json.into())).await?;
+        complete(&tx).await
+    }
+
+    /// The queued requests, the oldest first.
+    pub async fn pending(&self) -> Result<Vec<RequestSnapshot>, Error> {
+        let db = self.db().await?;
+        let entries = entries(&db).await?;
+        Ok(entries.into_iter().map(|(_, request)| request).collect())
+    }
+
+    /// Removes all the queued requests.
+    pub async fn clear(&self) -> Result<(), Error> {
+        let db = self.db().await?;
+        let tx = idb::transaction(&db, &[REQUESTS], IdbTransactionMode::Readwrite)?;
+        let store = tx.object_store(REQUESTS).map_err(crate::js_to_error)?;
+        request_ok(store.clear()).await?;
+        complete(&tx).await
+    }
+
+    /// Sends the queued requests in order, resolving to how many were delivered.
+    ///
+    /// This does nothing while the queue is already being replayed.
+    pub async fn replay(&self) -> Result<usize, Error> {
+        if self.replaying.replace(true) {
+            return Ok(0);
+        }
+        let result = self.replay_all().await;
+        self.replaying.set(false);
+        result
+    }
+
+    async fn replay_all(&self) -> Result<usize, Error> {
+        let db = self.db().await?;
+        let mut delivered = 0;
+        for (key, mut snapshot) in entries(&db).await? {
+            let mut retries = 0;
+            loop {
+                let request = match Request::try_from(snapshot.clone()) {
+                    Ok(request) => request,
+                    // it can never be sent
+                    Err(_) => {
+                        delete(&db, &key).await?;
+                        break;
+                    }
+                };
+                let response = match self.client.send(request).await {
+                    Ok(response) => response,
+                    Err(Error::Network(_)) => return Ok(delivered),
+                    Err(e) => return Err(e),
+                };
+                match outcome(response.status()) {
+                    Outcome::Delivered => {
+                        delete(&db, &key).await?;
+                        delivered += 1;
+                        break;
+                    }
+                    Outcome::RetryLater => return Ok(delivered),
+                    Outcome::Conflict => {
+                        let conflict = match &self.on_conflict {
+                            Some(hook) => hook(snapshot.clone(), response).await,
+                            None => Conflict::Drop,
+                        };
+                        match conflict {
+                            Conflict::Drop => {
+                                delete(&db, &key).await?;
+                                break;
+                            }
+                            Conflict::Keep => return Ok(delivered),
+                            Conflict::Retry(_) if retries >= self.max_conflict_retries => {
+                                return Ok(delivered)
+                            }
+                            Conflict::Retry(replacement) => {
+                                retries += 1;
+                                put(&db, &key, &replacement).await?;
+                                snapshot = replacement;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// Replays the queue whenever the browser goes back online, and right away if it is online,
+    /// until the returned [`ReplayOnReconnect`] is dropped.
+    ///
+    /// Errors while replaying are ignored, the requests stay queued until the next time.
+    pub fn replay_on_reconnect(&self) -> ReplayOnReconnect {
+        let target: EventTarget = js_sys::global().unchecked_into();
+        let queue = self.clone();
+        let listener = Closure::<dyn FnMut()>::new(move || {
+            let queue = queue.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let _ = queue.replay().await;
+            });
+        });
+        let _ =
+            target.add_event_listener_with_callback("online", listener.as_ref().unchecked_ref());
+        if crate::online::is_online() {
+            let queue = self.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let _ = queue.replay().await;
+            });
+        }
+        ReplayOnReconnect { target, listener }
+    }
+
+    async fn db(&self) -> Result<IdbDatabase, Error> {
+        idb::open(&self.name, |db| {
+            let params = IdbObjectStoreParameters::new();
+            params.set_auto_increment(true);
+            let _ = db.create_object_store_with_optional_parameters(REQUESTS, &params);
+        })
+        .await
+    }
+}
+
+impl fmt::Debug for OfflineQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OfflineQueue")
+            .field("name", &self.name)
+            .field("client", &self.client)
+            .field("max_conflict_retries", &self.max_conflict_retries)
+            .field("replaying", &self.replaying.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Replays an [`OfflineQueue`] whenever the browser goes back online, until it is dropped.
+///
+/// See [`OfflineQueue::replay_on_reconnect`].
+#[must_use = "the queue is only replayed until this is dropped"]
+pub struct ReplayOnReconnect {
+    target: EventTarget,
+    listener: Closure<dyn FnMut()>,
+}
+
+impl Drop for ReplayOnReconnect {
+    fn drop(&mut self) {
+        let _ = self
+            .target
+            .remove_event_listener_with_callback("online", self.listener.as_ref().unchecked_ref());
+    }
+}
+
+impl fmt::Debug for ReplayOnReconnect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplayOnReconnect").finish_non_exhaustive()
+    }
+}
+
+/// How a replayed request was answered.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Delivered,
+    Conflict,
+    RetryLater,
+}
+
+fn outcome(status: u16) -> Outcome {
+    match status {
+        409 | 412 => Outcome::Conflict,
+        429 | 500..=599 => Outcome::RetryLater,
+        _ => Outcome::Delivered,
+    }
+}
+
+/// The queued requests along with their keys, the oldest first. Requests which can't be read
+/// back are skipped.
+async fn entries(db: &IdbDatabase) -> Result<Vec<(JsValue, RequestSnapshot)>, Error> {
+    let tx = idb::transaction(db, &[REQUESTS], IdbTransactionMode::Readonly)?;
+    let store = tx.object_store(REQUESTS).map_err(crate::js_to_error)?;
+    let keys: Array = request(store.get_all_keys()).await?.unchecked_into();
+    let values: Array = request(store.get_all()).await?.unchecked_into();
+    Ok(keys
+        .iter()
+        .zip(values.iter())
+        .filter_map(|(key, value)| {
+            let request = serde_json::from_str(&value.as_string()?).ok()?;
+            Some((key, request))
+        })
+        .collect())
+}
+
+async fn delete(db: &IdbDatabase, key: &JsValue) -> Result<(), Error> {
+    let tx = idb::transaction(db, &[REQUESTS], IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(REQUESTS).map_err(crate::js_to_error)?;
+    request_ok(store.delete(key)).await?;
+    complete(&tx).await
+}
+
+async fn put(db: &IdbDatabase, key: &JsValue, snapshot: &RequestSnapshot) -> Result<(), Error> {
+    let json = serde_json::to_string(snapshot)?;
+    let tx = idb::transaction(db, &[REQUESTS], IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(REQUESTS).map_err(crate::js_to_error)?;
+    request_ok(store.put_with_key(&json.into(), key)).await?;
+    complete(&tx).await
+}
+
+async fn request_ok(result: Result<web_sys::IdbRequest, JsValue>) -> Result<(), Error> {
+    request(result).await.map(drop)
+}
+
+/// A [`Fetcher`] queueing the mutating requests sent through `inner` in an [`OfflineQueue`]
+/// when they fail with a network error.
+pub(crate) struct Queue {
+    queue: OfflineQueue,
+    inner: Rc<dyn Fetcher>,
+}
+
+impl Queue {
+    pub(crate) fn new(queue: OfflineQueue, inner: Rc<dyn Fetcher>) -> Self {
+        Self { queue, inner }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let method = request.method();
+        if method == Method::GET || method == Method::HEAD || method == Method::OPTIONS {
+            return self.inner.fetch(request).await;
+        }
+        // Take the snapshot beforehand, as sending the request uses its body up.
+        let snapshot = request.snapshot().await?;
+        match self.inner.fetch(request).await {
+            Err(Error::Network(e)) => match self.queue.enqueue(&snapshot).await {
+                Ok(()) => Err(Error::Queued(e)),
+                Err(_) => Err(Error::Network(e)),
+            },
+            result => result,
+        }
+    }
+}
+
+impl Fetcher for Queue {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_replayed_responses() {
+        assert_eq!(outcome(201), Outcome::Delivered);
+        assert_eq!(outcome(404), Outcome::Delivered);
+        assert_eq!(outcome(409), Outcome::Conflict);
+        assert_eq!(outcome(412), Outcome::Conflict);
+        assert_eq!(outcome(429), Outcome::RetryLater);
+        assert_eq!(outcome(503), Outcome::RetryLater);
+    }
+}
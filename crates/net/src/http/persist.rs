@@ -0,0 +1,350 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{IdbDatabase, IdbTransaction, IdbTransactionMode};
+
+use crate::http::idb::{self, complete, request};
+use crate::http::retry::is_network_error;
+use crate::http::{FetchFuture, Fetcher, Headers, Method, Request, Response};
+use crate::{js_to_error, Error};
+
+/// The store of the stored responses, keyed by URL.
+const RESPONSES: &str = "responses";
+/// The store of the size and age of the stored responses, keyed by URL.
+const ENTRIES: &str = "entries";
+
+/// Stores `GET` responses in IndexedDB, so that they survive reloads.
+///
+/// A request for a URL whose response was stored less than [`max_age`](Self::max_age) ago is
+/// answered from the database without hitting the network. Other requests are sent as usual,
+/// and their `200 OK` responses are stored, unless they have a `Cache-Control: no-store` header.
+/// If sending the request fails with a network error, a stored response is returned regardless of
+/// its age.
+///
+/// When the stored responses take up more than [`max_size`](Self::max_size), the least recently
+/// used ones are evicted. Responses are stored by URL only, ignoring the request headers and any
+/// `Vary` header, and the responses read from the database have an empty
+/// [`url`](Response::url).
+///
+/// Unlike the [`cache`](crate::cache) module, which is a thin wrapper around the Cache API, this
+/// is a layer of a [`Client`](crate::http::Client). Failing to read from or write to the database,
+/// e.g. in a private window, doesn't fail requests, which are then just sent to the network.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{Client, PersistentCache};
+/// use std::time::Duration;
+///
+/// # async fn no_run() {
+/// let cache = PersistentCache::new("dashboard")
+///     .max_size(20 * 1024 * 1024)
+///     .max_age(Duration::from_secs(10 * 60));
+/// let client = Client::new().with_persistent_cache(cache.clone());
+/// let resp = client.get("/api/metrics").send().await.unwrap();
+///
+/// // on logout
+/// cache.clear().await.unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PersistentCache {
+    name: String,
+    max_size: u64,
+    max_age: Duration,
+}
+
+impl PersistentCache {
+    /// Creates a cache stored in the IndexedDB database called `name`, holding up to 50 MiB of
+    /// responses, which are used for 5 minutes.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            max_size: 50 * 1024 * 1024,
+            max_age: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// Sets the total size of the stored responses, in bytes, beyond which the least recently
+    /// used ones are evicted.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Sets how long a stored response is returned instead of sending the request.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Deletes all the stored responses.
+    pub async fn clear(&self) -> Result<(), Error> {
+        let db = open(&self.name).await?;
+        let tx = transaction(&db, IdbTransactionMode::Readwrite)?;
+        for store in [RESPONSES, ENTRIES] {
+            tx.object_store(store)
+                .and_then(|store| store.clear())
+                .map_err(js_to_error)?;
+        }
+        complete(&tx).await
+    }
+}
+
+/// The size and age of a stored response.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    url: String,
+    size: u64,
+    /// When the response was stored, in milliseconds since the epoch.
+    stored_at: f64,
+    /// When the response was last stored or read, in milliseconds since the epoch.
+    last_used: f64,
+}
+
+impl Entry {
+    fn to_js(&self) -> Result<JsValue, JsValue> {
+        let object = Object::new();
+        Reflect::set(&object, &"url".into(), &self.url.as_str().into())?;
+        Reflect::set(&object, &"size".into(), &(self.size as f64).into())?;
+        Reflect::set(&object, &"storedAt".into(), &self.stored_at.into())?;
+        Reflect::set(&object, &"lastUsed".into(), &self.last_used.into())?;
+        Ok(object.into())
+    }
+
+    fn from_js(value: &JsValue) -> Option<Self> {
+        let number = |key: &str| Reflect::get(value, &key.into()).ok()?.as_f64();
+        Some(Self {
+            url: Reflect::get(value, &"url".into()).ok()?.as_string()?,
+            size: number("size")? as u64,
+            stored_at: number("storedAt")?,
+            last_used: number("lastUsed")?,
+        })
+    }
+}
+
+/// The URLs of the entries to evict to bring their total size down to `max_size`, the least
+/// recently used ones first.
+fn evictions(mut entries: Vec<Entry>, max_size: u64) -> Vec<String> {
+    entries.sort_by(|a, b| b.last_used.total_cmp(&a.last_used));
+    let mut total = 0;
+    entries
+        .into_iter()
+        .filter_map(|entry| {
+            total += entry.size;
+            (total > max_size).then_some(entry.url)
+        })
+        .collect()
+}
+
+/// A [`Fetcher`] storing the responses to requests sent through `inner` in IndexedDB, according
+/// to a [`PersistentCache`].
+pub(crate) struct Persist {
+    config: PersistentCache,
+    inner: Rc<dyn Fetcher>,
+    db: RefCell<Option<IdbDatabase>>,
+}
+
+impl Persist {
+    pub(crate) fn new(config: PersistentCache, inner: Rc<dyn Fetcher>) -> Self {
+        Self {
+            config,
+            inner,
+            db: RefCell::default(),
+        }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        if request.method() != Method::GET {
+            return self.inner.fetch(request).await;
+        }
+
+        let url = request.url();
+        let db = self.db().await.ok();
+        let now = js_sys::Date::now();
+        let stored = match &db {
+            Some(db) => read(db, &url, now).await.ok().flatten(),
+            None => None,
+        };
+        let fresh = |stored_at: f64| now - stored_at < self.config.max_age.as_millis() as f64;
+        let download_progress = request.download_progress();
+        let stored = match stored {
+            Some((stored_at, response)) if fresh(stored_at) => {
+                return Ok(response.with_download_progress(download_progress));
+            }
+            stored => stored,
+        };
+
+        let response = match self.inner.fetch(request).await {
+            Err(e) if is_network_error(&e) => {
+                return match stored {
+                    Some((_, response)) => Ok(response.with_download_progress(download_progress)),
+                    None => Err(e),
+                }
+            }
+            response => response?,
+        };
+        let no_store = response.cache_control().no_store();
+        if let (Some(db), 200, false) = (&db, response.status(), no_store) {
+            let _ = write(db, &url, &response, now, self.config.max_size).await;
+        }
+        Ok(response)
+    }
+
+    /// The database, which is opened by the first request.
+    async fn db(&self) -> Result<IdbDatabase, Error> {
+        if let Some(db) = &*self.db.borrow() {
+            return Ok(db.clone());
+        }
+        let db = open(&self.config.name).await?;
+        *self.db.borrow_mut() = Some(db.clone());
+        Ok(db)
+    }
+}
+
+impl Fetcher for Persist {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+/// Reads the response stored for `url`, along with when it was stored, marking it as used.
+async fn read(db: &IdbDatabase, url: &str, now: f64) -> Result<Option<(f64, Response)>, Error> {
+    let tx = transaction(db, IdbTransactionMode::Readwrite)?;
+    let entries = tx.object_store(ENTRIES).map_err(js_to_error)?;
+    let entry = request(entries.get(&url.into())).await?;
+    let mut entry = match Entry::from_js(&entry) {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    let responses = tx.object_store(RESPONSES).map_err(js_to_error)?;
+    let stored = request(responses.get(&url.into())).await?;
+    if stored.is_undefined() {
+        return Ok(None);
+    }
+    entry.last_used = now;
+    let entry_value = entry.to_js().map_err(js_to_error)?;
+    entries
+        .put_with_key(&entry_value, &url.into())
+        .map_err(js_to_error)?;
+    complete(&tx).await?;
+    Ok(Some((entry.stored_at, response_from_js(&stored)?)))
+}
+
+/// Stores `response` for `url`, then evicts the least recently used responses beyond `max_size`.
+async fn write(
+    db: &IdbDatabase,
+    url: &str,
+    response: &Response,
+    now: f64,
+    max_size: u64,
+) -> Result<(), Error> {
+    let copy = Response::from(response.clone_raw()?);
+    let body = Uint8Array::from(copy.binary().await?.as_slice());
+    let headers = Array::new();
+    let mut size = u64::from(body.length());
+    for (name, value) in response.headers().entries() {
+        size += (name.len() + value.len()) as u64;
+        headers.push(&Array::of2(&name.into(), &value.into()));
+    }
+    if size > max_size {
+        return Ok(());
+    }
+
+    let stored = Object::new();
+    let set = |key: &str, value: &JsValue| Reflect::set(&stored, &key.into(), value);
+    set("status", &response.status().into())
+        .and_then(|_| set("statusText", &response.status_text().into()))
+        .and_then(|_| set("headers", &headers))
+        .and_then(|_| set("body", &body))
+        .map_err(js_to_error)?;
+    let entry = Entry {
+        url: url.to_string(),
+        size,
+        stored_at: now,
+        last_used: now,
+    };
+
+    let tx = transaction(db, IdbTransactionMode::Readwrite)?;
+    let responses = tx.object_store(RESPONSES).map_err(js_to_error)?;
+    let entries = tx.object_store(ENTRIES).map_err(js_to_error)?;
+    responses
+        .put_with_key(&stored, &url.into())
+        .map_err(js_to_error)?;
+    let entry_value = entry.to_js().map_err(js_to_error)?;
+    request(entries.put_with_key(&entry_value, &url.into())).await?;
+
+    let all: Array = request(entries.get_all()).await?.unchecked_into();
+    let all = all
+        .iter()
+        .filter_map(|entry| Entry::from_js(&entry))
+        .collect();
+    for url in evictions(all, max_size) {
+        let key = JsValue::from_str(&url);
+        responses.delete(&key).map_err(js_to_error)?;
+        entries.delete(&key).map_err(js_to_error)?;
+    }
+    complete(&tx).await
+}
+
+fn response_from_js(stored: &JsValue) -> Result<Response, Error> {
+    let get = |key: &str| Reflect::get(stored, &key.into()).map_err(js_to_error);
+    let headers = Headers::new();
+    let entries: Array = get("headers")?.unchecked_into();
+    for entry in entries.iter() {
+        let entry: Array = entry.unchecked_into();
+        if let (Some(name), Some(value)) = (entry.get(0).as_string(), entry.get(1).as_string()) {
+            headers.append(&name, &value);
+        }
+    }
+    let mut body = get("body")?.unchecked_into::<Uint8Array>().to_vec();
+    Response::builder()
+        .status(get("status")?.as_f64().unwrap_or(200.0) as u16)
+        .status_text(&get("statusText")?.as_string().unwrap_or_default())
+        .headers(headers)
+        .body(Some(body.as_mut_slice()))
+}
+
+/// Opens the database called `name`, creating its stores if needed.
+async fn open(name: &str) -> Result<IdbDatabase, Error> {
+    idb::open(name, |db| {
+        for store in [RESPONSES, ENTRIES] {
+            let _ = db.create_object_store(store);
+        }
+    })
+    .await
+}
+
+fn transaction(db: &IdbDatabase, mode: IdbTransactionMode) -> Result<IdbTransaction, Error> {
+    idb::transaction(db, &[RESPONSES, ENTRIES], mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str, size: u64, last_used: f64) -> Entry {
+        Entry {
+            url: url.to_string(),
+            size,
+            stored_at: 0.0,
+            last_used,
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries() {
+        let entries = vec![
+            entry("a", 40, 1.0),
+            entry("b", 40, 3.0),
+            entry("c", 40, 2.0),
+            entry("d", 10, 0.0),
+        ];
+        assert_eq!(evictions(entries.clone(), 80), ["a", "d"]);
+        assert_eq!(evictions(entries.clone(), 120), ["d"]);
+        assert!(evictions(entries, 130).is_empty());
+    }
+}
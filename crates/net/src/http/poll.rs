@@ -0,0 +1,124 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use futures_core::Stream;
+
+use crate::http::{Client, ETag, Request, Response};
+use crate::Error;
+
+/// A [`Stream`] of the responses to a request sent again and again, see [`Client::poll`].
+#[must_use = "streams do nothing unless polled"]
+pub struct Polling {
+    client: Client,
+    template: Request,
+    interval: Duration,
+    etag: Option<ETag>,
+    last_modified: Option<SystemTime>,
+    next: Option<ResponseFuture>,
+    first: bool,
+    done: bool,
+}
+
+type ResponseFuture = Pin<Box<dyn Future<Output = Result<Response, Error>>>>;
+
+impl Polling {
+    pub(crate) fn new(client: Client, request: Request, interval: Duration) -> Self {
+        Self {
+            client,
+            template: request,
+            interval,
+            etag: None,
+            last_modified: None,
+            next: None,
+            first: true,
+            done: false,
+        }
+    }
+
+    /// Starts waiting for the next changed response.
+    fn schedule(&mut self) -> Result<ResponseFuture, Error> {
+        let template = self.template.try_clone()?;
+        let delay = match std::mem::replace(&mut self.first, false) {
+            true => None,
+            false => Some(self.interval),
+        };
+        Ok(Box::pin(next_change(
+            self.client.clone(),
+            template,
+            self.interval,
+            delay,
+            self.etag.clone(),
+            self.last_modified,
+        )))
+    }
+}
+
+/// Sends `template`, after `delay`, until the response isn't a `304 Not Modified`.
+async fn next_change(
+    client: Client,
+    template: Request,
+    interval: Duration,
+    mut delay: Option<Duration>,
+    etag: Option<ETag>,
+    last_modified: Option<SystemTime>,
+) -> Result<Response, Error> {
+    loop {
+        if let Some(delay) = delay {
+            gloo_timers::future::sleep(delay).await;
+        }
+        delay = Some(interval);
+        let builder = template.try_clone()?.into_builder();
+        let request = match (&etag, last_modified) {
+            (Some(etag), _) => builder.if_none_match(etag),
+            (None, Some(time)) => builder.if_modified_since(time),
+            (None, None) => builder,
+        }
+        .build()?;
+        let response = client.send(request).await?;
+        if response.status() != 304 {
+            return Ok(response);
+        }
+    }
+}
+
+impl Stream for Polling {
+    type Item = Result<Response, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        if self.next.is_none() {
+            match self.schedule() {
+                Ok(next) => self.next = Some(next),
+                // The request can't be copied to be sent again, e.g. its body was used.
+                Err(e) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+        let result = match self.next.as_mut().map(|next| next.as_mut().poll(cx)) {
+            Some(Poll::Ready(result)) => result,
+            _ => return Poll::Pending,
+        };
+        self.next = None;
+        if let Ok(response) = &result {
+            self.etag = response.etag();
+            self.last_modified = response.last_modified();
+        }
+        Poll::Ready(Some(result))
+    }
+}
+
+impl std::fmt::Debug for Polling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Polling")
+            .field("url", &self.template.url())
+            .field("interval", &self.interval)
+            .field("etag", &self.etag)
+            .finish_non_exhaustive()
+    }
+}
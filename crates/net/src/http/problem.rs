@@ -0,0 +1,147 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::http::Response;
+use crate::Error;
+
+/// The media type of [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) problem details.
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// A machine-readable description of an error, sent by servers as an
+/// [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) `application/problem+json` body.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::Request;
+/// # async fn no_run() -> Result<(), gloo_net::Error> {
+/// let resp = Request::post("/orders").json(&["book"])?.send().await?;
+/// if let Some(problem) = resp.problem_details().await? {
+///     if problem.type_ == "https://example.com/probs/out-of-stock" {
+///         // tell the user
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProblemDetails {
+    /// A URI identifying the type of problem, `about:blank` when the server sent none.
+    pub type_: String,
+    /// A short summary of the type of problem, like `Out of stock`.
+    pub title: Option<String>,
+    /// The HTTP status code the server used for this occurrence of the problem.
+    pub status: Option<u16>,
+    /// An explanation specific to this occurrence of the problem.
+    pub detail: Option<String>,
+    /// A URI identifying this occurrence of the problem.
+    pub instance: Option<String>,
+    /// The other members of the problem details object, specific to the type of problem.
+    pub extensions: Map<String, Value>,
+}
+
+impl ProblemDetails {
+    /// Reads problem details from a JSON object, ignoring the standard members which don't have
+    /// the expected type, as the RFC requires.
+    pub fn from_json(mut object: Map<String, Value>) -> Self {
+        let mut string = |name: &str| match object.remove(name) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        };
+        let type_ = string("type").unwrap_or_else(|| "about:blank".to_string());
+        let title = string("title");
+        let detail = string("detail");
+        let instance = string("instance");
+        let status = match object.remove("status") {
+            Some(Value::Number(status)) => status.as_u64().and_then(|s| u16::try_from(s).ok()),
+            _ => None,
+        };
+        Self {
+            type_,
+            title,
+            status,
+            detail,
+            instance,
+            extensions: object,
+        }
+    }
+
+    /// Parses problem details from the text of an `application/problem+json` body.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        match serde_json::from_str(body) {
+            Ok(Value::Object(object)) => Some(Self::from_json(object)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProblemDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.title.as_deref().unwrap_or(&self.type_))?;
+        if let Some(detail) = &self.detail {
+            write!(f, ": {detail}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Response {
+    /// Whether the body of this response holds [`ProblemDetails`], according to its
+    /// `Content-Type`.
+    pub fn is_problem(&self) -> bool {
+        self.media_type().as_deref() == Some(PROBLEM_JSON)
+    }
+
+    /// Reads the [`ProblemDetails`] in the body of an `application/problem+json` response.
+    ///
+    /// Resolves to `None`, without reading the body, if the response has another `Content-Type`.
+    /// Otherwise the body is read, and can't be read again, but the status and headers of the
+    /// response can still be looked at.
+    pub async fn problem_details(&self) -> Result<Option<ProblemDetails>, Error> {
+        if !self.is_problem() {
+            return Ok(None);
+        }
+        match self.json().await? {
+            Value::Object(object) => Ok(Some(ProblemDetails::from_json(object))),
+            other => Err(Error::GlooError(format!(
+                "problem details must be a JSON object, got {other}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_problem_details() {
+        let problem = ProblemDetails::parse(
+            r#"{
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "You do not have enough credit.",
+                "status": 403,
+                "detail": "Your current balance is 30, but that costs 50.",
+                "instance": 12,
+                "balance": 30
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(problem.type_, "https://example.com/probs/out-of-credit");
+        assert_eq!(problem.status, Some(403));
+        // members with the wrong type are ignored
+        assert_eq!(problem.instance, None);
+        assert_eq!(problem.extensions["balance"], 30);
+        assert_eq!(
+            problem.to_string(),
+            "You do not have enough credit.: Your current balance is 30, but that costs 50."
+        );
+
+        let blank = ProblemDetails::parse("{}").unwrap();
+        assert_eq!(blank.type_, "about:blank");
+        assert_eq!(blank.to_string(), "about:blank");
+        assert_eq!(ProblemDetails::parse("[]"), None);
+    }
+}
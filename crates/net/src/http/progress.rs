@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+
+/// The progress of a transfer, as reported to progress callbacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of bytes transferred so far.
+    pub loaded: u64,
+    /// The total number of bytes to transfer, if known.
+    ///
+    /// For downloads, this is taken from the `Content-Length` header. Note that when the response
+    /// is compressed, this is the compressed size while `loaded` counts decompressed bytes.
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// The fraction of the transfer completed, between `0.0` and `1.0`, if the total is known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some(f64::min(self.loaded as f64 / total as f64, 1.0)),
+            None => None,
+        }
+    }
+}
+
+/// A shared progress callback.
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(Rc<RefCell<dyn FnMut(Progress)>>);
+
+impl ProgressCallback {
+    pub(crate) fn new(callback: impl FnMut(Progress) + 'static) -> Self {
+        Self(Rc::new(RefCell::new(callback)))
+    }
+
+    pub(crate) fn call(&self, progress: Progress) {
+        (self.0.borrow_mut())(progress)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Counts the bytes of a transfer and reports them to a [`ProgressCallback`].
+#[derive(Debug)]
+pub(crate) struct ProgressTracker {
+    callback: ProgressCallback,
+    progress: Progress,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(callback: ProgressCallback, total: Option<u64>) -> Self {
+        Self {
+            callback,
+            progress: Progress { loaded: 0, total },
+        }
+    }
+
+    pub(crate) fn advance(&mut self, bytes: usize) {
+        self.progress.loaded += bytes as u64;
+        self.callback.call(self.progress);
+    }
+}
+
+/// A [`Stream`] of the [`Progress`] of a background transfer, like a
+/// `Download` or an `Upload`.
+#[must_use = "streams do nothing unless polled"]
+pub struct ProgressStream {
+    receiver: mpsc::UnboundedReceiver<Progress>,
+}
+
+impl Stream for ProgressStream {
+    type Item = Progress;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl fmt::Debug for ProgressStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressStream").finish_non_exhaustive()
+    }
+}
+
+/// Creates a [`ProgressStream`], along with the sender feeding it.
+#[cfg(any(feature = "download", feature = "upload"))]
+pub(crate) fn progress_channel() -> (mpsc::UnboundedSender<Progress>, ProgressStream) {
+    let (sender, receiver) = mpsc::unbounded();
+    (sender, ProgressStream { receiver })
+}
@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::http::breaker::origin;
+use crate::http::retry::retry_after;
+use crate::http::{FetchFuture, Fetcher, Request, Response};
+use crate::Error;
+
+/// How many requests may be sent to an origin in a given time.
+///
+/// Requests are limited with a token bucket per origin: each request takes a token, and tokens
+/// are added back at a steady rate, up to [`burst`](Self::burst) tokens. A request which finds
+/// the bucket empty waits for the next token instead of failing.
+///
+/// When an origin answers with `429 Too Many Requests`, the bucket is emptied, and if the
+/// response has a `Retry-After` header, no request is sent to the origin until that delay is
+/// over.
+///
+/// Each origin has its own bucket. Relative URLs all share the bucket of the current origin.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{Client, RateLimit};
+/// use std::time::Duration;
+///
+/// # fn no_run() {
+/// // at most 10 requests per second, in bursts of up to 5
+/// let client = Client::new().with_rate_limit(RateLimit::new(10, Duration::from_secs(1)).burst(5));
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    requests: u32,
+    per: Duration,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Allows `requests` requests every `per`, all of which may be sent at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` is 0 or `per` is zero.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        assert!(requests > 0, "a rate limit must allow at least one request");
+        assert!(!per.is_zero(), "a rate limit must have a non-zero period");
+        Self {
+            requests,
+            per,
+            burst: requests,
+        }
+    }
+
+    /// Sets how many requests may be sent at once after a quiet period, at least 1.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// The number of tokens added per millisecond.
+    fn rate(&self) -> f64 {
+        f64::from(self.requests) / self.per.as_secs_f64() / 1000.0
+    }
+}
+
+/// The token bucket for one origin.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    /// When `tokens` was last updated, in milliseconds since the epoch.
+    updated: f64,
+    /// Until when the origin asked to wait, in milliseconds since the epoch.
+    paused_until: f64,
+}
+
+impl Bucket {
+    fn new(config: &RateLimit, now: f64) -> Self {
+        Self {
+            tokens: f64::from(config.burst),
+            updated: now,
+            paused_until: 0.0,
+        }
+    }
+
+    /// Takes a token at `now`, or returns how long to wait before trying again.
+    fn acquire(&mut self, config: &RateLimit, now: f64) -> Option<Duration> {
+        if now < self.paused_until {
+            return Some(millis(self.paused_until - now));
+        }
+        let elapsed = f64::max(now - self.updated, 0.0);
+        self.tokens = f64::min(
+            self.tokens + elapsed * config.rate(),
+            f64::from(config.burst),
+        );
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(millis((1.0 - self.tokens) / config.rate()))
+        }
+    }
+
+    /// Empties the bucket after a `429 Too Many Requests`, waiting for `retry_after` if given.
+    fn throttle(&mut self, retry_after: Option<Duration>, now: f64) {
+        self.tokens = 0.0;
+        self.updated = now;
+        if let Some(delay) = retry_after {
+            self.paused_until = f64::max(self.paused_until, now + delay.as_millis() as f64);
+        }
+    }
+}
+
+fn millis(millis: f64) -> Duration {
+    Duration::from_micros((millis * 1000.0).round() as u64)
+}
+
+/// A [`Fetcher`] sending requests through `inner` according to a [`RateLimit`].
+pub(crate) struct Throttle {
+    config: RateLimit,
+    inner: Rc<dyn Fetcher>,
+    buckets: RefCell<HashMap<String, Bucket>>,
+}
+
+impl Throttle {
+    pub(crate) fn new(config: RateLimit, inner: Rc<dyn Fetcher>) -> Self {
+        Self {
+            config,
+            inner,
+            buckets: RefCell::default(),
+        }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let origin = origin(&request.url());
+        loop {
+            let now = js_sys::Date::now();
+            let wait = self
+                .buckets
+                .borrow_mut()
+                .entry(origin.clone())
+                .or_insert_with(|| Bucket::new(&self.config, now))
+                .acquire(&self.config, now);
+            match wait {
+                Some(wait) => gloo_timers::future::sleep(wait).await,
+                None => break,
+            }
+        }
+
+        let response = self.inner.fetch(request).await?;
+        if response.status() == 429 {
+            if let Some(bucket) = self.buckets.borrow_mut().get_mut(&origin) {
+                bucket.throttle(retry_after(&response), js_sys::Date::now());
+            }
+        }
+        Ok(response)
+    }
+}
+
+impl Fetcher for Throttle {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refills_tokens_at_a_steady_rate() {
+        let config = RateLimit::new(10, Duration::from_secs(1)).burst(2);
+        let mut bucket = Bucket::new(&config, 0.0);
+        assert_eq!(bucket.acquire(&config, 0.0), None);
+        assert_eq!(bucket.acquire(&config, 0.0), None);
+        assert_eq!(
+            bucket.acquire(&config, 0.0),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            bucket.acquire(&config, 50.0),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(bucket.acquire(&config, 100.0), None);
+        // no more than `burst` tokens pile up
+        assert_eq!(bucket.acquire(&config, 10_000.0), None);
+        assert_eq!(bucket.acquire(&config, 10_000.0), None);
+        assert!(bucket.acquire(&config, 10_000.0).is_some());
+    }
+
+    #[test]
+    fn waits_for_retry_after() {
+        let config = RateLimit::new(10, Duration::from_secs(1));
+        let mut bucket = Bucket::new(&config, 0.0);
+        bucket.throttle(Some(Duration::from_secs(2)), 0.0);
+        assert_eq!(
+            bucket.acquire(&config, 500.0),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(bucket.acquire(&config, 2000.0), None);
+    }
+}
@@ -1,24 +1,68 @@
+use std::time::SystemTime;
 use std::{convert::From, fmt};
 
-use crate::{js_to_error, Error};
+use crate::{js_to_error, Error, StatusError};
 use js_sys::{ArrayBuffer, Uint8Array};
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
 use web_sys::ResponseInit;
 
-use crate::http::Headers;
+use crate::http::conditional::parse_http_date;
+use crate::http::progress::{ProgressCallback, ProgressTracker};
+#[cfg(feature = "io")]
+use crate::http::BodyReader;
 #[cfg(feature = "json")]
-#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+use crate::http::ProblemDetails;
+use crate::http::{BodyStream, ETag, EventStream, Headers, Request, ResourceTiming, ServerTiming};
+#[cfg(any(feature = "json", feature = "cbor", feature = "msgpack"))]
+use serde::de::value::StringDeserializer;
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
 use serde::de::DeserializeOwned;
 
 /// The [`Request`]'s response
-pub struct Response(web_sys::Response);
+pub struct Response {
+    raw: web_sys::Response,
+    download_progress: Option<ProgressCallback>,
+}
 
 impl Response {
     /// Returns an instance of response builder
     pub fn builder() -> ResponseBuilder {
         ResponseBuilder::new()
     }
+
+    /// Creates a response redirecting to `url` with `status`, which must be one of `301`, `302`,
+    /// `303`, `307` or `308`.
+    ///
+    /// A relative `url` is resolved against the base URL of the document, or the location of the
+    /// worker. The response has no body and immutable headers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Response;
+    /// # fn no_run() -> Result<(), gloo_net::Error> {
+    /// // in the `fetch` event listener of a service worker
+    /// let resp = Response::redirect("/login", 303)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn redirect(url: &str, status: u16) -> Result<Self, Error> {
+        web_sys::Response::redirect_with_status(url, status)
+            .map(Self::from)
+            .map_err(js_to_error)
+    }
+
+    /// Creates a network error response, of type `error`, which makes the `fetch` it answers fail,
+    /// e.g. from a service worker.
+    pub fn error() -> Self {
+        Self::from(web_sys::Response::error())
+    }
     /// The type read-only property of the Response interface contains the type of the response.
     ///
     /// It can be one of the following:
@@ -34,31 +78,216 @@ impl Response {
     ///  - opaqueredirect: The fetch request was made with redirect: "manual". The Response's
     ///    status is 0, headers are empty, body is null and trailer is empty.
     pub fn type_(&self) -> web_sys::ResponseType {
-        self.0.type_()
+        self.raw.type_()
+    }
+
+    /// Whether this is an opaque response, of type `opaque` or `opaqueredirect`, whose status,
+    /// headers and body are hidden from the page.
+    pub fn is_opaque(&self) -> bool {
+        matches!(
+            self.type_(),
+            web_sys::ResponseType::Opaque | web_sys::ResponseType::Opaqueredirect
+        )
+    }
+
+    /// Whether this response is a redirect: either it has a redirect status (`301`, `302`,
+    /// `303`, `307` or `308`) and a `Location` header, or it is an opaque redirect.
+    pub fn is_redirect(&self) -> bool {
+        self.type_() == web_sys::ResponseType::Opaqueredirect
+            || (is_redirect_status(self.status()) && self.headers().has("Location"))
+    }
+
+    /// The absolute URL this redirect response points to.
+    ///
+    /// This is `None` for responses which aren't redirects, and for opaque redirects: browsers
+    /// hide the `Location` of redirects fetched with [`RequestRedirect::Manual`], so it is only
+    /// available for redirects answered by a [`Fetcher`](crate::http::Fetcher) other than the
+    /// browser's `fetch`, such as a service worker's.
+    ///
+    /// [`RequestRedirect::Manual`]: web_sys::RequestRedirect::Manual
+    pub fn location(&self) -> Option<String> {
+        self.location_relative_to(&self.url())
+    }
+
+    /// The `Location` of this redirect, resolved against `base` if it is relative.
+    fn location_relative_to(&self, base: &str) -> Option<String> {
+        if !is_redirect_status(self.status()) {
+            return None;
+        }
+        let location = self.headers().get("Location")?;
+        let url = match base {
+            "" => web_sys::Url::new(&location),
+            base => web_sys::Url::new_with_base(&location, base),
+        };
+        url.ok().map(|url| url.href())
+    }
+
+    /// Builds the request following this redirect, from the request which was answered with it.
+    ///
+    /// This follows the rules browsers use: after a `303` (or a `301` or `302` to a `POST`), the
+    /// new request is a `GET` without a body; otherwise the method and body are kept, so the body
+    /// of `request` must not have been used. The `Authorization` header is dropped when the
+    /// redirect leads to another origin. This errors if the [`location`](Self::location) of the
+    /// redirect is unknown.
+    ///
+    /// # Example
+    ///
+    /// Follow redirects, but only within the origin:
+    ///
+    /// ```
+    /// # use gloo_net::http::{Request, Response};
+    /// # use gloo_net::Error;
+    /// use web_sys::RequestRedirect;
+    ///
+    /// # async fn no_run() -> Result<Response, Error> {
+    /// let mut request = Request::get("https://example.com/start")
+    ///     .redirect(RequestRedirect::Manual)
+    ///     .build()?;
+    /// loop {
+    ///     let response = request.try_clone()?.send().await?;
+    ///     match response.location() {
+    ///         Some(location) if location.starts_with("https://example.com/") => {
+    ///             request = response.follow_manually(request).await?;
+    ///         }
+    ///         _ => return Ok(response),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn follow_manually(&self, request: Request) -> Result<Request, Error> {
+        // Responses which weren't fetched, e.g. built by a `Fetcher`, have no URL.
+        let base = match self.url() {
+            url if url.is_empty() => request.url(),
+            url => url,
+        };
+        let location = self.location_relative_to(&base).ok_or_else(|| {
+            Error::GlooError("the location of the redirect is unknown".to_string())
+        })?;
+        request.redirect(&location, self.status()).await
     }
 
     /// The URL of the response.
     ///
     /// The returned value will be the final URL obtained after any redirects.
     pub fn url(&self) -> String {
-        self.0.url()
+        self.raw.url()
     }
 
     /// Whether or not this response is the result of a request you made which was redirected.
     pub fn redirected(&self) -> bool {
-        self.0.redirected()
+        self.raw.redirected()
+    }
+
+    /// The Resource Timing entry the browser recorded for this response, breaking its duration
+    /// down into DNS lookup, connection, time to first byte and download.
+    ///
+    /// Browsers only record the entry once the whole body has been received, so call this after
+    /// reading it. The entry is looked up by [`url`](Self::url), the most recent one for that URL
+    /// being returned, which isn't found for redirected responses since the browser records it
+    /// under the URL originally requested. It is also missing when the performance timeline is
+    /// full; browsers keep 250 resource entries by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// # async fn no_run() {
+    /// let resp = Request::get("/path").send().await.unwrap();
+    /// let body = resp.text().await.unwrap();
+    /// let time_to_first_byte = resp.timing().and_then(|timing| timing.ttfb());
+    /// # }
+    /// ```
+    pub fn timing(&self) -> Option<ResourceTiming> {
+        ResourceTiming::find(&self.url())
+    }
+
+    /// The metrics in the `Server-Timing` header of the response.
+    ///
+    /// The header of a cross-origin response is only visible if the server lists it in
+    /// `Access-Control-Expose-Headers`, see [`ResourceTiming::server_timing`] otherwise.
+    pub fn server_timing(&self) -> Vec<ServerTiming> {
+        self.headers()
+            .get("Server-Timing")
+            .map(|header| ServerTiming::parse_all(&header))
+            .unwrap_or_default()
     }
 
     /// the [HTTP status code](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status) of the
     /// response.
     pub fn status(&self) -> u16 {
-        self.0.status()
+        self.raw.status()
     }
 
     /// Whether the [HTTP status code](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status)
     /// was a success code (in the range `200 - 299`).
     pub fn ok(&self) -> bool {
-        self.0.ok()
+        self.raw.ok()
+    }
+
+    /// Turns a response with a client (`4xx`) or server (`5xx`) error status into a
+    /// [`StatusError`], and passes any other response through.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let text = Request::get("/path")
+    ///     .send()
+    ///     .await?
+    ///     .error_for_status()?
+    ///     .text()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn error_for_status(self) -> Result<Self, StatusError> {
+        match self.status_error() {
+            Some(error) => Err(error),
+            None => Ok(self),
+        }
+    }
+
+    /// Like [`error_for_status`](Self::error_for_status), but reads the body of an error
+    /// response into the [`StatusError`], as servers often explain the error there.
+    ///
+    /// With the `json` feature, an `application/problem+json` body is also parsed into the
+    /// [`ProblemDetails`](crate::http::ProblemDetails) of the error.
+    pub async fn error_for_status_with_body(self) -> Result<Self, StatusError> {
+        match self.status_error() {
+            Some(mut error) => {
+                #[cfg(feature = "json")]
+                let is_problem = self.is_problem();
+                // Read a copy, so that the response in the error can still be read.
+                error.body = match self.clone_raw() {
+                    Ok(copy) => Response::from(copy).text().await.ok(),
+                    Err(_) => None,
+                };
+                #[cfg(feature = "json")]
+                if is_problem {
+                    error.problem = error
+                        .body
+                        .as_deref()
+                        .and_then(ProblemDetails::parse)
+                        .map(Box::new);
+                }
+                Err(error)
+            }
+            None => Ok(self),
+        }
+    }
+
+    fn status_error(&self) -> Option<StatusError> {
+        let status = self.status();
+        (400..=599).contains(&status).then(|| StatusError {
+            status,
+            status_text: self.status_text(),
+            url: self.url(),
+            body: None,
+            response: Clone::clone(&self.raw),
+            #[cfg(feature = "json")]
+            problem: None,
+        })
     }
 
     /// The status message corresponding to the
@@ -68,29 +297,50 @@ impl Response {
     /// For example, this would be 'OK' for a status code 200, 'Continue' for 100, or 'Not Found'
     /// for 404.
     pub fn status_text(&self) -> String {
-        self.0.status_text()
+        self.raw.status_text()
     }
 
     /// Gets the headers.
     pub fn headers(&self) -> Headers {
-        Headers::from_raw(self.0.headers())
+        Headers::from_raw(self.raw.headers())
+    }
+
+    /// Parses the `ETag` header, the entity tag of the resource.
+    pub fn etag(&self) -> Option<ETag> {
+        ETag::parse(&self.headers().get("ETag")?)
+    }
+
+    /// Parses the `Last-Modified` header, the time the resource last changed.
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        parse_http_date(&self.headers().get("Last-Modified")?)
+    }
+
+    /// Parses the `Content-Length` header, the size of the body in bytes.
+    ///
+    /// When the response is compressed, this is the compressed size, not the size of the body as
+    /// read. The header is missing from streamed responses, and from cross-origin responses
+    /// unless the server exposes it.
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers()
+            .get("Content-Length")
+            .and_then(|len| len.trim().parse().ok())
     }
 
     /// Has the response body been consumed?
     ///
     /// If true, then any future attempts to consume the body will error.
     pub fn body_used(&self) -> bool {
-        self.0.body_used()
+        self.raw.body_used()
     }
 
     /// Gets the body.
     pub fn body(&self) -> Option<web_sys::ReadableStream> {
-        self.0.body()
+        self.raw.body()
     }
 
     /// Reads the response to completion, returning it as `FormData`.
     pub async fn form_data(&self) -> Result<web_sys::FormData, Error> {
-        let promise = self.0.form_data().map_err(js_to_error)?;
+        let promise = self.raw.form_data().map_err(js_to_error)?;
         let val = JsFuture::from(promise).await.map_err(js_to_error)?;
         Ok(web_sys::FormData::from(val))
     }
@@ -99,12 +349,178 @@ impl Response {
     #[cfg(feature = "json")]
     #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
     pub async fn json<T: DeserializeOwned>(&self) -> Result<T, Error> {
-        serde_json::from_str::<T>(&self.text().await?).map_err(Error::from)
+        serde_json::from_str::<T>(&self.text().await?)
+            .map_err(|e| Error::body(format!("decode the JSON body of `{}`", self.url()), e))
+    }
+
+    /// Reads the response to completion, decoding it from CBOR.
+    ///
+    /// This errors if the response has a `Content-Type` other than `application/cbor` (or a
+    /// `+cbor` type).
+    #[cfg(feature = "cbor")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    pub async fn cbor<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        self.expect_content_type(CBOR_TYPES, "+cbor")?;
+        let body = self.binary().await?;
+        ciborium::de::from_reader(body.as_slice())
+            .map_err(|e| Error::body(format!("decode the CBOR body of `{}`", self.url()), e))
+    }
+
+    /// Reads the response to completion, decoding it from MessagePack.
+    ///
+    /// This errors if the response has a `Content-Type` other than `application/msgpack`,
+    /// `application/x-msgpack`, `application/vnd.msgpack` (or a `+msgpack` type).
+    #[cfg(feature = "msgpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+    pub async fn msgpack<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        self.expect_content_type(MSGPACK_TYPES, "+msgpack")?;
+        let body = self.binary().await?;
+        rmp_serde::from_slice(&body).map_err(|e| {
+            Error::body(
+                format!("decode the MessagePack body of `{}`", self.url()),
+                e,
+            )
+        })
+    }
+
+    /// Reads the response to completion, decoding it with `bincode`.
+    ///
+    /// Only use this with a server which encodes `T` with the same version and configuration of
+    /// `bincode`, typically one written in Rust sharing the type definitions. This errors if the
+    /// response has a `Content-Type` other than `application/x-bincode` or
+    /// `application/octet-stream`.
+    #[cfg(feature = "bincode")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bincode")))]
+    pub async fn bincode<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        self.expect_content_type(
+            &["application/x-bincode", "application/octet-stream"],
+            "+bincode",
+        )?;
+        let body = self.binary().await?;
+        bincode::deserialize(&body)
+            .map_err(|e| Error::body(format!("decode the bincode body of `{}`", self.url()), e))
+    }
+
+    /// Reads the response to completion, decoding it as a protobuf message.
+    ///
+    /// This errors if the response has a `Content-Type` other than `application/x-protobuf`,
+    /// `application/protobuf` or `application/vnd.google.protobuf`.
+    #[cfg(feature = "prost")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
+    pub async fn protobuf<T: prost::Message + Default>(&self) -> Result<T, Error> {
+        self.expect_content_type(
+            &[
+                "application/x-protobuf",
+                "application/protobuf",
+                "application/vnd.google.protobuf",
+            ],
+            "+proto",
+        )?;
+        let body = self.binary().await?;
+        T::decode(body.as_slice())
+            .map_err(|e| Error::body(format!("decode the protobuf body of `{}`", self.url()), e))
+    }
+
+    /// Errors with [`Error::UnexpectedMediaType`] unless the `Content-Type` of the response, if it
+    /// has one, is one of `essences` or ends in `suffix`.
+    #[cfg(any(
+        feature = "cbor",
+        feature = "msgpack",
+        feature = "bincode",
+        feature = "prost"
+    ))]
+    fn expect_content_type(&self, essences: &[&str], suffix: &str) -> Result<(), Error> {
+        match self.media_type() {
+            Some(actual) if !is_media_type(&actual, essences, suffix) => {
+                Err(Error::UnexpectedMediaType {
+                    expected: essences[0].to_string(),
+                    actual,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The lowercased `Content-Type` of the response, without its parameters.
+    #[cfg(any(
+        feature = "json",
+        feature = "cbor",
+        feature = "msgpack",
+        feature = "bincode",
+        feature = "prost"
+    ))]
+    pub(crate) fn media_type(&self) -> Option<String> {
+        let content_type = self.headers().get("Content-Type")?;
+        let essence = content_type.split(';').next().unwrap_or_default();
+        Some(essence.trim().to_ascii_lowercase())
+    }
+
+    /// Reads the response to completion, decoding it according to its `Content-Type`.
+    ///
+    /// JSON (`application/json` or a `+json` type), CBOR and MessagePack are decoded when the
+    /// matching feature is enabled, and `text/*` responses are deserialized from their text, for
+    /// types like `String`. Any other media type, or a missing `Content-Type`, is an
+    /// [`Error::UnsupportedMediaType`]. Use [`RequestBuilder::accept`] to tell the server which
+    /// ones are supported.
+    ///
+    /// [`RequestBuilder::accept`]: crate::http::RequestBuilder::accept
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// # #[derive(serde::Deserialize)]
+    /// # struct User;
+    /// # async fn no_run() {
+    /// let resp = Request::get("/user")
+    ///     .accept("application/cbor, application/json;q=0.9")
+    ///     .send()
+    ///     .await
+    ///     .unwrap();
+    /// let user: User = resp.decode().await.unwrap();
+    /// # }
+    /// ```
+    #[cfg(any(feature = "json", feature = "cbor", feature = "msgpack"))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "json", feature = "cbor", feature = "msgpack")))
+    )]
+    pub async fn decode<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let media_type = self.media_type().unwrap_or_default();
+        #[cfg(feature = "json")]
+        if is_media_type(&media_type, JSON_TYPES, "+json") {
+            return self.json().await;
+        }
+        #[cfg(feature = "cbor")]
+        if is_media_type(&media_type, CBOR_TYPES, "+cbor") {
+            return self.cbor().await;
+        }
+        #[cfg(feature = "msgpack")]
+        if is_media_type(&media_type, MSGPACK_TYPES, "+msgpack") {
+            return self.msgpack().await;
+        }
+        if media_type.starts_with("text/") {
+            let text = self.text().await?;
+            let deserializer = StringDeserializer::<serde::de::value::Error>::new(text);
+            return T::deserialize(deserializer)
+                .map_err(|e| Error::body(format!("decode the text body of `{}`", self.url()), e));
+        }
+        Err(Error::UnsupportedMediaType(
+            self.headers().get("Content-Type").unwrap_or_default(),
+        ))
     }
 
     /// Reads the response as a String.
+    ///
+    /// When download progress is tracked, the body is read chunk by chunk and errors if it is
+    /// not valid UTF-8.
     pub async fn text(&self) -> Result<String, Error> {
-        let promise = self.0.text().unwrap();
+        if let Some(stream) = self.tracked_stream()? {
+            let body = stream.collect().await?;
+            return String::from_utf8(body)
+                .map_err(|e| Error::body(format!("decode the text body of `{}`", self.url()), e));
+        }
+        let promise = self.raw.text().unwrap();
         let val = JsFuture::from(promise).await.map_err(js_to_error)?;
         let string = js_sys::JsString::from(val);
         Ok(String::from(&string))
@@ -115,7 +531,10 @@ impl Response {
     /// This works by obtaining the response as an `ArrayBuffer`, creating a `Uint8Array` from it
     /// and then converting it to `Vec<u8>`
     pub async fn binary(&self) -> Result<Vec<u8>, Error> {
-        let promise = self.0.array_buffer().map_err(js_to_error)?;
+        if let Some(stream) = self.tracked_stream()? {
+            return stream.collect().await;
+        }
+        let promise = self.raw.array_buffer().map_err(js_to_error)?;
         let array_buffer: ArrayBuffer = JsFuture::from(promise)
             .await
             .map_err(js_to_error)?
@@ -125,17 +544,284 @@ impl Response {
         typed_buff.copy_to(&mut body);
         Ok(body)
     }
+
+    /// Reads the response body as a [`Stream`](futures_core::Stream) of chunks, as they arrive
+    /// from the network.
+    ///
+    /// This errors if the body has already been consumed.
+    pub fn into_stream(self) -> Result<BodyStream, Error> {
+        let tracker = self.download_tracker();
+        Ok(BodyStream::new(self.raw.body())?.track(tracker))
+    }
+
+    /// Turns the response into an [`http::Response`] with a streamed body, which implements
+    /// [`http_body::Body`], so that it can be handled by utilities of the `hyper` ecosystem, like
+    /// decompression layers.
+    ///
+    /// This errors if the body has already been consumed, or if the response is opaque, as it
+    /// has no status.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// use http_body::Body;
+    ///
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let response = Request::get("/data").send().await?.into_http()?;
+    /// let mut body = response.into_body();
+    /// while let Some(chunk) = body.data().await {
+    ///     let chunk: bytes::Bytes = chunk?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "http-body")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http-body")))]
+    pub fn into_http(self) -> Result<http::Response<BodyStream>, Error> {
+        let mut builder = http::Response::builder().status(self.status());
+        for (name, value) in self.headers().entries() {
+            builder = builder.header(name, value);
+        }
+        builder
+            .body(self.into_stream()?)
+            .map_err(|e| Error::GlooError(format!("invalid response: {}", e)))
+    }
+
+    /// Reports the download progress of the body to `callback` while it is being read.
+    pub(crate) fn with_download_progress(mut self, callback: Option<ProgressCallback>) -> Self {
+        self.download_progress = callback;
+        self
+    }
+
+    /// Copies the response, including its body.
+    ///
+    /// The body is teed, so that both responses read the same chunks, e.g. to store one in a
+    /// cache while the other is decoded. This only errors when the body has already been used.
+    /// The copy doesn't report the
+    /// [download progress](crate::http::RequestBuilder::on_download_progress).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let resp = Request::get("/articles/1").send().await?;
+    /// let copy = resp.try_clone()?;
+    /// let article = resp.text().await?;
+    /// // store `copy`, e.g. in the Cache API
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_clone(&self) -> Result<Response, Error> {
+        self.clone_raw().map(Response::from)
+    }
+
+    /// Copies the underlying response, whose body must not have been used yet.
+    pub(crate) fn clone_raw(&self) -> Result<web_sys::Response, Error> {
+        web_sys::Response::clone(&self.raw).map_err(js_to_error)
+    }
+
+    fn download_tracker(&self) -> Option<ProgressTracker> {
+        self.download_progress
+            .clone()
+            .map(|callback| ProgressTracker::new(callback, self.content_length()))
+    }
+
+    /// The body stream, if the download progress of this response is being tracked.
+    fn tracked_stream(&self) -> Result<Option<BodyStream>, Error> {
+        match self.download_tracker() {
+            Some(tracker) => Ok(Some(BodyStream::new(self.raw.body())?.track(Some(tracker)))),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes the body to `file` as it arrives from the network, so that it never sits in memory
+    /// as a whole, replacing the previous content of the file.
+    ///
+    /// Browsers only write the file when the whole body was received; if reading it fails, the
+    /// file is left untouched. The handle usually comes from `showSaveFilePicker`, see
+    /// [`save_as`](Self::save_as), or from the origin private file system.
+    ///
+    /// This errors if the body has already been consumed.
+    #[cfg(feature = "file-system")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "file-system")))]
+    pub async fn save_to(self, file: &web_sys::FileSystemFileHandle) -> Result<(), Error> {
+        let writable: web_sys::FileSystemWritableFileStream =
+            JsFuture::from(file.create_writable())
+                .await
+                .map_err(js_to_error)?
+                .unchecked_into();
+
+        let tracked = match self.tracked_stream() {
+            Ok(Some(stream)) => Ok(stream),
+            Ok(None) => {
+                // Let the browser move the data, unless the download progress is reported. The
+                // pipe aborts the file itself when the body fails.
+                let done = match self.raw.body() {
+                    Some(body) => body.pipe_to(&writable),
+                    None => writable.close(),
+                };
+                return JsFuture::from(done).await.map(drop).map_err(js_to_error);
+            }
+            Err(e) => Err(e),
+        };
+        let written = async {
+            let mut stream = tracked?;
+            while let Some(chunk) = stream.next_chunk().await {
+                let written = writable.write_with_u8_array(&chunk?);
+                JsFuture::from(written.map_err(js_to_error)?)
+                    .await
+                    .map_err(js_to_error)?;
+            }
+            Ok(())
+        }
+        .await;
+        if let Err(e) = written {
+            // discard what was written, leaving the file as it was
+            let _ = JsFuture::from(writable.abort()).await;
+            return Err(e);
+        }
+        JsFuture::from(writable.close())
+            .await
+            .map(drop)
+            .map_err(js_to_error)
+    }
+
+    /// Asks the user where to save the body, suggesting `file_name`, then writes it there with
+    /// [`save_to`](Self::save_to).
+    ///
+    /// This uses `showSaveFilePicker`, which is only available in some browsers, in a window, and
+    /// in response to a user gesture, like a click. If the user cancels the dialog, this fails
+    /// with [`Error::Aborted`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let resp = Request::get("/exports/dataset.csv").send().await?.error_for_status()?;
+    /// resp.save_as("dataset.csv").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "file-system")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "file-system")))]
+    pub async fn save_as(self, file_name: &str) -> Result<(), Error> {
+        let global = js_sys::global();
+        let picker = js_sys::Reflect::get(&global, &JsValue::from_str("showSaveFilePicker"))
+            .ok()
+            .and_then(|picker| picker.dyn_into::<js_sys::Function>().ok())
+            .ok_or_else(|| Error::GlooError("`showSaveFilePicker` is not available".to_string()))?;
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(
+            &options,
+            &JsValue::from_str("suggestedName"),
+            &JsValue::from_str(file_name),
+        )
+        .map_err(js_to_error)?;
+        let promise = picker.call1(&global, &options).map_err(js_to_error)?;
+        let file = JsFuture::from(js_sys::Promise::from(promise))
+            .await
+            .map_err(js_to_error)?;
+        self.save_to(file.unchecked_ref()).await
+    }
+
+    /// Parses the response body as a `text/event-stream`, yielding the
+    /// [server-sent events](https://developer.mozilla.org/en-US/docs/Web/API/Server-sent_events)
+    /// as they arrive.
+    ///
+    /// This errors if the body has already been consumed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// use futures::StreamExt;
+    ///
+    /// # async fn no_run() {
+    /// let resp = Request::post("/completions")
+    ///     .header("Authorization", "Bearer token")
+    ///     .body("{\"stream\":true}")
+    ///     .unwrap()
+    ///     .send()
+    ///     .await
+    ///     .unwrap();
+    /// let mut events = resp.events().unwrap();
+    /// while let Some(Ok(event)) = events.next().await {
+    ///     println!("{}: {}", event.event, event.data);
+    /// }
+    /// # }
+    /// ```
+    pub fn events(self) -> Result<EventStream, Error> {
+        self.into_stream().map(EventStream::new)
+    }
+
+    /// Reads the response body through an
+    /// [`AsyncRead`](futures_io::AsyncRead)/[`AsyncBufRead`](futures_io::AsyncBufRead), so it
+    /// can be handed to parsers and decoders built on `futures::io`.
+    ///
+    /// This errors if the body has already been consumed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::Request;
+    /// use futures::io::AsyncReadExt;
+    ///
+    /// # async fn no_run() {
+    /// let resp = Request::get("/archive.zip").send().await.unwrap();
+    /// let mut reader = resp.into_async_read().unwrap();
+    /// let mut buf = Vec::new();
+    /// reader.read_to_end(&mut buf).await.unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "io")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+    pub fn into_async_read(self) -> Result<BodyReader, Error> {
+        self.into_stream().map(BodyReader::new)
+    }
+}
+
+fn is_redirect_status(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+#[cfg(feature = "json")]
+const JSON_TYPES: &[&str] = &["application/json"];
+#[cfg(feature = "cbor")]
+const CBOR_TYPES: &[&str] = &["application/cbor"];
+#[cfg(feature = "msgpack")]
+const MSGPACK_TYPES: &[&str] = &[
+    "application/msgpack",
+    "application/x-msgpack",
+    "application/vnd.msgpack",
+];
+
+/// Whether the lowercased media type `actual` is one of `essences` or ends in `suffix`.
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode",
+    feature = "prost"
+))]
+fn is_media_type(actual: &str, essences: &[&str], suffix: &str) -> bool {
+    essences.contains(&actual) || actual.ends_with(suffix)
 }
 
 impl From<web_sys::Response> for Response {
     fn from(raw: web_sys::Response) -> Self {
-        Self(raw)
+        Self {
+            raw,
+            download_progress: None,
+        }
     }
 }
 
 impl From<Response> for web_sys::Response {
     fn from(res: Response) -> Self {
-        res.0
+        res.raw
     }
 }
 
@@ -171,12 +857,40 @@ impl ResponseBuilder {
         self
     }
 
-    /// Sets a header.
+    /// Sets a header, replacing any previous values.
+    ///
+    /// This throws a JavaScript exception if the name or the value is invalid, see
+    /// [`try_header`](Self::try_header) for headers which aren't known in advance.
     pub fn header(self, key: &str, value: &str) -> Self {
         self.headers.set(key, value);
         self
     }
 
+    /// Sets a header, replacing any previous values, or fails if the name or the value is
+    /// invalid, e.g. contains a line break.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::ResponseBuilder;
+    /// # fn no_run(file_name: &str) -> Result<(), gloo_net::Error> {
+    /// let resp = ResponseBuilder::new()
+    ///     .try_header("X-File-Name", file_name)?
+    ///     .body(None::<&str>)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_header(self, key: &str, value: &str) -> Result<Self, Error> {
+        self.headers.try_set(key, value)?;
+        Ok(self)
+    }
+
+    /// Adds a value to a header, keeping any previous values.
+    pub fn append_header(self, key: &str, value: &str) -> Self {
+        self.headers.append(key, value);
+        self
+    }
+
     /// Set the status code
     pub fn status(mut self, status: u16) -> Self {
         self.options.status(status);
@@ -191,15 +905,22 @@ impl ResponseBuilder {
 
     /// A convenience method to set JSON as response body
     ///
+    /// This fails, rather than panicking, if `value` can't be serialized, e.g. a map with
+    /// non-string keys.
+    ///
     /// # Note
     ///
-    /// This method also sets the `Content-Type` header to `application/json`
+    /// This method also sets the `Content-Type` header to `application/json`, unless a content
+    /// type was set before, e.g. `application/problem+json`.
     #[cfg(feature = "json")]
     #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
     pub fn json<T: serde::Serialize + ?Sized>(self, value: &T) -> Result<Response, Error> {
-        let json = serde_json::to_string(value)?;
-        self.header("Content-Type", "application/json")
-            .body(Some(json.as_str()))
+        let json = serde_json::to_string(value)
+            .map_err(|e| Error::body("encode the JSON body of a response", e))?;
+        if !self.headers.has("Content-Type") {
+            self.headers.set("Content-Type", "application/json");
+        }
+        self.body(Some(json.as_str()))
     }
 
     /// Set the response body and return the response
@@ -210,7 +931,7 @@ impl ResponseBuilder {
         self.options.headers(&self.headers.into_raw());
         let init = self.options;
 
-        data.into_raw(init).map(Response).map_err(js_to_error)
+        data.into_raw(init).map(Response::from).map_err(js_to_error)
     }
 }
 
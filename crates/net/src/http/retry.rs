@@ -0,0 +1,262 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::http::{FetchFuture, Fetcher, Method, Request, Response};
+use crate::Error;
+
+/// When and how often to retry a failed request.
+///
+/// A request is retried when sending it fails with a network error, or when the server responds
+/// with `429 Too Many Requests` or a `5xx` status. Only requests with an idempotent method (`GET`,
+/// `HEAD`, `OPTIONS`, `PUT`, `DELETE` and `TRACE`) are retried, unless
+/// [`retry_non_idempotent`](Self::retry_non_idempotent) is set.
+///
+/// Between attempts, the policy waits for an exponentially growing backoff with "full jitter":
+/// a random duration between zero and `initial_backoff * 2^attempt`, capped at `max_backoff`.
+/// When the response carries a `Retry-After` header, that delay is used instead. If the server
+/// asks to wait longer than `max_backoff`, the response is returned without retrying. With
+/// [`wait_for_online`](Self::wait_for_online), requests failing while the browser is offline
+/// are retried once it is back online instead.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{Client, Request, RetryPolicy};
+/// use std::time::Duration;
+///
+/// # async fn no_run() {
+/// let policy = RetryPolicy::new()
+///     .max_retries(5)
+///     .initial_backoff(Duration::from_millis(200));
+///
+/// // for a single request
+/// let resp = Request::get("/flaky").retry(policy).send().await;
+/// // or for every request sent through a client
+/// let client = Client::new().with_retry(policy);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub(crate) max_retries: u32,
+    initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    retry_non_idempotent: bool,
+    wait_for_online: bool,
+}
+
+impl RetryPolicy {
+    /// Creates a policy retrying up to 3 times, with an initial backoff of 100ms and a maximum
+    /// backoff of 10s.
+    pub fn new() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            retry_non_idempotent: false,
+            wait_for_online: false,
+        }
+    }
+
+    /// Sets how many times a request is retried after the first attempt.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the backoff before the first retry, which doubles with every further retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the longest time to wait between two attempts.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Whether to also retry requests with a non-idempotent method, like `POST` and `PATCH`.
+    ///
+    /// Only enable this when the server is known to handle duplicate requests safely.
+    pub fn retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    /// Whether to wait for the browser to be back [online](crate::online_status) before retrying
+    /// a request which failed while it was offline, rather than retrying it after the backoff.
+    pub fn wait_for_online(mut self, wait: bool) -> Self {
+        self.wait_for_online = wait;
+        self
+    }
+
+    fn should_retry_method(&self, method: &Method) -> bool {
+        self.retry_non_idempotent
+            || matches!(
+                *method,
+                Method::GET
+                    | Method::HEAD
+                    | Method::OPTIONS
+                    | Method::PUT
+                    | Method::DELETE
+                    | Method::TRACE
+            )
+    }
+
+    /// The backoff before retry number `attempt + 1`, given a random number in `[0, 1)`.
+    pub(crate) fn backoff(&self, attempt: u32, random: f64) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max_backoff);
+        Duration::min(exponential, self.max_backoff).mul_f64(random)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether sending a request failed because of the network, as opposed to e.g. being aborted.
+pub(crate) fn is_network_error(error: &Error) -> bool {
+    matches!(error, Error::Network(_))
+}
+
+/// Whether a response with this status is worth retrying.
+pub(crate) fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..=599).contains(&status)
+}
+
+/// The delay requested by the `Retry-After` header of `response`, if any.
+pub(crate) fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get("Retry-After")?;
+    parse_retry_after_seconds(&value).or_else(|| {
+        let date = js_sys::Date::parse(value.trim());
+        let now = js_sys::Date::now();
+        (!date.is_nan()).then(|| Duration::from_millis(f64::max(date - now, 0.0) as u64))
+    })
+}
+
+/// Parses the `delay-seconds` form of a `Retry-After` header.
+fn parse_retry_after_seconds(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok().map(Duration::from_secs)
+}
+
+/// A [`Fetcher`] retrying requests sent through `inner` according to a [`RetryPolicy`].
+pub(crate) struct Retry {
+    policy: RetryPolicy,
+    inner: Rc<dyn Fetcher>,
+}
+
+impl Retry {
+    pub(crate) fn new(policy: RetryPolicy, inner: Rc<dyn Fetcher>) -> Self {
+        Self { policy, inner }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        if !self.policy.should_retry_method(&request.method()) {
+            return self.inner.fetch(request).await;
+        }
+
+        let mut request = request;
+        let mut attempt = 0;
+        loop {
+            // Send a copy and keep the original for the next attempt, as long as there is one.
+            let copy = if attempt < self.policy.max_retries {
+                request.try_clone().ok()
+            } else {
+                None
+            };
+            let (result, original) = match copy {
+                Some(copy) => (self.inner.fetch(copy).await, request),
+                None => return self.inner.fetch(request).await,
+            };
+            request = original;
+
+            let delay = match &result {
+                Err(e) if is_network_error(e) => None,
+                Ok(response) if is_retryable_status(response.status()) => {
+                    match retry_after(response) {
+                        Some(delay) if delay > self.policy.max_backoff => return result,
+                        delay => delay,
+                    }
+                }
+                _ => return result,
+            };
+            if result.is_err() && self.policy.wait_for_online {
+                let status = crate::online_status();
+                if !status.is_online() {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(attempt = attempt + 1, "retrying request once online");
+                    status.until_online().await;
+                    attempt += 1;
+                    continue;
+                }
+            }
+            let delay =
+                delay.unwrap_or_else(|| self.policy.backoff(attempt, js_sys::Math::random()));
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                attempt = attempt + 1,
+                delay_ms = delay.as_millis() as u64,
+                "retrying request"
+            );
+            gloo_timers::future::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl Fetcher for Retry {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1));
+        assert_eq!(policy.backoff(0, 1.0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2, 1.0), Duration::from_millis(400));
+        assert_eq!(policy.backoff(2, 0.5), Duration::from_millis(200));
+        assert_eq!(policy.backoff(10, 1.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(u32::MAX, 1.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3, 0.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn only_idempotent_methods_are_retried() {
+        let policy = RetryPolicy::new();
+        assert!(policy.should_retry_method(&Method::GET));
+        assert!(policy.should_retry_method(&Method::PUT));
+        assert!(!policy.should_retry_method(&Method::POST));
+        assert!(policy
+            .retry_non_idempotent(true)
+            .should_retry_method(&Method::POST));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(
+            parse_retry_after_seconds(" 120 "),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(parse_retry_after_seconds("-1"), None);
+        assert_eq!(
+            parse_retry_after_seconds("Wed, 21 Oct 2015 07:28:00 GMT"),
+            None
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::http::{FetchFuture, Fetcher, Method, Request, Response};
+use crate::Error;
+
+/// A response stored for revalidation, along with its validators.
+struct Entry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Never read, only cloned, so that its body can be handed out any number of times.
+    response: Response,
+}
+
+/// A [`Fetcher`] revalidating `GET` responses which carry an `ETag` or `Last-Modified` header.
+///
+/// Such responses are stored by URL. The next request for the URL is sent with the matching
+/// `If-None-Match` and `If-Modified-Since` headers, and when the server answers with
+/// `304 Not Modified`, the stored response is returned in its place.
+pub(crate) struct Revalidate {
+    inner: Rc<dyn Fetcher>,
+    entries: RefCell<HashMap<String, Entry>>,
+}
+
+impl Revalidate {
+    pub(crate) fn new(inner: Rc<dyn Fetcher>) -> Self {
+        Self {
+            inner,
+            entries: RefCell::default(),
+        }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let headers = request.headers();
+        // Leave requests alone which are already conditional.
+        if request.method() != Method::GET
+            || headers.has("If-None-Match")
+            || headers.has("If-Modified-Since")
+        {
+            return self.inner.fetch(request).await;
+        }
+
+        let url = request.url();
+        let revalidating = match self.entries.borrow().get(&url) {
+            Some(entry) => {
+                if let Some(etag) = &entry.etag {
+                    headers.set("If-None-Match", etag);
+                }
+                if let Some(last_modified) = &entry.last_modified {
+                    headers.set("If-Modified-Since", last_modified);
+                }
+                true
+            }
+            None => false,
+        };
+
+        let download_progress = request.download_progress();
+        let response = self.inner.fetch(request).await?;
+        if revalidating && response.status() == 304 {
+            if let Some(entry) = self.entries.borrow().get(&url) {
+                let cached = entry.response.clone_raw()?;
+                return Ok(Response::from(cached).with_download_progress(download_progress));
+            }
+        }
+
+        let headers = response.headers();
+        let etag = headers.get("ETag");
+        let last_modified = headers.get("Last-Modified");
+        if response.status() == 200 && (etag.is_some() || last_modified.is_some()) {
+            let entry = Entry {
+                etag,
+                last_modified,
+                response: Response::from(response.clone_raw()?),
+            };
+            self.entries.borrow_mut().insert(url, entry);
+        } else if response.ok() {
+            // The resource changed and can no longer be revalidated.
+            self.entries.borrow_mut().remove(&url);
+        }
+        Ok(response)
+    }
+}
+
+impl Fetcher for Revalidate {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
@@ -0,0 +1,131 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use js_sys::Uint8Array;
+use serde::{Deserialize, Serialize};
+
+use crate::http::{Headers, Method, Request, RequestBuilder, Response};
+use crate::Error;
+
+/// A plain-data copy of a [`Request`], with its body buffered, which can be serialized.
+///
+/// Snapshots can be persisted, used as fixtures to replay requests in tests, or sent to a worker
+/// with `postMessage` once serialized. Only the method, URL, headers and body are kept, the other
+/// options of the request, like its [`RequestMode`](web_sys::RequestMode), are not.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::{Request, RequestSnapshot};
+/// # use std::convert::TryFrom;
+/// # async fn no_run() -> Result<(), gloo_net::Error> {
+/// let request = Request::post("/events").text("clicked")?;
+/// let json = serde_json::to_string(&request.snapshot().await?)?;
+///
+/// // later, maybe after a reload
+/// let snapshot: RequestSnapshot = serde_json::from_str(&json)?;
+/// let resp = Request::try_from(snapshot)?.send().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestSnapshot {
+    /// The method, like `GET`.
+    pub method: String,
+    /// The absolute URL.
+    pub url: String,
+    /// The headers, as `(name, value)` pairs.
+    pub headers: Vec<(String, String)>,
+    /// The body, if the request has one.
+    pub body: Option<Vec<u8>>,
+}
+
+/// A plain-data copy of a [`Response`], with its body buffered, which can be serialized.
+///
+/// See [`RequestSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseSnapshot {
+    /// The status code, like `200`.
+    pub status: u16,
+    /// The status message, like `OK`.
+    pub status_text: String,
+    /// The URL of the response, which is lost when converting the snapshot back into a
+    /// [`Response`].
+    pub url: String,
+    /// The headers, as `(name, value)` pairs.
+    pub headers: Vec<(String, String)>,
+    /// The body.
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Copies this request, reading a copy of its body, into a [`RequestSnapshot`].
+    ///
+    /// The body of the request itself is left unused, so it can still be sent.
+    pub async fn snapshot(&self) -> Result<RequestSnapshot, Error> {
+        let body = match self.body() {
+            Some(_) => Some(self.try_clone()?.binary().await?),
+            None => None,
+        };
+        Ok(RequestSnapshot {
+            method: self.method().to_string(),
+            url: self.url(),
+            headers: self.headers().entries().collect(),
+            body,
+        })
+    }
+}
+
+impl TryFrom<RequestSnapshot> for Request {
+    type Error = Error;
+
+    fn try_from(snapshot: RequestSnapshot) -> Result<Self, Self::Error> {
+        let method = Method::from_str(&snapshot.method)
+            .map_err(|_| Error::GlooError(format!("invalid method `{}`", snapshot.method)))?;
+        let mut builder = RequestBuilder::new(&snapshot.url).method(method);
+        for (name, value) in &snapshot.headers {
+            builder = builder.append_header(name, value);
+        }
+        match snapshot.body {
+            Some(body) => builder.body(Uint8Array::from(body.as_slice())),
+            None => builder.build(),
+        }
+    }
+}
+
+impl Response {
+    /// Copies this response, reading a copy of its body, into a [`ResponseSnapshot`].
+    ///
+    /// The body of the response itself is left unused, so it can still be read.
+    pub async fn snapshot(&self) -> Result<ResponseSnapshot, Error> {
+        let body = Response::from(self.clone_raw()?).binary().await?;
+        Ok(ResponseSnapshot {
+            status: self.status(),
+            status_text: self.status_text(),
+            url: self.url(),
+            headers: self.headers().entries().collect(),
+            body,
+        })
+    }
+}
+
+impl TryFrom<ResponseSnapshot> for Response {
+    type Error = Error;
+
+    fn try_from(mut snapshot: ResponseSnapshot) -> Result<Self, Self::Error> {
+        let headers = Headers::new();
+        for (name, value) in &snapshot.headers {
+            headers.append(name, value);
+        }
+        // Responses with a null body status can't have a body, not even an empty one.
+        let body = match snapshot.status {
+            101 | 204 | 205 | 304 => None,
+            _ => Some(snapshot.body.as_mut_slice()),
+        };
+        Response::builder()
+            .status(snapshot.status)
+            .status_text(&snapshot.status_text)
+            .headers(headers)
+            .body(body)
+    }
+}
@@ -0,0 +1,231 @@
+use js_sys::{Array, Promise};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{ExtendableEvent, ServiceWorkerRegistration};
+
+use crate::http::OfflineQueue;
+use crate::registration::{self, await_promise};
+use crate::{js_to_error, Error};
+
+#[wasm_bindgen]
+extern "C" {
+    /// The `SyncManager` of a service worker registration.
+    #[derive(Debug, Clone)]
+    type SyncManager;
+
+    #[wasm_bindgen(method, catch)]
+    fn register(this: &SyncManager, tag: &str) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = getTags)]
+    fn get_tags(this: &SyncManager) -> Result<Promise, JsValue>;
+
+    /// The `SyncEvent` dispatched to a service worker.
+    #[wasm_bindgen(extends = ExtendableEvent)]
+    #[derive(Debug, Clone)]
+    type RawSyncEvent;
+
+    #[wasm_bindgen(method, getter)]
+    fn tag(this: &RawSyncEvent) -> String;
+
+    #[wasm_bindgen(method, getter, js_name = lastChance)]
+    fn last_chance(this: &RawSyncEvent) -> bool;
+}
+
+/// The [Background Sync API](https://developer.mozilla.org/en-US/docs/Web/API/Background_Synchronization_API)
+/// of a service worker registration, which wakes the service worker up with a [`SyncEvent`] once
+/// the browser is online, even after the page was closed.
+///
+/// The API is only available in Chromium-based browsers.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::BackgroundSync;
+/// # async fn no_run() -> Result<(), gloo_net::Error> {
+/// let sync = BackgroundSync::ready().await?;
+/// sync.register("upload-photos").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BackgroundSync {
+    manager: SyncManager,
+}
+
+impl BackgroundSync {
+    /// The Background Sync of the active service worker registration: the one controlling the
+    /// page in a window, or the registration of the service worker itself.
+    pub async fn ready() -> Result<Self, Error> {
+        Self::from_registration(&registration::ready(unsupported).await?)
+    }
+
+    /// The Background Sync of `registration`.
+    pub fn from_registration(registration: &ServiceWorkerRegistration) -> Result<Self, Error> {
+        Ok(Self {
+            manager: registration::manager(registration, "sync", unsupported)?,
+        })
+    }
+
+    /// Asks for a [`SyncEvent`] with `tag` once the browser is online, right away if it is.
+    ///
+    /// Registering a tag which is already pending does nothing, so this can be called each time
+    /// there is new work to sync.
+    pub async fn register(&self, tag: &str) -> Result<(), Error> {
+        await_promise(self.manager.register(tag).map_err(js_to_error)?)
+            .await
+            .map(drop)
+    }
+
+    /// The tags of the pending registrations.
+    pub async fn tags(&self) -> Result<Vec<String>, Error> {
+        let tags = await_promise(self.manager.get_tags().map_err(js_to_error)?).await?;
+        Ok(tags
+            .unchecked_into::<Array>()
+            .iter()
+            .filter_map(|tag| tag.as_string())
+            .collect())
+    }
+}
+
+/// A `sync` event, dispatched to a service worker for a tag registered with
+/// [`BackgroundSync::register`].
+///
+/// The browser considers the sync done once the promise handed to
+/// [`wait_until`](Self::wait_until) settles, and registers it again for later if it rejects, until
+/// the [last chance](Self::last_chance).
+#[derive(Debug, Clone)]
+pub struct SyncEvent {
+    raw: RawSyncEvent,
+}
+
+impl SyncEvent {
+    /// Wraps `event` if it is a `sync` event.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::{OfflineQueue, SyncEvent};
+    /// # fn no_run(event: web_sys::Event) {
+    /// // in the `sync` event listener of the service worker
+    /// let queue = OfflineQueue::new("outbox");
+    /// if let Some(event) = SyncEvent::from_event(event) {
+    ///     queue.handle_sync(&event);
+    /// }
+    /// # }
+    /// ```
+    pub fn from_event(event: web_sys::Event) -> Option<Self> {
+        if event.type_() != "sync" {
+            return None;
+        }
+        Some(Self {
+            raw: event.unchecked_into(),
+        })
+    }
+
+    /// The tag this event was registered with.
+    pub fn tag(&self) -> String {
+        self.raw.tag()
+    }
+
+    /// Whether the browser gives up on this tag if this attempt fails.
+    pub fn last_chance(&self) -> bool {
+        self.raw.last_chance()
+    }
+
+    /// Keeps the service worker alive until `promise` settles, and registers the tag again for
+    /// later if it rejects.
+    pub fn wait_until(&self, promise: &Promise) -> Result<(), Error> {
+        self.raw.wait_until(promise).map_err(js_to_error)
+    }
+
+    /// The underlying event.
+    pub fn as_raw(&self) -> &ExtendableEvent {
+        &self.raw
+    }
+}
+
+/// The result of replaying an [`OfflineQueue`] for a [`SyncEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncOutcome {
+    /// How many requests were delivered.
+    pub delivered: usize,
+    /// How many requests are still queued.
+    pub remaining: usize,
+}
+
+impl SyncOutcome {
+    /// Whether the queue was emptied.
+    pub fn is_complete(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+impl OfflineQueue {
+    /// The tag this queue registers its [`SyncEvent`]s with.
+    pub fn sync_tag(&self) -> String {
+        format!("gloo-net-offline-queue:{}", self.name)
+    }
+
+    /// Asks the service worker to replay this queue once the browser is online, even if the
+    /// page was closed by then. The service worker must call
+    /// [`handle_sync`](Self::handle_sync) for its `sync` events.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use gloo_net::http::{Client, OfflineQueue};
+    /// # async fn no_run() -> Result<(), gloo_net::Error> {
+    /// let queue = OfflineQueue::new("outbox");
+    /// let client = Client::new().with_offline_queue(queue.clone());
+    /// if let Err(gloo_net::Error::Queued(_)) = client.post("/notes").body("hi")?.send().await {
+    ///     queue.register_sync().await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn register_sync(&self) -> Result<(), Error> {
+        BackgroundSync::ready()
+            .await?
+            .register(&self.sync_tag())
+            .await
+    }
+
+    /// Replays this queue for `event`, if it was registered with
+    /// [`register_sync`](Self::register_sync), returning whether it was.
+    ///
+    /// The service worker is kept alive while replaying. The sync fails, so that the browser
+    /// tries again later, if requests are still queued afterwards.
+    pub fn handle_sync(&self, event: &SyncEvent) -> bool {
+        if event.tag() != self.sync_tag() {
+            return false;
+        }
+        let queue = self.clone();
+        let promise = wasm_bindgen_futures::future_to_promise(async move {
+            match queue.sync().await {
+                Ok(outcome) if outcome.is_complete() => Ok(JsValue::from(outcome.delivered)),
+                Ok(outcome) => Err(JsValue::from_str(&format!(
+                    "{} requests are still queued",
+                    outcome.remaining
+                ))),
+                Err(e) => Err(JsValue::from_str(&e.to_string())),
+            }
+        });
+        // this only fails when called after the event was dispatched
+        let _ = event.wait_until(&promise);
+        true
+    }
+
+    /// Replays this queue, and counts the requests which are still queued afterwards.
+    pub async fn sync(&self) -> Result<SyncOutcome, Error> {
+        let delivered = self.replay().await?;
+        let remaining = self.pending().await?.len();
+        Ok(SyncOutcome {
+            delivered,
+            remaining,
+        })
+    }
+}
+
+fn unsupported() -> Error {
+    Error::GlooError("the Background Sync API is not available".to_string())
+}
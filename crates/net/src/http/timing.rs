@@ -0,0 +1,254 @@
+use std::time::Duration;
+
+use js_sys::Reflect;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Performance, PerformanceResourceTiming};
+
+use crate::http::headers::{split_unquoted, unquote};
+
+/// The Resource Timing entry the browser recorded for a request, see [`Response::timing`].
+///
+/// Timestamps are only exposed for same-origin requests, and for cross-origin requests whose
+/// response carries a matching `Timing-Allow-Origin` header. Otherwise, the phases below are
+/// `None` and the sizes are 0, while the overall [`duration`](Self::duration) is still known.
+///
+/// [`Response::timing`]: crate::http::Response::timing
+#[derive(Debug, Clone)]
+pub struct ResourceTiming {
+    raw: PerformanceResourceTiming,
+}
+
+impl ResourceTiming {
+    /// Finds the most recent entry recorded for `url`.
+    pub(crate) fn find(url: &str) -> Option<Self> {
+        let entries = performance()?.get_entries_by_name_with_entry_type(url, "resource");
+        entries
+            .at(-1)
+            .dyn_into::<PerformanceResourceTiming>()
+            .ok()
+            .map(|raw| Self { raw })
+    }
+
+    /// The time from the start of the request until the end of its response.
+    pub fn duration(&self) -> Duration {
+        millis(self.raw.duration())
+    }
+
+    /// The time spent following redirects.
+    pub fn redirect(&self) -> Option<Duration> {
+        phase(self.raw.redirect_start(), self.raw.redirect_end())
+    }
+
+    /// The time spent resolving the domain name, which is zero when it was cached.
+    pub fn dns(&self) -> Option<Duration> {
+        phase(self.raw.domain_lookup_start(), self.raw.domain_lookup_end())
+    }
+
+    /// The time spent establishing the connection, including the TLS handshake. This is zero when
+    /// an existing connection was reused.
+    pub fn connect(&self) -> Option<Duration> {
+        phase(self.raw.connect_start(), self.raw.connect_end())
+    }
+
+    /// The time spent on the TLS handshake, or `None` for insecure connections.
+    pub fn tls(&self) -> Option<Duration> {
+        phase(self.raw.secure_connection_start(), self.raw.connect_end())
+    }
+
+    /// The time from sending the request until the first byte of the response arrived.
+    pub fn ttfb(&self) -> Option<Duration> {
+        phase(self.raw.request_start(), self.raw.response_start())
+    }
+
+    /// The time from the first until the last byte of the response.
+    pub fn download(&self) -> Option<Duration> {
+        phase(self.raw.response_start(), self.raw.response_end())
+    }
+
+    /// The size of the response as transferred, including its headers, in bytes. This is 0 when
+    /// it was served from the HTTP cache.
+    pub fn transfer_size(&self) -> u64 {
+        self.raw.transfer_size() as u64
+    }
+
+    /// The size of the response body before decoding its `Content-Encoding`, in bytes.
+    pub fn encoded_body_size(&self) -> u64 {
+        self.raw.encoded_body_size() as u64
+    }
+
+    /// The size of the response body after decoding its `Content-Encoding`, in bytes.
+    pub fn decoded_body_size(&self) -> u64 {
+        self.raw.decoded_body_size() as u64
+    }
+
+    /// The network protocol used, like `h2` or `http/1.1`.
+    pub fn protocol(&self) -> String {
+        self.raw.next_hop_protocol()
+    }
+
+    /// The metrics the server sent in the `Server-Timing` header, as recorded by the browser.
+    ///
+    /// Unlike [`Response::server_timing`](crate::http::Response::server_timing), this works for
+    /// cross-origin responses which don't expose the header, as long as they carry a matching
+    /// `Timing-Allow-Origin` header.
+    pub fn server_timing(&self) -> Vec<ServerTiming> {
+        let entries = Reflect::get(&self.raw, &JsValue::from_str("serverTiming"))
+            .ok()
+            .and_then(|entries| entries.dyn_into::<js_sys::Array>().ok());
+        let get = |entry: &JsValue, key: &str| Reflect::get(entry, &JsValue::from_str(key)).ok();
+        entries
+            .iter()
+            .flat_map(|entries| entries.iter())
+            .filter_map(|entry| {
+                let name = get(&entry, "name")?.as_string()?;
+                let duration = get(&entry, "duration").and_then(|d| d.as_f64());
+                let description = get(&entry, "description").and_then(|d| d.as_string());
+                Some(ServerTiming {
+                    name,
+                    duration: duration.filter(|&d| d > 0.0).map(millis),
+                    description: description.filter(|d| !d.is_empty()),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A metric from the [`Server-Timing`](https://www.w3.org/TR/server-timing/) header of a
+/// response, like the time the server spent querying its database.
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::Request;
+/// # async fn no_run() {
+/// let resp = Request::get("/path").send().await.unwrap();
+/// for metric in resp.server_timing() {
+///     let ms = metric.duration().map(|d| d.as_secs_f64() * 1000.0);
+///     println!("{}: {:?} ms", metric.name(), ms);
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerTiming {
+    name: String,
+    duration: Option<Duration>,
+    description: Option<String>,
+}
+
+impl ServerTiming {
+    /// Parses the metrics of a `Server-Timing` header, like
+    /// `db;dur=53.2, cache;desc="Cache Read";dur=23.2`, skipping invalid ones.
+    pub fn parse_all(header: &str) -> Vec<Self> {
+        split_unquoted(header, ',')
+            .into_iter()
+            .filter_map(Self::parse)
+            .collect()
+    }
+
+    fn parse(metric: &str) -> Option<Self> {
+        let mut params = split_unquoted(metric, ';').into_iter();
+        let name = params.next()?.trim();
+        if name.is_empty() || name.contains(|c: char| c == '"' || c == '=' || c.is_whitespace()) {
+            return None;
+        }
+        let mut timing = Self {
+            name: name.to_string(),
+            duration: None,
+            description: None,
+        };
+        // only the first occurrence of each parameter counts
+        for param in params {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = unquote(value.trim());
+            match key.trim().to_ascii_lowercase().as_str() {
+                "dur" if timing.duration.is_none() => {
+                    timing.duration = value.parse().ok().map(millis);
+                }
+                "desc" if timing.description.is_none() => timing.description = Some(value),
+                _ => {}
+            }
+        }
+        Some(timing)
+    }
+
+    /// The name of the metric, like `db`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// How long the server spent on this metric, if it sent a `dur`.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// The description of the metric, if it sent a `desc`, like `Database query`.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+impl From<ResourceTiming> for PerformanceResourceTiming {
+    fn from(timing: ResourceTiming) -> Self {
+        timing.raw
+    }
+}
+
+/// The `performance` of the global scope.
+fn performance() -> Option<Performance> {
+    Reflect::get(&js_sys::global(), &JsValue::from_str("performance"))
+        .ok()?
+        .dyn_into()
+        .ok()
+}
+
+fn millis(millis: f64) -> Duration {
+    Duration::from_secs_f64(f64::max(millis, 0.0) / 1000.0)
+}
+
+/// The duration between two timestamps of an entry, where a zero `start` means the phase didn't
+/// happen or its timestamps are hidden.
+fn phase(start: f64, end: f64) -> Option<Duration> {
+    (start > 0.0 && end >= start).then(|| millis(end - start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_server_timing() {
+        let metrics = ServerTiming::parse_all(
+            r#"db;dur=53.2, cache;desc="Cache, \"Read\"";dur=23.25;dur=1, missedCache, =bad"#,
+        );
+        assert_eq!(
+            metrics,
+            vec![
+                ServerTiming {
+                    name: "db".to_string(),
+                    duration: Some(Duration::from_micros(53_200)),
+                    description: None,
+                },
+                ServerTiming {
+                    name: "cache".to_string(),
+                    duration: Some(Duration::from_micros(23_250)),
+                    description: Some(r#"Cache, "Read""#.to_string()),
+                },
+                ServerTiming {
+                    name: "missedCache".to_string(),
+                    duration: None,
+                    description: None,
+                },
+            ]
+        );
+        assert_eq!(ServerTiming::parse_all(""), vec![]);
+    }
+
+    #[test]
+    fn phases_with_hidden_timestamps_are_none() {
+        assert_eq!(phase(10.0, 35.5), Some(Duration::from_micros(25_500)));
+        assert_eq!(phase(10.0, 10.0), Some(Duration::ZERO));
+        assert_eq!(phase(0.0, 0.0), None);
+        assert_eq!(phase(0.0, 12.0), None);
+        assert_eq!(phase(12.0, 0.0), None);
+    }
+}
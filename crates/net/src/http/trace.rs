@@ -0,0 +1,43 @@
+use crate::http::{Fetcher, Request, Response};
+use crate::Error;
+
+/// Sends `request` through `fetcher`.
+#[cfg(not(feature = "tracing"))]
+pub(crate) async fn send<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    request: Request,
+) -> Result<Response, Error> {
+    fetcher.fetch(request).await
+}
+
+/// Sends `request` through `fetcher` in a span recording the method, URL, status and duration
+/// of the request.
+#[cfg(feature = "tracing")]
+pub(crate) async fn send<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    request: Request,
+) -> Result<Response, Error> {
+    use tracing::field::Empty;
+    use tracing::Instrument;
+
+    let span = tracing::info_span!(
+        "http.request",
+        http.method = %request.method(),
+        url = %request.url(),
+        http.status = Empty,
+        duration_ms = Empty,
+    );
+    let start = js_sys::Date::now();
+    let result = fetcher.fetch(request).instrument(span.clone()).await;
+    span.record("duration_ms", js_sys::Date::now() - start);
+    match &result {
+        Ok(response) => {
+            span.record("http.status", response.status());
+        }
+        Err(Error::Aborted) => {
+            tracing::debug!(parent: &span, "request aborted");
+        }
+        Err(e) => tracing::warn!(parent: &span, error = %e, "request failed"),
+    }
+    result
+}
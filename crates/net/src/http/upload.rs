@@ -0,0 +1,460 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use futures_channel::{mpsc, oneshot};
+use web_sys::Blob;
+
+use crate::http::progress::progress_channel;
+use crate::http::request::btoa;
+use crate::http::retry::{is_network_error, is_retryable_status};
+use crate::http::{Client, Progress, ProgressStream, RetryPolicy};
+use crate::{js_to_error, Error};
+
+/// The future returned by the methods of [`UploadProtocol`].
+pub type UploadFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + 'a>>;
+
+/// How an [`UploadManager`] talks to the server: creating an upload, finding out how much of it
+/// the server has, and sending it chunk by chunk.
+///
+/// [`Tus`] implements the [tus](https://tus.io/protocols/resumable-upload) protocol. Implement
+/// this trait for servers with their own resumable upload API.
+pub trait UploadProtocol {
+    /// Creates an upload of `size` bytes at `endpoint`, resolving to its URL.
+    fn create<'a>(
+        &'a self,
+        client: &'a Client,
+        endpoint: &'a str,
+        size: u64,
+        metadata: &'a [(String, String)],
+    ) -> UploadFuture<'a, String>;
+
+    /// Resolves to how many bytes of the upload at `url` the server has received.
+    fn offset<'a>(&'a self, client: &'a Client, url: &'a str) -> UploadFuture<'a, u64>;
+
+    /// Sends `chunk`, which starts at `offset`, to the upload at `url`, resolving to the new
+    /// offset.
+    fn send_chunk<'a>(
+        &'a self,
+        client: &'a Client,
+        url: &'a str,
+        offset: u64,
+        chunk: &'a Blob,
+    ) -> UploadFuture<'a, u64>;
+}
+
+/// The version of the tus protocol spoken by [`Tus`].
+const TUS_VERSION: &str = "1.0.0";
+
+/// The core [tus](https://tus.io/protocols/resumable-upload) protocol, along with its `creation`
+/// extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tus;
+
+impl UploadProtocol for Tus {
+    fn create<'a>(
+        &'a self,
+        client: &'a Client,
+        endpoint: &'a str,
+        size: u64,
+        metadata: &'a [(String, String)],
+    ) -> UploadFuture<'a, String> {
+        Box::pin(async move {
+            let mut builder = client
+                .post(endpoint)
+                .header("Tus-Resumable", TUS_VERSION)
+                .header("Upload-Length", &size.to_string());
+            if !metadata.is_empty() {
+                builder = builder.header("Upload-Metadata", &encode_metadata(metadata));
+            }
+            let response = builder.send().await?.error_for_status()?;
+            let location = response.headers().get("Location").ok_or_else(|| {
+                Error::GlooError("the created upload has no `Location` header".to_string())
+            })?;
+            let url =
+                web_sys::Url::new_with_base(&location, &response.url()).map_err(js_to_error)?;
+            Ok(url.href())
+        })
+    }
+
+    fn offset<'a>(&'a self, client: &'a Client, url: &'a str) -> UploadFuture<'a, u64> {
+        Box::pin(async move {
+            let response = client
+                .request(http::Method::HEAD, url)
+                .header("Tus-Resumable", TUS_VERSION)
+                .send()
+                .await?
+                .error_for_status()?;
+            upload_offset(&response)
+        })
+    }
+
+    fn send_chunk<'a>(
+        &'a self,
+        client: &'a Client,
+        url: &'a str,
+        offset: u64,
+        chunk: &'a Blob,
+    ) -> UploadFuture<'a, u64> {
+        Box::pin(async move {
+            let response = client
+                .patch(url)
+                .header("Tus-Resumable", TUS_VERSION)
+                .header("Upload-Offset", &offset.to_string())
+                .content_type("application/offset+octet-stream")
+                .body(chunk)?
+                .send()
+                .await?
+                .error_for_status()?;
+            upload_offset(&response)
+        })
+    }
+}
+
+fn upload_offset(response: &crate::http::Response) -> Result<u64, Error> {
+    response
+        .headers()
+        .get("Upload-Offset")
+        .and_then(|offset| offset.trim().parse().ok())
+        .ok_or_else(|| Error::GlooError("the response has no valid `Upload-Offset`".to_string()))
+}
+
+/// Encodes the `Upload-Metadata` header of tus, with base64 encoded values.
+fn encode_metadata(metadata: &[(String, String)]) -> String {
+    metadata
+        .iter()
+        .map(|(key, value)| {
+            // `btoa` takes a "binary string", with one character per byte.
+            let bytes: String = value.bytes().map(char::from).collect();
+            format!("{} {}", key, btoa(&bytes))
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Uploads files in chunks, resuming where the server stopped receiving after errors.
+///
+/// Each chunk is retried according to a [`RetryPolicy`] when it fails with a network error or a
+/// `429` or `5xx` status, after asking the server how much it actually received. An upload
+/// interrupted for good, e.g. by a reload, can be resumed with [`resume`](Self::resume), given
+/// its [`url`](Upload::url).
+///
+/// # Example
+///
+/// ```
+/// # use gloo_net::http::UploadManager;
+/// use futures::StreamExt;
+///
+/// # async fn no_run(file: web_sys::Blob) -> Result<(), gloo_net::Error> {
+/// let manager = UploadManager::new("/files/");
+/// let upload = manager.upload(&file, &[("filename", "holiday.jpg")]);
+///
+/// let mut progress = upload.progress_stream();
+/// while let Some(progress) = progress.next().await {
+///     // update a progress bar, e.g. with `progress.fraction()`
+/// }
+/// let url = upload.finish().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct UploadManager {
+    config: Rc<RefCell<Config>>,
+}
+
+/// The configuration of an [`UploadManager`], copied by each upload when it starts, so that it
+/// can be changed while uploads run.
+#[derive(Clone)]
+struct Config {
+    endpoint: String,
+    client: Client,
+    chunk_size: u64,
+    retry: RetryPolicy,
+    protocol: Rc<dyn UploadProtocol>,
+}
+
+impl UploadManager {
+    /// Creates a manager creating uploads at `endpoint` with the [`Tus`] protocol, in chunks of
+    /// 5 MiB.
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            config: Rc::new(RefCell::new(Config {
+                endpoint: endpoint.to_string(),
+                client: Client::new(),
+                chunk_size: 5 * 1024 * 1024,
+                retry: RetryPolicy::new(),
+                protocol: Rc::new(Tus),
+            })),
+        }
+    }
+
+    /// Sets the client the uploads are sent through.
+    pub fn client(self, client: Client) -> Self {
+        self.config.borrow_mut().client = client;
+        self
+    }
+
+    /// Sets the size of the chunks, in bytes.
+    pub fn chunk_size(self, chunk_size: u64) -> Self {
+        self.config.borrow_mut().chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Sets how failed chunks are retried.
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        self.config.borrow_mut().retry = policy;
+        self
+    }
+
+    /// Sets the protocol spoken with the server.
+    pub fn protocol(self, protocol: impl UploadProtocol + 'static) -> Self {
+        self.config.borrow_mut().protocol = Rc::new(protocol);
+        self
+    }
+
+    /// Starts uploading `file`, with `metadata` like its name.
+    pub fn upload(&self, file: &Blob, metadata: &[(&str, &str)]) -> Upload {
+        let metadata = metadata
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        self.start(file, None, metadata)
+    }
+
+    /// Resumes uploading `file` to the upload at `url`, created before by this manager.
+    pub fn resume(&self, file: &Blob, url: &str) -> Upload {
+        self.start(file, Some(url.to_string()), Vec::new())
+    }
+
+    fn start(&self, file: &Blob, url: Option<String>, metadata: Vec<(String, String)>) -> Upload {
+        let state = Rc::new(RefCell::new(State {
+            url,
+            status: UploadStatus::Uploading,
+            progress: Progress {
+                loaded: 0,
+                total: Some(file.size() as u64),
+            },
+            error: None,
+            listeners: Vec::new(),
+            waiting: Vec::new(),
+        }));
+        let config = self.config.borrow().clone();
+        let file = file.clone();
+        let task = state.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = transfer(&config, &task, &file, metadata).await;
+            let mut state = task.borrow_mut();
+            // cancelled while the last chunk was being sent
+            if state.status == UploadStatus::Cancelled {
+                return;
+            }
+            match result {
+                Ok(()) => state.finish(UploadStatus::Completed),
+                Err(e) => {
+                    state.error = Some(e.to_string());
+                    state.finish(UploadStatus::Failed);
+                }
+            }
+        });
+        Upload { state }
+    }
+}
+
+async fn transfer(
+    config: &Config,
+    state: &Rc<RefCell<State>>,
+    file: &Blob,
+    metadata: Vec<(String, String)>,
+) -> Result<(), Error> {
+    let (client, protocol) = (&config.client, &config.protocol);
+    let size = file.size() as u64;
+    let resumed = state.borrow().url.clone();
+    let (url, mut offset) = match resumed {
+        Some(url) => {
+            let offset = protocol.offset(client, &url).await?;
+            (url, offset)
+        }
+        None => {
+            let url = protocol
+                .create(client, &config.endpoint, size, &metadata)
+                .await?;
+            state.borrow_mut().url = Some(url.clone());
+            (url, 0)
+        }
+    };
+    state.borrow_mut().advance(offset);
+
+    let mut attempt = 0;
+    while offset < size {
+        if state.borrow().status == UploadStatus::Cancelled {
+            return Err(Error::GlooError("the upload was cancelled".to_string()));
+        }
+        let end = u64::min(offset + config.chunk_size, size);
+        let chunk = file
+            .slice_with_f64_and_f64(offset as f64, end as f64)
+            .map_err(js_to_error)?;
+        let error = match protocol.send_chunk(client, &url, offset, &chunk).await {
+            Ok(new_offset) if new_offset > offset => {
+                offset = new_offset;
+                attempt = 0;
+                state.borrow_mut().advance(offset);
+                continue;
+            }
+            // Counts as a failed attempt, rather than sending the same chunk forever.
+            Ok(_) => Error::GlooError(format!(
+                "the server didn't accept the chunk at offset {}",
+                offset
+            )),
+            Err(e) if is_retryable(&e) => e,
+            Err(e) => return Err(e),
+        };
+        if attempt >= config.retry.max_retries {
+            return Err(error);
+        }
+        let delay = config.retry.backoff(attempt, js_sys::Math::random());
+        gloo_timers::future::sleep(delay).await;
+        attempt += 1;
+        // The chunk may have been partially received.
+        if let Ok(received) = protocol.offset(client, &url).await {
+            offset = received;
+        }
+    }
+    Ok(())
+}
+
+/// Whether sending a chunk failed in a way worth retrying.
+fn is_retryable(error: &Error) -> bool {
+    match error {
+        Error::StatusError(error) => is_retryable_status(error.status()),
+        error => is_network_error(error),
+    }
+}
+
+/// Where an [`Upload`] is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadStatus {
+    /// Sending data.
+    Uploading,
+    /// All the data was received by the server.
+    Completed,
+    /// Failed, see [`Upload::finish`] for the error.
+    Failed,
+    /// Cancelled with [`Upload::cancel`].
+    Cancelled,
+}
+
+struct State {
+    url: Option<String>,
+    status: UploadStatus,
+    progress: Progress,
+    error: Option<String>,
+    listeners: Vec<mpsc::UnboundedSender<Progress>>,
+    waiting: Vec<oneshot::Sender<()>>,
+}
+
+impl State {
+    fn advance(&mut self, offset: u64) {
+        self.progress.loaded = offset;
+        let progress = self.progress;
+        self.listeners
+            .retain(|listener| listener.unbounded_send(progress).is_ok());
+    }
+
+    fn finish(&mut self, status: UploadStatus) {
+        self.status = status;
+        // ends the progress streams
+        self.listeners.clear();
+        for waiting in self.waiting.drain(..) {
+            let _ = waiting.send(());
+        }
+    }
+}
+
+/// A handle to an upload of an [`UploadManager`].
+#[derive(Clone)]
+pub struct Upload {
+    state: Rc<RefCell<State>>,
+}
+
+impl Upload {
+    /// The URL of the upload on the server, once it was created, to
+    /// [resume](UploadManager::resume) it later.
+    pub fn url(&self) -> Option<String> {
+        self.state.borrow().url.clone()
+    }
+
+    /// Where the upload is at.
+    pub fn status(&self) -> UploadStatus {
+        self.state.borrow().status
+    }
+
+    /// How much the server received so far.
+    pub fn progress(&self) -> Progress {
+        self.state.borrow().progress
+    }
+
+    /// A stream of the [`progress`](Self::progress), updated after each chunk, which ends when
+    /// the upload is finished.
+    pub fn progress_stream(&self) -> ProgressStream {
+        let (sender, stream) = progress_channel();
+        let mut state = self.state.borrow_mut();
+        if state.status == UploadStatus::Uploading {
+            state.listeners.push(sender);
+        }
+        stream
+    }
+
+    /// Stops the upload after the current chunk. The upload is left on the server.
+    pub fn cancel(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.status == UploadStatus::Uploading {
+            state.finish(UploadStatus::Cancelled);
+        }
+    }
+
+    /// Waits for the upload to complete, resolving to its URL.
+    pub async fn finish(&self) -> Result<String, Error> {
+        let waiting = {
+            let mut state = self.state.borrow_mut();
+            if state.status == UploadStatus::Uploading {
+                let (sender, receiver) = oneshot::channel();
+                state.waiting.push(sender);
+                Some(receiver)
+            } else {
+                None
+            }
+        };
+        if let Some(waiting) = waiting {
+            let _ = waiting.await;
+        }
+        let state = self.state.borrow();
+        match (state.status, &state.url, &state.error) {
+            (UploadStatus::Completed, Some(url), _) => Ok(url.clone()),
+            (_, _, Some(error)) => Err(Error::GlooError(format!("upload failed: {}", error))),
+            _ => Err(Error::GlooError("the upload was cancelled".to_string())),
+        }
+    }
+}
+
+impl fmt::Debug for Upload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Upload")
+            .field("url", &state.url)
+            .field("status", &state.status)
+            .field("progress", &state.progress)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for UploadManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config = self.config.borrow();
+        f.debug_struct("UploadManager")
+            .field("endpoint", &config.endpoint)
+            .field("chunk_size", &config.chunk_size)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,127 @@
+use crate::http::progress::ProgressCallback;
+use crate::http::{Headers, Progress};
+use crate::{fetch_to_error, js_to_error, Error};
+use js_sys::{Object, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    ProgressEvent, RequestCredentials, ResponseInit, XmlHttpRequest, XmlHttpRequestResponseType,
+};
+
+/// Sends `request` with an `XMLHttpRequest`, reporting the upload progress of its body to
+/// `upload_progress`.
+pub(crate) async fn send(
+    request: web_sys::Request,
+    upload_progress: ProgressCallback,
+) -> Result<web_sys::Response, Error> {
+    let body = match request.body() {
+        Some(_) => {
+            let promise = request.blob().map_err(js_to_error)?;
+            let blob = JsFuture::from(promise).await.map_err(js_to_error)?;
+            Some(blob.unchecked_into::<web_sys::Blob>())
+        }
+        None => None,
+    };
+
+    let xhr = XmlHttpRequest::new().map_err(js_to_error)?;
+    xhr.open_with_async(&request.method(), &request.url(), true)
+        .map_err(js_to_error)?;
+    xhr.set_response_type(XmlHttpRequestResponseType::Arraybuffer);
+    xhr.set_with_credentials(request.credentials() == RequestCredentials::Include);
+    for (name, value) in Headers::from_raw(request.headers()).entries() {
+        xhr.set_request_header(&name, &value).map_err(js_to_error)?;
+    }
+
+    let on_progress = Closure::<dyn FnMut(ProgressEvent)>::new(move |e: ProgressEvent| {
+        upload_progress.call(Progress {
+            loaded: e.loaded() as u64,
+            total: e.length_computable().then(|| e.total() as u64),
+        })
+    });
+    xhr.upload()
+        .map_err(js_to_error)?
+        .set_onprogress(Some(on_progress.as_ref().unchecked_ref()));
+
+    // Every terminal event resolves the promise; which one it was is checked afterwards.
+    let done = Promise::new(&mut |resolve, _reject| {
+        xhr.set_onload(Some(&resolve));
+        xhr.set_onerror(Some(&resolve));
+        xhr.set_onabort(Some(&resolve));
+        xhr.set_ontimeout(Some(&resolve));
+    });
+
+    let signal = request.signal();
+    let on_abort = {
+        let xhr = xhr.clone();
+        Closure::<dyn FnMut()>::new(move || {
+            let _ = xhr.abort();
+        })
+    };
+    // A listener rather than `onabort`, which would replace the handler of the signal's owner.
+    signal
+        .add_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref())
+        .map_err(js_to_error)?;
+
+    let event = async {
+        if signal.aborted() {
+            let _ = xhr.abort();
+        } else {
+            xhr.send_with_opt_blob(body.as_ref()).map_err(js_to_error)?;
+        }
+        JsFuture::from(done).await.map_err(js_to_error)
+    }
+    .await;
+    let _ = signal.remove_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref());
+    match event?.unchecked_into::<web_sys::Event>().type_().as_str() {
+        "load" => into_response(&request, &xhr),
+        "abort" => Err(Error::Aborted),
+        _ => Err(fetch_to_error(
+            js_sys::TypeError::new("Failed to fetch").into(),
+        )),
+    }
+}
+
+fn into_response(
+    request: &web_sys::Request,
+    xhr: &XmlHttpRequest,
+) -> Result<web_sys::Response, Error> {
+    let status = xhr.status().map_err(js_to_error)?;
+    let headers = Headers::new();
+    let raw_headers = xhr.get_all_response_headers().map_err(js_to_error)?;
+    for line in raw_headers.split("\r\n") {
+        if let Some((name, value)) = line.split_once(':') {
+            headers.append(name.trim(), value.trim());
+        }
+    }
+
+    let init = ResponseInit::new();
+    init.set_status(status);
+    init.set_status_text(&xhr.status_text().map_err(js_to_error)?);
+    init.set_headers(&headers.into_raw());
+
+    // These statuses must not have a body, or the `Response` constructor throws.
+    let body = match status {
+        101 | 204 | 205 | 304 => None,
+        _ => xhr
+            .response()
+            .map_err(js_to_error)?
+            .dyn_into::<Object>()
+            .ok(),
+    };
+    let response = web_sys::Response::new_with_opt_buffer_source_and_init(body.as_ref(), &init)
+        .map_err(js_to_error)?;
+
+    // A constructed `Response` has no URL, so shadow the getters with what the XHR reports.
+    let url = xhr.response_url();
+    let redirected = url != request.url();
+    for (name, value) in [
+        ("url", JsValue::from_str(&url)),
+        ("redirected", JsValue::from_bool(redirected)),
+    ] {
+        let descriptor = Object::new();
+        Reflect::set(&descriptor, &"value".into(), &value).map_err(js_to_error)?;
+        Object::define_property(&response, &name.into(), &descriptor);
+    }
+    Ok(response)
+}
@@ -10,15 +10,52 @@
 )]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(feature = "cache")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cache")))]
+pub mod cache;
+#[cfg(feature = "reqwest-compat")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reqwest-compat")))]
+pub mod compat;
 mod error;
 #[cfg(feature = "eventsource")]
 #[cfg_attr(docsrs, doc(cfg(feature = "eventsource")))]
 pub mod eventsource;
+#[cfg(feature = "graphql")]
+#[cfg_attr(docsrs, doc(cfg(feature = "graphql")))]
+pub mod graphql;
 #[cfg(feature = "http")]
 #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
 pub mod http;
+#[cfg(feature = "mock-api")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mock-api")))]
+pub mod mock;
+#[cfg(feature = "network-information")]
+#[cfg_attr(docsrs, doc(cfg(feature = "network-information")))]
+pub mod network;
+#[cfg(feature = "online")]
+mod online;
+#[cfg(feature = "push")]
+#[cfg_attr(docsrs, doc(cfg(feature = "push")))]
+pub mod push;
+#[cfg(any(
+    feature = "background-sync",
+    feature = "background-fetch",
+    feature = "push"
+))]
+mod registration;
+#[cfg(any(feature = "service-worker", feature = "mock-api"))]
+mod routing;
+#[cfg(feature = "service-worker")]
+#[cfg_attr(docsrs, doc(cfg(feature = "service-worker")))]
+pub mod sw;
+#[cfg(feature = "test")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+pub mod test;
 #[cfg(feature = "websocket")]
 #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
 pub mod websocket;
 
 pub use error::*;
+#[cfg(feature = "online")]
+#[cfg_attr(docsrs, doc(cfg(feature = "online")))]
+pub use online::{online_status, OnlineStatus};
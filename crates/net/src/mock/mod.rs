@@ -0,0 +1,235 @@
+//! Mocking the backend of a page, for demos and component playgrounds.
+//!
+//! A [`MockApi`] replaces the `fetch` of the page with one answering the requests matching its
+//! routes from Rust handlers. Unlike the [`test`](crate::test) module, which only answers the
+//! requests sent with this crate, this covers every `fetch` of the page, including the ones of
+//! JavaScript libraries, and leaves the requests matching no route to the network.
+//!
+//! To also answer navigations, or requests made before the Rust code runs, use a
+//! [`Router`](crate::sw::Router) in a service worker instead.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use gloo_net::http::Response;
+//! use gloo_net::mock::MockApi;
+//!
+//! # fn no_run() -> Result<(), gloo_net::Error> {
+//! MockApi::new()
+//!     .get("/api/users/:id", |_request, params| async move {
+//!         let id = params.get("id").unwrap_or_default().to_string();
+//!         Response::builder().body(Some(format!("user {}", id).as_str()))
+//!     })
+//!     .post("/api/users", |_request, _params| async {
+//!         Response::builder().status(201).body(None::<&str>)
+//!     })
+//!     .delay(Duration::from_millis(300))
+//!     .start()?
+//!     .forget();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use js_sys::{Array, Function, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::http::{Method, Request, Response};
+use crate::routing::{self, Routes};
+use crate::Error;
+
+pub use crate::routing::Params;
+
+/// Answers the `fetch`es of the page matching its routes, see the [module](self) docs.
+///
+/// Routes are matched in the order they were added. Their patterns are paths, like
+/// `/users/:id/posts`, where a segment starting with `:` matches any segment and captures it
+/// as a [parameter](Params), and a trailing `*` matches the rest of the path, captured as `*`.
+/// Patterns starting with `/` only match requests to the origin of the page; an absolute
+/// pattern, like `https://api.example.com/users/:id`, matches requests to its own origin. The
+/// query string isn't matched.
+///
+/// Handlers which fail are answered with a `500 Internal Server Error`, whose body is the
+/// error message. Requests sent from handlers go through the mocks too.
+#[derive(Debug, Default)]
+pub struct MockApi {
+    routes: Routes,
+    delay: Option<Duration>,
+    strict: bool,
+}
+
+impl MockApi {
+    /// Creates a mock API without routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers the `method` requests matching `pattern` with `handler`.
+    pub fn route<F, Fut>(mut self, method: Method, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.routes.add(method, pattern, handler);
+        self
+    }
+
+    /// Answers the [`GET`][Method::GET] requests matching `pattern` with `handler`.
+    pub fn get<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::GET, pattern, handler)
+    }
+
+    /// Answers the [`POST`][Method::POST] requests matching `pattern` with `handler`.
+    pub fn post<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::POST, pattern, handler)
+    }
+
+    /// Answers the [`PUT`][Method::PUT] requests matching `pattern` with `handler`.
+    pub fn put<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::PUT, pattern, handler)
+    }
+
+    /// Answers the [`PATCH`][Method::PATCH] requests matching `pattern` with `handler`.
+    pub fn patch<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::PATCH, pattern, handler)
+    }
+
+    /// Answers the [`DELETE`][Method::DELETE] requests matching `pattern` with `handler`.
+    pub fn delete<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::DELETE, pattern, handler)
+    }
+
+    /// Answers the requests matching no route with `handler`, instead of sending them over the
+    /// network.
+    pub fn fallback<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.routes.fallback(handler);
+        self
+    }
+
+    /// Waits for `delay` before answering each request, to simulate the latency of a real
+    /// backend.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Fails the requests matching no route with a network error, instead of sending them over
+    /// the network, to make sure the page doesn't reach a real backend.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Replaces the `fetch` of the page, until the returned [`RunningMockApi`] is dropped.
+    pub fn start(self) -> Result<RunningMockApi, Error> {
+        let global = js_sys::global();
+        let key = JsValue::from_str("fetch");
+        let original: Function = Reflect::get(&global, &key)
+            .ok()
+            .and_then(|fetch| fetch.dyn_into().ok())
+            .ok_or_else(|| Error::GlooError("the `fetch` API is not available".to_string()))?;
+        let constructor: Function = Reflect::get(&global, &JsValue::from_str("Request"))
+            .map_err(crate::js_to_error)?
+            .unchecked_into();
+        let api = self;
+        let passthrough = original.clone();
+        let this = global.clone();
+        let fetch = Closure::<dyn FnMut(JsValue, JsValue) -> Promise>::new(
+            move |input: JsValue, init: JsValue| {
+                let raw = match Reflect::construct(&constructor, &Array::of2(&input, &init)) {
+                    Ok(request) => request,
+                    Err(e) => return Promise::reject(&e),
+                };
+                let request = Request::from(raw.clone().unchecked_into::<web_sys::Request>());
+                match api.routes.find(&request) {
+                    Some((handler, params)) => {
+                        let response = handler(request, params);
+                        let delay = api.delay;
+                        wasm_bindgen_futures::future_to_promise(async move {
+                            if let Some(delay) = delay {
+                                gloo_timers::future::sleep(delay).await;
+                            }
+                            routing::respond(response).await
+                        })
+                    }
+                    None if api.strict => Promise::reject(&js_sys::TypeError::new(&format!(
+                        "no mock matches {} {}",
+                        request.method(),
+                        request.url()
+                    ))),
+                    // Constructing the request used up the body of `input`, so it is passed on
+                    // instead.
+                    None => match passthrough.call1(&this, &raw) {
+                        Ok(promise) => promise.unchecked_into(),
+                        Err(e) => Promise::reject(&e),
+                    },
+                }
+            },
+        );
+        Reflect::set(&global, &key, fetch.as_ref()).map_err(crate::js_to_error)?;
+        Ok(RunningMockApi {
+            original,
+            _fetch: fetch,
+        })
+    }
+}
+
+/// A [`MockApi`] answering the `fetch`es of the page, until it is dropped, which restores the
+/// original `fetch`.
+#[must_use = "the mocks are removed when this is dropped"]
+pub struct RunningMockApi {
+    original: Function,
+    _fetch: Closure<dyn FnMut(JsValue, JsValue) -> Promise>,
+}
+
+impl RunningMockApi {
+    /// Keeps the mocks for the lifetime of the page, e.g. in a demo mode.
+    pub fn forget(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for RunningMockApi {
+    fn drop(&mut self) {
+        let _ = Reflect::set(
+            &js_sys::global(),
+            &JsValue::from_str("fetch"),
+            &self.original,
+        );
+    }
+}
+
+impl fmt::Debug for RunningMockApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunningMockApi").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,265 @@
+//! The quality of the connection of the browser, from the
+//! [Network Information API](https://developer.mozilla.org/en-US/docs/Web/API/Network_Information_API).
+//!
+//! Apps can use it to adapt to the connection, like loading smaller images, or polling less
+//! often, on slow connections or when the user asked to save data. A [`Client`] can also
+//! forward it to the server, see [`Client::with_network_hints`].
+//!
+//! The API is only available in Chromium-based browsers.
+//!
+//! # Example
+//!
+//! ```
+//! use gloo_net::network::NetworkInformation;
+//!
+//! # fn no_run() {
+//! let thumbnail = match NetworkInformation::current() {
+//!     Some(network) if network.is_constrained() => "/thumbnails/small.jpg",
+//!     _ => "/thumbnails/large.jpg",
+//! };
+//! # }
+//! ```
+//!
+//! [`Client`]: crate::http::Client
+//! [`Client::with_network_hints`]: crate::http::Client::with_network_hints
+
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+use js_sys::Reflect;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::EventTarget;
+
+#[wasm_bindgen]
+extern "C" {
+    /// The `NetworkInformation` of `navigator.connection`.
+    #[wasm_bindgen(extends = EventTarget)]
+    #[derive(Debug, Clone)]
+    type RawConnection;
+
+    #[wasm_bindgen(method, getter, js_name = effectiveType)]
+    fn effective_type(this: &RawConnection) -> Option<String>;
+
+    #[wasm_bindgen(method, getter)]
+    fn downlink(this: &RawConnection) -> Option<f64>;
+
+    #[wasm_bindgen(method, getter)]
+    fn rtt(this: &RawConnection) -> Option<f64>;
+
+    #[wasm_bindgen(method, getter, js_name = saveData)]
+    fn save_data(this: &RawConnection) -> Option<bool>;
+}
+
+/// The type of cellular connection whose quality is the closest to the one of the connection,
+/// whatever its actual type is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EffectiveType {
+    /// Only fit for small transfers, like text-only pages.
+    Slow2g,
+    /// Fit for small images.
+    TwoG,
+    /// Fit for large assets, like high resolution images and audio.
+    ThreeG,
+    /// Fit for video, and anything else.
+    FourG,
+}
+
+impl EffectiveType {
+    fn parse(effective_type: &str) -> Option<Self> {
+        match effective_type {
+            "slow-2g" => Some(Self::Slow2g),
+            "2g" => Some(Self::TwoG),
+            "3g" => Some(Self::ThreeG),
+            "4g" => Some(Self::FourG),
+            _ => None,
+        }
+    }
+
+    /// The `effectiveType`, like `"3g"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Slow2g => "slow-2g",
+            Self::TwoG => "2g",
+            Self::ThreeG => "3g",
+            Self::FourG => "4g",
+        }
+    }
+}
+
+impl fmt::Display for EffectiveType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The quality of the connection of the browser, as estimated from recent transfers.
+///
+/// Browsers round the estimates, and leave out the ones they don't have.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetworkInformation {
+    /// The type of cellular connection closest in quality.
+    pub effective_type: Option<EffectiveType>,
+    /// The bandwidth, in megabits per second.
+    pub downlink: Option<f64>,
+    /// The round-trip time.
+    pub rtt: Option<Duration>,
+    /// Whether the user asked to use less data.
+    pub save_data: bool,
+}
+
+impl NetworkInformation {
+    /// The current quality of the connection, unless the browser doesn't tell.
+    pub fn current() -> Option<Self> {
+        connection().map(|connection| Self::read(&connection))
+    }
+
+    /// Follows the quality of the connection, until the returned stream is dropped.
+    ///
+    /// The stream yields the new quality each time it changes, and ends right away when the
+    /// browser doesn't tell.
+    pub fn changes() -> NetworkChanges {
+        let (sender, receiver) = mpsc::unbounded();
+        let connection = match connection() {
+            Some(connection) => connection,
+            None => {
+                return NetworkChanges {
+                    listener: None,
+                    receiver,
+                }
+            }
+        };
+        let raw = connection.clone();
+        let listener = Closure::<dyn FnMut()>::new(move || {
+            let _ = sender.unbounded_send(Self::read(&raw));
+        });
+        let _ = connection
+            .add_event_listener_with_callback("change", listener.as_ref().unchecked_ref());
+        NetworkChanges {
+            listener: Some((connection.unchecked_into(), listener)),
+            receiver,
+        }
+    }
+
+    /// Whether the connection calls for light payloads: the user asked to save data, or it is
+    /// as slow as a 2G connection.
+    pub fn is_constrained(&self) -> bool {
+        self.save_data
+            || matches!(
+                self.effective_type,
+                Some(EffectiveType::Slow2g) | Some(EffectiveType::TwoG)
+            )
+    }
+
+    /// The [Client Hints](https://wicg.github.io/netinfo/#client-hints) headers telling this to
+    /// a server.
+    #[cfg(any(feature = "http", test))]
+    pub(crate) fn client_hints(&self) -> Vec<(&'static str, String)> {
+        let mut hints = Vec::new();
+        if let Some(effective_type) = self.effective_type {
+            hints.push(("ECT", effective_type.to_string()));
+        }
+        if let Some(downlink) = self.downlink {
+            hints.push(("Downlink", downlink.to_string()));
+        }
+        if let Some(rtt) = self.rtt {
+            hints.push(("RTT", rtt.as_millis().to_string()));
+        }
+        if self.save_data {
+            hints.push(("Save-Data", "on".to_string()));
+        }
+        hints
+    }
+
+    fn read(connection: &RawConnection) -> Self {
+        Self {
+            effective_type: connection
+                .effective_type()
+                .and_then(|effective_type| EffectiveType::parse(&effective_type)),
+            downlink: connection.downlink(),
+            rtt: connection
+                .rtt()
+                .map(|rtt| Duration::from_millis(rtt as u64)),
+            save_data: connection.save_data().unwrap_or(false),
+        }
+    }
+}
+
+/// A [`Stream`] of the changes of the [`NetworkInformation`], see
+/// [`NetworkInformation::changes`].
+#[must_use = "streams do nothing unless polled"]
+pub struct NetworkChanges {
+    listener: Option<(EventTarget, Closure<dyn FnMut()>)>,
+    receiver: mpsc::UnboundedReceiver<NetworkInformation>,
+}
+
+impl Stream for NetworkChanges {
+    type Item = NetworkInformation;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for NetworkChanges {
+    fn drop(&mut self) {
+        if let Some((target, listener)) = &self.listener {
+            let _ = target
+                .remove_event_listener_with_callback("change", listener.as_ref().unchecked_ref());
+        }
+    }
+}
+
+impl fmt::Debug for NetworkChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetworkChanges").finish_non_exhaustive()
+    }
+}
+
+/// The `navigator.connection` of the global scope, if there is one.
+fn connection() -> Option<RawConnection> {
+    let navigator = Reflect::get(&js_sys::global(), &JsValue::from_str("navigator")).ok()?;
+    let connection = Reflect::get(&navigator, &JsValue::from_str("connection")).ok()?;
+    if connection.is_undefined() || connection.is_null() {
+        return None;
+    }
+    Some(connection.unchecked_into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_client_hints() {
+        let network = NetworkInformation {
+            effective_type: Some(EffectiveType::Slow2g),
+            downlink: Some(1.75),
+            rtt: Some(Duration::from_millis(150)),
+            save_data: true,
+        };
+        assert_eq!(
+            network.client_hints(),
+            vec![
+                ("ECT", "slow-2g".to_string()),
+                ("Downlink", "1.75".to_string()),
+                ("RTT", "150".to_string()),
+                ("Save-Data", "on".to_string()),
+            ]
+        );
+        assert!(network.is_constrained());
+
+        let network = NetworkInformation {
+            effective_type: Some(EffectiveType::FourG),
+            downlink: None,
+            rtt: None,
+            save_data: false,
+        };
+        assert_eq!(network.client_hints(), vec![("ECT", "4g".to_string())]);
+        assert!(!network.is_constrained());
+    }
+}
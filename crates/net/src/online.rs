@@ -0,0 +1,123 @@
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+use js_sys::Reflect;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::EventTarget;
+
+/// Follows whether the browser is online, until the returned [`OnlineStatus`] is dropped.
+///
+/// # Example
+///
+/// ```
+/// use futures::StreamExt;
+///
+/// # async fn no_run() {
+/// let mut status = gloo_net::online_status();
+/// println!("online: {}", status.is_online());
+/// while let Some(online) = status.next().await {
+///     println!("online: {}", online);
+/// }
+/// # }
+/// ```
+pub fn online_status() -> OnlineStatus {
+    let target: EventTarget = js_sys::global().unchecked_into();
+    let (sender, receiver) = mpsc::unbounded();
+    let listener = |online: bool| {
+        let sender = sender.clone();
+        Closure::<dyn FnMut()>::new(move || {
+            let _ = sender.unbounded_send(online);
+        })
+    };
+    let status = OnlineStatus {
+        target,
+        online: listener(true),
+        offline: listener(false),
+        receiver,
+    };
+    for (event, listener) in status.listeners().iter() {
+        let _ = status
+            .target
+            .add_event_listener_with_callback(event, listener);
+    }
+    status
+}
+
+/// Whether the browser is online, and a [`Stream`] of the changes, see [`online_status`].
+///
+/// `navigator.onLine` being `true` only tells that the browser is connected to a network, which
+/// may not reach the internet. Being `false` is reliable.
+#[must_use = "streams do nothing unless polled"]
+pub struct OnlineStatus {
+    target: EventTarget,
+    online: Closure<dyn FnMut()>,
+    offline: Closure<dyn FnMut()>,
+    receiver: mpsc::UnboundedReceiver<bool>,
+}
+
+impl OnlineStatus {
+    /// Whether the browser is online right now, according to `navigator.onLine`.
+    pub fn is_online(&self) -> bool {
+        is_online()
+    }
+
+    fn listeners(&self) -> [(&str, &js_sys::Function); 2] {
+        [
+            ("online", self.online.as_ref().unchecked_ref()),
+            ("offline", self.offline.as_ref().unchecked_ref()),
+        ]
+    }
+
+    /// Waits until the browser is online, right away if it is.
+    pub async fn until_online(mut self) {
+        if self.is_online() {
+            return;
+        }
+        let mut this = Pin::new(&mut self);
+        while let Some(online) = std::future::poll_fn(|cx| this.as_mut().poll_next(cx)).await {
+            if online {
+                return;
+            }
+        }
+    }
+}
+
+impl Stream for OnlineStatus {
+    type Item = bool;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for OnlineStatus {
+    fn drop(&mut self) {
+        for (event, listener) in self.listeners().iter() {
+            let _ = self
+                .target
+                .remove_event_listener_with_callback(event, listener);
+        }
+    }
+}
+
+impl fmt::Debug for OnlineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnlineStatus")
+            .field("online", &self.is_online())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether the browser is online, according to `navigator.onLine`, assuming it is where this
+/// isn't available.
+pub(crate) fn is_online() -> bool {
+    Reflect::get(&js_sys::global(), &JsValue::from_str("navigator"))
+        .and_then(|navigator| Reflect::get(&navigator, &JsValue::from_str("onLine")))
+        .ok()
+        .and_then(|online| online.as_bool())
+        .unwrap_or(true)
+}
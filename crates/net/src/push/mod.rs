@@ -0,0 +1,341 @@
+//! Receiving push messages from the backend, through the
+//! [Push API](https://developer.mozilla.org/en-US/docs/Web/API/Push_API).
+//!
+//! The page [subscribes](Push::subscribe) to push messages with the public VAPID key of the
+//! backend, and sends the [`PushSubscription`] to the backend, which then pushes messages to
+//! its endpoint. They wake up the service worker, which reads them from [`messages`], and
+//! usually shows a notification.
+//!
+//! # Example
+//!
+//! ```
+//! use gloo_net::http::Request;
+//! use gloo_net::push::Push;
+//!
+//! # async fn no_run() -> Result<(), gloo_net::Error> {
+//! // in the page
+//! let push = Push::ready().await?;
+//! let subscription = push.subscribe("BEl62iUYgUivxIkv69yViEuiBIa-Ib9-SkvMeAtA3LFgDzkrxZJjSgSnfckjBJuBkr3qBUYIHBQFLXYp5Nksh8U").await?;
+//! Request::post("/api/push/subscriptions")
+//!     .json(&subscription.to_json()?)?
+//!     .send()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ```
+//! use futures::StreamExt;
+//! use gloo_net::push;
+//!
+//! // in the service worker
+//! # async fn no_run() {
+//! let mut messages = push::messages();
+//! while let Some(message) = messages.next().await {
+//!     let text = message.text();
+//!     // show a notification, then drop the message
+//! }
+//! # }
+//! ```
+
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+use js_sys::{Promise, Reflect, Uint8Array};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{EventTarget, PushEvent, PushSubscriptionOptionsInit, ServiceWorkerRegistration};
+
+use crate::registration::{self, await_promise};
+use crate::{js_to_error, Error};
+
+/// The `PushManager` of a service worker registration, subscribing to push messages.
+#[derive(Debug, Clone)]
+pub struct Push {
+    manager: web_sys::PushManager,
+}
+
+impl Push {
+    /// The push manager of the active service worker registration: the one controlling the page
+    /// in a window, or the registration of the service worker itself.
+    pub async fn ready() -> Result<Self, Error> {
+        Self::from_registration(&registration::ready(unsupported).await?)
+    }
+
+    /// The push manager of `registration`.
+    pub fn from_registration(registration: &ServiceWorkerRegistration) -> Result<Self, Error> {
+        Ok(Self {
+            manager: registration::manager(registration, "pushManager", unsupported)?,
+        })
+    }
+
+    /// Subscribes to the push messages sent by the backend whose public VAPID key is
+    /// `application_server_key`, encoded in URL-safe base64 as VAPID libraries print it.
+    ///
+    /// This asks the user for the permission to show notifications, if they didn't answer yet,
+    /// and returns the existing subscription if there is one for the same key. Every push
+    /// message must show a notification.
+    pub async fn subscribe(&self, application_server_key: &str) -> Result<Subscription, Error> {
+        let key = decode_base64_url(application_server_key).ok_or_else(|| {
+            Error::GlooError(format!(
+                "invalid application server key `{}`",
+                application_server_key
+            ))
+        })?;
+        let options = PushSubscriptionOptionsInit::new();
+        options.set_user_visible_only(true);
+        options.set_application_server_key(&Uint8Array::from(key.as_slice()));
+        let promise = self
+            .manager
+            .subscribe_with_options(&options)
+            .map_err(js_to_error)?;
+        Ok(Subscription {
+            raw: await_promise(promise).await?.unchecked_into(),
+        })
+    }
+
+    /// The current subscription, if there is one.
+    pub async fn subscription(&self) -> Result<Option<Subscription>, Error> {
+        let promise = self.manager.get_subscription().map_err(js_to_error)?;
+        let raw = await_promise(promise).await?;
+        if raw.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(Subscription {
+            raw: raw.unchecked_into(),
+        }))
+    }
+}
+
+/// A subscription to push messages, see [`Push::subscribe`].
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    raw: web_sys::PushSubscription,
+}
+
+impl Subscription {
+    /// The URL the backend sends the push messages to.
+    pub fn endpoint(&self) -> String {
+        self.raw.endpoint()
+    }
+
+    /// What the backend needs to send push messages to this subscription.
+    pub fn to_json(&self) -> Result<PushSubscription, Error> {
+        let json = self.raw.to_json().map_err(js_to_error)?;
+        let keys = json.get_keys();
+        let key = |key: Option<String>| {
+            key.ok_or_else(|| Error::GlooError("the push subscription has no keys".to_string()))
+        };
+        let expiration_time = Reflect::get(&self.raw, &JsValue::from_str("expirationTime"))
+            .ok()
+            .and_then(|time| time.as_f64())
+            .map(|time| time as u64);
+        Ok(PushSubscription {
+            endpoint: self.endpoint(),
+            expiration_time,
+            keys: PushKeys {
+                p256dh: key(keys.as_ref().and_then(|keys| keys.get_p256dh()))?,
+                auth: key(keys.as_ref().and_then(|keys| keys.get_auth()))?,
+            },
+        })
+    }
+
+    /// Stops the push messages, returning whether this was still subscribed.
+    pub async fn unsubscribe(&self) -> Result<bool, Error> {
+        let promise = self.raw.unsubscribe().map_err(js_to_error)?;
+        Ok(await_promise(promise).await?.is_truthy())
+    }
+
+    /// The underlying `PushSubscription`.
+    pub fn as_raw(&self) -> &web_sys::PushSubscription {
+        &self.raw
+    }
+}
+
+/// A [`Subscription`] as sent to the backend: the JSON of `PushSubscription.toJSON()`, which
+/// Web Push libraries read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushSubscription {
+    /// The URL the backend sends the push messages to.
+    pub endpoint: String,
+    /// When the subscription expires, in milliseconds since the Unix epoch, if it does.
+    pub expiration_time: Option<u64>,
+    /// The keys encrypting the push messages.
+    pub keys: PushKeys,
+}
+
+/// The keys encrypting the push messages of a [`PushSubscription`], in URL-safe base64.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushKeys {
+    /// The public key of the browser, on the P-256 curve.
+    pub p256dh: String,
+    /// The authentication secret.
+    pub auth: String,
+}
+
+/// Receives the push messages of the service worker, until the returned stream is dropped.
+///
+/// The stream never ends. The service worker is kept alive while a [`PushMessage`] is around,
+/// so hold on to it until the work it triggers, like showing a notification, is done.
+pub fn messages() -> PushMessages {
+    let target: EventTarget = js_sys::global().unchecked_into();
+    let (sender, receiver) = mpsc::unbounded();
+    let listener = Closure::<dyn FnMut(PushEvent)>::new(move |event: PushEvent| {
+        // the stream is read after the event was dispatched, when `waitUntil` can't be called
+        // anymore, so the event waits for the message to be dropped instead
+        let mut done = None;
+        let handled = Promise::new(&mut |resolve, _reject| done = Some(resolve));
+        let _ = event.wait_until(&handled);
+        let _ = sender.unbounded_send(PushMessage { event, done });
+    });
+    let _ = target.add_event_listener_with_callback("push", listener.as_ref().unchecked_ref());
+    PushMessages {
+        target,
+        listener,
+        receiver,
+    }
+}
+
+/// A [`Stream`] of the push messages of a service worker, see [`messages`].
+#[must_use = "streams do nothing unless polled"]
+pub struct PushMessages {
+    target: EventTarget,
+    listener: Closure<dyn FnMut(PushEvent)>,
+    receiver: mpsc::UnboundedReceiver<PushMessage>,
+}
+
+impl Stream for PushMessages {
+    type Item = PushMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for PushMessages {
+    fn drop(&mut self) {
+        let _ = self
+            .target
+            .remove_event_listener_with_callback("push", self.listener.as_ref().unchecked_ref());
+    }
+}
+
+impl fmt::Debug for PushMessages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PushMessages").finish_non_exhaustive()
+    }
+}
+
+/// A push message received by the service worker, which is kept alive until this is dropped.
+pub struct PushMessage {
+    event: PushEvent,
+    done: Option<js_sys::Function>,
+}
+
+impl PushMessage {
+    /// Whether the message has a payload.
+    pub fn has_data(&self) -> bool {
+        self.event.data().is_some()
+    }
+
+    /// The payload as text, empty without a payload.
+    pub fn text(&self) -> String {
+        self.event
+            .data()
+            .map(|data| data.text())
+            .unwrap_or_default()
+    }
+
+    /// The payload as bytes, empty without a payload.
+    pub fn binary(&self) -> Result<Vec<u8>, Error> {
+        match self.event.data() {
+            Some(data) => {
+                let buffer = data.array_buffer().map_err(js_to_error)?;
+                Ok(Uint8Array::new(&buffer).to_vec())
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Deserializes the payload from JSON.
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_json::from_str(&self.text())?)
+    }
+
+    /// The underlying event.
+    pub fn as_raw(&self) -> &PushEvent {
+        &self.event
+    }
+}
+
+impl Drop for PushMessage {
+    fn drop(&mut self) {
+        if let Some(done) = self.done.take() {
+            let _ = done.call0(&JsValue::UNDEFINED);
+        }
+    }
+}
+
+impl fmt::Debug for PushMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PushMessage")
+            .field("event", &self.event)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Decodes URL-safe base64, with or without padding.
+fn decode_base64_url(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in encoded.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' | b'+' => 62,
+            b'_' | b'/' => 63,
+            _ => return None,
+        };
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    // a single character left over can't encode a byte
+    if bits >= 6 {
+        return None;
+    }
+    Some(bytes)
+}
+
+fn unsupported() -> Error {
+    Error::GlooError("the Push API is not available".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_base64_url() {
+        assert_eq!(decode_base64_url("").unwrap(), b"");
+        assert_eq!(decode_base64_url("aGk").unwrap(), b"hi");
+        assert_eq!(decode_base64_url("aGk=").unwrap(), b"hi");
+        assert_eq!(decode_base64_url("aGVsbG8").unwrap(), b"hello");
+        assert_eq!(decode_base64_url("-_8").unwrap(), [0xfb, 0xff]);
+        assert_eq!(decode_base64_url("+/8=").unwrap(), [0xfb, 0xff]);
+        assert!(decode_base64_url("a").is_none());
+        assert!(decode_base64_url("a b").is_none());
+    }
+}
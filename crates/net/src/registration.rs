@@ -0,0 +1,50 @@
+//! Access to the service worker registration, for the APIs hanging off it.
+
+use js_sys::{Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{ServiceWorkerContainer, ServiceWorkerRegistration};
+
+use crate::{js_to_error, Error};
+
+/// The active service worker registration: the one controlling the page in a window, or the
+/// registration of the service worker itself.
+///
+/// Fails with `unsupported` when there is no service worker API.
+pub(crate) async fn ready(unsupported: fn() -> Error) -> Result<ServiceWorkerRegistration, Error> {
+    let global = js_sys::global();
+    match Reflect::get(&global, &JsValue::from_str("registration")) {
+        Ok(registration) if registration.is_instance_of::<ServiceWorkerRegistration>() => {
+            Ok(registration.unchecked_into())
+        }
+        _ => {
+            let container: ServiceWorkerContainer =
+                Reflect::get(&global, &JsValue::from_str("navigator"))
+                    .and_then(|navigator| {
+                        Reflect::get(&navigator, &JsValue::from_str("serviceWorker"))
+                    })
+                    .map_err(js_to_error)?
+                    .dyn_into()
+                    .map_err(|_| unsupported())?;
+            let registration = await_promise(container.ready().map_err(js_to_error)?).await?;
+            Ok(registration.unchecked_into())
+        }
+    }
+}
+
+/// Reads the `name` manager of `registration`, failing with `unsupported` when it is missing.
+pub(crate) fn manager<T: JsCast>(
+    registration: &ServiceWorkerRegistration,
+    name: &str,
+    unsupported: fn() -> Error,
+) -> Result<T, Error> {
+    let manager = Reflect::get(registration, &JsValue::from_str(name)).map_err(js_to_error)?;
+    if manager.is_undefined() {
+        return Err(unsupported());
+    }
+    Ok(manager.unchecked_into())
+}
+
+pub(crate) async fn await_promise(promise: Promise) -> Result<JsValue, Error> {
+    JsFuture::from(promise).await.map_err(js_to_error)
+}
@@ -0,0 +1,265 @@
+//! Matching requests against the routes of a [`Router`](crate::sw::Router) or a
+//! [`MockApi`](crate::mock::MockApi).
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use js_sys::Reflect;
+use wasm_bindgen::JsValue;
+
+use crate::http::{Method, Request, Response};
+use crate::Error;
+
+pub(crate) type HandlerFuture = Pin<Box<dyn Future<Output = Result<Response, Error>>>>;
+pub(crate) type Handler = dyn Fn(Request, Params) -> HandlerFuture;
+
+/// A list of routes, with a fallback for the requests matching none.
+#[derive(Default)]
+pub(crate) struct Routes {
+    routes: Vec<Route>,
+    fallback: Option<Rc<Handler>>,
+}
+
+struct Route {
+    method: Method,
+    pattern: Pattern,
+    handler: Rc<Handler>,
+}
+
+impl Routes {
+    pub(crate) fn add<F, Fut>(&mut self, method: Method, pattern: &str, handler: F)
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.routes.push(Route {
+            method,
+            pattern: Pattern::parse(pattern),
+            handler: boxed(handler),
+        });
+    }
+
+    pub(crate) fn fallback<F, Fut>(&mut self, handler: F)
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.fallback = Some(boxed(handler));
+    }
+
+    /// The handler of the first route matching `request`, with the parameters it captured.
+    pub(crate) fn find(&self, request: &Request) -> Option<(Rc<Handler>, Params)> {
+        let url = web_sys::Url::new(&request.url()).ok()?;
+        let method = request.method();
+        let own_origin = own_origin();
+        let route = self.routes.iter().find_map(|route| {
+            if route.method != method {
+                return None;
+            }
+            let params =
+                route
+                    .pattern
+                    .matches(&url.origin(), &url.pathname(), own_origin.as_deref())?;
+            Some((route.handler.clone(), params))
+        });
+        match (route, &self.fallback) {
+            (Some(route), _) => Some(route),
+            (None, Some(fallback)) => Some((fallback.clone(), Params::default())),
+            (None, None) => None,
+        }
+    }
+}
+
+impl fmt::Debug for Routes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Routes")
+            .field(
+                "routes",
+                &self
+                    .routes
+                    .iter()
+                    .map(|route| format!("{} {}", route.method, route.pattern))
+                    .collect::<Vec<_>>(),
+            )
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+fn boxed<F, Fut>(handler: F) -> Rc<Handler>
+where
+    F: Fn(Request, Params) -> Fut + 'static,
+    Fut: Future<Output = Result<Response, Error>> + 'static,
+{
+    Rc::new(move |request, params| Box::pin(handler(request, params)) as HandlerFuture)
+}
+
+/// Waits for the response of a handler, answering errors with a `500 Internal Server Error`
+/// whose body is the error message.
+pub(crate) async fn respond(response: HandlerFuture) -> Result<JsValue, JsValue> {
+    let response = match response.await {
+        Ok(response) => response,
+        Err(e) => Response::builder()
+            .status(500)
+            .body(Some(e.to_string().as_str()))
+            .map_err(|e| JsValue::from_str(&e.to_string()))?,
+    };
+    Ok(JsValue::from(web_sys::Response::from(response)))
+}
+
+/// The origin of the page or of the worker.
+fn own_origin() -> Option<String> {
+    let location = Reflect::get(&js_sys::global(), &JsValue::from_str("location")).ok()?;
+    Reflect::get(&location, &JsValue::from_str("origin"))
+        .ok()?
+        .as_string()
+}
+
+/// The parameters captured from the path of a request by the pattern of a route, as they
+/// appear in the URL, e.g. still percent-encoded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    params: Vec<(String, String)>,
+}
+
+impl Params {
+    /// The value of the parameter `name`, like `id` for `/users/:id`, or `*` for the rest of a
+    /// path matched by a trailing `*`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterate over (name, value) pairs of the parameters.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+/// The pattern of a route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    /// The origin of an absolute pattern.
+    origin: Option<String>,
+    path: String,
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Self {
+        let after_scheme = match pattern.find("://") {
+            Some(i) => i + 3,
+            None => {
+                return Self {
+                    origin: None,
+                    path: pattern.to_string(),
+                }
+            }
+        };
+        let (origin, path) = match pattern[after_scheme..].find('/') {
+            Some(i) => pattern.split_at(after_scheme + i),
+            None => (pattern, "/"),
+        };
+        Self {
+            origin: Some(origin.to_string()),
+            path: path.to_string(),
+        }
+    }
+
+    /// The parameters captured from a request to `origin` and `path`, if it matches.
+    fn matches(&self, origin: &str, path: &str, own_origin: Option<&str>) -> Option<Params> {
+        let expected = self.origin.as_deref().or(own_origin);
+        if expected.is_some_and(|expected| expected != origin) {
+            return None;
+        }
+        let mut params = Vec::new();
+        let mut segments = path.split('/');
+        for part in self.path.split('/') {
+            if part == "*" {
+                let rest: Vec<_> = segments.collect();
+                params.push(("*".to_string(), rest.join("/")));
+                return Some(Params { params });
+            }
+            let segment = segments.next()?;
+            match part.strip_prefix(':') {
+                Some(name) if !segment.is_empty() => {
+                    params.push((name.to_string(), segment.to_string()))
+                }
+                Some(_) => return None,
+                None if part == segment => {}
+                None => return None,
+            }
+        }
+        match segments.next() {
+            Some(_) => None,
+            None => Some(Params { params }),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            f.write_str(origin)?;
+        }
+        f.write_str(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWN: Option<&str> = Some("https://app.example.com");
+
+    fn params(pattern: &str, origin: &str, path: &str) -> Option<Vec<(String, String)>> {
+        Pattern::parse(pattern)
+            .matches(origin, path, OWN)
+            .map(|params| params.params)
+    }
+
+    #[test]
+    fn matches_paths() {
+        let app = "https://app.example.com";
+        assert_eq!(params("/", app, "/"), Some(vec![]));
+        assert_eq!(params("/users", app, "/users"), Some(vec![]));
+        assert_eq!(params("/users", app, "/users/1"), None);
+        assert_eq!(params("/users/:id", app, "/users"), None);
+        assert_eq!(params("/users/:id", app, "/users/"), None);
+        assert_eq!(
+            params("/users/:id/posts", app, "/users/42/posts"),
+            Some(vec![("id".to_string(), "42".to_string())])
+        );
+        assert_eq!(
+            params("/assets/*", app, "/assets/img/logo.png"),
+            Some(vec![("*".to_string(), "img/logo.png".to_string())])
+        );
+        assert_eq!(
+            params("/users", "https://other.example.com", "/users"),
+            None
+        );
+    }
+
+    #[test]
+    fn matches_origins() {
+        let api = "https://api.example.com";
+        assert_eq!(
+            params("https://api.example.com/users/:id", api, "/users/1"),
+            Some(vec![("id".to_string(), "1".to_string())])
+        );
+        assert_eq!(
+            params(
+                "https://api.example.com/users/:id",
+                "https://app.example.com",
+                "/users/1"
+            ),
+            None
+        );
+        assert_eq!(params("https://api.example.com", api, "/"), Some(vec![]));
+    }
+}
@@ -0,0 +1,185 @@
+//! Answering the requests of a page from its service worker.
+//!
+//! A [`Router`] dispatches the `fetch` events of a service worker to handlers by method and
+//! path, and responds to them with the [`Response`] of the handler. Requests which match no
+//! route are left to the browser, which sends them over the network as usual.
+//!
+//! # Example
+//!
+//! ```
+//! use gloo_net::http::{Request, Response};
+//! use gloo_net::sw::Router;
+//!
+//! // in the service worker
+//! # fn no_run() {
+//! Router::new()
+//!     .get("/api/users/:id", |_request, params| async move {
+//!         let id = params.get("id").unwrap_or_default().to_string();
+//!         Response::builder().body(Some(format!("user {}", id).as_str()))
+//!     })
+//!     // fall back to the network, e.g. to log the requests
+//!     .get("/assets/*", |request: Request, _params| request.send())
+//!     .listen()
+//!     .forget();
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{EventTarget, FetchEvent};
+
+use crate::http::{Method, Request, Response};
+use crate::routing::{self, Routes};
+use crate::Error;
+
+pub use crate::routing::Params;
+
+/// Routes the `fetch` events of a service worker to handlers.
+///
+/// Routes are matched in the order they were added. Their patterns are paths, like
+/// `/users/:id/posts`, where a segment starting with `:` matches any segment and captures it
+/// as a [parameter](Params), and a trailing `*` matches the rest of the path, captured as `*`.
+/// Patterns starting with `/` only match requests to the origin of the service worker; an
+/// absolute pattern, like `https://api.example.com/users/:id`, matches requests to its own
+/// origin. The query string isn't matched.
+///
+/// Handlers which fail are answered with a `500 Internal Server Error`, whose body is the
+/// error message.
+#[derive(Debug, Default)]
+pub struct Router {
+    routes: Routes,
+}
+
+impl Router {
+    /// Creates a router without routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers the `method` requests matching `pattern` with `handler`.
+    pub fn route<F, Fut>(mut self, method: Method, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.routes.add(method, pattern, handler);
+        self
+    }
+
+    /// Answers the [`GET`][Method::GET] requests matching `pattern` with `handler`.
+    pub fn get<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::GET, pattern, handler)
+    }
+
+    /// Answers the [`POST`][Method::POST] requests matching `pattern` with `handler`.
+    pub fn post<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::POST, pattern, handler)
+    }
+
+    /// Answers the [`PUT`][Method::PUT] requests matching `pattern` with `handler`.
+    pub fn put<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::PUT, pattern, handler)
+    }
+
+    /// Answers the [`PATCH`][Method::PATCH] requests matching `pattern` with `handler`.
+    pub fn patch<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::PATCH, pattern, handler)
+    }
+
+    /// Answers the [`DELETE`][Method::DELETE] requests matching `pattern` with `handler`.
+    pub fn delete<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.route(Method::DELETE, pattern, handler)
+    }
+
+    /// Answers the requests matching no route with `handler`, instead of leaving them to the
+    /// browser.
+    pub fn fallback<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(Request, Params) -> Fut + 'static,
+        Fut: Future<Output = Result<Response, Error>> + 'static,
+    {
+        self.routes.fallback(handler);
+        self
+    }
+
+    /// Responds to `event` with the handler of the first matching route, returning whether one
+    /// matched.
+    pub fn handle(&self, event: &FetchEvent) -> bool {
+        let request = Request::from(event.request());
+        let (handler, params) = match self.routes.find(&request) {
+            Some(route) => route,
+            None => return false,
+        };
+        let promise =
+            wasm_bindgen_futures::future_to_promise(routing::respond(handler(request, params)));
+        event.respond_with(&promise).is_ok()
+    }
+
+    /// Handles the `fetch` events of the service worker, until the returned [`FetchListener`]
+    /// is dropped.
+    ///
+    /// Service workers must add their `fetch` listener while their script first runs, so this
+    /// is usually called from the `main` of the worker, and the listener
+    /// [forgotten](FetchListener::forget).
+    pub fn listen(self) -> FetchListener {
+        let target: EventTarget = js_sys::global().unchecked_into();
+        let listener = Closure::<dyn FnMut(FetchEvent)>::new(move |event: FetchEvent| {
+            self.handle(&event);
+        });
+        let _ = target.add_event_listener_with_callback("fetch", listener.as_ref().unchecked_ref());
+        FetchListener { target, listener }
+    }
+}
+
+/// Handles the `fetch` events of a service worker with a [`Router`], until it is dropped.
+///
+/// See [`Router::listen`].
+#[must_use = "the events are only handled until this is dropped"]
+pub struct FetchListener {
+    target: EventTarget,
+    listener: Closure<dyn FnMut(FetchEvent)>,
+}
+
+impl FetchListener {
+    /// Keeps handling the events for the lifetime of the service worker.
+    pub fn forget(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for FetchListener {
+    fn drop(&mut self) {
+        let _ = self
+            .target
+            .remove_event_listener_with_callback("fetch", self.listener.as_ref().unchecked_ref());
+    }
+}
+
+impl fmt::Debug for FetchListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FetchListener").finish_non_exhaustive()
+    }
+}
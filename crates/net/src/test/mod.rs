@@ -0,0 +1,326 @@
+//! Mocking `fetch` and WebSockets in tests.
+//!
+//! Once a [`MockFetch`] is installed, requests are answered by the mocks registered on it
+//! instead of being sent over the network. This covers every request sent with
+//! [`Request::send`](crate::http::Request::send), including the ones sent by a
+//! [`Client`](crate::http::Client), so code making requests can be tested without a server.
+//!
+//! # Example
+//!
+//! ```
+//! use gloo_net::http::{Request, Response};
+//! use gloo_net::test::{Matcher, MockFetch};
+//!
+//! # async fn no_run() {
+//! let fetch = MockFetch::install();
+//! let user = fetch.mock(
+//!     Matcher::get("/api/users/1"),
+//!     Response::builder().status(200).body(Some(r#"{"name":"Ferris"}"#)).unwrap(),
+//! );
+//!
+//! let resp = Request::get("/api/users/1").send().await.unwrap();
+//! assert_eq!(resp.text().await.unwrap(), r#"{"name":"Ferris"}"#);
+//! user.assert_called(1);
+//! # }
+//! ```
+//!
+//! With the `websocket` feature, a [`MockWebSocket`] stands in for a WebSocket, see its
+//! documentation.
+
+#[cfg(feature = "websocket")]
+mod websocket;
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use js_sys::Promise;
+use wasm_bindgen::JsValue;
+
+use crate::http::{Method, Request, Response};
+use crate::Error;
+#[cfg(feature = "websocket")]
+#[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+pub use websocket::{MockServer, MockWebSocket};
+
+thread_local! {
+    static INSTALLED: RefCell<Option<Rc<Registry>>> = const { RefCell::new(None) };
+}
+
+type Responder = dyn Fn(&Request) -> Result<Response, Error>;
+
+#[derive(Default)]
+struct Registry {
+    routes: RefCell<Vec<Route>>,
+}
+
+struct Route {
+    matcher: Matcher,
+    responder: Rc<Responder>,
+    calls: Rc<RefCell<Vec<Call>>>,
+}
+
+/// Answers requests with mocks for as long as it is alive.
+///
+/// Dropping it sends requests over the network again. Only one `MockFetch` is installed at a
+/// time: installing another one replaces it.
+pub struct MockFetch {
+    registry: Rc<Registry>,
+}
+
+impl MockFetch {
+    /// Starts answering requests with the mocks registered on the returned `MockFetch`.
+    ///
+    /// Requests matching none of the mocks fail with an [`Error::GlooError`].
+    pub fn install() -> Self {
+        let registry = Rc::new(Registry::default());
+        INSTALLED.with(|installed| *installed.borrow_mut() = Some(registry.clone()));
+        Self { registry }
+    }
+
+    /// Answers the requests matching `matcher` with copies of `response`.
+    ///
+    /// Mocks registered later take precedence over earlier ones, so a test can override a mock
+    /// set up for all tests.
+    pub fn mock(&self, matcher: Matcher, response: Response) -> Mock {
+        self.mock_with(matcher, move |_| response.clone_raw().map(Response::from))
+    }
+
+    /// Answers the requests matching `matcher` with the response built by `respond`.
+    pub fn mock_with<F>(&self, matcher: Matcher, respond: F) -> Mock
+    where
+        F: Fn(&Request) -> Result<Response, Error> + 'static,
+    {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        self.registry.routes.borrow_mut().push(Route {
+            matcher,
+            responder: Rc::new(respond),
+            calls: calls.clone(),
+        });
+        Mock { calls }
+    }
+
+    /// Removes all the mocks.
+    pub fn reset(&self) {
+        self.registry.routes.borrow_mut().clear();
+    }
+}
+
+impl Drop for MockFetch {
+    fn drop(&mut self) {
+        INSTALLED.with(|installed| {
+            let mut installed = installed.borrow_mut();
+            if installed
+                .as_ref()
+                .is_some_and(|registry| Rc::ptr_eq(registry, &self.registry))
+            {
+                *installed = None;
+            }
+        });
+    }
+}
+
+impl fmt::Debug for MockFetch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockFetch")
+            .field("mocks", &self.registry.routes.borrow().len())
+            .finish()
+    }
+}
+
+/// Answers the request from the installed [`MockFetch`], if any.
+pub(crate) fn intercept(request: &web_sys::Request) -> Option<Result<Promise, Error>> {
+    let registry = INSTALLED.with(|installed| installed.borrow().clone())?;
+    let request = Request::from(Clone::clone(request));
+    let route = registry
+        .routes
+        .borrow()
+        .iter()
+        .rev()
+        .find(|route| route.matcher.matches(&request))
+        .map(|route| (route.responder.clone(), route.calls.clone()));
+    let (responder, calls) = match route {
+        Some(route) => route,
+        None => {
+            return Some(Err(Error::GlooError(format!(
+                "no mock matches {} {}",
+                request.method(),
+                request.url()
+            ))))
+        }
+    };
+    calls.borrow_mut().push(Call {
+        method: request.method(),
+        url: request.url(),
+    });
+    Some(
+        responder(&request)
+            .map(|response| Promise::resolve(&JsValue::from(web_sys::Response::from(response)))),
+    )
+}
+
+/// Which requests a mock answers.
+///
+/// URLs starting with `/` are compared to the path of the requests, absolute ones to the whole
+/// URL. The query string is only compared if the pattern has one, and a pattern ending with `*`
+/// matches any URL starting with what comes before it.
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    method: Option<Method>,
+    url: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl Matcher {
+    /// Matches every request.
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    /// Matches the requests with the given method and URL.
+    pub fn new(method: Method, url: &str) -> Self {
+        Self {
+            method: Some(method),
+            url: Some(url.to_string()),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Matches the `GET` requests to `url`.
+    pub fn get(url: &str) -> Self {
+        Self::new(Method::GET, url)
+    }
+
+    /// Matches the `POST` requests to `url`.
+    pub fn post(url: &str) -> Self {
+        Self::new(Method::POST, url)
+    }
+
+    /// Matches the `PUT` requests to `url`.
+    pub fn put(url: &str) -> Self {
+        Self::new(Method::PUT, url)
+    }
+
+    /// Matches the `PATCH` requests to `url`.
+    pub fn patch(url: &str) -> Self {
+        Self::new(Method::PATCH, url)
+    }
+
+    /// Matches the `DELETE` requests to `url`.
+    pub fn delete(url: &str) -> Self {
+        Self::new(Method::DELETE, url)
+    }
+
+    /// Only matches the requests with a `name` header set to `value`.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn matches(&self, request: &Request) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|method| *method == request.method())
+            && self
+                .url
+                .as_ref()
+                .is_none_or(|pattern| url_matches(pattern, &request.url()))
+            && self
+                .headers
+                .iter()
+                .all(|(name, value)| request.headers().get(name).as_deref() == Some(value.as_str()))
+    }
+}
+
+/// Whether the absolute `url` matches `pattern`, as described on [`Matcher`].
+fn url_matches(pattern: &str, url: &str) -> bool {
+    let url = url.split('#').next().unwrap_or_default();
+    let url = if pattern.starts_with('/') {
+        let after_scheme = url.find("://").map_or(0, |i| i + 3);
+        url[after_scheme..]
+            .find('/')
+            .map_or("/", |i| &url[after_scheme + i..])
+    } else {
+        url
+    };
+    let url = if pattern.contains('?') {
+        url
+    } else {
+        url.split('?').next().unwrap_or_default()
+    };
+    match pattern.strip_suffix('*') {
+        Some(prefix) => url.starts_with(prefix),
+        None => url == pattern,
+    }
+}
+
+/// A request answered by a mock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    method: Method,
+    url: String,
+}
+
+impl Call {
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The absolute URL of the request.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// A mock registered on a [`MockFetch`], recording the requests it answered.
+#[derive(Debug, Clone)]
+pub struct Mock {
+    calls: Rc<RefCell<Vec<Call>>>,
+}
+
+impl Mock {
+    /// How many requests this mock answered.
+    pub fn call_count(&self) -> usize {
+        self.calls.borrow().len()
+    }
+
+    /// The requests this mock answered, oldest first.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.borrow().clone()
+    }
+
+    /// Panics unless this mock answered exactly `times` requests.
+    #[track_caller]
+    pub fn assert_called(&self, times: usize) {
+        let calls = self.call_count();
+        assert_eq!(
+            calls, times,
+            "expected the mock to be called {times} times, but it was called {calls} times"
+        );
+    }
+
+    /// Panics if this mock answered any request.
+    #[track_caller]
+    pub fn assert_not_called(&self) {
+        self.assert_called(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_urls() {
+        let url = "https://example.com/api/users/1?fields=name#top";
+        assert!(url_matches("/api/users/1", url));
+        assert!(url_matches("/api/users/1?fields=name", url));
+        assert!(!url_matches("/api/users/1?fields=id", url));
+        assert!(url_matches("/api/users/*", url));
+        assert!(!url_matches("/api/users", url));
+        assert!(url_matches("https://example.com/api/users/1", url));
+        assert!(!url_matches("https://example.org/api/users/1", url));
+        assert!(url_matches("/", "https://example.com"));
+    }
+}
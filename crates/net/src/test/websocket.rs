@@ -0,0 +1,360 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use crate::websocket::events::{CloseEvent, ErrorEvent};
+use crate::websocket::{Message, State, WebSocketError};
+
+/// The URL of every mock connection, which the errors of the socket carry.
+const URL: &str = "ws://mock.invalid/";
+
+/// A WebSocket without a server, for testing protocol code.
+///
+/// It is a [`Sink`] of messages and a [`Stream`] of received messages, like a
+/// [`WebSocket`](crate::websocket::futures::WebSocket), so code written against those traits
+/// runs over it. The [`MockServer`] it comes with plays the other end: it injects the messages
+/// the socket receives, tells what the socket sent, and closes or fails the connection.
+///
+/// # Example
+///
+/// ```
+/// use futures::{SinkExt, StreamExt};
+/// use gloo_net::test::MockWebSocket;
+/// use gloo_net::websocket::Message;
+///
+/// # async fn no_run() {
+/// let (mut ws, server) = MockWebSocket::pair();
+///
+/// ws.send(Message::Text("ping".to_string())).await.unwrap();
+/// server.assert_sent(&[Message::Text("ping".to_string())]);
+///
+/// server.send(Message::Text("pong".to_string()));
+/// server.close(1000, "done");
+/// assert_eq!(ws.next().await.unwrap().unwrap(), Message::Text("pong".to_string()));
+/// assert!(ws.next().await.unwrap().is_err());
+/// assert!(ws.next().await.is_none());
+/// # }
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct MockWebSocket {
+    shared: Rc<Shared>,
+}
+
+/// The other end of a [`MockWebSocket`].
+pub struct MockServer {
+    shared: Rc<Shared>,
+}
+
+struct Shared {
+    state: Cell<State>,
+    /// What the socket receives next.
+    incoming: RefCell<VecDeque<Incoming>>,
+    /// The messages the socket sent, which the server didn't take yet.
+    outgoing: RefCell<VecDeque<Message>>,
+    /// How the socket closed the connection, if it did.
+    client_close: RefCell<Option<CloseEvent>>,
+    /// Whether the stream of the socket reported the end of the connection.
+    ended: Cell<bool>,
+    socket_waker: RefCell<Option<Waker>>,
+    server_waker: RefCell<Option<Waker>>,
+}
+
+enum Incoming {
+    Message(Message),
+    Error,
+    Close(CloseEvent),
+}
+
+impl Shared {
+    fn wake_socket(&self) {
+        if let Some(waker) = self.socket_waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_server(&self) {
+        if let Some(waker) = self.server_waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl MockWebSocket {
+    /// Creates an open socket, along with its server.
+    pub fn pair() -> (Self, MockServer) {
+        Self::with_state(State::Open)
+    }
+
+    /// Creates a socket still connecting, which can't send until [`MockServer::open`] is
+    /// called, along with its server.
+    pub fn connecting() -> (Self, MockServer) {
+        Self::with_state(State::Connecting)
+    }
+
+    fn with_state(state: State) -> (Self, MockServer) {
+        let shared = Rc::new(Shared {
+            state: Cell::new(state),
+            incoming: RefCell::default(),
+            outgoing: RefCell::default(),
+            client_close: RefCell::default(),
+            ended: Cell::new(false),
+            socket_waker: RefCell::default(),
+            server_waker: RefCell::default(),
+        });
+        (
+            Self {
+                shared: Rc::clone(&shared),
+            },
+            MockServer { shared },
+        )
+    }
+
+    /// The current state of the connection.
+    pub fn state(&self) -> State {
+        self.shared.state.get()
+    }
+
+    /// Closes the connection, like [`WebSocket::close`], which the server sees with
+    /// [`MockServer::client_close`].
+    ///
+    /// [`WebSocket::close`]: crate::websocket::futures::WebSocket::close
+    pub fn close(self, code: Option<u16>, reason: Option<&str>) {
+        self.close_with(code.unwrap_or(1005), reason.unwrap_or_default());
+    }
+
+    fn close_with(&self, code: u16, reason: &str) {
+        if self.shared.state.get() == State::Closed {
+            return;
+        }
+        self.shared.state.set(State::Closed);
+        *self.shared.client_close.borrow_mut() = Some(CloseEvent {
+            url: URL.to_string(),
+            code,
+            reason: reason.to_string(),
+            was_clean: true,
+        });
+        self.shared.wake_server();
+    }
+}
+
+impl Stream for MockWebSocket {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let shared = &self.shared;
+        if shared.ended.get() {
+            return Poll::Ready(None);
+        }
+        match shared.incoming.borrow_mut().pop_front() {
+            Some(Incoming::Message(message)) => Poll::Ready(Some(Ok(message))),
+            Some(Incoming::Error) => Poll::Ready(Some(Err(connection_error()))),
+            Some(Incoming::Close(event)) => {
+                shared.ended.set(true);
+                Poll::Ready(Some(Err(WebSocketError::ConnectionClose(event))))
+            }
+            None if shared.state.get() == State::Closed => {
+                shared.ended.set(true);
+                Poll::Ready(None)
+            }
+            None => {
+                *shared.socket_waker.borrow_mut() = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Sending is pending while the socket is connecting, and fails with
+/// [`WebSocketError::ConnectionError`] once the connection closed.
+impl Sink<Message> for MockWebSocket {
+    type Error = WebSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.shared.state.get() == State::Connecting {
+            *self.shared.socket_waker.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        if self.shared.state.get() != State::Open {
+            return Err(connection_error());
+        }
+        self.shared.outgoing.borrow_mut().push_back(item);
+        self.shared.wake_server();
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for MockWebSocket {
+    fn drop(&mut self) {
+        // like a dropped `WebSocket`, which closes the connection
+        self.close_with(1005, "");
+    }
+}
+
+impl fmt::Debug for MockWebSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockWebSocket")
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+impl MockServer {
+    /// Opens the connection of a [connecting](MockWebSocket::connecting) socket.
+    pub fn open(&self) {
+        if self.shared.state.get() == State::Connecting {
+            self.shared.state.set(State::Open);
+            self.shared.wake_socket();
+        }
+    }
+
+    /// Sends `message` to the socket.
+    pub fn send(&self, message: Message) {
+        self.push(Incoming::Message(message));
+    }
+
+    /// Fails the connection, which the socket receives as a
+    /// [`WebSocketError::ConnectionError`], like after an `error` event.
+    pub fn error(&self) {
+        self.push(Incoming::Error);
+    }
+
+    /// Closes the connection with `code` and `reason`, which the socket receives as a
+    /// [`WebSocketError::ConnectionClose`] after the messages sent before, then ends its stream.
+    pub fn close(&self, code: u16, reason: &str) {
+        self.push(Incoming::Close(CloseEvent {
+            url: URL.to_string(),
+            code,
+            reason: reason.to_string(),
+            was_clean: true,
+        }));
+        self.shared.state.set(State::Closed);
+        self.shared.wake_server();
+    }
+
+    fn push(&self, incoming: Incoming) {
+        self.shared.incoming.borrow_mut().push_back(incoming);
+        self.shared.wake_socket();
+    }
+
+    /// Takes the messages the socket sent since the last call.
+    pub fn sent(&self) -> Vec<Message> {
+        self.shared.outgoing.borrow_mut().drain(..).collect()
+    }
+
+    /// Panics unless the socket sent exactly `expected` since the messages were last taken,
+    /// which this takes.
+    #[track_caller]
+    pub fn assert_sent(&self, expected: &[Message]) {
+        let sent = self.sent();
+        assert_eq!(
+            sent, expected,
+            "expected the socket to send {expected:?}, but it sent {sent:?}"
+        );
+    }
+
+    /// How the socket closed the connection, or `None` while it didn't, e.g. because it is
+    /// still open or the server closed it.
+    pub fn client_close(&self) -> Option<CloseEvent> {
+        self.shared.client_close.borrow().clone()
+    }
+}
+
+/// The messages the socket sends, ending once the connection closed and every message was
+/// taken.
+impl Stream for MockServer {
+    type Item = Message;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(message) = self.shared.outgoing.borrow_mut().pop_front() {
+            return Poll::Ready(Some(message));
+        }
+        if self.shared.state.get() == State::Closed {
+            return Poll::Ready(None);
+        }
+        *self.shared.server_waker.borrow_mut() = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+fn connection_error() -> WebSocketError {
+    WebSocketError::ConnectionError(ErrorEvent::unknown(URL.to_string()))
+}
+
+impl fmt::Debug for MockServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockServer")
+            .field("state", &self.shared.state.get())
+            .field("unread", &self.shared.outgoing.borrow().len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::{SinkExt, StreamExt};
+
+    fn text(text: &str) -> Message {
+        Message::Text(text.to_string())
+    }
+
+    #[test]
+    fn plays_both_ends() {
+        block_on(async {
+            let (mut ws, mut server) = MockWebSocket::connecting();
+            assert_eq!(ws.state(), State::Connecting);
+            server.open();
+            ws.send(text("hello")).await.unwrap();
+            assert_eq!(server.next().await, Some(text("hello")));
+
+            server.send(text("one"));
+            server.error();
+            server.close(4000, "bye");
+            assert_eq!(ws.next().await.unwrap().unwrap(), text("one"));
+            assert!(matches!(
+                ws.next().await,
+                Some(Err(WebSocketError::ConnectionError(_)))
+            ));
+            match ws.next().await {
+                Some(Err(WebSocketError::ConnectionClose(event))) => assert_eq!(event.code, 4000),
+                other => panic!("unexpected item: {:?}", other),
+            }
+            assert!(ws.next().await.is_none());
+            assert!(ws.send(text("late")).await.is_err());
+            assert_eq!(server.client_close().map(|event| event.code), None);
+        });
+    }
+
+    #[test]
+    fn tells_how_the_socket_closed() {
+        let (mut ws, server) = MockWebSocket::pair();
+        Pin::new(&mut ws).start_send(text("a")).unwrap();
+        Pin::new(&mut ws).start_send(text("b")).unwrap();
+        server.assert_sent(&[text("a"), text("b")]);
+        assert!(server.sent().is_empty());
+
+        MockWebSocket::close(ws, Some(1000), Some("done"));
+        let close = server.client_close().unwrap();
+        assert_eq!((close.code, close.reason.as_str()), (1000, "done"));
+    }
+}
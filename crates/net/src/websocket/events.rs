@@ -1,8 +1,14 @@
 //! WebSocket Events
 
+use std::fmt;
+
+use crate::websocket::Message;
+
 /// Data emitted by `onclose` event
 #[derive(Clone, Debug)]
 pub struct CloseEvent {
+    /// The URL of the connection
+    pub url: String,
     /// Close code
     pub code: u16,
     /// Close reason
@@ -10,3 +16,319 @@ pub struct CloseEvent {
     /// If the websockets was closed cleanly
     pub was_clean: bool,
 }
+
+impl CloseEvent {
+    /// The close code, as a [`CloseCode`].
+    pub fn close_code(&self) -> CloseCode {
+        CloseCode::from(self.code)
+    }
+}
+
+/// Data emitted by `onerror` event, which fires when the connection fails, right before the
+/// close event.
+///
+/// Browsers tell scripts little about why a connection failed, so that pages can't probe the
+/// network of the user: they fire a bare `Event`, and only log the cause on the console. Other
+/// runtimes, like Deno, fire an `ErrorEvent` with a message. The [`kind`](Self::kind) is a
+/// guess from the message, the close code following the event, and whether the connection was
+/// open.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ErrorEvent {
+    /// The URL of the connection.
+    pub url: String,
+    /// The message of the event, when the runtime gave one.
+    pub message: Option<String>,
+    /// What likely went wrong.
+    pub kind: ErrorKind,
+}
+
+impl ErrorEvent {
+    pub(crate) fn new(
+        url: String,
+        message: Option<String>,
+        close_code: Option<u16>,
+        was_open: bool,
+    ) -> Self {
+        let kind = ErrorKind::classify(message.as_deref(), close_code, was_open);
+        Self { url, message, kind }
+    }
+
+    /// An error which no event told more about, like a connection which ended without one.
+    pub(crate) fn unknown(url: String) -> Self {
+        Self {
+            url,
+            message: None,
+            kind: ErrorKind::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for ErrorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{} ({})", message, self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// What likely made a connection fail, see [`ErrorEvent::kind`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The host name couldn't be resolved. Browsers report this as [`Refused`](Self::Refused).
+    Dns,
+    /// The server couldn't be reached, or refused the connection or the handshake.
+    Refused,
+    /// The TLS handshake failed, like when the certificate is invalid.
+    Tls,
+    /// The runtime blocked the connection, like an insecure `ws:` connection from an HTTPS
+    /// page, or one the Content Security Policy forbids.
+    Security,
+    /// The connection was open, and got lost without a close frame.
+    Lost,
+    /// Nothing tells what went wrong.
+    Unknown,
+}
+
+impl ErrorKind {
+    fn classify(message: Option<&str>, close_code: Option<u16>, was_open: bool) -> Self {
+        let message = message.unwrap_or_default().to_ascii_lowercase();
+        let mentions = |words: &[&str]| words.iter().any(|word| message.contains(word));
+        if mentions(&[
+            "securityerror",
+            "insecure",
+            "mixed content",
+            "content security policy",
+        ]) {
+            Self::Security
+        } else if mentions(&["certificate", "tls", "ssl"]) || close_code == Some(1015) {
+            Self::Tls
+        } else if mentions(&[
+            "enotfound",
+            "eai_again",
+            "name_not_resolved",
+            "dns",
+            "lookup",
+        ]) {
+            Self::Dns
+        } else if mentions(&["econnrefused", "refused", "unreachable", "handshake"]) {
+            Self::Refused
+        } else if was_open {
+            Self::Lost
+        } else if close_code == Some(1006) {
+            Self::Refused
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Dns => "the host name couldn't be resolved",
+            Self::Refused => "the server couldn't be reached or refused the connection",
+            Self::Tls => "the TLS handshake failed",
+            Self::Security => "the connection was blocked",
+            Self::Lost => "the connection was lost",
+            Self::Unknown => "unknown error",
+        })
+    }
+}
+
+/// The code telling why a connection was closed.
+///
+/// See the [registry of close codes](https://www.iana.org/assignments/websocket/websocket.xhtml#close-code-number)
+/// to learn more.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CloseCode {
+    /// `1000`: the connection did what it was opened for.
+    Normal,
+    /// `1001`: the server is going down, or the browser navigated away from the page.
+    GoingAway,
+    /// `1002`: an endpoint received a frame breaking the WebSocket protocol.
+    ProtocolError,
+    /// `1003`: an endpoint received a type of message it doesn't accept, like binary messages
+    /// when it only accepts text.
+    Unsupported,
+    /// `1005`: the close frame had no code. This is never sent.
+    NoStatus,
+    /// `1006`: the connection was lost without a close frame, like when the server couldn't be
+    /// reached. This is never sent.
+    Abnormal,
+    /// `1007`: a message had data not matching its type, like text which isn't UTF-8.
+    InvalidData,
+    /// `1008`: a message broke the policy of an endpoint, like one sent without being
+    /// authenticated.
+    PolicyViolation,
+    /// `1009`: a message was too big to be processed.
+    TooBig,
+    /// `1010`: the server didn't negotiate an extension the client requires.
+    MissingExtension,
+    /// `1011`: the server hit an unexpected error.
+    InternalError,
+    /// `1012`: the server is restarting.
+    ServiceRestart,
+    /// `1013`: the server is overloaded, and the client should connect again later.
+    TryAgainLater,
+    /// `1014`: a gateway got an invalid response from the server behind it.
+    BadGateway,
+    /// `1015`: the TLS handshake failed. This is never sent.
+    TlsHandshake,
+    /// `3000`-`3999`: a code registered by a library or framework, like `3000` for
+    /// unauthorized in some of them.
+    Registered(u16),
+    /// `4000`-`4999`: a code private to the application.
+    Application(u16),
+    /// Any other code, reserved by the protocol.
+    Other(u16),
+}
+
+impl CloseCode {
+    /// Whether this can be passed to [`WebSocket::close_with`]: browsers only let pages close
+    /// connections with [`Normal`](Self::Normal), [`Registered`](Self::Registered) and
+    /// [`Application`](Self::Application) codes.
+    ///
+    /// [`WebSocket::close_with`]: crate::websocket::futures::WebSocket::close_with
+    pub fn is_sendable(&self) -> bool {
+        let code = u16::from(*self);
+        code == 1000 || (3000..=4999).contains(&code)
+    }
+}
+
+impl From<u16> for CloseCode {
+    fn from(code: u16) -> Self {
+        match code {
+            1000 => Self::Normal,
+            1001 => Self::GoingAway,
+            1002 => Self::ProtocolError,
+            1003 => Self::Unsupported,
+            1005 => Self::NoStatus,
+            1006 => Self::Abnormal,
+            1007 => Self::InvalidData,
+            1008 => Self::PolicyViolation,
+            1009 => Self::TooBig,
+            1010 => Self::MissingExtension,
+            1011 => Self::InternalError,
+            1012 => Self::ServiceRestart,
+            1013 => Self::TryAgainLater,
+            1014 => Self::BadGateway,
+            1015 => Self::TlsHandshake,
+            3000..=3999 => Self::Registered(code),
+            4000..=4999 => Self::Application(code),
+            _ => Self::Other(code),
+        }
+    }
+}
+
+impl From<CloseCode> for u16 {
+    fn from(code: CloseCode) -> Self {
+        match code {
+            CloseCode::Normal => 1000,
+            CloseCode::GoingAway => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::Unsupported => 1003,
+            CloseCode::NoStatus => 1005,
+            CloseCode::Abnormal => 1006,
+            CloseCode::InvalidData => 1007,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::TooBig => 1009,
+            CloseCode::MissingExtension => 1010,
+            CloseCode::InternalError => 1011,
+            CloseCode::ServiceRestart => 1012,
+            CloseCode::TryAgainLater => 1013,
+            CloseCode::BadGateway => 1014,
+            CloseCode::TlsHandshake => 1015,
+            CloseCode::Registered(code) | CloseCode::Application(code) | CloseCode::Other(code) => {
+                code
+            }
+        }
+    }
+}
+
+impl fmt::Display for CloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", u16::from(*self))
+    }
+}
+
+/// An item of the stream of [`WebSocket::events`], telling the closing of the connection apart
+/// from its messages.
+///
+/// [`WebSocket::events`]: crate::websocket::futures::WebSocket::events
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WebSocketEvent {
+    /// A message was received.
+    Message(Message),
+    /// The connection was closed. This is the last item of the stream.
+    Closed {
+        /// Why the connection was closed.
+        code: CloseCode,
+        /// The reason given by the endpoint which closed the connection, often empty.
+        reason: String,
+        /// Whether the closing handshake completed.
+        was_clean: bool,
+    },
+}
+
+impl From<CloseEvent> for WebSocketEvent {
+    fn from(event: CloseEvent) -> Self {
+        Self::Closed {
+            code: event.close_code(),
+            reason: event.reason,
+            was_clean: event.was_clean,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_codes_round_trip() {
+        for code in (0..=5000).chain(vec![u16::MAX]) {
+            assert_eq!(u16::from(CloseCode::from(code)), code);
+        }
+        assert_eq!(CloseCode::from(1008), CloseCode::PolicyViolation);
+        assert_eq!(CloseCode::from(4001), CloseCode::Application(4001));
+        assert_eq!(CloseCode::from(1004), CloseCode::Other(1004));
+        assert!(CloseCode::Normal.is_sendable());
+        assert!(CloseCode::Application(4001).is_sendable());
+        assert!(!CloseCode::GoingAway.is_sendable());
+        assert!(!CloseCode::Other(2000).is_sendable());
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let kind = |message: Option<&str>, code, was_open| {
+            ErrorEvent::new(String::new(), message.map(str::to_string), code, was_open).kind
+        };
+        assert_eq!(kind(None, Some(1006), false), ErrorKind::Refused);
+        assert_eq!(kind(None, Some(1006), true), ErrorKind::Lost);
+        assert_eq!(kind(None, Some(1015), false), ErrorKind::Tls);
+        assert_eq!(kind(None, None, false), ErrorKind::Unknown);
+        assert_eq!(
+            kind(
+                Some("getaddrinfo ENOTFOUND example.invalid"),
+                Some(1006),
+                false
+            ),
+            ErrorKind::Dns
+        );
+        assert_eq!(
+            kind(Some("connect ECONNREFUSED 127.0.0.1:80"), None, false),
+            ErrorKind::Refused
+        );
+        assert_eq!(
+            kind(Some("invalid peer certificate: UnknownIssuer"), None, false),
+            ErrorKind::Tls
+        );
+        assert_eq!(
+            kind(Some("SecurityError: insecure connection"), None, false),
+            ErrorKind::Security
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+
+/// The extensions the server accepted during the handshake, parsed from the
+/// `Sec-WebSocket-Extensions` header, see [`WebSocket::negotiated_extensions`].
+///
+/// Browsers offer `permessage-deflate` on their own, and compress every message once the
+/// server accepts it. Apps can check [`is_compressed`](Self::is_compressed) to know whether
+/// compressing large payloads themselves is worth it.
+///
+/// # Example
+///
+/// ```
+/// use gloo_net::websocket::Extensions;
+///
+/// let extensions = Extensions::parse("permessage-deflate; client_max_window_bits=15");
+/// assert!(extensions.is_compressed());
+/// let deflate = extensions.permessage_deflate().unwrap();
+/// assert_eq!(deflate.client_max_window_bits, Some(15));
+/// ```
+///
+/// [`WebSocket::negotiated_extensions`]: crate::websocket::futures::WebSocket::negotiated_extensions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extensions {
+    extensions: Vec<Extension>,
+}
+
+impl Extensions {
+    /// Parses the value of a `Sec-WebSocket-Extensions` header, like the
+    /// [`extensions`](crate::websocket::futures::WebSocket::extensions) of a connection.
+    ///
+    /// Extensions without a name are left out, and quotes around parameter values are removed.
+    pub fn parse(header: &str) -> Self {
+        let extensions = header
+            .split(',')
+            .filter_map(|extension| {
+                let mut parts = extension.split(';').map(str::trim);
+                let name = parts.next().filter(|name| !name.is_empty())?;
+                let params = parts
+                    .filter(|param| !param.is_empty())
+                    .map(|param| match param.split_once('=') {
+                        Some((name, value)) => (
+                            name.trim().to_string(),
+                            Some(value.trim().trim_matches('"').to_string()),
+                        ),
+                        None => (param.to_string(), None),
+                    })
+                    .collect();
+                Some(Extension {
+                    name: name.to_string(),
+                    params,
+                })
+            })
+            .collect();
+        Self { extensions }
+    }
+
+    /// The extensions, in the order the server listed them.
+    pub fn iter(&self) -> impl Iterator<Item = &Extension> {
+        self.extensions.iter()
+    }
+
+    /// The extension named `name`, like `permessage-deflate`.
+    pub fn get(&self, name: &str) -> Option<&Extension> {
+        self.extensions
+            .iter()
+            .find(|extension| extension.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether no extension is in use, which is also the case while the connection isn't open.
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    /// Whether the messages are compressed with `permessage-deflate`.
+    pub fn is_compressed(&self) -> bool {
+        self.get(PERMESSAGE_DEFLATE).is_some()
+    }
+
+    /// The parameters of `permessage-deflate`, or `None` when it isn't in use.
+    pub fn permessage_deflate(&self) -> Option<PerMessageDeflate> {
+        let extension = self.get(PERMESSAGE_DEFLATE)?;
+        let window_bits = |name| extension.param(name).and_then(|bits| bits.parse().ok());
+        Some(PerMessageDeflate {
+            server_no_context_takeover: extension.has_param("server_no_context_takeover"),
+            client_no_context_takeover: extension.has_param("client_no_context_takeover"),
+            server_max_window_bits: window_bits("server_max_window_bits"),
+            client_max_window_bits: window_bits("client_max_window_bits"),
+        })
+    }
+}
+
+impl fmt::Display for Extensions {
+    /// Formats the extensions like a `Sec-WebSocket-Extensions` header.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, extension) in self.extensions.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(&extension.name)?;
+            for (name, value) in &extension.params {
+                write!(f, "; {}", name)?;
+                if let Some(value) = value {
+                    write!(f, "={}", value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// An extension the server accepted, with its parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    name: String,
+    params: Vec<(String, Option<String>)>,
+}
+
+impl Extension {
+    /// The name of the extension, like `permessage-deflate`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The value of the parameter `name`, or `None` when it is missing or has no value.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))?
+            .1
+            .as_deref()
+    }
+
+    /// Whether the extension has the parameter `name`, with or without a value.
+    pub fn has_param(&self, name: &str) -> bool {
+        self.params
+            .iter()
+            .any(|(param, _)| param.eq_ignore_ascii_case(name))
+    }
+}
+
+/// How the messages are compressed with `permessage-deflate`, see
+/// [`Extensions::permessage_deflate`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PerMessageDeflate {
+    /// Whether the server starts every message with an empty compression context, compressing
+    /// repeated payloads less well.
+    pub server_no_context_takeover: bool,
+    /// Whether the browser starts every message with an empty compression context.
+    pub client_no_context_takeover: bool,
+    /// The size of the window the server compresses with, as a power of two, when limited.
+    pub server_max_window_bits: Option<u8>,
+    /// The size of the window the browser compresses with, as a power of two, when limited.
+    pub client_max_window_bits: Option<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_extensions() {
+        let extensions = Extensions::parse(
+            r#"permessage-deflate; server_no_context_takeover; client_max_window_bits="10", x-custom"#,
+        );
+        assert_eq!(
+            extensions.iter().map(Extension::name).collect::<Vec<_>>(),
+            ["permessage-deflate", "x-custom"]
+        );
+        assert_eq!(
+            extensions.permessage_deflate(),
+            Some(PerMessageDeflate {
+                server_no_context_takeover: true,
+                client_max_window_bits: Some(10),
+                ..PerMessageDeflate::default()
+            })
+        );
+        assert_eq!(
+            extensions.to_string(),
+            "permessage-deflate; server_no_context_takeover; client_max_window_bits=10, x-custom"
+        );
+
+        let none = Extensions::parse("");
+        assert!(none.is_empty());
+        assert!(!none.is_compressed());
+        assert_eq!(none.permessage_deflate(), None);
+    }
+}
@@ -28,18 +28,29 @@
 //! # }
 //! ```
 use crate::js_to_js_error;
-use crate::websocket::{events::CloseEvent, Message, State, WebSocketError};
+use crate::websocket::events::{CloseCode, CloseEvent, ErrorEvent, WebSocketEvent};
+use crate::websocket::metrics::{message_size, raw_message_size, Counters};
+use crate::websocket::{
+    ConnectionState, ConnectionStates, Extensions, Message, Metrics, RawMessage, State,
+    WebSocketError,
+};
 use futures_channel::mpsc;
 use futures_core::{ready, Stream};
 use futures_sink::Sink;
+use gloo_timers::future::{sleep, TimeoutFuture};
 use gloo_utils::errors::JsError;
+use js_sys::Uint8Array;
 use pin_project::{pin_project, pinned_drop};
 use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
 use web_sys::{BinaryType, MessageEvent};
 
 /// Wrapper around browser's WebSocket API.
@@ -48,8 +59,17 @@ use web_sys::{BinaryType, MessageEvent};
 pub struct WebSocket {
     ws: web_sys::WebSocket,
     sink_waker: Rc<RefCell<Option<Waker>>>,
+    state_listeners: StateListeners,
+    counters: Rc<Counters>,
     #[pin]
     message_receiver: mpsc::UnboundedReceiver<StreamMessage>,
+    /// A `Blob` message being read, to be yielded as bytes.
+    pending_blob: Option<(web_sys::Blob, JsFuture)>,
+    /// The event received after an error event, looked at to tell what went wrong.
+    peeked: Option<StreamMessage>,
+    /// When to give up on the connection if it isn't open yet, see
+    /// [`open_timeout`](Self::open_timeout).
+    open_deadline: Option<TimeoutFuture>,
     #[allow(clippy::type_complexity)]
     closures: (
         Closure<dyn FnMut()>,
@@ -73,6 +93,20 @@ impl WebSocket {
         Self::setup(web_sys::WebSocket::new(url))
     }
 
+    /// Establish a WebSocket connection to `path`, relative to the page, like `/ws` or `live`.
+    ///
+    /// The path is resolved like a link of the page: against the base URL of the document,
+    /// which is its own URL unless set with a `<base>` element, or against the location of the
+    /// script in a worker. Its scheme then becomes `wss` for pages served over HTTPS, and `ws`
+    /// otherwise. On `https://example.com/app/`, `/ws` opens `wss://example.com/ws`, and `live`
+    /// opens `wss://example.com/app/live`, so apps deployed under a sub-path keep working.
+    ///
+    /// This errors like [`open`](Self::open), or when there is no location to resolve the path
+    /// against.
+    pub fn open_relative(path: &str) -> Result<Self, JsError> {
+        Self::open(&relative_url(path)?)
+    }
+
     /// Establish a WebSocket connection.
     ///
     /// This function may error in the following cases:
@@ -93,45 +127,43 @@ impl WebSocket {
     /// - The port to which the connection is being attempted is being blocked.
     /// - The URL is invalid.
     /// - The specified protocols are not supported
-    /// - The protocols cannot be converted to a JSON string list
+    ///
+    /// The server selects one of the protocols during the handshake, see
+    /// [`negotiated_protocol`](Self::negotiated_protocol).
     ///
     /// The error returned is [`JsError`]. See the
     /// [MDN Documentation](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket/WebSocket#exceptions_thrown)
     /// to learn more.
-    ///
-    /// This function requires `json` features because protocols are parsed by `serde` into `JsValue`.
-    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
-    #[cfg(feature = "json")]
-    pub fn open_with_protocols<S: AsRef<str> + serde::Serialize>(
-        url: &str,
-        protocols: &[S],
-    ) -> Result<Self, JsError> {
-        let json = <JsValue as gloo_utils::format::JsValueSerdeExt>::from_serde(protocols)
-            .map_err(|err| {
-                js_sys::Error::new(&format!(
-                    "Failed to convert protocols to Javascript value: {err}"
-                ))
-            })?;
-        Self::setup(web_sys::WebSocket::new_with_str_sequence(url, &json))
+    pub fn open_with_protocols<S: AsRef<str>>(url: &str, protocols: &[S]) -> Result<Self, JsError> {
+        let protocols = protocols
+            .iter()
+            .map(|protocol| JsValue::from_str(protocol.as_ref()))
+            .collect::<js_sys::Array>();
+        Self::setup(web_sys::WebSocket::new_with_str_sequence(url, &protocols))
     }
 
     fn setup(ws: Result<web_sys::WebSocket, JsValue>) -> Result<Self, JsError> {
         let waker: Rc<RefCell<Option<Waker>>> = Rc::new(RefCell::new(None));
         let ws = ws.map_err(js_to_js_error)?;
 
-        // We rely on this because the other type Blob can be converted to Vec<u8> only through a
-        // promise which makes it awkward to use in our event callbacks where we want to guarantee
-        // the order of the events stays the same.
+        // `Blob`s can only be read to bytes through a promise, which holds up the messages after
+        // them, so binary messages are received as `ArrayBuffer`s unless asked otherwise.
         ws.set_binary_type(BinaryType::Arraybuffer);
 
         let (sender, receiver) = mpsc::unbounded();
+        let state_listeners = StateListeners::default();
+        let counters = Rc::new(Counters::default());
 
         let open_callback: Closure<dyn FnMut()> = {
             let waker = Rc::clone(&waker);
+            let state_listeners = Rc::clone(&state_listeners);
+            let counters = Rc::clone(&counters);
             Closure::wrap(Box::new(move || {
                 if let Some(waker) = waker.borrow_mut().take() {
                     waker.wake();
                 }
+                counters.opened(js_sys::Date::now());
+                notify(&state_listeners, ConnectionState::Open);
             }) as Box<dyn FnMut()>)
         };
 
@@ -144,8 +176,10 @@ impl WebSocket {
 
         let message_callback: Closure<dyn FnMut(MessageEvent)> = {
             let sender = sender.clone();
+            let counters = Rc::clone(&counters);
             Closure::wrap(Box::new(move |e: MessageEvent| {
                 let msg = parse_message(e);
+                counters.received(raw_message_size(&msg));
                 let _ = sender.unbounded_send(StreamMessage::Message(msg));
             }) as Box<dyn FnMut(MessageEvent)>)
         };
@@ -156,11 +190,19 @@ impl WebSocket {
         let error_callback: Closure<dyn FnMut(web_sys::Event)> = {
             let sender = sender.clone();
             let waker = Rc::clone(&waker);
-            Closure::wrap(Box::new(move |_e: web_sys::Event| {
+            let counters = Rc::clone(&counters);
+            Closure::wrap(Box::new(move |e: web_sys::Event| {
                 if let Some(waker) = waker.borrow_mut().take() {
                     waker.wake();
                 }
-                let _ = sender.unbounded_send(StreamMessage::ErrorEvent);
+                let message = e
+                    .dyn_ref::<web_sys::ErrorEvent>()
+                    .map(web_sys::ErrorEvent::message)
+                    .filter(|message| !message.is_empty());
+                let _ = sender.unbounded_send(StreamMessage::ErrorEvent {
+                    message,
+                    was_open: counters.is_open(),
+                });
             }) as Box<dyn FnMut(web_sys::Event)>)
         };
 
@@ -168,8 +210,14 @@ impl WebSocket {
             .map_err(js_to_js_error)?;
 
         let close_callback: Closure<dyn FnMut(web_sys::CloseEvent)> = {
+            let state_listeners = Rc::clone(&state_listeners);
+            let counters = Rc::clone(&counters);
+            let url = ws.url();
             Closure::wrap(Box::new(move |e: web_sys::CloseEvent| {
+                counters.closed();
+                notify(&state_listeners, ConnectionState::Closed { code: e.code() });
                 let close_event = CloseEvent {
+                    url: url.clone(),
                     code: e.code(),
                     reason: e.reason(),
                     was_clean: e.was_clean(),
@@ -189,7 +237,12 @@ impl WebSocket {
         Ok(Self {
             ws,
             sink_waker: waker,
+            state_listeners,
+            counters,
             message_receiver: receiver,
+            pending_blob: None,
+            peeked: None,
+            open_deadline: None,
             closures: (
                 open_callback,
                 message_callback,
@@ -199,11 +252,179 @@ impl WebSocket {
         })
     }
 
+    /// Fails the connection with [`WebSocketError::ConnectTimeout`] unless it opens within
+    /// `timeout`, rather than waiting for as long as the browser does, e.g. when a proxy never
+    /// answers the handshake.
+    ///
+    /// The timeout starts with this call, but the deadline is only checked when the websocket is
+    /// polled, by sending or receiving, which then error with
+    /// [`WebSocketError::ConnectTimeout`] and close the websocket. A websocket which isn't polled
+    /// stays connecting past the deadline, until it is polled again. The stream of messages goes
+    /// on with the close event, and ends.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::SinkExt;
+    /// use gloo_net::websocket::{futures::WebSocket, Message, WebSocketError};
+    /// use std::time::Duration;
+    ///
+    /// # async fn no_run() {
+    /// let mut ws = WebSocket::open("wss://example.com/live")
+    ///     .unwrap()
+    ///     .open_timeout(Duration::from_secs(10));
+    /// match ws.send(Message::Text("hello".to_string())).await {
+    ///     Err(WebSocketError::ConnectTimeout { .. }) => { /* try another server */ }
+    ///     _ => {}
+    /// }
+    /// # }
+    /// ```
+    pub fn open_timeout(mut self, timeout: Duration) -> Self {
+        self.open_deadline = Some(sleep(timeout));
+        self
+    }
+
+    /// Closes the websocket if it is still connecting once the open timeout elapsed.
+    ///
+    /// This is the only place the deadline is checked, it registers `cx` to be woken when it
+    /// passes.
+    fn poll_open_timeout(&mut self, cx: &mut Context<'_>) -> Poll<WebSocketError> {
+        let Some(deadline) = &mut self.open_deadline else {
+            return Poll::Pending;
+        };
+        if self.ws.ready_state() != web_sys::WebSocket::CONNECTING {
+            self.open_deadline = None;
+            return Poll::Pending;
+        }
+        ready!(Pin::new(deadline).poll(cx));
+        self.open_deadline = None;
+        let _ = self.ws.close();
+        Poll::Ready(WebSocketError::ConnectTimeout { url: self.ws.url() })
+    }
+
     /// Closes the websocket.
     ///
     /// See the [MDN Documentation](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket/close#parameters)
     /// to learn about parameters passed to this function and when it can return an `Err(_)`
     pub fn close(self, code: Option<u16>, reason: Option<&str>) -> Result<(), JsError> {
+        self.close_raw(code, reason)
+    }
+
+    /// Closes the websocket with a [`CloseCode`] telling why, and a `reason` of up to 123 bytes.
+    ///
+    /// This errors if the code isn't [sendable](CloseCode::is_sendable) or the reason is too
+    /// long.
+    pub fn close_with(self, code: CloseCode, reason: &str) -> Result<(), JsError> {
+        self.close_raw(Some(code.into()), Some(reason))
+    }
+
+    /// Tells the closing of the connection apart from its messages, turning the
+    /// [`WebSocketError::ConnectionClose`] error ending the stream into a
+    /// [`WebSocketEvent::Closed`] item.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::StreamExt;
+    /// use gloo_net::websocket::events::{CloseCode, WebSocketEvent};
+    /// use gloo_net::websocket::futures::WebSocket;
+    ///
+    /// # async fn no_run() {
+    /// let mut events = WebSocket::open("wss://example.com/live").unwrap().events();
+    /// while let Some(Ok(event)) = events.next().await {
+    ///     match event {
+    ///         WebSocketEvent::Message(message) => { /* handle the message */ }
+    ///         WebSocketEvent::Closed {
+    ///             code: CloseCode::PolicyViolation,
+    ///             ..
+    ///         } => { /* log in again */ }
+    ///         WebSocketEvent::Closed { .. } => { /* connect again */ }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn events(self) -> Events<Self> {
+        Events::new(self)
+    }
+
+    /// Closes the websocket once the messages sent were handed over to the network, and waits
+    /// for the close event, for up to `timeout`.
+    ///
+    /// Unlike [`close`](Self::close), which drops the websocket right away, this gives the last
+    /// messages and the closing handshake the time to go through, so the server sees them and
+    /// the close code. The messages received meanwhile are dropped. This errors with
+    /// [`WebSocketError::CloseTimeout`] when the timeout elapses first, dropping the websocket
+    /// anyway, and like [`close_with`](Self::close_with) when the code or the reason are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::SinkExt;
+    /// use gloo_net::websocket::{events::CloseCode, futures::WebSocket, Message};
+    /// use std::time::Duration;
+    ///
+    /// # async fn no_run() -> Result<(), gloo_net::websocket::WebSocketError> {
+    /// let mut ws = WebSocket::open("wss://example.com/upload").unwrap();
+    /// ws.send(Message::Bytes(vec![0; 1 << 20])).await?;
+    /// let close = ws
+    ///     .close_graceful(CloseCode::Normal, "upload done", Duration::from_secs(5))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn close_graceful(
+        mut self,
+        code: CloseCode,
+        reason: &str,
+        timeout: Duration,
+    ) -> Result<CloseEvent, WebSocketError> {
+        let url = self.ws.url();
+        let mut deadline = sleep(timeout);
+        let closing = async {
+            while self.ws.buffered_amount() > 0 {
+                sleep(Duration::from_millis(10)).await;
+            }
+            self.close_raw(Some(code.into()), Some(reason))
+                .map_err(|error| WebSocketError::MessageSendError {
+                    url: url.clone(),
+                    error,
+                })?;
+            Ok(self.closed().await)
+        };
+        let mut closing = std::pin::pin!(closing);
+        std::future::poll_fn(|cx| {
+            if let Poll::Ready(result) = closing.as_mut().poll(cx) {
+                return Poll::Ready(result);
+            }
+            match Pin::new(&mut deadline).poll(cx) {
+                Poll::Ready(()) => {
+                    Poll::Ready(Err(WebSocketError::CloseTimeout { url: url.clone() }))
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// Waits for the close event, dropping the messages received meanwhile.
+    pub(super) async fn closed(&mut self) -> CloseEvent {
+        loop {
+            match std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await {
+                Some(Err(WebSocketError::ConnectionClose(event))) => return event,
+                Some(_) => continue,
+                None => {
+                    return CloseEvent {
+                        url: self.ws.url(),
+                        code: 1006,
+                        reason: String::new(),
+                        was_clean: false,
+                    }
+                }
+            }
+        }
+    }
+
+    pub(super) fn close_raw(&self, code: Option<u16>, reason: Option<&str>) -> Result<(), JsError> {
         let result = match (code, reason) {
             (None, None) => self.ws.close(),
             (Some(code), None) => self.ws.close_with_code(code),
@@ -215,6 +436,11 @@ impl WebSocket {
         result.map_err(js_to_js_error)
     }
 
+    /// The URL of the connection, resolved by the browser, which the errors carry too.
+    pub fn url(&self) -> String {
+        self.ws.url()
+    }
+
     /// The current state of the websocket.
     pub fn state(&self) -> State {
         let ready_state = self.ws.ready_state();
@@ -227,31 +453,498 @@ impl WebSocket {
         }
     }
 
-    /// The extensions in use.
+    /// The changes of the [state](Self::state) of the connection, from
+    /// [`Connecting`](ConnectionState::Connecting) to [`Open`](ConnectionState::Open) and
+    /// [`Closed`](ConnectionState::Closed), apart from the stream of messages.
+    ///
+    /// The stream ends once the connection is closed, right away if it already is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::StreamExt;
+    /// use gloo_net::websocket::{futures::WebSocket, ConnectionState};
+    /// use wasm_bindgen_futures::spawn_local;
+    ///
+    /// # fn no_run() {
+    /// let ws = WebSocket::open("wss://example.com/live").unwrap();
+    /// let mut states = ws.states();
+    /// spawn_local(async move {
+    ///     while let Some(state) = states.next().await {
+    ///         let online = state == ConnectionState::Open;
+    ///         // update the status indicator
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn states(&self) -> ConnectionStates {
+        let (sender, receiver) = mpsc::unbounded();
+        if self.ws.ready_state() != web_sys::WebSocket::CLOSED {
+            self.state_listeners.borrow_mut().push(sender);
+        }
+        ConnectionStates::new(receiver)
+    }
+
+    /// A snapshot of the counters of the connection, e.g. for a debugging overlay.
+    ///
+    /// Messages are counted as they are handed to and received from the browser, whether
+    /// they are read as [`Message`]s or [raw](Self::next_raw).
+    pub fn metrics(&self) -> Metrics {
+        self.counters.snapshot(js_sys::Date::now())
+    }
+
+    /// Sets how binary messages are received: as an `ArrayBuffer`, which is the default, or as a
+    /// `Blob`, which browsers may keep out of the memory of the page.
+    ///
+    /// Either way, the stream of messages yields [`Message::Bytes`], reading `Blob`s in order,
+    /// while [`next_raw`](Self::next_raw) hands them over as they are.
+    pub fn set_binary_type(&self, binary_type: BinaryType) {
+        self.ws.set_binary_type(binary_type);
+    }
+
+    /// How binary messages are received, see [`set_binary_type`](Self::set_binary_type).
+    pub fn binary_type(&self) -> BinaryType {
+        self.ws.binary_type()
+    }
+
+    /// Receives the next message, handing binary messages over as the `ArrayBuffer` or `Blob`
+    /// the browser received, instead of copying them into a `Vec<u8>`.
+    ///
+    /// This avoids copying large messages into the memory of the WebAssembly module when they
+    /// are only passed on to other web APIs, like a `Worker`, `decodeAudioData()` or a
+    /// `WebGLRenderingContext`. Like the stream of messages, this yields a
+    /// [`WebSocketError::ConnectionClose`] error, then `None`, once the connection closes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gloo_net::websocket::{futures::WebSocket, RawMessage};
+    ///
+    /// # async fn no_run() {
+    /// let mut ws = WebSocket::open("wss://example.com/frames").unwrap();
+    /// while let Some(Ok(message)) = ws.next_raw().await {
+    ///     if let RawMessage::ArrayBuffer(buffer) = message {
+    ///         let bytes = js_sys::Uint8Array::new(&buffer);
+    ///         // hand the frame over, or copy only the part needed
+    ///         let header = bytes.subarray(0, 4).to_vec();
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn next_raw(&mut self) -> Option<Result<RawMessage, WebSocketError>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next_raw(cx)).await
+    }
+
+    fn poll_next_raw(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<RawMessage, WebSocketError>>> {
+        if let Poll::Ready(error) = self.as_mut().get_mut().poll_open_timeout(cx) {
+            return Poll::Ready(Some(Err(error)));
+        }
+        let mut this = self.project();
+        if let Some((blob, _)) = this.pending_blob.take() {
+            return Poll::Ready(Some(Ok(RawMessage::Blob(blob))));
+        }
+        let msg = match this.peeked.take() {
+            Some(msg) => Some(msg),
+            None => ready!(this.message_receiver.as_mut().poll_next(cx)),
+        };
+        match msg {
+            Some(StreamMessage::Message(msg)) => Poll::Ready(Some(Ok(msg))),
+            Some(StreamMessage::ErrorEvent { message, was_open }) => {
+                // browsers fire the close event right after, its code tells more
+                *this.peeked = this.message_receiver.as_mut().get_mut().try_recv().ok();
+                let close_code = match this.peeked {
+                    Some(StreamMessage::CloseEvent(event)) => Some(event.code),
+                    _ => None,
+                };
+                let event = ErrorEvent::new(this.ws.url(), message, close_code, was_open);
+                Poll::Ready(Some(Err(WebSocketError::ConnectionError(event))))
+            }
+            Some(StreamMessage::CloseEvent(e)) => {
+                Poll::Ready(Some(Err(WebSocketError::ConnectionClose(e))))
+            }
+            Some(StreamMessage::ConnectionClose) => Poll::Ready(None),
+            None => Poll::Ready(None),
+        }
+    }
+
+    /// The extensions in use, as the raw `Sec-WebSocket-Extensions` header the server sent,
+    /// which is empty until the connection is open.
     pub fn extensions(&self) -> String {
         self.ws.extensions()
     }
 
-    /// The sub-protocol in use.
+    /// The extensions in use, parsed, e.g. to tell whether the messages are compressed with
+    /// `permessage-deflate`.
+    ///
+    /// Like [`extensions`](Self::extensions), this is empty until the connection is open.
+    pub fn negotiated_extensions(&self) -> Extensions {
+        Extensions::parse(&self.extensions())
+    }
+
+    /// The sub-protocol in use, which is empty until the connection is open, or when the server
+    /// selected none.
     pub fn protocol(&self) -> String {
         self.ws.protocol()
     }
+
+    /// Waits for the handshake, returning the sub-protocol the server selected, if it did.
+    ///
+    /// This errors with [`WebSocketError::ConnectionError`] if the connection couldn't be
+    /// opened.
+    pub async fn negotiated_protocol(&mut self) -> Result<Option<String>, WebSocketError> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_ready(cx)).await?;
+        if self.ws.ready_state() != web_sys::WebSocket::OPEN {
+            return Err(WebSocketError::ConnectionError(ErrorEvent::unknown(
+                self.ws.url(),
+            )));
+        }
+        let protocol = self.protocol();
+        Ok(Some(protocol).filter(|protocol| !protocol.is_empty()))
+    }
+
+    /// Waits for the handshake, returning the sub-protocol the server selected, for protocols
+    /// which can't do without one.
+    ///
+    /// Servers may accept the connection without selecting any of the protocols asked for, in
+    /// which case this closes the connection, and errors with
+    /// [`WebSocketError::NoProtocolSelected`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gloo_net::websocket::futures::WebSocket;
+    ///
+    /// # async fn no_run() -> Result<(), gloo_net::websocket::WebSocketError> {
+    /// let mut ws =
+    ///     WebSocket::open_with_protocols("wss://example.com/live", &["v2.live", "v1.live"]).unwrap();
+    /// let protocol = ws.require_protocol().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn require_protocol(&mut self) -> Result<String, WebSocketError> {
+        match self.negotiated_protocol().await? {
+            Some(protocol) => Ok(protocol),
+            None => {
+                let _ = self
+                    .ws
+                    .close_with_code_and_reason(1000, "no sub-protocol was selected");
+                Err(WebSocketError::NoProtocolSelected { url: self.ws.url() })
+            }
+        }
+    }
+}
+
+impl WebSocket {
+    /// Splits the connection into owned sending and receiving halves, which can be moved to
+    /// separate tasks.
+    ///
+    /// Unlike `StreamExt::split`, which still gives a `SplitSink` and a `SplitStream`, the halves
+    /// don't lock each other. The connection is closed once both halves are dropped, or by
+    /// [`WebSocketSender::close`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::{SinkExt, StreamExt};
+    /// use gloo_net::websocket::{futures::WebSocket, Message};
+    /// use wasm_bindgen_futures::spawn_local;
+    ///
+    /// # fn no_run() {
+    /// let ws = WebSocket::open("wss://example.com/chat").unwrap();
+    /// let (mut sender, mut receiver) = ws.into_halves();
+    /// let (outgoing, mut queue) = futures::channel::mpsc::unbounded::<String>();
+    ///
+    /// spawn_local(async move {
+    ///     while let Some(text) = queue.next().await {
+    ///         sender.send(Message::Text(text)).await.unwrap();
+    ///     }
+    /// });
+    /// spawn_local(async move {
+    ///     while let Some(Ok(message)) = receiver.next().await {
+    ///         // update the app state
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn into_halves(self) -> (WebSocketSender, WebSocketReceiver) {
+        let ws = Rc::new(RefCell::new(self));
+        (
+            WebSocketSender { ws: Rc::clone(&ws) },
+            WebSocketReceiver { ws },
+        )
+    }
+}
+
+/// The sending half of a [`WebSocket`], see [`WebSocket::into_halves`].
+#[must_use = "sinks do nothing unless polled"]
+pub struct WebSocketSender {
+    ws: Rc<RefCell<WebSocket>>,
+}
+
+impl WebSocketSender {
+    /// Closes the connection, which ends the stream of the receiving half after its close
+    /// event.
+    ///
+    /// See [`WebSocket::close`].
+    pub fn close(self, code: Option<u16>, reason: Option<&str>) -> Result<(), JsError> {
+        self.close_raw(code, reason)
+    }
+
+    pub(super) fn close_raw(&self, code: Option<u16>, reason: Option<&str>) -> Result<(), JsError> {
+        self.ws.borrow().close_raw(code, reason)
+    }
+
+    /// Closes the connection with a [`CloseCode`], see [`WebSocket::close_with`].
+    pub fn close_with(self, code: CloseCode, reason: &str) -> Result<(), JsError> {
+        self.close(Some(code.into()), Some(reason))
+    }
+
+    /// The URL of the connection, see [`WebSocket::url`].
+    pub fn url(&self) -> String {
+        self.ws.borrow().url()
+    }
+
+    /// The current state of the websocket.
+    pub fn state(&self) -> State {
+        self.ws.borrow().state()
+    }
+
+    /// The changes of the state of the connection, see [`WebSocket::states`].
+    pub fn states(&self) -> ConnectionStates {
+        self.ws.borrow().states()
+    }
+
+    /// A snapshot of the counters of the connection, see [`WebSocket::metrics`].
+    pub fn metrics(&self) -> Metrics {
+        self.ws.borrow().metrics()
+    }
+
+    /// The extensions in use, see [`WebSocket::negotiated_extensions`].
+    pub fn negotiated_extensions(&self) -> Extensions {
+        self.ws.borrow().negotiated_extensions()
+    }
+
+    /// Whether `receiver` is the other half of the same connection.
+    pub fn is_pair_of(&self, receiver: &WebSocketReceiver) -> bool {
+        Rc::ptr_eq(&self.ws, &receiver.ws)
+    }
+
+    /// Puts the connection back together from its halves, or gives them back if they aren't
+    /// halves of the same connection.
+    pub fn reunite(
+        self,
+        receiver: WebSocketReceiver,
+    ) -> Result<WebSocket, (WebSocketSender, WebSocketReceiver)> {
+        if !self.is_pair_of(&receiver) {
+            return Err((self, receiver));
+        }
+        drop(receiver);
+        match Rc::try_unwrap(self.ws) {
+            Ok(ws) => Ok(ws.into_inner()),
+            Err(_) => unreachable!("a connection has two halves"),
+        }
+    }
+}
+
+impl Sink<Message> for WebSocketSender {
+    type Error = WebSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *self.ws.borrow_mut()).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut *self.ws.borrow_mut()).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *self.ws.borrow_mut()).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *self.ws.borrow_mut()).poll_close(cx)
+    }
+}
+
+impl fmt::Debug for WebSocketSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketSender")
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a [`WebSocket`], see [`WebSocket::into_halves`].
+#[must_use = "streams do nothing unless polled"]
+pub struct WebSocketReceiver {
+    ws: Rc<RefCell<WebSocket>>,
+}
+
+impl WebSocketReceiver {
+    /// The URL of the connection, see [`WebSocket::url`].
+    pub fn url(&self) -> String {
+        self.ws.borrow().url()
+    }
+
+    /// The current state of the websocket.
+    pub fn state(&self) -> State {
+        self.ws.borrow().state()
+    }
+
+    /// The changes of the state of the connection, see [`WebSocket::states`].
+    pub fn states(&self) -> ConnectionStates {
+        self.ws.borrow().states()
+    }
+
+    /// A snapshot of the counters of the connection, see [`WebSocket::metrics`].
+    pub fn metrics(&self) -> Metrics {
+        self.ws.borrow().metrics()
+    }
+
+    /// The extensions in use, see [`WebSocket::negotiated_extensions`].
+    pub fn negotiated_extensions(&self) -> Extensions {
+        self.ws.borrow().negotiated_extensions()
+    }
+
+    /// Tells the closing of the connection apart from its messages, see [`WebSocket::events`].
+    pub fn events(self) -> Events<Self> {
+        Events::new(self)
+    }
+
+    /// Sets how binary messages are received, see [`WebSocket::set_binary_type`].
+    pub fn set_binary_type(&self, binary_type: BinaryType) {
+        self.ws.borrow().set_binary_type(binary_type);
+    }
+
+    /// Receives the next message without copying binary messages, see [`WebSocket::next_raw`].
+    pub async fn next_raw(&mut self) -> Option<Result<RawMessage, WebSocketError>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self.ws.borrow_mut()).poll_next_raw(cx)).await
+    }
+}
+
+impl Stream for WebSocketReceiver {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut *self.ws.borrow_mut()).poll_next(cx)
+    }
+}
+
+impl fmt::Debug for WebSocketReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketReceiver")
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A stream of [`WebSocketEvent`]s, see [`WebSocket::events`].
+///
+/// It forwards the messages sent to it to the connection, when it wraps a [`WebSocket`].
+#[must_use = "streams do nothing unless polled"]
+pub struct Events<S> {
+    inner: S,
+    closed: bool,
+}
+
+impl<S> Events<S> {
+    fn new(inner: S) -> Self {
+        Self {
+            inner,
+            closed: false,
+        }
+    }
+
+    /// The wrapped connection, or half of it.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Stream for Events<S>
+where
+    S: Stream<Item = Result<Message, WebSocketError>> + Unpin,
+{
+    type Item = Result<WebSocketEvent, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.closed {
+            return Poll::Ready(None);
+        }
+        let item = match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+            Some(Ok(message)) => Ok(WebSocketEvent::Message(message)),
+            Some(Err(WebSocketError::ConnectionClose(event))) => {
+                self.closed = true;
+                Ok(event.into())
+            }
+            Some(Err(error)) => Err(error),
+            None => return Poll::Ready(None),
+        };
+        Poll::Ready(Some(item))
+    }
+}
+
+impl<S: Sink<Message> + Unpin> Sink<Message> for Events<S> {
+    type Error = S::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut self.inner).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+impl<S> fmt::Debug for Events<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Events")
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The streams of the changes of the state of a connection, see [`WebSocket::states`].
+type StateListeners = Rc<RefCell<Vec<mpsc::UnboundedSender<ConnectionState>>>>;
+
+fn notify(listeners: &StateListeners, state: ConnectionState) {
+    let mut listeners = listeners.borrow_mut();
+    listeners.retain(|listener| listener.unbounded_send(state).is_ok());
+    if let ConnectionState::Closed { .. } = state {
+        listeners.clear();
+    }
 }
 
 #[derive(Clone)]
 enum StreamMessage {
-    ErrorEvent,
+    ErrorEvent {
+        message: Option<String>,
+        was_open: bool,
+    },
     CloseEvent(CloseEvent),
-    Message(Message),
+    Message(RawMessage),
     ConnectionClose,
 }
 
-fn parse_message(event: MessageEvent) -> Message {
-    if let Ok(array_buffer) = event.data().dyn_into::<js_sys::ArrayBuffer>() {
-        let array = js_sys::Uint8Array::new(&array_buffer);
-        Message::Bytes(array.to_vec())
-    } else if let Ok(txt) = event.data().dyn_into::<js_sys::JsString>() {
-        Message::Text(String::from(&txt))
+fn parse_message(event: MessageEvent) -> RawMessage {
+    let data = event.data();
+    if data.is_instance_of::<js_sys::ArrayBuffer>() {
+        RawMessage::ArrayBuffer(data.unchecked_into())
+    } else if let Some(txt) = data.as_string() {
+        RawMessage::Text(txt)
+    } else if data.is_instance_of::<web_sys::Blob>() {
+        RawMessage::Blob(data.unchecked_into())
     } else {
         unreachable!("message event, received Unknown: {:?}", event.data());
     }
@@ -260,9 +953,12 @@ fn parse_message(event: MessageEvent) -> Message {
 impl Sink<Message> for WebSocket {
     type Error = WebSocketError;
 
-    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         let ready_state = self.ws.ready_state();
         if ready_state == 0 {
+            if let Poll::Ready(error) = self.as_mut().get_mut().poll_open_timeout(cx) {
+                return Poll::Ready(Err(error));
+            }
             *self.sink_waker.borrow_mut() = Some(cx.waker().clone());
             Poll::Pending
         } else {
@@ -271,13 +967,20 @@ impl Sink<Message> for WebSocket {
     }
 
     fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        let size = message_size(&item);
         let result = match item {
             Message::Bytes(bytes) => self.ws.send_with_u8_array(&bytes),
             Message::Text(message) => self.ws.send_with_str(&message),
         };
         match result {
-            Ok(_) => Ok(()),
-            Err(e) => Err(WebSocketError::MessageSendError(js_to_js_error(e))),
+            Ok(_) => {
+                self.counters.sent(size);
+                Ok(())
+            }
+            Err(e) => Err(WebSocketError::MessageSendError {
+                url: self.ws.url(),
+                error: js_to_js_error(e),
+            }),
         }
     }
 
@@ -293,22 +996,70 @@ impl Sink<Message> for WebSocket {
 impl Stream for WebSocket {
     type Item = Result<Message, WebSocketError>;
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let msg = ready!(self.project().message_receiver.poll_next(cx));
-        match msg {
-            Some(StreamMessage::Message(msg)) => Poll::Ready(Some(Ok(msg))),
-            Some(StreamMessage::ErrorEvent) => {
-                Poll::Ready(Some(Err(WebSocketError::ConnectionError)))
-            }
-            Some(StreamMessage::CloseEvent(e)) => {
-                Poll::Ready(Some(Err(WebSocketError::ConnectionClose(e))))
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            // a `Blob` is read before the messages after it, keeping them in order
+            if let Some((_, reading)) = &mut self.pending_blob {
+                let buffer = ready!(Pin::new(reading).poll(cx));
+                self.pending_blob = None;
+                let url = self.ws.url();
+                let message = buffer
+                    .map(|buffer| Message::Bytes(Uint8Array::new(&buffer).to_vec()))
+                    .map_err(|_| WebSocketError::ConnectionError(ErrorEvent::unknown(url)));
+                return Poll::Ready(Some(message));
             }
-            Some(StreamMessage::ConnectionClose) => Poll::Ready(None),
-            None => Poll::Ready(None),
+            let message = match ready!(self.as_mut().poll_next_raw(cx)) {
+                Some(Ok(RawMessage::Text(text))) => Ok(Message::Text(text)),
+                Some(Ok(RawMessage::ArrayBuffer(buffer))) => {
+                    Ok(Message::Bytes(Uint8Array::new(&buffer).to_vec()))
+                }
+                Some(Ok(RawMessage::Blob(blob))) => {
+                    let reading = JsFuture::from(blob.array_buffer());
+                    self.pending_blob = Some((blob, reading));
+                    continue;
+                }
+                Some(Err(error)) => Err(error),
+                None => return Poll::Ready(None),
+            };
+            return Poll::Ready(Some(message));
         }
     }
 }
 
+/// Resolves `path` against the base URL of the page, as a `ws` or `wss` URL.
+fn relative_url(path: &str) -> Result<String, JsError> {
+    let base = base_url().ok_or_else(|| {
+        js_to_js_error(js_sys::Error::new("there is no location to resolve the URL against").into())
+    })?;
+    let url = web_sys::Url::new_with_base(path, &base).map_err(js_to_js_error)?;
+    Ok(websocket_url(&url.href()))
+}
+
+/// The `baseURI` of the document, or the `location` of the global scope, like in a worker.
+fn base_url() -> Option<String> {
+    let global = js_sys::global();
+    let get = |target: &JsValue, key: &str| {
+        js_sys::Reflect::get(target, &JsValue::from_str(key))
+            .ok()
+            .filter(|value| !value.is_undefined() && !value.is_null())
+    };
+    get(&global, "document")
+        .and_then(|document| get(&document, "baseURI"))
+        .or_else(|| get(&global, "location").and_then(|location| get(&location, "href")))
+        .and_then(|url| url.as_string())
+}
+
+/// Swaps the `http` scheme of `url` for `ws`, and `https` for `wss`.
+pub(crate) fn websocket_url(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("https:") {
+        format!("wss:{}", rest)
+    } else if let Some(rest) = url.strip_prefix("http:") {
+        format!("ws:{}", rest)
+    } else {
+        url.to_string()
+    }
+}
+
 #[pinned_drop]
 impl PinnedDrop for WebSocket {
     fn drop(self: Pin<&mut Self>) {
@@ -378,4 +1129,102 @@ mod tests {
             );
         });
     }
+
+    #[wasm_bindgen_test]
+    async fn blob_messages_are_read_in_order() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let mut ws = WebSocket::open(ws_echo_server_url).unwrap();
+        ws.set_binary_type(BinaryType::Blob);
+        // ignore the info message of the echo-server
+        let _ = ws.next().await;
+
+        ws.send(Message::Bytes(vec![1, 2, 3])).await.unwrap();
+        ws.send(Message::Text("after".to_string())).await.unwrap();
+        assert_eq!(
+            ws.next().await.unwrap().unwrap(),
+            Message::Bytes(vec![1, 2, 3])
+        );
+        assert_eq!(
+            ws.next().await.unwrap().unwrap(),
+            Message::Text("after".to_string())
+        );
+
+        ws.send(Message::Bytes(vec![4])).await.unwrap();
+        match ws.next_raw().await.unwrap().unwrap() {
+            RawMessage::Blob(blob) => assert_eq!(blob.size(), 1.0),
+            message => panic!("expected a blob, got {:?}", message),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn states_follow_the_connection() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let ws = WebSocket::open(ws_echo_server_url).unwrap();
+        let mut states = ws.states();
+        assert_eq!(states.next().await, Some(ConnectionState::Open));
+
+        ws.close_with(CloseCode::Normal, "done").unwrap();
+        assert_eq!(
+            states.next().await,
+            Some(ConnectionState::Closed { code: 1000 })
+        );
+        assert_eq!(states.next().await, None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn open_timeout_fails_unanswered_connections() {
+        // a non-routable address, which never answers the handshake
+        let mut ws = WebSocket::open("ws://10.255.255.1:81/")
+            .unwrap()
+            .open_timeout(Duration::from_millis(100));
+        match ws.next().await {
+            Some(Err(WebSocketError::ConnectTimeout { url })) => {
+                assert_eq!(url, "ws://10.255.255.1:81/")
+            }
+            item => panic!("expected a connect timeout, got {:?}", item),
+        }
+        // the close event follows, and ends the stream
+        while let Some(item) = ws.next().await {
+            assert!(item.is_err());
+        }
+        assert_eq!(ws.state(), State::Closed);
+    }
+
+    #[test]
+    fn websocket_urls() {
+        assert_eq!(
+            websocket_url("https://example.com/app/live"),
+            "wss://example.com/app/live"
+        );
+        assert_eq!(
+            websocket_url("http://localhost:8080/ws?token=1"),
+            "ws://localhost:8080/ws?token=1"
+        );
+        assert_eq!(
+            websocket_url("wss://example.com/ws"),
+            "wss://example.com/ws"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn halves_reunite() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let (sender, receiver) = WebSocket::open(ws_echo_server_url).unwrap().into_halves();
+        let (other_sender, other_receiver) =
+            WebSocket::open(ws_echo_server_url).unwrap().into_halves();
+        assert!(sender.is_pair_of(&receiver));
+        assert!(!sender.is_pair_of(&other_receiver));
+
+        let Err((sender, other_receiver)) = sender.reunite(other_receiver) else {
+            panic!("halves of different connections were reunited");
+        };
+        assert!(sender.reunite(receiver).is_ok());
+        assert!(other_sender.reunite(other_receiver).is_ok());
+    }
 }
@@ -0,0 +1,177 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use gloo_timers::future::{sleep, TimeoutFuture};
+
+use crate::websocket::metrics::since;
+use crate::websocket::Message;
+
+/// Tells the replies to the keepalive message apart.
+type IsReply = Rc<dyn Fn(&Message) -> bool>;
+
+/// Application-level keepalive messages, checking that a connection is still alive.
+///
+/// Browsers don't expose the ping and pong frames of the WebSocket protocol, so a connection
+/// whose network silently went away, e.g. behind a proxy or after the computer slept, can look
+/// open for minutes. With a heartbeat, a keepalive message is sent once no message was received
+/// for an [`interval`](Self::interval), and the connection is considered stale if no message
+/// arrives within the [`timeout`](Self::timeout) after it. A stale connection is closed and
+/// opened again, or reported with [`WebSocketError::StaleConnection`] when
+/// [`reconnect`](Self::reconnect) is off.
+///
+/// See [`ReconnectingWebSocketBuilder::heartbeat`].
+///
+/// # Example
+///
+/// ```
+/// use gloo_net::websocket::{Heartbeat, Message, ReconnectingWebSocket};
+/// use std::time::Duration;
+///
+/// # fn no_run() {
+/// let ws = ReconnectingWebSocket::builder("wss://example.com/live")
+///     .heartbeat(
+///         Heartbeat::new(Message::Text("ping".to_string()))
+///             .interval(Duration::from_secs(20))
+///             .reply(|message| *message == Message::Text("pong".to_string())),
+///     )
+///     .open()
+///     .unwrap();
+/// # }
+/// ```
+///
+/// [`WebSocketError::StaleConnection`]: crate::websocket::WebSocketError::StaleConnection
+/// [`ReconnectingWebSocketBuilder::heartbeat`]: crate::websocket::ReconnectingWebSocketBuilder::heartbeat
+#[derive(Clone)]
+pub struct Heartbeat {
+    message: Message,
+    interval: Duration,
+    timeout: Duration,
+    reply: Option<IsReply>,
+    reconnect: bool,
+}
+
+impl Heartbeat {
+    /// Sends `message` as the keepalive message, after 30s without messages, and waits 10s for
+    /// a reply.
+    pub fn new(message: Message) -> Self {
+        Self {
+            message,
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+            reply: None,
+            reconnect: true,
+        }
+    }
+
+    /// Sets how long the connection can go without receiving a message before the keepalive
+    /// message is sent.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets how long to wait for a message after sending the keepalive message.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Tells the replies to the keepalive message apart, like a `pong` message, which are then
+    /// left out of the stream of messages.
+    ///
+    /// By default, any message proves the connection alive, and every message is passed on.
+    pub fn reply(mut self, is_reply: impl Fn(&Message) -> bool + 'static) -> Self {
+        self.reply = Some(Rc::new(is_reply));
+        self
+    }
+
+    /// Whether to close a stale connection and open it again, which is the default, rather than
+    /// report it on the stream of messages and keep it.
+    pub fn reconnect(mut self, reconnect: bool) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    pub(crate) fn reconnects(&self) -> bool {
+        self.reconnect
+    }
+
+    /// Starts following a connection which just opened.
+    pub(crate) fn start(&self) -> Pulse {
+        Pulse {
+            heartbeat: self.clone(),
+            timer: sleep(self.interval),
+            sent_at: None,
+            last_rtt: None,
+        }
+    }
+}
+
+impl fmt::Debug for Heartbeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heartbeat")
+            .field("message", &self.message)
+            .field("interval", &self.interval)
+            .field("timeout", &self.timeout)
+            .field("reconnect", &self.reconnect)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a [`Pulse`] asks for.
+pub(crate) enum Beat {
+    /// Send the keepalive message.
+    Send(Message),
+    /// No message arrived in time after the keepalive message.
+    Missed,
+}
+
+/// The heartbeat of one connection.
+pub(crate) struct Pulse {
+    heartbeat: Heartbeat,
+    timer: TimeoutFuture,
+    /// When the keepalive message awaiting a reply was sent, in milliseconds since the epoch.
+    sent_at: Option<f64>,
+    last_rtt: Option<Duration>,
+}
+
+impl Pulse {
+    /// Notes that `message` was received, returning whether it is a reply to the keepalive
+    /// message, which shouldn't be passed on.
+    pub(crate) fn received(&mut self, message: &Message) -> bool {
+        self.timer = sleep(self.heartbeat.interval);
+        let is_reply = match &self.heartbeat.reply {
+            Some(is_reply) => is_reply(message),
+            None => false,
+        };
+        // without a way to tell replies apart, any message is taken for the reply
+        let answered = is_reply || self.heartbeat.reply.is_none();
+        if let Some(sent_at) = self.sent_at.take().filter(|_| answered) {
+            self.last_rtt = Some(since(sent_at, js_sys::Date::now()));
+        }
+        is_reply
+    }
+
+    /// How long the last reply to the keepalive message took to arrive.
+    pub(crate) fn last_rtt(&self) -> Option<Duration> {
+        self.last_rtt
+    }
+
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Beat> {
+        if Pin::new(&mut self.timer).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        if self.sent_at.take().is_some() {
+            self.timer = sleep(self.heartbeat.interval);
+            Poll::Ready(Beat::Missed)
+        } else {
+            self.timer = sleep(self.heartbeat.timeout);
+            self.sent_at = Some(js_sys::Date::now());
+            Poll::Ready(Beat::Send(self.heartbeat.message.clone()))
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::cell::Cell;
+use std::time::Duration;
+
+use crate::websocket::{Message, RawMessage};
+
+/// A snapshot of the counters of a connection, e.g. for a debugging overlay.
+///
+/// See [`WebSocket::metrics`] and [`ReconnectingWebSocket::metrics`].
+///
+/// [`WebSocket::metrics`]: crate::websocket::futures::WebSocket::metrics
+/// [`ReconnectingWebSocket::metrics`]: crate::websocket::ReconnectingWebSocket::metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// How many messages were sent.
+    pub messages_sent: u64,
+    /// How many messages were received.
+    pub messages_received: u64,
+    /// The size of the messages sent, text messages counting their UTF-8 bytes.
+    pub bytes_sent: u64,
+    /// The size of the messages received, text messages counting their UTF-8 bytes.
+    pub bytes_received: u64,
+    /// How many times the connection was opened again, always `0` for a [`WebSocket`].
+    ///
+    /// [`WebSocket`]: crate::websocket::futures::WebSocket
+    pub reconnects: u32,
+    /// How long the last reply to the keepalive message of the [`Heartbeat`] took to arrive.
+    ///
+    /// [`Heartbeat`]: crate::websocket::Heartbeat
+    pub last_rtt: Option<Duration>,
+    /// How long the connection has been open, or `None` while it isn't.
+    pub uptime: Option<Duration>,
+}
+
+/// The counters behind [`Metrics`].
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    messages_sent: Cell<u64>,
+    messages_received: Cell<u64>,
+    bytes_sent: Cell<u64>,
+    bytes_received: Cell<u64>,
+    reconnects: Cell<u32>,
+    last_rtt: Cell<Option<Duration>>,
+    /// When the connection opened, in milliseconds since the epoch.
+    opened_at: Cell<Option<f64>>,
+}
+
+impl Counters {
+    pub(crate) fn sent(&self, bytes: usize) {
+        self.messages_sent.set(self.messages_sent.get() + 1);
+        self.bytes_sent.set(self.bytes_sent.get() + bytes as u64);
+    }
+
+    pub(crate) fn received(&self, bytes: usize) {
+        self.messages_received.set(self.messages_received.get() + 1);
+        self.bytes_received
+            .set(self.bytes_received.get() + bytes as u64);
+    }
+
+    pub(crate) fn reconnected(&self) {
+        self.reconnects.set(self.reconnects.get() + 1);
+    }
+
+    pub(crate) fn set_rtt(&self, rtt: Duration) {
+        self.last_rtt.set(Some(rtt));
+    }
+
+    pub(crate) fn opened(&self, now: f64) {
+        self.opened_at.set(Some(now));
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.opened_at.get().is_some()
+    }
+
+    pub(crate) fn closed(&self) {
+        self.opened_at.set(None);
+    }
+
+    pub(crate) fn snapshot(&self, now: f64) -> Metrics {
+        Metrics {
+            messages_sent: self.messages_sent.get(),
+            messages_received: self.messages_received.get(),
+            bytes_sent: self.bytes_sent.get(),
+            bytes_received: self.bytes_received.get(),
+            reconnects: self.reconnects.get(),
+            last_rtt: self.last_rtt.get(),
+            uptime: self.opened_at.get().map(|opened| since(opened, now)),
+        }
+    }
+}
+
+/// The time elapsed between two timestamps in milliseconds, like those of `Date.now()`.
+pub(crate) fn since(then: f64, now: f64) -> Duration {
+    Duration::from_secs_f64((now - then).max(0.0) / 1000.0)
+}
+
+pub(crate) fn message_size(message: &Message) -> usize {
+    match message {
+        Message::Text(text) => text.len(),
+        Message::Bytes(bytes) => bytes.len(),
+    }
+}
+
+pub(crate) fn raw_message_size(message: &RawMessage) -> usize {
+    match message {
+        RawMessage::Text(text) => text.len(),
+        RawMessage::ArrayBuffer(buffer) => buffer.byte_length() as usize,
+        RawMessage::Blob(blob) => blob.size() as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_add_up() {
+        let counters = Counters::default();
+        counters.sent(message_size(&Message::Text("héllo".to_string())));
+        counters.sent(message_size(&Message::Bytes(vec![0; 10])));
+        counters.received(3);
+        assert_eq!(
+            counters.snapshot(0.0),
+            Metrics {
+                messages_sent: 2,
+                messages_received: 1,
+                bytes_sent: 16,
+                bytes_received: 3,
+                ..Metrics::default()
+            }
+        );
+
+        counters.opened(1_000.0);
+        counters.reconnected();
+        counters.set_rtt(Duration::from_millis(40));
+        let metrics = counters.snapshot(3_500.0);
+        assert_eq!(metrics.uptime, Some(Duration::from_millis(2_500)));
+        assert_eq!(metrics.reconnects, 1);
+        assert_eq!(metrics.last_rtt, Some(Duration::from_millis(40)));
+
+        counters.closed();
+        assert_eq!(counters.snapshot(4_000.0).uptime, None);
+    }
+}
@@ -2,15 +2,103 @@
 //!
 //! This API is provided in the following flavors:
 //! - [Futures API][futures]
+//!
+//! Nothing here depends on `window`, so connections can be opened in dedicated workers too,
+//! moving heavy realtime processing off the main thread.
 
 pub mod events;
+mod extensions;
 pub mod futures;
+mod heartbeat;
+mod metrics;
+#[cfg(feature = "mqtt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mqtt")))]
+pub mod mqtt;
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        feature = "json",
+        feature = "cbor",
+        feature = "msgpack",
+        feature = "bincode"
+    )))
+)]
+pub mod mux;
+#[cfg(feature = "phoenix")]
+#[cfg_attr(docsrs, doc(cfg(feature = "phoenix")))]
+pub mod phoenix;
+mod pool;
+mod reconnecting;
+#[cfg(feature = "rpc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rpc")))]
+pub mod rpc;
+#[cfg(feature = "shared-worker")]
+#[cfg_attr(docsrs, doc(cfg(feature = "shared-worker")))]
+pub mod shared;
+#[cfg(feature = "socketio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "socketio")))]
+pub mod socketio;
+#[cfg(feature = "stomp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "stomp")))]
+pub mod stomp;
+mod stream;
+#[cfg(feature = "tungstenite")]
+mod tungstenite;
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
+mod typed;
+
+pub use extensions::{Extension, Extensions, PerMessageDeflate};
+pub use heartbeat::Heartbeat;
+pub use metrics::Metrics;
+pub use pool::{PooledWebSocket, WebSocketPool};
+pub use reconnecting::{
+    ConnectionStates, OverflowPolicy, ReconnectingWebSocket, ReconnectingWebSocketBuilder,
+};
+pub use stream::WebSocketStream;
+#[cfg(feature = "bincode")]
+pub use typed::Bincode;
+#[cfg(feature = "cbor")]
+pub use typed::Cbor;
+#[cfg(feature = "json")]
+pub use typed::Json;
+#[cfg(feature = "msgpack")]
+pub use typed::MsgPack;
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        feature = "json",
+        feature = "cbor",
+        feature = "msgpack",
+        feature = "bincode"
+    )))
+)]
+pub use typed::{Codec, CodecError, TypedSink, TypedStream};
 
-use events::CloseEvent;
+use events::{CloseEvent, ErrorEvent};
 use gloo_utils::errors::JsError;
 use std::fmt;
 
 /// Message sent to and received from WebSocket.
+///
+/// With the `tungstenite` feature, it converts to and from `tungstenite::Message`, so that
+/// protocol code written for native clients and servers can be shared with the browser.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Message {
     /// String message
@@ -19,11 +107,24 @@ pub enum Message {
     Bytes(Vec<u8>),
 }
 
+/// A message as received by the browser, see
+/// [`WebSocket::next_raw`](futures::WebSocket::next_raw).
+#[derive(Debug, Clone)]
+pub enum RawMessage {
+    /// String message
+    Text(String),
+    /// Binary message, received as an `ArrayBuffer`
+    ArrayBuffer(js_sys::ArrayBuffer),
+    /// Binary message, received as a `Blob`, see
+    /// [`WebSocket::set_binary_type`](futures::WebSocket::set_binary_type)
+    Blob(web_sys::Blob),
+}
+
 /// The state of the websocket.
 ///
 /// See [`WebSocket.readyState` on MDN](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket/readyState)
 /// to learn more.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum State {
     /// The connection has not yet been established.
     Connecting,
@@ -36,30 +137,221 @@ pub enum State {
     Closed,
 }
 
+/// The state of a connection which is opened again when it closes, like a
+/// [`ReconnectingWebSocket`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The connection is being established.
+    Connecting,
+    /// The connection is established and messages flow.
+    Open,
+    /// The connection closed or failed, and will be established again after a backoff.
+    Reconnecting {
+        /// How many attempts to reconnect were made in a row, this one included.
+        attempt: u32,
+    },
+    /// The connection is closed for good.
+    Closed {
+        /// The close code of the last connection: `1000` when it was closed on purpose, and
+        /// `1006` when it failed without a close code.
+        code: u16,
+    },
+}
+
 /// Error returned by WebSocket
+///
+/// Every error tells the URL of the connection it is about, see [`url`](Self::url).
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum WebSocketError {
-    /// The `error` event
-    ConnectionError,
+    /// The `error` event, or the connection failing without one
+    ConnectionError(ErrorEvent),
     /// The `close` event
     ConnectionClose(CloseEvent),
     /// Message failed to send.
-    MessageSendError(JsError),
+    MessageSendError {
+        /// The URL of the connection.
+        url: String,
+        /// The error thrown by the browser.
+        error: JsError,
+    },
+    /// The connection didn't open in time, see
+    /// [`WebSocket::open_timeout`](futures::WebSocket::open_timeout).
+    ConnectTimeout {
+        /// The URL of the connection.
+        url: String,
+    },
+    /// The buffer of messages waiting to be sent is full, see
+    /// [`ReconnectingWebSocketBuilder::buffer`].
+    BufferFull {
+        /// The URL of the connection.
+        url: String,
+    },
+    /// The close event didn't arrive in time, see
+    /// [`WebSocket::close_graceful`](futures::WebSocket::close_graceful).
+    CloseTimeout {
+        /// The URL of the connection.
+        url: String,
+    },
+    /// The connection couldn't be opened, because the URL or the sub-protocols are invalid, or
+    /// the port is blocked.
+    OpenError {
+        /// The URL of the connection.
+        url: String,
+        /// The error thrown by the browser.
+        error: JsError,
+    },
+    /// No message arrived in time after a keepalive message, see [`Heartbeat`].
+    StaleConnection {
+        /// The URL of the connection.
+        url: String,
+    },
+    /// The server accepted the connection without selecting any of the sub-protocols asked for,
+    /// see [`WebSocket::require_protocol`].
+    ///
+    /// [`WebSocket::require_protocol`]: futures::WebSocket::require_protocol
+    NoProtocolSelected {
+        /// The URL of the connection.
+        url: String,
+    },
+    /// The channel is already open, see [`Multiplexer::channel`](mux::Multiplexer::channel).
+    ChannelInUse {
+        /// The URL of the connection.
+        url: String,
+        /// The id of the channel.
+        id: u32,
+    },
+    /// The channel, or the connection it was opened on, was closed.
+    ChannelClosed {
+        /// The URL of the connection.
+        url: String,
+        /// The id of the channel.
+        id: u32,
+    },
+    /// A message couldn't be encoded or decoded, see [`WebSocket::typed`].
+    ///
+    /// [`WebSocket::typed`]: futures::WebSocket::typed
+    CodecError {
+        /// The URL of the connection.
+        url: String,
+        /// The error of the codec.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl WebSocketError {
+    /// The URL of the connection the error is about.
+    pub fn url(&self) -> &str {
+        match self {
+            Self::ConnectionError(event) => &event.url,
+            Self::ConnectionClose(event) => &event.url,
+            Self::MessageSendError { url, .. }
+            | Self::ConnectTimeout { url }
+            | Self::BufferFull { url }
+            | Self::CloseTimeout { url }
+            | Self::OpenError { url, .. }
+            | Self::StaleConnection { url }
+            | Self::NoProtocolSelected { url }
+            | Self::ChannelInUse { url, .. }
+            | Self::ChannelClosed { url, .. }
+            | Self::CodecError { url, .. } => url,
+        }
+    }
+
+    /// A copy of the error, for each of the handles, channels or subscriptions of a connection
+    /// it ends. The errors of the browser and of codecs are copied as their name and message.
+    pub(crate) fn duplicate(&self) -> Self {
+        let url = self.url().to_string();
+        match self {
+            Self::ConnectionError(event) => Self::ConnectionError(event.clone()),
+            Self::ConnectionClose(event) => Self::ConnectionClose(event.clone()),
+            Self::MessageSendError { error, .. } => Self::MessageSendError {
+                url,
+                error: copy_js_error(error),
+            },
+            Self::ConnectTimeout { .. } => Self::ConnectTimeout { url },
+            Self::BufferFull { .. } => Self::BufferFull { url },
+            Self::CloseTimeout { .. } => Self::CloseTimeout { url },
+            Self::OpenError { error, .. } => Self::OpenError {
+                url,
+                error: copy_js_error(error),
+            },
+            Self::StaleConnection { .. } => Self::StaleConnection { url },
+            Self::NoProtocolSelected { .. } => Self::NoProtocolSelected { url },
+            Self::ChannelInUse { id, .. } => Self::ChannelInUse { url, id: *id },
+            Self::ChannelClosed { id, .. } => Self::ChannelClosed { url, id: *id },
+            Self::CodecError { source, .. } => Self::CodecError {
+                url,
+                source: source.to_string().into(),
+            },
+        }
+    }
+}
+
+fn copy_js_error(error: &JsError) -> JsError {
+    let copy = js_sys::Error::new(&error.message);
+    copy.set_name(&error.name);
+    JsError::from(copy)
 }
 
 impl fmt::Display for WebSocketError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            WebSocketError::ConnectionError => write!(f, "WebSocket connection failed"),
+            WebSocketError::ConnectionError(e) => {
+                write!(f, "WebSocket connection to {} failed: {e}", e.url)
+            }
             WebSocketError::ConnectionClose(e) => write!(
                 f,
-                "WebSocket Closed: code: {}, reason: {}",
-                e.code, e.reason
+                "WebSocket to {} Closed: code: {}, reason: {}",
+                e.url, e.code, e.reason
             ),
-            WebSocketError::MessageSendError(e) => write!(f, "{e}"),
+            WebSocketError::MessageSendError { url, error } => {
+                write!(f, "WebSocket to {url} failed to send a message: {error}")
+            }
+            WebSocketError::ConnectTimeout { url } => {
+                write!(f, "WebSocket connection to {url} timed out")
+            }
+            WebSocketError::BufferFull { url } => {
+                write!(f, "WebSocket send buffer of {url} is full")
+            }
+            WebSocketError::CloseTimeout { url } => {
+                write!(f, "WebSocket to {url} close timed out")
+            }
+            WebSocketError::OpenError { url, error } => {
+                write!(f, "WebSocket to {url} couldn't be opened: {error}")
+            }
+            WebSocketError::StaleConnection { url } => {
+                write!(
+                    f,
+                    "WebSocket connection to {url} is stale: the heartbeat got no reply"
+                )
+            }
+            WebSocketError::NoProtocolSelected { url } => {
+                write!(
+                    f,
+                    "WebSocket server at {url} selected none of the sub-protocols"
+                )
+            }
+            WebSocketError::ChannelInUse { url, id } => {
+                write!(f, "WebSocket channel {id} of {url} is already open")
+            }
+            WebSocketError::ChannelClosed { url, id } => {
+                write!(f, "WebSocket channel {id} of {url} is closed")
+            }
+            WebSocketError::CodecError { url, source } => {
+                write!(f, "WebSocket message codec error on {url}: {source}")
+            }
         }
     }
 }
 
-impl std::error::Error for WebSocketError {}
+impl std::error::Error for WebSocketError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WebSocketError::MessageSendError { error, .. }
+            | WebSocketError::OpenError { error, .. } => Some(error),
+            WebSocketError::CodecError { source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
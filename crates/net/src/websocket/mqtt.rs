@@ -0,0 +1,994 @@
+//! An [MQTT](https://mqtt.org) client over WebSocket, speaking MQTT 3.1.1 or 5, e.g. for IoT
+//! dashboards.
+//!
+//! A [`MqttClient`] connects to a broker, then [publishes](MqttClient::publish) messages to
+//! topics and [subscribes](MqttClient::subscribe) to topic filters, each subscription being a
+//! stream of the messages published to its matching topics. Payloads can be decoded with a
+//! [`Codec`], both the ones received with [typed subscriptions](Subscription::typed) and the
+//! ones published with [`publish_as`](MqttClient::publish_as).
+//!
+//! Messages are published and subscribed to with QoS 0 or 1. The client sends a `PINGREQ`
+//! every [keep alive](MqttOptions::keep_alive) interval, and fails the connection with
+//! [`MqttError::KeepAliveTimeout`] when the broker stops answering. The features of MQTT 5
+//! beyond those of 3.1.1, like properties, aren't supported.
+//!
+//! # Example
+//!
+//! ```
+//! use futures::StreamExt;
+//! use gloo_net::websocket::mqtt::{MqttClient, MqttOptions, QoS};
+//! use gloo_net::websocket::Json;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Reading {
+//!     celsius: f64,
+//! }
+//!
+//! # async fn no_run() -> Result<(), gloo_net::websocket::mqtt::MqttError> {
+//! let options = MqttOptions::new("dashboard-1").credentials("user", "secret");
+//! let client = MqttClient::connect("wss://broker.example.com/mqtt", options).await?;
+//!
+//! let mut readings = client
+//!     .subscribe("sensors/+/temperature", QoS::AtLeastOnce)
+//!     .await?
+//!     .typed::<Reading, Json>();
+//! client
+//!     .publish("dashboards/1/online", b"1".to_vec(), QoS::AtLeastOnce, true)
+//!     .await?;
+//!
+//! while let Some(reading) = readings.next().await {
+//!     let (reading, publish) = reading?;
+//!     // plot reading.celsius for publish.topic
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::{ready, Stream};
+use futures_sink::Sink;
+use gloo_timers::future::sleep;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error as ThisError;
+
+use crate::websocket::events::ErrorEvent;
+use crate::websocket::futures::{WebSocket, WebSocketReceiver, WebSocketSender};
+use crate::websocket::{Codec, CodecError, Message, WebSocketError};
+
+/// The error of a MQTT client, or of a subscription.
+#[derive(Debug, ThisError)]
+#[non_exhaustive]
+pub enum MqttError {
+    /// The WebSocket couldn't be opened.
+    #[error("{0}")]
+    Open(gloo_utils::errors::JsError),
+    /// The broker refused the connection, with this return code, like `5` for "not
+    /// authorized" in MQTT 3.1.1, or `0x87` in MQTT 5.
+    #[error("the broker refused the connection with code {0}")]
+    ConnectionRefused(u8),
+    /// The broker refused the subscription.
+    #[error("the broker refused the subscription")]
+    SubscriptionRefused,
+    /// The broker didn't answer a `PINGREQ` in time.
+    #[error("the broker stopped answering")]
+    KeepAliveTimeout,
+    /// The broker sent a malformed packet.
+    #[error("malformed MQTT packet")]
+    MalformedPacket,
+    /// The connection closed.
+    #[error("the connection is closed")]
+    Closed,
+    /// The connection failed.
+    #[error("{0}")]
+    WebSocket(WebSocketError),
+    /// A payload couldn't be encoded or decoded.
+    #[error("{0}")]
+    Codec(CodecError),
+}
+
+/// The version of MQTT spoken with the broker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MqttVersion {
+    /// MQTT 3.1.1
+    V311,
+    /// MQTT 5
+    V5,
+}
+
+/// The quality of service of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QoS {
+    /// The message is sent once, and may be lost.
+    AtMostOnce = 0,
+    /// The message is sent until acknowledged, and may arrive twice.
+    AtLeastOnce = 1,
+}
+
+/// How to connect to the broker, see [`MqttClient::connect`].
+#[derive(Debug, Clone)]
+pub struct MqttOptions {
+    client_id: String,
+    credentials: Option<(String, Option<String>)>,
+    keep_alive: Duration,
+    clean_session: bool,
+    version: MqttVersion,
+}
+
+impl MqttOptions {
+    /// Connects as `client_id` with MQTT 3.1.1, in a clean session, with a 60s keep alive.
+    pub fn new(client_id: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            credentials: None,
+            keep_alive: Duration::from_secs(60),
+            clean_session: true,
+            version: MqttVersion::V311,
+        }
+    }
+
+    /// Logs in with `username` and `password`.
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), Some(password.to_string())));
+        self
+    }
+
+    /// Sets the longest time without a packet from the client, after which the broker
+    /// considers it gone. Zero turns the keep alive off.
+    pub fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Whether the broker drops the session, like the subscriptions, when the client
+    /// disconnects, which is the default.
+    pub fn clean_session(mut self, clean_session: bool) -> Self {
+        self.clean_session = clean_session;
+        self
+    }
+
+    /// Sets the version of MQTT to speak.
+    pub fn version(mut self, version: MqttVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    fn packet(&self) -> Vec<u8> {
+        let v5 = self.version == MqttVersion::V5;
+        let mut body = Vec::new();
+        put_str(&mut body, "MQTT");
+        body.push(if v5 { 5 } else { 4 });
+        let mut flags = 0;
+        if let Some((_, password)) = &self.credentials {
+            flags |= 0x80;
+            if password.is_some() {
+                flags |= 0x40;
+            }
+        }
+        if self.clean_session {
+            flags |= 0x02;
+        }
+        body.push(flags);
+        let keep_alive = self.keep_alive.as_secs().min(u16::MAX.into()) as u16;
+        body.extend_from_slice(&keep_alive.to_be_bytes());
+        if v5 {
+            body.push(0);
+        }
+        put_str(&mut body, &self.client_id);
+        if let Some((username, password)) = &self.credentials {
+            put_str(&mut body, username);
+            if let Some(password) = password {
+                put_str(&mut body, password);
+            }
+        }
+        packet(0x10, body)
+    }
+}
+
+/// A MQTT client, see the [module documentation](self).
+///
+/// Clones share the same connection, which is closed once every clone is dropped.
+#[derive(Clone)]
+pub struct MqttClient {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    sender: RefCell<Option<WebSocketSender>>,
+    routes: Rc<Routes>,
+    v5: bool,
+    next_id: Cell<u16>,
+}
+
+/// Where the packets of the broker go, shared with the task reading the connection.
+#[derive(Default)]
+struct Routes {
+    subscriptions: RefCell<HashMap<u16, Route>>,
+    /// The packets waiting for an acknowledgement, by packet id.
+    acks: RefCell<HashMap<u16, oneshot::Sender<Vec<u8>>>>,
+    closed: Cell<bool>,
+}
+
+struct Route {
+    filter: String,
+    sender: mpsc::UnboundedSender<Result<Publish, MqttError>>,
+}
+
+impl MqttClient {
+    /// Opens a WebSocket to the broker at `url`, with the `mqtt` sub-protocol, and connects.
+    ///
+    /// A task then reads the connection until it closes. This must be called in a
+    /// wasm-bindgen-futures context.
+    pub async fn connect(url: &str, options: MqttOptions) -> Result<Self, MqttError> {
+        let ws = WebSocket::open_with_protocol(url, "mqtt").map_err(MqttError::Open)?;
+        let v5 = options.version == MqttVersion::V5;
+        let (mut sender, receiver) = ws.into_halves();
+        std::future::poll_fn(|cx| Pin::new(&mut sender).poll_ready(cx))
+            .await
+            .map_err(MqttError::WebSocket)?;
+        Pin::new(&mut sender)
+            .start_send(Message::Bytes(options.packet()))
+            .map_err(MqttError::WebSocket)?;
+
+        let mut reader = Reader {
+            receiver,
+            buffer: Vec::new(),
+            v5,
+        };
+        match std::future::poll_fn(|cx| reader.poll_next(cx)).await {
+            Some(Ok(Packet::ConnAck { code: 0 })) => {}
+            Some(Ok(Packet::ConnAck { code })) => return Err(MqttError::ConnectionRefused(code)),
+            Some(Ok(_)) => return Err(MqttError::MalformedPacket),
+            Some(Err(error)) => return Err(error),
+            None => return Err(MqttError::Closed),
+        }
+
+        let inner = Rc::new(Inner {
+            sender: RefCell::new(Some(sender)),
+            routes: Rc::new(Routes::default()),
+            v5,
+            next_id: Cell::new(1),
+        });
+        let keep_alive = Some(options.keep_alive).filter(|keep_alive| !keep_alive.is_zero());
+        wasm_bindgen_futures::spawn_local(read(
+            reader,
+            Rc::clone(&inner.routes),
+            Rc::downgrade(&inner),
+            keep_alive.map(|keep_alive| keep_alive.mul_f32(1.5)),
+        ));
+        if let Some(keep_alive) = keep_alive {
+            wasm_bindgen_futures::spawn_local(ping(Rc::downgrade(&inner), keep_alive));
+        }
+        Ok(Self { inner })
+    }
+
+    /// Publishes `payload` to `topic`, waiting for the broker to acknowledge it with QoS 1.
+    ///
+    /// A retained message is kept by the broker, and sent to the clients subscribing later.
+    pub async fn publish(
+        &self,
+        topic: &str,
+        payload: impl Into<Vec<u8>>,
+        qos: QoS,
+        retain: bool,
+    ) -> Result<(), MqttError> {
+        let payload = payload.into();
+        match qos {
+            QoS::AtMostOnce => {
+                let packet = publish_packet(topic, None, &payload, retain, self.inner.v5);
+                self.inner.send(packet)
+            }
+            QoS::AtLeastOnce => {
+                self.inner
+                    .send_acked(|id| {
+                        publish_packet(topic, Some(id), &payload, retain, self.inner.v5)
+                    })
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Publishes `value` to `topic`, encoded with `C`, see [`publish`](Self::publish).
+    pub async fn publish_as<T: Serialize, C: Codec>(
+        &self,
+        topic: &str,
+        value: &T,
+        qos: QoS,
+        retain: bool,
+    ) -> Result<(), MqttError> {
+        let payload = match C::encode(value).map_err(MqttError::Codec)? {
+            Message::Text(text) => text.into_bytes(),
+            Message::Bytes(bytes) => bytes,
+        };
+        self.publish(topic, payload, qos, retain).await
+    }
+
+    /// Subscribes to the topics matching `filter`, like `sensors/+/temperature` or
+    /// `sensors/#`, once the broker acknowledged it.
+    ///
+    /// The messages are received with the lower of `qos` and the QoS they were published with.
+    /// Dropping the subscription unsubscribes.
+    pub async fn subscribe(&self, filter: &str, qos: QoS) -> Result<Subscription, MqttError> {
+        let (sender, receiver) = mpsc::unbounded();
+        let v5 = self.inner.v5;
+        let mut subscribed = None;
+        let codes = self
+            .inner
+            .send_acked(|id| {
+                // routed before the acknowledgement, which retained messages may come before
+                self.inner.routes.subscriptions.borrow_mut().insert(
+                    id,
+                    Route {
+                        filter: filter.to_string(),
+                        sender,
+                    },
+                );
+                subscribed = Some(id);
+                subscribe_packet(id, filter, qos, v5)
+            })
+            .await;
+        let id = subscribed.ok_or(MqttError::Closed)?;
+        let subscription = Subscription {
+            id,
+            filter: filter.to_string(),
+            receiver,
+            client: Rc::downgrade(&self.inner),
+        };
+        // SUBACK return codes of 0x80 and above are failures
+        match codes?.first() {
+            Some(code) if *code < 0x80 => Ok(subscription),
+            _ => Err(MqttError::SubscriptionRefused),
+        }
+    }
+
+    /// Disconnects gracefully, telling the broker not to publish the will message, then
+    /// closes the connection.
+    pub fn disconnect(self) {
+        let _ = self.inner.send(disconnect_packet(self.inner.v5));
+        self.inner.close();
+    }
+}
+
+impl Inner {
+    fn send(&self, packet: Vec<u8>) -> Result<(), MqttError> {
+        if self.routes.closed.get() {
+            return Err(MqttError::Closed);
+        }
+        // the connection is open, which is all sending needs
+        match &mut *self.sender.borrow_mut() {
+            Some(sender) => Pin::new(sender)
+                .start_send(Message::Bytes(packet))
+                .map_err(MqttError::WebSocket),
+            None => Err(MqttError::Closed),
+        }
+    }
+
+    /// Sends the packet `build` builds with a fresh packet id, and waits for the body of its
+    /// acknowledgement, after the packet id.
+    async fn send_acked(&self, build: impl FnOnce(u16) -> Vec<u8>) -> Result<Vec<u8>, MqttError> {
+        let id = self.next_id();
+        let (ack, acked) = oneshot::channel();
+        self.routes.acks.borrow_mut().insert(id, ack);
+        if let Err(error) = self.send(build(id)) {
+            self.routes.acks.borrow_mut().remove(&id);
+            self.routes.subscriptions.borrow_mut().remove(&id);
+            return Err(error);
+        }
+        acked.await.map_err(|_| MqttError::Closed)
+    }
+
+    /// The next packet id, skipping zero and the ids in use.
+    fn next_id(&self) -> u16 {
+        loop {
+            let id = self.next_id.get();
+            self.next_id.set(id.checked_add(1).unwrap_or(1));
+            let in_use = self.routes.acks.borrow().contains_key(&id)
+                || self.routes.subscriptions.borrow().contains_key(&id);
+            if !in_use {
+                return id;
+            }
+        }
+    }
+
+    fn close(&self) {
+        if let Some(sender) = self.sender.borrow_mut().take() {
+            let _ = sender.close(Some(1000), None);
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl fmt::Debug for MqttClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let routes = &self.inner.routes;
+        f.debug_struct("MqttClient")
+            .field("subscriptions", &routes.subscriptions.borrow().len())
+            .field("closed", &routes.closed.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message published to a topic, received on a [`Subscription`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Publish {
+    /// The topic the message was published to.
+    pub topic: String,
+    /// The payload.
+    pub payload: Vec<u8>,
+    /// The quality of service it was received with.
+    pub qos: QoS,
+    /// Whether the message was retained by the broker, rather than just published.
+    pub retain: bool,
+}
+
+impl Publish {
+    /// Decodes the payload with `C`.
+    pub fn payload_as<T: DeserializeOwned, C: Codec>(&self) -> Result<T, MqttError> {
+        C::decode(Message::Bytes(self.payload.clone())).map_err(MqttError::Codec)
+    }
+}
+
+/// The messages published to the topics matching a filter, see [`MqttClient::subscribe`].
+///
+/// The stream reports the failure of the connection as an error, and ends once it closes.
+#[must_use = "streams do nothing unless polled"]
+pub struct Subscription {
+    id: u16,
+    filter: String,
+    receiver: mpsc::UnboundedReceiver<Result<Publish, MqttError>>,
+    client: Weak<Inner>,
+}
+
+impl Subscription {
+    /// The topic filter subscribed to.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// Decodes the payloads with `C`, yielding each value along with its message.
+    pub fn typed<T: DeserializeOwned, C: Codec>(self) -> TypedSubscription<T, C> {
+        TypedSubscription {
+            inner: self,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<Publish, MqttError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let Some(client) = self.client.upgrade() else {
+            return;
+        };
+        client.routes.subscriptions.borrow_mut().remove(&self.id);
+        // other subscriptions to the same filter share it on the broker
+        let shared = client
+            .routes
+            .subscriptions
+            .borrow()
+            .values()
+            .any(|route| route.filter == self.filter);
+        if !shared {
+            let id = client.next_id();
+            let _ = client.send(unsubscribe_packet(id, &self.filter, client.v5));
+        }
+    }
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("filter", &self.filter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The messages of a subscription, decoded with `C`, see [`Subscription::typed`].
+#[must_use = "streams do nothing unless polled"]
+pub struct TypedSubscription<T, C> {
+    inner: Subscription,
+    _marker: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T, C> TypedSubscription<T, C> {
+    /// The subscription the messages are decoded from.
+    pub fn into_inner(self) -> Subscription {
+        self.inner
+    }
+}
+
+impl<T: DeserializeOwned, C: Codec> Stream for TypedSubscription<T, C> {
+    type Item = Result<(T, Publish), MqttError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = ready!(Pin::new(&mut self.inner).poll_next(cx));
+        Poll::Ready(item.map(|publish| {
+            let publish = publish?;
+            Ok((publish.payload_as::<T, C>()?, publish))
+        }))
+    }
+}
+
+impl<T, C> fmt::Debug for TypedSubscription<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedSubscription")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// Reads the packets of the broker, which WebSocket messages may split or bundle.
+struct Reader {
+    receiver: WebSocketReceiver,
+    buffer: Vec<u8>,
+    v5: bool,
+}
+
+impl Reader {
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Packet, MqttError>>> {
+        loop {
+            match split_packet(&self.buffer) {
+                Some(Ok((header, length, consumed))) => {
+                    let body = &self.buffer[consumed - length..consumed];
+                    let packet = Packet::parse(header, body, self.v5);
+                    self.buffer.drain(..consumed);
+                    return Poll::Ready(Some(packet.ok_or(MqttError::MalformedPacket)));
+                }
+                Some(Err(())) => return Poll::Ready(Some(Err(MqttError::MalformedPacket))),
+                None => {}
+            }
+            match ready!(Pin::new(&mut self.receiver).poll_next(cx)) {
+                Some(Ok(Message::Bytes(bytes))) => self.buffer.extend_from_slice(&bytes),
+                Some(Ok(Message::Text(_))) => {
+                    return Poll::Ready(Some(Err(MqttError::MalformedPacket)))
+                }
+                Some(Err(WebSocketError::ConnectionClose(_))) | None => return Poll::Ready(None),
+                Some(Err(error)) => return Poll::Ready(Some(Err(MqttError::WebSocket(error)))),
+            }
+        }
+    }
+}
+
+/// What happened while reading the connection.
+enum Step {
+    Incoming(Option<Result<Packet, MqttError>>),
+    KeepAliveMissed,
+}
+
+/// Reads the connection until it closes, passing on the packets of the broker.
+async fn read(
+    mut reader: Reader,
+    routes: Rc<Routes>,
+    client: Weak<Inner>,
+    timeout: Option<Duration>,
+) {
+    let mut deadline = timeout.map(sleep);
+    let url = reader.receiver.url();
+    let error = loop {
+        let step = std::future::poll_fn(|cx| {
+            if let Poll::Ready(item) = reader.poll_next(cx) {
+                return Poll::Ready(Step::Incoming(item));
+            }
+            match &mut deadline {
+                Some(deadline) => Pin::new(deadline).poll(cx).map(|()| Step::KeepAliveMissed),
+                None => Poll::Pending,
+            }
+        })
+        .await;
+        let packet = match step {
+            Step::Incoming(Some(Ok(packet))) => packet,
+            Step::Incoming(Some(Err(error))) => break Some(error),
+            Step::Incoming(None) => break None,
+            Step::KeepAliveMissed => break Some(MqttError::KeepAliveTimeout),
+        };
+        deadline = timeout.map(sleep);
+        match packet {
+            Packet::Publish {
+                topic,
+                id,
+                payload,
+                qos,
+                retain,
+            } => {
+                if let (Some(id), Some(client)) = (id, client.upgrade()) {
+                    let _ = client.send(puback_packet(id));
+                }
+                let publish = Publish {
+                    topic,
+                    payload,
+                    qos,
+                    retain,
+                };
+                for route in routes.subscriptions.borrow().values() {
+                    if topic_matches(&route.filter, &publish.topic) {
+                        let _ = route.sender.unbounded_send(Ok(publish.clone()));
+                    }
+                }
+            }
+            Packet::Ack { id, body } => {
+                if let Some(ack) = routes.acks.borrow_mut().remove(&id) {
+                    let _ = ack.send(body);
+                }
+            }
+            Packet::Disconnect => break None,
+            Packet::ConnAck { .. } | Packet::PingResp | Packet::Other => {}
+        }
+    };
+    if let Some(client) = client.upgrade() {
+        client.close();
+    }
+    routes.closed.set(true);
+    for (_, route) in routes.subscriptions.borrow_mut().drain() {
+        let error = match &error {
+            Some(MqttError::KeepAliveTimeout) => MqttError::KeepAliveTimeout,
+            Some(MqttError::MalformedPacket) => MqttError::MalformedPacket,
+            Some(MqttError::WebSocket(error)) => MqttError::WebSocket(error.duplicate()),
+            Some(_) => MqttError::WebSocket(WebSocketError::ConnectionError(ErrorEvent::unknown(
+                url.clone(),
+            ))),
+            None => continue,
+        };
+        let _ = route.sender.unbounded_send(Err(error));
+    }
+    // dropping the senders fails the packets still waiting with `MqttError::Closed`
+    routes.acks.borrow_mut().clear();
+}
+
+/// Sends a `PINGREQ` every `interval`, until the client is dropped.
+async fn ping(client: Weak<Inner>, interval: Duration) {
+    loop {
+        sleep(interval).await;
+        let Some(client) = client.upgrade() else {
+            break;
+        };
+        if client.send(vec![0xc0, 0]).is_err() {
+            break;
+        }
+    }
+}
+
+/// Whether `topic` matches `filter`, whose `+` levels match any one level, and whose `#` last
+/// level matches any number of levels.
+fn topic_matches(filter: &str, topic: &str) -> bool {
+    // wildcards don't match the topics starting with `$`, like `$SYS`
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut topic = topic.split('/');
+    for level in filter.split('/') {
+        match (level, topic.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (level, Some(name)) if level == name => {}
+            _ => return false,
+        }
+    }
+    topic.next().is_none()
+}
+
+/// A packet of the broker.
+#[derive(Debug, PartialEq)]
+enum Packet {
+    ConnAck {
+        code: u8,
+    },
+    Publish {
+        topic: String,
+        id: Option<u16>,
+        payload: Vec<u8>,
+        qos: QoS,
+        retain: bool,
+    },
+    /// A `PUBACK`, `SUBACK` or `UNSUBACK`, with what follows the packet id.
+    Ack {
+        id: u16,
+        body: Vec<u8>,
+    },
+    PingResp,
+    Disconnect,
+    Other,
+}
+
+impl Packet {
+    fn parse(header: u8, body: &[u8], v5: bool) -> Option<Self> {
+        let mut body = Cursor(body);
+        Some(match header >> 4 {
+            2 => {
+                body.u8()?;
+                Packet::ConnAck { code: body.u8()? }
+            }
+            3 => {
+                let qos = match (header >> 1) & 3 {
+                    0 => QoS::AtMostOnce,
+                    1 => QoS::AtLeastOnce,
+                    // QoS 2 is never subscribed to
+                    _ => return None,
+                };
+                let topic = body.str()?;
+                let id = match qos {
+                    QoS::AtMostOnce => None,
+                    QoS::AtLeastOnce => Some(body.u16()?),
+                };
+                if v5 {
+                    body.properties()?;
+                }
+                Packet::Publish {
+                    topic,
+                    id,
+                    payload: body.0.to_vec(),
+                    qos,
+                    retain: header & 1 == 1,
+                }
+            }
+            4 | 11 => Packet::Ack {
+                id: body.u16()?,
+                body: Vec::new(),
+            },
+            9 => {
+                let id = body.u16()?;
+                if v5 {
+                    body.properties()?;
+                }
+                Packet::Ack {
+                    id,
+                    body: body.0.to_vec(),
+                }
+            }
+            13 => Packet::PingResp,
+            14 => Packet::Disconnect,
+            _ => Packet::Other,
+        })
+    }
+}
+
+/// Reads the fields of a packet.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        if self.0.len() < length {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let length = self.u16()?.into();
+        String::from_utf8(self.bytes(length)?.to_vec()).ok()
+    }
+
+    /// Skips the properties of a MQTT 5 packet.
+    fn properties(&mut self) -> Option<()> {
+        let (length, size) = varint(self.0)?.ok()?;
+        self.bytes(size + length)?;
+        Some(())
+    }
+}
+
+/// Reads the variable byte integer at the start of `data`, returning it and its size, `None`
+/// when incomplete, or an error when longer than 4 bytes.
+fn varint(data: &[u8]) -> Option<Result<(usize, usize), ()>> {
+    let mut value = 0;
+    for (i, byte) in data.iter().enumerate() {
+        if i == 4 {
+            return Some(Err(()));
+        }
+        value |= usize::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(Ok((value, i + 1)));
+        }
+    }
+    if data.len() >= 4 {
+        Some(Err(()))
+    } else {
+        None
+    }
+}
+
+/// Finds the packet at the start of `buffer`: its fixed header, the length of its body, and
+/// its total length. This is `None` until the whole packet is buffered.
+fn split_packet(buffer: &[u8]) -> Option<Result<(u8, usize, usize), ()>> {
+    let header = *buffer.first()?;
+    let (length, size) = match varint(&buffer[1..])? {
+        Ok(length) => length,
+        Err(()) => return Some(Err(())),
+    };
+    let consumed = 1 + size + length;
+    if buffer.len() < consumed {
+        return None;
+    }
+    Some(Ok((header, length, consumed)))
+}
+
+fn put_str(buffer: &mut Vec<u8>, text: &str) {
+    let length = text.len().min(u16::MAX.into());
+    buffer.extend_from_slice(&(length as u16).to_be_bytes());
+    buffer.extend_from_slice(&text.as_bytes()[..length]);
+}
+
+/// Builds a packet out of its fixed header and body.
+fn packet(header: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+    loop {
+        let byte = (length % 128) as u8;
+        length /= 128;
+        if length == 0 {
+            packet.push(byte);
+            break;
+        }
+        packet.push(byte | 0x80);
+    }
+    packet.extend(body);
+    packet
+}
+
+fn publish_packet(topic: &str, id: Option<u16>, payload: &[u8], retain: bool, v5: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_str(&mut body, topic);
+    let mut header = 0x30 | u8::from(retain);
+    if let Some(id) = id {
+        header |= 0x02;
+        body.extend_from_slice(&id.to_be_bytes());
+    }
+    if v5 {
+        body.push(0);
+    }
+    body.extend_from_slice(payload);
+    packet(header, body)
+}
+
+fn puback_packet(id: u16) -> Vec<u8> {
+    // a MQTT 5 PUBACK without reason code means success
+    packet(0x40, id.to_be_bytes().to_vec())
+}
+
+fn subscribe_packet(id: u16, filter: &str, qos: QoS, v5: bool) -> Vec<u8> {
+    let mut body = id.to_be_bytes().to_vec();
+    if v5 {
+        body.push(0);
+    }
+    put_str(&mut body, filter);
+    body.push(qos as u8);
+    packet(0x82, body)
+}
+
+fn unsubscribe_packet(id: u16, filter: &str, v5: bool) -> Vec<u8> {
+    let mut body = id.to_be_bytes().to_vec();
+    if v5 {
+        body.push(0);
+    }
+    put_str(&mut body, filter);
+    packet(0xa2, body)
+}
+
+fn disconnect_packet(v5: bool) -> Vec<u8> {
+    if v5 {
+        // normal disconnection, without properties
+        packet(0xe0, vec![0, 0])
+    } else {
+        packet(0xe0, Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_packets() {
+        let connect = MqttOptions::new("c1")
+            .credentials("u", "p")
+            .keep_alive(Duration::from_secs(30))
+            .packet();
+        assert_eq!(
+            connect,
+            [
+                &[0x10, 20, 0, 4][..],
+                b"MQTT",
+                &[4, 0xc2, 0, 30, 0, 2],
+                b"c1",
+                &[0, 1, b'u', 0, 1, b'p'],
+            ]
+            .concat()
+        );
+        assert_eq!(
+            publish_packet("a/b", Some(7), b"hi", true, false),
+            [&[0x33, 9, 0, 3][..], b"a/b", &[0, 7], b"hi"].concat()
+        );
+        assert_eq!(
+            subscribe_packet(1, "a/#", QoS::AtLeastOnce, true),
+            [&[0x82, 9, 0, 1, 0, 0, 3][..], b"a/#", &[1]].concat()
+        );
+        assert_eq!(packet(0x30, vec![0; 200])[..3], [0x30, 0xc8, 0x01]);
+    }
+
+    #[test]
+    fn parses_packets() {
+        let buffer = [
+            &[0x20, 2, 0, 0, 0x32, 8, 0, 3][..],
+            b"a/b",
+            &[0, 9],
+            b"x",
+            &[0x90],
+        ]
+        .concat();
+        let (header, length, consumed) = split_packet(&buffer).unwrap().unwrap();
+        assert_eq!(
+            Packet::parse(header, &buffer[consumed - length..consumed], false),
+            Some(Packet::ConnAck { code: 0 })
+        );
+        let buffer = &buffer[consumed..];
+        let (header, length, consumed) = split_packet(buffer).unwrap().unwrap();
+        assert_eq!(
+            Packet::parse(header, &buffer[consumed - length..consumed], false),
+            Some(Packet::Publish {
+                topic: "a/b".to_string(),
+                id: Some(9),
+                payload: b"x".to_vec(),
+                qos: QoS::AtLeastOnce,
+                retain: false,
+            })
+        );
+        // an incomplete SUBACK waits for more data
+        assert_eq!(split_packet(&buffer[consumed..]), None);
+
+        let suback = [0, 3, 0, 1];
+        assert_eq!(
+            Packet::parse(0x90, &suback, true),
+            Some(Packet::Ack {
+                id: 3,
+                body: vec![1]
+            })
+        );
+        assert_eq!(split_packet(&[0x30, 0xff, 0xff, 0xff, 0xff]), Some(Err(())));
+    }
+
+    #[test]
+    fn matches_topics() {
+        assert!(topic_matches("a/b", "a/b"));
+        assert!(topic_matches("a/+/c", "a/b/c"));
+        assert!(topic_matches("a/#", "a"));
+        assert!(topic_matches("a/#", "a/b/c"));
+        assert!(topic_matches("#", "a/b"));
+        assert!(!topic_matches("a/+", "a/b/c"));
+        assert!(!topic_matches("a/b", "a"));
+        assert!(!topic_matches("#", "$SYS/uptime"));
+        assert!(topic_matches("$SYS/#", "$SYS/uptime"));
+    }
+}
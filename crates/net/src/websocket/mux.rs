@@ -0,0 +1,445 @@
+//! Independent channels sharing one WebSocket.
+//!
+//! Each [channel](Multiplexer::channel) is a typed [`Sink`] and [`Stream`] pair, like the one of
+//! [`WebSocket::typed`], whose messages are framed with the id of the channel. Channels are
+//! opened and closed on their own, while the connection stays open until the multiplexer and
+//! every channel are dropped.
+//!
+//! # Framing
+//!
+//! Every frame is a binary message: one byte telling its kind, the id of the channel as a
+//! big-endian `u32`, then the payload.
+//!
+//! | Kind | Meaning                                  | Payload           |
+//! |------|------------------------------------------|-------------------|
+//! | `0`  | the channel was opened                   | none              |
+//! | `1`  | a text message was sent on the channel   | the UTF-8 text    |
+//! | `2`  | a binary message was sent on the channel | the bytes         |
+//! | `3`  | the channel was closed                   | none              |
+//!
+//! The server sends the messages of a channel once it was opened, and either side may close
+//! it. Messages which aren't frames, and frames for channels which aren't open, are dropped.
+//!
+//! # Example
+//!
+//! ```
+//! use futures::{SinkExt, StreamExt};
+//! use gloo_net::websocket::futures::WebSocket;
+//! use gloo_net::websocket::mux::Multiplexer;
+//! use gloo_net::websocket::Json;
+//!
+//! # async fn no_run() -> Result<(), gloo_net::websocket::WebSocketError> {
+//! let mux = Multiplexer::new(WebSocket::open("wss://example.com/mux").unwrap());
+//! let (mut chat, mut messages) = mux.channel::<String, String, Json>(1)?;
+//! let (_, mut prices) = mux.channel::<(), (String, f64), Json>(2)?;
+//!
+//! chat.send("hello".to_string()).await?;
+//! while let Some((symbol, price)) = prices.next().await.transpose()? {
+//!     // update the ticker
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`WebSocket::typed`]: crate::websocket::futures::WebSocket::typed
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+use futures_sink::Sink;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::websocket::futures::WebSocket;
+use crate::websocket::{Codec, Message, WebSocketError};
+
+/// Shares one WebSocket between channels, see the [module documentation](self).
+///
+/// Clones open channels over the same connection.
+#[derive(Clone)]
+pub struct Multiplexer {
+    outgoing: mpsc::UnboundedSender<Message>,
+    routes: Rc<Routes>,
+}
+
+/// The open channels, shared with the task driving the connection.
+#[derive(Default)]
+struct Routes {
+    /// The URL of the connection, for the errors of the channels.
+    url: String,
+    channels: RefCell<HashMap<u32, Route>>,
+    closed: Cell<bool>,
+}
+
+struct Route {
+    sender: mpsc::UnboundedSender<Result<Message, WebSocketError>>,
+    open: Rc<Cell<bool>>,
+}
+
+impl Routes {
+    /// Forgets the channel `id`, ending its stream.
+    fn close(&self, id: u32) {
+        if let Some(route) = self.channels.borrow_mut().remove(&id) {
+            route.open.set(false);
+        }
+    }
+}
+
+impl Multiplexer {
+    /// Shares `ws` between channels.
+    ///
+    /// A task drives the connection until it closes. This must be called in a
+    /// wasm-bindgen-futures context.
+    pub fn new(ws: WebSocket) -> Self {
+        let (outgoing, outgoing_receiver) = mpsc::unbounded();
+        let routes = Rc::new(Routes {
+            url: ws.url(),
+            ..Routes::default()
+        });
+        wasm_bindgen_futures::spawn_local(drive(ws, outgoing_receiver, Rc::clone(&routes)));
+        Self { outgoing, routes }
+    }
+
+    /// Opens the channel `id`, sending `Tx` and receiving `Rx` encoded with the codec `C`.
+    ///
+    /// The channel is closed by closing its sink, like with `SinkExt::close`, by the server,
+    /// which ends its stream, or once both halves are dropped. This errors with
+    /// [`WebSocketError::ChannelInUse`] if the channel is already open, and
+    /// [`WebSocketError::ChannelClosed`] if the connection closed.
+    #[allow(clippy::type_complexity)]
+    pub fn channel<Tx, Rx, C>(
+        &self,
+        id: u32,
+    ) -> Result<(ChannelSink<Tx, C>, ChannelStream<Rx, C>), WebSocketError>
+    where
+        Tx: Serialize,
+        Rx: DeserializeOwned,
+        C: Codec,
+    {
+        if self.routes.closed.get() {
+            return Err(WebSocketError::ChannelClosed {
+                url: self.routes.url.clone(),
+                id,
+            });
+        }
+        let mut channels = self.routes.channels.borrow_mut();
+        if channels.contains_key(&id) {
+            return Err(WebSocketError::ChannelInUse {
+                url: self.routes.url.clone(),
+                id,
+            });
+        }
+        self.outgoing
+            .unbounded_send(encode(FrameKind::Open, id, &[]))
+            .map_err(|_| WebSocketError::ChannelClosed {
+                url: self.routes.url.clone(),
+                id,
+            })?;
+        let (sender, receiver) = mpsc::unbounded();
+        let open = Rc::new(Cell::new(true));
+        channels.insert(
+            id,
+            Route {
+                sender,
+                open: Rc::clone(&open),
+            },
+        );
+        let handle = Rc::new(ChannelHandle {
+            id,
+            open,
+            outgoing: self.outgoing.clone(),
+            routes: Rc::clone(&self.routes),
+        });
+        Ok((
+            ChannelSink {
+                handle: Rc::clone(&handle),
+                _marker: PhantomData,
+            },
+            ChannelStream {
+                handle,
+                receiver,
+                _marker: PhantomData,
+            },
+        ))
+    }
+}
+
+impl fmt::Debug for Multiplexer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multiplexer")
+            .field("channels", &self.routes.channels.borrow().len())
+            .field("closed", &self.routes.closed.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A channel, shared by its halves.
+struct ChannelHandle {
+    id: u32,
+    /// Whether the channel is still open on both sides.
+    open: Rc<Cell<bool>>,
+    outgoing: mpsc::UnboundedSender<Message>,
+    routes: Rc<Routes>,
+}
+
+impl ChannelHandle {
+    fn send(&self, message: Message) -> Result<(), WebSocketError> {
+        if !self.open.get() {
+            return Err(self.closed());
+        }
+        let frame = match message {
+            Message::Text(text) => encode(FrameKind::Text, self.id, text.as_bytes()),
+            Message::Bytes(bytes) => encode(FrameKind::Binary, self.id, &bytes),
+        };
+        self.outgoing
+            .unbounded_send(frame)
+            .map_err(|_| self.closed())
+    }
+
+    fn closed(&self) -> WebSocketError {
+        WebSocketError::ChannelClosed {
+            url: self.routes.url.clone(),
+            id: self.id,
+        }
+    }
+
+    fn close(&self) {
+        if self.open.get() {
+            let _ = self
+                .outgoing
+                .unbounded_send(encode(FrameKind::Close, self.id, &[]));
+            self.routes.close(self.id);
+        }
+    }
+}
+
+impl Drop for ChannelHandle {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// The sending half of a channel, see [`Multiplexer::channel`].
+#[must_use = "sinks do nothing unless polled"]
+pub struct ChannelSink<Tx, C> {
+    handle: Rc<ChannelHandle>,
+    _marker: PhantomData<fn(Tx) -> C>,
+}
+
+impl<Tx, C> ChannelSink<Tx, C> {
+    /// The id of the channel.
+    pub fn id(&self) -> u32 {
+        self.handle.id
+    }
+}
+
+impl<Tx: Serialize, C: Codec> Sink<Tx> for ChannelSink<Tx, C> {
+    type Error = WebSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Tx) -> Result<(), Self::Error> {
+        let message = C::encode(&item).map_err(|source| WebSocketError::CodecError {
+            url: self.handle.routes.url.clone(),
+            source,
+        })?;
+        self.handle.send(message)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Closes the channel, on both sides.
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.handle.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<Tx, C> fmt::Debug for ChannelSink<Tx, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelSink")
+            .field("id", &self.handle.id)
+            .field("open", &self.handle.open.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a channel, see [`Multiplexer::channel`].
+///
+/// The stream ends when the channel or the connection is closed.
+#[must_use = "streams do nothing unless polled"]
+pub struct ChannelStream<Rx, C> {
+    handle: Rc<ChannelHandle>,
+    receiver: mpsc::UnboundedReceiver<Result<Message, WebSocketError>>,
+    _marker: PhantomData<fn() -> (Rx, C)>,
+}
+
+impl<Rx, C> ChannelStream<Rx, C> {
+    /// The id of the channel.
+    pub fn id(&self) -> u32 {
+        self.handle.id
+    }
+}
+
+impl<Rx: DeserializeOwned, C: Codec> Stream for ChannelStream<Rx, C> {
+    type Item = Result<Rx, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let url = &this.handle.routes.url;
+        Pin::new(&mut this.receiver).poll_next(cx).map(|message| {
+            message.map(|message| {
+                message.and_then(|message| {
+                    C::decode(message).map_err(|source| WebSocketError::CodecError {
+                        url: url.clone(),
+                        source,
+                    })
+                })
+            })
+        })
+    }
+}
+
+impl<Rx, C> fmt::Debug for ChannelStream<Rx, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelStream")
+            .field("id", &self.handle.id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Open = 0,
+    Text = 1,
+    Binary = 2,
+    Close = 3,
+}
+
+fn encode(kind: FrameKind, id: u32, payload: &[u8]) -> Message {
+    let mut frame = Vec::with_capacity(5 + payload.len());
+    frame.push(kind as u8);
+    frame.extend_from_slice(&id.to_be_bytes());
+    frame.extend_from_slice(payload);
+    Message::Bytes(frame)
+}
+
+/// The kind, channel and message of a frame, unless it isn't one.
+fn decode(frame: Vec<u8>) -> Option<(FrameKind, u32, Option<Message>)> {
+    let kind = match frame.first()? {
+        0 => FrameKind::Open,
+        1 => FrameKind::Text,
+        2 => FrameKind::Binary,
+        3 => FrameKind::Close,
+        _ => return None,
+    };
+    let id = u32::from_be_bytes(frame.get(1..5)?.try_into().ok()?);
+    let message = match kind {
+        FrameKind::Text => Some(Message::Text(String::from_utf8(frame[5..].to_vec()).ok()?)),
+        FrameKind::Binary => Some(Message::Bytes(frame[5..].to_vec())),
+        FrameKind::Open | FrameKind::Close => None,
+    };
+    Some((kind, id, message))
+}
+
+enum Event {
+    Incoming(Option<Result<Message, WebSocketError>>),
+    Outgoing(Option<Message>),
+}
+
+/// Sends the frames of the channels, and routes the frames received to them, until the
+/// connection closes, or the multiplexer and every channel are dropped.
+async fn drive(
+    mut ws: WebSocket,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    routes: Rc<Routes>,
+) {
+    loop {
+        let event = std::future::poll_fn(|cx| {
+            if let Poll::Ready(item) = Pin::new(&mut ws).poll_next(cx) {
+                return Poll::Ready(Event::Incoming(item));
+            }
+            // frames are held in the queue until the connection is open
+            if Pin::new(&mut ws).poll_ready(cx).is_ready() {
+                if let Poll::Ready(frame) = Pin::new(&mut outgoing).poll_next(cx) {
+                    return Poll::Ready(Event::Outgoing(frame));
+                }
+            }
+            Poll::Pending
+        })
+        .await;
+        match event {
+            Event::Incoming(Some(Ok(Message::Bytes(frame)))) => match decode(frame) {
+                Some((FrameKind::Close, id, _)) => routes.close(id),
+                Some((_, id, Some(message))) => {
+                    if let Some(route) = routes.channels.borrow().get(&id) {
+                        let _ = route.sender.unbounded_send(Ok(message));
+                    }
+                }
+                _ => {}
+            },
+            Event::Incoming(Some(Ok(Message::Text(_)))) => {}
+            Event::Incoming(Some(Err(WebSocketError::ConnectionClose(_))))
+            | Event::Incoming(None) => break,
+            Event::Incoming(Some(Err(error))) => {
+                for route in routes.channels.borrow().values() {
+                    let _ = route.sender.unbounded_send(Err(error.duplicate()));
+                }
+            }
+            Event::Outgoing(Some(frame)) => {
+                let _ = Pin::new(&mut ws).start_send(frame);
+            }
+            Event::Outgoing(None) => break,
+        }
+    }
+    routes.closed.set(true);
+    for (_, route) in routes.channels.borrow_mut().drain() {
+        route.open.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip() {
+        let frame = match encode(FrameKind::Text, 258, b"hi") {
+            Message::Bytes(frame) => frame,
+            Message::Text(_) => unreachable!(),
+        };
+        assert_eq!(frame, [1, 0, 0, 1, 2, b'h', b'i']);
+        assert_eq!(
+            decode(frame),
+            Some((FrameKind::Text, 258, Some(Message::Text("hi".to_string()))))
+        );
+        assert_eq!(
+            decode(vec![2, 0, 0, 0, 7, 0xff]),
+            Some((FrameKind::Binary, 7, Some(Message::Bytes(vec![0xff]))))
+        );
+        assert_eq!(
+            decode(vec![3, 0, 0, 0, 7]),
+            Some((FrameKind::Close, 7, None))
+        );
+        assert_eq!(
+            decode(vec![0, 0, 0, 0, 1]),
+            Some((FrameKind::Open, 1, None))
+        );
+        assert_eq!(decode(vec![1, 0, 0, 0, 1, 0xff]), None);
+        assert_eq!(decode(vec![4, 0, 0, 0, 1]), None);
+        assert_eq!(decode(vec![1, 0, 0]), None);
+        assert_eq!(decode(Vec::new()), None);
+    }
+}
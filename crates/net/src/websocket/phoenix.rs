@@ -0,0 +1,982 @@
+//! A [Phoenix Channels](https://hexdocs.pm/phoenix/channels.html) client, speaking the V2 JSON
+//! serializer of Phoenix 1.6 and later.
+//!
+//! A [`PhoenixSocket`] holds one connection to the socket of an endpoint, opened again whenever
+//! it drops, like a [`ReconnectingWebSocket`], and sends the `phoenix` heartbeat. Each
+//! [`Channel`] joins a topic over that connection, and joins it again on every new connection.
+//! Messages are [pushed](Channel::push) to the channel, optionally waiting for its
+//! [reply](Channel::request), and its broadcasts are received as [streams](Channel::on). The
+//! [presence](Channel::presence) of a topic is kept in sync with the diffs of the server.
+//!
+//! # Example
+//!
+//! ```
+//! use futures::StreamExt;
+//! use gloo_net::websocket::phoenix::PhoenixSocket;
+//! use serde_json::json;
+//! use std::time::Duration;
+//!
+//! # async fn no_run() -> Result<(), gloo_net::websocket::phoenix::PhoenixError> {
+//! let socket = PhoenixSocket::builder("https://example.com/socket")
+//!     .param("token", "secret")
+//!     .connect()
+//!     .unwrap();
+//! let lobby = socket.channel("room:lobby", &json!({}))?;
+//! lobby.join(Duration::from_secs(10)).await?;
+//!
+//! let mut messages = lobby.on("new_msg");
+//! let id: u64 = lobby
+//!     .request("new_msg", &json!({ "body": "hello" }), Duration::from_secs(10))
+//!     .await?;
+//!
+//! let mut presence = lobby.presence();
+//! while let Some(Ok(message)) = messages.next().await {
+//!     let body: String = message.payload_as()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ReconnectingWebSocket`]: crate::websocket::ReconnectingWebSocket
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::Stream;
+use futures_sink::Sink;
+use gloo_timers::future::sleep;
+use gloo_utils::errors::JsError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error as ThisError;
+
+use crate::websocket::futures::websocket_url;
+use crate::websocket::{
+    ConnectionState, Heartbeat, Message, ReconnectingWebSocket, WebSocketError,
+};
+
+/// The ref of the heartbeats, which Phoenix echoes in its replies.
+const HEARTBEAT_REF: &str = "heartbeat";
+
+/// The error of a join, of a push, or of a stream of messages.
+#[derive(Debug, ThisError)]
+#[non_exhaustive]
+pub enum PhoenixError {
+    /// The channel refused the join, answering `{:error, response}`, e.g. because the user
+    /// isn't allowed in the topic.
+    #[error("the channel refused to join: {0}")]
+    Refused(Value),
+    /// The channel answered a push with another status than `ok`, like `{:error, response}`.
+    #[error("the channel replied with `{status}`: {response}")]
+    Reply {
+        /// The status of the reply, usually `error`.
+        status: String,
+        /// The response of the reply.
+        response: Value,
+    },
+    /// The reply didn't arrive within the timeout.
+    #[error("the reply timed out")]
+    Timeout,
+    /// The process of the channel crashed on the server, after which the channel joins again.
+    #[error("the channel crashed")]
+    Crashed,
+    /// The socket was dropped, the connection closed for good, or the channel left its topic.
+    #[error("the channel is closed")]
+    Closed,
+    /// The connection failed.
+    #[error("{0}")]
+    WebSocket(WebSocketError),
+    /// A payload couldn't be encoded, or decoded, as JSON.
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A Phoenix socket, see the [module documentation](self).
+///
+/// Clones share the same connection, which is closed once every clone and every [`Channel`] are
+/// dropped.
+#[derive(Clone)]
+pub struct PhoenixSocket {
+    client: Rc<Client>,
+}
+
+impl PhoenixSocket {
+    /// Connects to the socket at `url`, like `https://example.com/socket` for a socket mounted
+    /// at `/socket` by the endpoint, without parameters.
+    pub fn connect(url: &str) -> Result<Self, JsError> {
+        Self::builder(url).connect()
+    }
+
+    /// Starts configuring a connection to the socket at `url`.
+    pub fn builder(url: &str) -> PhoenixSocketBuilder {
+        PhoenixSocketBuilder {
+            url: url.to_string(),
+            params: Vec::new(),
+            heartbeat: Duration::from_secs(30),
+        }
+    }
+
+    /// Joins `topic`, like `room:lobby`, with `params`, which the `join` callback of the channel
+    /// receives. The channel joins again on every new connection.
+    ///
+    /// The messages pushed before the channel joined wait to be sent until it does. Joining a
+    /// topic a channel of this socket already joined returns a handle to that channel.
+    pub fn channel<P: Serialize + ?Sized>(
+        &self,
+        topic: &str,
+        params: &P,
+    ) -> Result<Channel, PhoenixError> {
+        let shared = &self.client.shared;
+        let joined = shared.topics.borrow().contains_key(topic);
+        if !joined {
+            let params = serde_json::to_value(params)?;
+            shared
+                .topics
+                .borrow_mut()
+                .insert(topic.to_string(), Topic::new(params));
+            if shared.open.get() {
+                shared.join(topic);
+            }
+        }
+        Ok(Channel {
+            client: Rc::clone(&self.client),
+            topic: topic.to_string(),
+        })
+    }
+}
+
+impl fmt::Debug for PhoenixSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhoenixSocket")
+            .field("topics", &self.client.shared.topics.borrow().len())
+            .field("closed", &self.client.shared.closed.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Configures a [`PhoenixSocket`].
+#[derive(Debug)]
+pub struct PhoenixSocketBuilder {
+    url: String,
+    params: Vec<(String, String)>,
+    heartbeat: Duration,
+}
+
+impl PhoenixSocketBuilder {
+    /// Adds a parameter to the URL of the socket, which its `connect` callback receives, like
+    /// a token.
+    pub fn param(mut self, name: &str, value: &str) -> Self {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets how long the connection can go without receiving a message before a heartbeat is
+    /// sent. Defaults to 30s, like the JavaScript client.
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = interval;
+        self
+    }
+
+    /// Connects, and keeps connecting again in the background until the socket is dropped.
+    ///
+    /// This fails like [`ReconnectingWebSocket::open`]. It must be called in a
+    /// wasm-bindgen-futures context.
+    pub fn connect(self) -> Result<PhoenixSocket, JsError> {
+        let mut url = format!(
+            "{}/websocket?vsn=2.0.0",
+            websocket_url(&self.url).trim_end_matches('/')
+        );
+        for (name, value) in &self.params {
+            url.push_str(&format!(
+                "&{}={}",
+                js_sys::encode_uri_component(name),
+                js_sys::encode_uri_component(value)
+            ));
+        }
+        let heartbeat = encode(None, Some(HEARTBEAT_REF), "phoenix", "heartbeat", json!({}));
+        let ws = ReconnectingWebSocket::builder(&url)
+            .heartbeat(
+                Heartbeat::new(heartbeat)
+                    .interval(self.heartbeat)
+                    .reply(|message| match message {
+                        Message::Text(text) => parse(text).is_some_and(|message| {
+                            message.topic == "phoenix"
+                                && message.reference.as_deref() == Some(HEARTBEAT_REF)
+                        }),
+                        Message::Bytes(_) => false,
+                    }),
+            )
+            .open()?;
+        let (outgoing, outgoing_receiver) = mpsc::unbounded();
+        let shared = Rc::new(Shared {
+            outgoing,
+            topics: RefCell::default(),
+            replies: RefCell::default(),
+            next_ref: Cell::new(0),
+            open: Cell::new(false),
+            closed: Cell::new(false),
+        });
+        wasm_bindgen_futures::spawn_local(drive(ws, outgoing_receiver, Rc::clone(&shared)));
+        Ok(PhoenixSocket {
+            client: Rc::new(Client { shared }),
+        })
+    }
+}
+
+/// A channel joining one topic of the socket, see [`PhoenixSocket::channel`].
+///
+/// Clones are handles to the same channel.
+#[derive(Clone)]
+pub struct Channel {
+    client: Rc<Client>,
+    topic: String,
+}
+
+impl Channel {
+    /// The topic of the channel.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Whether the channel joined its topic, over the current connection.
+    pub fn is_joined(&self) -> bool {
+        let topics = self.client.shared.topics.borrow();
+        topics
+            .get(&self.topic)
+            .is_some_and(|topic| topic.joined.is_some())
+    }
+
+    /// Waits for the channel to join its topic, and returns the response of its `join`
+    /// callback, failing with [`PhoenixError::Refused`] when the channel refuses the join, or
+    /// [`PhoenixError::Timeout`] if it doesn't answer within `timeout`.
+    ///
+    /// The channel joins on its own, so this returns at once when it already joined.
+    pub async fn join(&self, timeout: Duration) -> Result<Value, PhoenixError> {
+        let (waiter, mut joined) = oneshot::channel();
+        {
+            let mut topics = self.client.shared.topics.borrow_mut();
+            let topic = topics.get_mut(&self.topic).ok_or(PhoenixError::Closed)?;
+            if let Some(response) = &topic.joined {
+                return Ok(response.clone());
+            }
+            topic.join_waiters.push(waiter);
+        }
+        let mut timer = sleep(timeout);
+        std::future::poll_fn(|cx| {
+            if let Poll::Ready(result) = Pin::new(&mut joined).poll(cx) {
+                return Poll::Ready(result.unwrap_or(Err(PhoenixError::Closed)));
+            }
+            Pin::new(&mut timer)
+                .poll(cx)
+                .map(|()| Err(PhoenixError::Timeout))
+        })
+        .await
+    }
+
+    /// Pushes `event` with `payload` to the channel, without waiting for its reply.
+    pub fn push<P: Serialize + ?Sized>(
+        &self,
+        event: &str,
+        payload: &P,
+    ) -> Result<(), PhoenixError> {
+        let reference = self.client.shared.next_ref();
+        self.client.send(
+            &self.topic,
+            reference,
+            event,
+            serde_json::to_value(payload)?,
+        )
+    }
+
+    /// Pushes `event` with `payload` to the channel, see [`push`](Self::push), and decodes the
+    /// response of its `{:reply, {:ok, response}, socket}`.
+    ///
+    /// This fails with [`PhoenixError::Reply`] when the reply has another status than `ok`, or
+    /// with [`PhoenixError::Timeout`] if it doesn't arrive within `timeout`.
+    pub async fn request<P, R>(
+        &self,
+        event: &str,
+        payload: &P,
+        timeout: Duration,
+    ) -> Result<R, PhoenixError>
+    where
+        P: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let shared = &self.client.shared;
+        let reference = shared.next_ref();
+        let (reply, mut replied) = oneshot::channel();
+        shared.replies.borrow_mut().insert(reference.clone(), reply);
+        // forgets the reply however this returns, including when this future is dropped
+        let _pending = PendingReply {
+            shared,
+            reference: reference.clone(),
+        };
+        self.client.send(
+            &self.topic,
+            reference,
+            event,
+            serde_json::to_value(payload)?,
+        )?;
+
+        let mut timer = sleep(timeout);
+        let reply = std::future::poll_fn(|cx| {
+            if let Poll::Ready(reply) = Pin::new(&mut replied).poll(cx) {
+                return Poll::Ready(reply.map_err(|_| PhoenixError::Closed));
+            }
+            Pin::new(&mut timer)
+                .poll(cx)
+                .map(|()| Err(PhoenixError::Timeout))
+        })
+        .await?;
+        match reply.status.as_str() {
+            "ok" => Ok(R::deserialize(reply.response)?),
+            _ => Err(PhoenixError::Reply {
+                status: reply.status,
+                response: reply.response,
+            }),
+        }
+    }
+
+    /// The stream of the messages named `event` the channel broadcasts or pushes.
+    pub fn on(&self, event: &str) -> Messages {
+        self.listen(Some(event.to_string()))
+    }
+
+    /// The stream of all the messages the channel broadcasts or pushes.
+    pub fn messages(&self) -> Messages {
+        self.listen(None)
+    }
+
+    fn listen(&self, event: Option<String>) -> Messages {
+        let (sender, receiver) = mpsc::unbounded();
+        if let Some(topic) = self.client.shared.topics.borrow_mut().get_mut(&self.topic) {
+            topic.listeners.push(Listener { event, sender });
+        }
+        Messages { receiver }
+    }
+
+    /// The stream of the presence of the topic, yielding it whenever the `presence_state` or
+    /// `presence_diff` messages of `Phoenix.Presence` change it, starting with the current one.
+    pub fn presence(&self) -> PresenceUpdates {
+        let (sender, receiver) = mpsc::unbounded();
+        if let Some(topic) = self.client.shared.topics.borrow_mut().get_mut(&self.topic) {
+            if topic.presence_synced {
+                let _ = sender.unbounded_send(topic.presence.clone());
+            }
+            topic.presence_listeners.push(sender);
+        }
+        PresenceUpdates { receiver }
+    }
+
+    /// Leaves the topic, which ends the streams of the channel and its clones.
+    pub fn leave(self) {
+        let shared = &self.client.shared;
+        let topic = shared.topics.borrow_mut().remove(&self.topic);
+        if let Some(Topic {
+            joined: Some(_),
+            join_ref,
+            ..
+        }) = topic
+        {
+            let reference = shared.next_ref();
+            let message = encode(
+                join_ref.as_deref(),
+                Some(&reference),
+                &self.topic,
+                "phx_leave",
+                json!({}),
+            );
+            let _ = shared.outgoing.unbounded_send(message);
+        }
+    }
+}
+
+impl fmt::Debug for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Channel")
+            .field("topic", &self.topic)
+            .field("joined", &self.is_joined())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message the channel broadcast or pushed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMessage {
+    /// The name of the message, like `new_msg`.
+    pub event: String,
+    /// The payload of the message.
+    pub payload: Value,
+}
+
+impl ChannelMessage {
+    /// Decodes the payload.
+    pub fn payload_as<T: DeserializeOwned>(&self) -> Result<T, PhoenixError> {
+        Ok(T::deserialize(&self.payload)?)
+    }
+}
+
+/// The messages of a channel, see [`Channel::on`].
+///
+/// The stream reports the failure of the connection, the crashes of the channel and its
+/// refusal to join as errors. It ends once the channel leaves its topic, or the connection
+/// closes for good.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Messages {
+    receiver: mpsc::UnboundedReceiver<Result<ChannelMessage, PhoenixError>>,
+}
+
+impl Stream for Messages {
+    type Item = Result<ChannelMessage, PhoenixError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+/// Who is present on a topic, tracked by `Phoenix.Presence`.
+///
+/// Each key, usually a user id, is present once per metadata, e.g. once per open tab.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Presence {
+    entries: BTreeMap<String, Vec<Value>>,
+}
+
+impl Presence {
+    /// The keys present, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// The metadata of `key`, or `None` when it isn't present.
+    pub fn metas(&self, key: &str) -> Option<&[Value]> {
+        self.entries.get(key).map(Vec::as_slice)
+    }
+
+    /// The keys present, with their metadata.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[Value])> {
+        self.entries
+            .iter()
+            .map(|(key, metas)| (key.as_str(), metas.as_slice()))
+    }
+
+    /// How many keys are present.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nobody is present.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Replaces the presence with the one of a `presence_state` message.
+    fn sync_state(&mut self, state: Value) {
+        self.entries = entries(state);
+    }
+
+    /// Applies the joins and leaves of a `presence_diff` message, like `syncDiff` of the
+    /// JavaScript client.
+    fn sync_diff(&mut self, diff: Value) {
+        let Diff { joins, leaves } = Diff::deserialize(diff).unwrap_or_default();
+        for (key, mut metas) in entries(joins) {
+            if let Some(current) = self.entries.get(&key) {
+                let joined: Vec<_> = metas.iter().map(phx_ref).collect();
+                let kept = current
+                    .iter()
+                    .filter(|meta| !joined.contains(&phx_ref(meta)))
+                    .cloned();
+                metas.splice(0..0, kept.collect::<Vec<_>>());
+            }
+            self.entries.insert(key, metas);
+        }
+        for (key, metas) in entries(leaves) {
+            let Some(current) = self.entries.get_mut(&key) else {
+                continue;
+            };
+            let left: Vec<_> = metas.iter().map(phx_ref).collect();
+            current.retain(|meta| !left.contains(&phx_ref(meta)));
+            if current.is_empty() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+struct Diff {
+    #[serde(default)]
+    joins: Value,
+    #[serde(default)]
+    leaves: Value,
+}
+
+/// The metadata of each key of a presence state, like `{"42": {"metas": [...]}}`.
+fn entries(state: Value) -> BTreeMap<String, Vec<Value>> {
+    let Value::Object(state) = state else {
+        return BTreeMap::new();
+    };
+    state
+        .into_iter()
+        .map(|(key, mut entry)| {
+            let metas = match entry.get_mut("metas").map(Value::take) {
+                Some(Value::Array(metas)) => metas,
+                _ => Vec::new(),
+            };
+            (key, metas)
+        })
+        .collect()
+}
+
+/// The `phx_ref` Phoenix gives each metadata.
+fn phx_ref(meta: &Value) -> Option<&str> {
+    meta.get("phx_ref")?.as_str()
+}
+
+/// The presence of a topic, see [`Channel::presence`].
+///
+/// The stream ends once the channel leaves its topic, or the connection closes for good.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct PresenceUpdates {
+    receiver: mpsc::UnboundedReceiver<Presence>,
+}
+
+impl Stream for PresenceUpdates {
+    type Item = Presence;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+/// The handles of a socket, closing the connection once dropped.
+struct Client {
+    shared: Rc<Shared>,
+}
+
+impl Client {
+    /// Pushes `event` to `topic`, or holds it until the channel joined.
+    fn send(
+        &self,
+        topic: &str,
+        reference: String,
+        event: &str,
+        payload: Value,
+    ) -> Result<(), PhoenixError> {
+        if self.shared.closed.get() {
+            return Err(PhoenixError::Closed);
+        }
+        let mut topics = self.shared.topics.borrow_mut();
+        let channel = topics.get_mut(topic).ok_or(PhoenixError::Closed)?;
+        if channel.joined.is_some() {
+            let message = encode(
+                channel.join_ref.as_deref(),
+                Some(&reference),
+                topic,
+                event,
+                payload,
+            );
+            self.shared
+                .outgoing
+                .unbounded_send(message)
+                .map_err(|_| PhoenixError::Closed)
+        } else {
+            channel
+                .waiting
+                .push((reference, event.to_string(), payload));
+            Ok(())
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.shared.outgoing.close_channel();
+    }
+}
+
+/// The state shared with the task driving the connection.
+struct Shared {
+    /// The messages to send, once the channel of their topic joined.
+    outgoing: mpsc::UnboundedSender<Message>,
+    topics: RefCell<HashMap<String, Topic>>,
+    replies: RefCell<HashMap<String, oneshot::Sender<Reply>>>,
+    next_ref: Cell<u64>,
+    /// Whether the current connection is open.
+    open: Cell<bool>,
+    closed: Cell<bool>,
+}
+
+impl Shared {
+    fn next_ref(&self) -> String {
+        let reference = self.next_ref.get();
+        self.next_ref.set(reference + 1);
+        reference.to_string()
+    }
+
+    /// Sends the join of the channel of `topic`, whose ref becomes its join ref.
+    fn join(&self, topic: &str) {
+        let reference = self.next_ref();
+        let mut topics = self.topics.borrow_mut();
+        let Some(channel) = topics.get_mut(topic) else {
+            return;
+        };
+        channel.joined = None;
+        channel.join_ref = Some(reference.clone());
+        let message = encode(
+            Some(&reference),
+            Some(&reference),
+            topic,
+            "phx_join",
+            channel.params.clone(),
+        );
+        let _ = self.outgoing.unbounded_send(message);
+    }
+}
+
+struct Topic {
+    params: Value,
+    /// The ref of the last join, which the messages of the channel carry.
+    join_ref: Option<String>,
+    /// The response of the join, once the channel joined over the current connection.
+    joined: Option<Value>,
+    join_waiters: Vec<oneshot::Sender<Result<Value, PhoenixError>>>,
+    /// The messages pushed before the channel joined: their ref, event and payload.
+    waiting: Vec<(String, String, Value)>,
+    listeners: Vec<Listener>,
+    presence: Presence,
+    /// Whether a `presence_state` message arrived.
+    presence_synced: bool,
+    presence_listeners: Vec<mpsc::UnboundedSender<Presence>>,
+}
+
+impl Topic {
+    fn new(params: Value) -> Self {
+        Self {
+            params,
+            join_ref: None,
+            joined: None,
+            join_waiters: Vec::new(),
+            waiting: Vec::new(),
+            listeners: Vec::new(),
+            presence: Presence::default(),
+            presence_synced: false,
+            presence_listeners: Vec::new(),
+        }
+    }
+
+    /// Passes the item built by `item` on to the listeners of the messages named `event`, or
+    /// to every listener without a name.
+    fn dispatch(
+        &mut self,
+        event: Option<&str>,
+        item: impl Fn() -> Result<ChannelMessage, PhoenixError>,
+    ) {
+        self.listeners.retain(|listener| {
+            let matches = match (&listener.event, event) {
+                (Some(wanted), Some(event)) => wanted == event,
+                (Some(_), None) | (None, _) => true,
+            };
+            !matches || listener.sender.unbounded_send(item()).is_ok()
+        });
+    }
+
+    fn presence_changed(&mut self) {
+        let presence = &self.presence;
+        self.presence_listeners
+            .retain(|listener| listener.unbounded_send(presence.clone()).is_ok());
+    }
+}
+
+struct Listener {
+    /// The name of the messages listened to, or `None` for all of them.
+    event: Option<String>,
+    sender: mpsc::UnboundedSender<Result<ChannelMessage, PhoenixError>>,
+}
+
+/// A push waiting for its reply, forgotten when dropped.
+struct PendingReply<'a> {
+    shared: &'a Shared,
+    reference: String,
+}
+
+impl Drop for PendingReply<'_> {
+    fn drop(&mut self) {
+        self.shared.replies.borrow_mut().remove(&self.reference);
+    }
+}
+
+/// The payload of a `phx_reply` message.
+#[derive(Debug, Deserialize)]
+struct Reply {
+    status: String,
+    #[serde(default)]
+    response: Value,
+}
+
+/// A message of the V2 serializer, a `[join_ref, ref, topic, event, payload]` array.
+#[derive(Debug, PartialEq, Deserialize)]
+struct RawMessage {
+    join_ref: Option<String>,
+    reference: Option<String>,
+    topic: String,
+    event: String,
+    payload: Value,
+}
+
+fn encode(
+    join_ref: Option<&str>,
+    reference: Option<&str>,
+    topic: &str,
+    event: &str,
+    payload: Value,
+) -> Message {
+    Message::Text(json!([join_ref, reference, topic, event, payload]).to_string())
+}
+
+fn parse(text: &str) -> Option<RawMessage> {
+    serde_json::from_str(text).ok()
+}
+
+/// What happened on the connection.
+enum Step {
+    Incoming(Option<Result<Message, WebSocketError>>),
+    State(Option<ConnectionState>),
+    Outgoing(Option<Message>),
+}
+
+/// Runs the socket over `ws`, passing messages between it and the channels.
+async fn drive(
+    mut ws: ReconnectingWebSocket,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    shared: Rc<Shared>,
+) {
+    let mut states = ws.states();
+    loop {
+        let step = std::future::poll_fn(|cx| {
+            if let Poll::Ready(state) = Pin::new(&mut states).poll_next(cx) {
+                return Poll::Ready(Step::State(state));
+            }
+            if let Poll::Ready(item) = Pin::new(&mut ws).poll_next(cx) {
+                return Poll::Ready(Step::Incoming(item));
+            }
+            Pin::new(&mut outgoing).poll_next(cx).map(Step::Outgoing)
+        })
+        .await;
+        match step {
+            Step::Incoming(Some(Ok(Message::Text(text)))) => {
+                if let Some(message) = parse(&text) {
+                    receive(message, &shared);
+                }
+            }
+            Step::Incoming(Some(Ok(Message::Bytes(_)))) => {}
+            Step::Incoming(Some(Err(error))) => {
+                for topic in shared.topics.borrow_mut().values_mut() {
+                    topic.dispatch(None, || Err(PhoenixError::WebSocket(error.duplicate())));
+                }
+            }
+            Step::Incoming(None) | Step::State(None) => break,
+            // the channels join their topics again
+            Step::State(Some(ConnectionState::Open)) => {
+                shared.open.set(true);
+                let topics: Vec<_> = shared.topics.borrow().keys().cloned().collect();
+                for topic in topics {
+                    shared.join(&topic);
+                }
+            }
+            Step::State(Some(_)) => {
+                shared.open.set(false);
+                for topic in shared.topics.borrow_mut().values_mut() {
+                    topic.joined = None;
+                }
+                // the replies of the pushes sent over the lost connection never arrive
+                shared.replies.borrow_mut().clear();
+            }
+            Step::Outgoing(Some(message)) => send(&mut ws, message),
+            Step::Outgoing(None) => {
+                let _ = std::future::poll_fn(|cx| Pin::new(&mut ws).poll_close(cx)).await;
+                break;
+            }
+        }
+    }
+    shared.closed.set(true);
+    shared.open.set(false);
+    // dropping the senders ends the streams, and fails the joins and pushes waiting
+    shared.topics.borrow_mut().clear();
+    shared.replies.borrow_mut().clear();
+}
+
+fn send(ws: &mut ReconnectingWebSocket, message: Message) {
+    // a reconnecting websocket holds the message until it can send it
+    let _ = Pin::new(ws).start_send(message);
+}
+
+/// Handles a message of the server.
+fn receive(message: RawMessage, shared: &Rc<Shared>) {
+    let RawMessage {
+        join_ref,
+        reference,
+        topic: name,
+        event,
+        payload,
+    } = message;
+    let mut topics = shared.topics.borrow_mut();
+    let Some(topic) = topics.get_mut(&name) else {
+        return;
+    };
+    // the messages of an earlier join of the topic are stale
+    if join_ref.is_some() && join_ref != topic.join_ref {
+        return;
+    }
+    match event.as_str() {
+        "phx_reply" if reference.is_some() && reference == topic.join_ref => {
+            let reply = match Reply::deserialize(payload) {
+                Ok(reply) => reply,
+                Err(_) => return,
+            };
+            if reply.status == "ok" {
+                topic.joined = Some(reply.response.clone());
+                for waiter in topic.join_waiters.drain(..) {
+                    let _ = waiter.send(Ok(reply.response.clone()));
+                }
+                for (reference, event, payload) in std::mem::take(&mut topic.waiting) {
+                    let message = encode(
+                        topic.join_ref.as_deref(),
+                        Some(&reference),
+                        &name,
+                        &event,
+                        payload,
+                    );
+                    let _ = shared.outgoing.unbounded_send(message);
+                }
+            } else {
+                for waiter in topic.join_waiters.drain(..) {
+                    let _ = waiter.send(Err(PhoenixError::Refused(reply.response.clone())));
+                }
+                topic.dispatch(None, || Err(PhoenixError::Refused(reply.response.clone())));
+            }
+        }
+        "phx_reply" => {
+            let reply =
+                reference.and_then(|reference| shared.replies.borrow_mut().remove(&reference));
+            if let (Some(sender), Ok(reply)) = (reply, Reply::deserialize(payload)) {
+                let _ = sender.send(reply);
+            }
+        }
+        // the process of the channel crashed, it joins again after a second
+        "phx_error" => {
+            topic.joined = None;
+            topic.dispatch(None, || Err(PhoenixError::Crashed));
+            let shared = Rc::downgrade(shared);
+            wasm_bindgen_futures::spawn_local(rejoin(shared, name));
+        }
+        // the channel left the topic, ending its streams
+        "phx_close" => {
+            topics.remove(&name);
+        }
+        _ => {
+            match event.as_str() {
+                "presence_state" => {
+                    topic.presence.sync_state(payload.clone());
+                    topic.presence_synced = true;
+                    topic.presence_changed();
+                }
+                "presence_diff" => {
+                    topic.presence.sync_diff(payload.clone());
+                    topic.presence_changed();
+                }
+                _ => {}
+            }
+            topic.dispatch(Some(&event), || {
+                Ok(ChannelMessage {
+                    event: event.clone(),
+                    payload: payload.clone(),
+                })
+            });
+        }
+    }
+}
+
+/// Joins `topic` again after its channel crashed, unless it left or joined meanwhile.
+async fn rejoin(shared: Weak<Shared>, topic: String) {
+    sleep(Duration::from_secs(1)).await;
+    let Some(shared) = shared.upgrade() else {
+        return;
+    };
+    let crashed = shared
+        .topics
+        .borrow()
+        .get(&topic)
+        .is_some_and(|channel| channel.joined.is_none());
+    if crashed && shared.open.get() {
+        shared.join(&topic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_and_parses_messages() {
+        let message = encode(Some("1"), Some("2"), "room:lobby", "phx_join", json!({}));
+        assert_eq!(
+            message,
+            Message::Text(r#"["1","2","room:lobby","phx_join",{}]"#.to_string())
+        );
+        assert_eq!(
+            parse(r#"[null,null,"room:lobby","new_msg",{"body":"hi"}]"#),
+            Some(RawMessage {
+                join_ref: None,
+                reference: None,
+                topic: "room:lobby".to_string(),
+                event: "new_msg".to_string(),
+                payload: json!({ "body": "hi" }),
+            })
+        );
+        assert_eq!(parse(r#"{"topic":"room:lobby"}"#), None);
+    }
+
+    #[test]
+    fn syncs_presence() {
+        let mut presence = Presence::default();
+        presence.sync_state(json!({
+            "alice": { "metas": [{ "phx_ref": "a1", "tab": 1 }] },
+            "bob": { "metas": [{ "phx_ref": "b1" }] },
+        }));
+        assert_eq!(presence.keys().collect::<Vec<_>>(), ["alice", "bob"]);
+
+        presence.sync_diff(json!({
+            "joins": { "alice": { "metas": [{ "phx_ref": "a2", "tab": 2 }] } },
+            "leaves": { "bob": { "metas": [{ "phx_ref": "b1" }] } },
+        }));
+        assert_eq!(presence.len(), 1);
+        assert_eq!(
+            presence.metas("alice").unwrap(),
+            [
+                json!({ "phx_ref": "a1", "tab": 1 }),
+                json!({ "phx_ref": "a2", "tab": 2 })
+            ]
+        );
+
+        presence.sync_diff(json!({
+            "joins": {},
+            "leaves": { "alice": { "metas": [{ "phx_ref": "a1" }] } },
+        }));
+        assert_eq!(
+            presence.iter().collect::<Vec<_>>(),
+            [("alice", &[json!({ "phx_ref": "a2", "tab": 2 })][..])]
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+use futures_sink::Sink;
+use gloo_utils::errors::JsError;
+
+use crate::websocket::futures::{WebSocket, WebSocketReceiver, WebSocketSender};
+use crate::websocket::{ConnectionStates, Message, State, WebSocketError};
+
+/// The URL and sub-protocols a connection was opened with.
+type Key = (String, Vec<String>);
+
+/// Hands out handles to a single connection per URL and sub-protocols, so that components
+/// opening the same WebSocket don't each open their own.
+///
+/// The first [`open`](Self::open) of a URL opens the connection, and later ones get a handle to
+/// it until it closes. Every handle sends over the connection and receives every message
+/// arriving after it was handed out, and the connection closes once the last handle is dropped.
+/// The pool is cheap to clone, and its clones share the connections, so it can be passed down
+/// to the components of an app.
+///
+/// # Example
+///
+/// ```
+/// use futures::{SinkExt, StreamExt};
+/// use gloo_net::websocket::{Message, WebSocketPool};
+///
+/// # async fn no_run() {
+/// let pool = WebSocketPool::new();
+/// let mut chat = pool.open("wss://example.com/live").unwrap();
+/// let mut notifications = pool.open("wss://example.com/live").unwrap();
+/// assert_eq!(pool.len(), 1);
+///
+/// chat.send(Message::Text("hello".to_string())).await.unwrap();
+/// // both handles receive the reply
+/// let reply = notifications.next().await;
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct WebSocketPool {
+    connections: Rc<RefCell<HashMap<Key, Weak<Connection>>>>,
+}
+
+/// A connection of a [`WebSocketPool`], shared by its handles.
+struct Connection {
+    key: Key,
+    sender: RefCell<WebSocketSender>,
+    routes: Rc<Routes>,
+    connections: Weak<RefCell<HashMap<Key, Weak<Connection>>>>,
+}
+
+/// Where the items received go, shared with the task reading the connection.
+#[derive(Default)]
+struct Routes {
+    handles: RefCell<HashMap<u64, mpsc::UnboundedSender<Result<Message, WebSocketError>>>>,
+    next_id: Cell<u64>,
+    /// The handles waiting for the connection to open, which the socket can't wake all.
+    ready_wakers: RefCell<Vec<Waker>>,
+}
+
+impl WebSocketPool {
+    /// Creates a pool without connections.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hands out a handle to the connection to `url`, opening it unless it is open or opening.
+    ///
+    /// This errors like [`WebSocket::open`] when the connection has to be opened. It must be
+    /// called in a `wasm_bindgen_futures` context, which reads the connection.
+    pub fn open(&self, url: &str) -> Result<PooledWebSocket, JsError> {
+        self.open_with_protocols::<&str>(url, &[])
+    }
+
+    /// Hands out a handle to the connection to `url` with sub-`protocols`, see
+    /// [`open`](Self::open).
+    ///
+    /// Only the connections opened with the same URL and sub-protocols are shared.
+    pub fn open_with_protocols<S: AsRef<str>>(
+        &self,
+        url: &str,
+        protocols: &[S],
+    ) -> Result<PooledWebSocket, JsError> {
+        let key = (
+            url.to_string(),
+            protocols
+                .iter()
+                .map(|protocol| protocol.as_ref().to_string())
+                .collect::<Vec<_>>(),
+        );
+        let open = self
+            .connections
+            .borrow()
+            .get(&key)
+            .and_then(Weak::upgrade)
+            .filter(|connection| {
+                matches!(
+                    connection.sender.borrow().state(),
+                    State::Connecting | State::Open
+                )
+            });
+        let connection = match open {
+            Some(connection) => connection,
+            None => {
+                let ws = WebSocket::open_with_protocols(url, protocols)?;
+                let states = ws.states();
+                let (sender, receiver) = ws.into_halves();
+                let connection = Rc::new(Connection {
+                    key: key.clone(),
+                    sender: RefCell::new(sender),
+                    routes: Rc::default(),
+                    connections: Rc::downgrade(&self.connections),
+                });
+                wasm_bindgen_futures::spawn_local(read(
+                    receiver,
+                    states,
+                    Rc::clone(&connection.routes),
+                ));
+                self.connections
+                    .borrow_mut()
+                    .insert(key, Rc::downgrade(&connection));
+                connection
+            }
+        };
+        Ok(PooledWebSocket::new(connection))
+    }
+
+    /// The number of connections with handles, open or not.
+    pub fn len(&self) -> usize {
+        self.connections
+            .borrow()
+            .values()
+            .filter(|connection| connection.strong_count() > 0)
+            .count()
+    }
+
+    /// Whether no connection has handles.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for WebSocketPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketPool")
+            .field("connections", &self.len())
+            .finish()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.sender.borrow().close_raw(Some(1000), None);
+        if let Some(connections) = self.connections.upgrade() {
+            let mut connections = connections.borrow_mut();
+            // the pool may hold a newer connection to the same URL
+            if connections
+                .get(&self.key)
+                .is_some_and(|connection| connection.strong_count() == 0)
+            {
+                connections.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// A handle to a connection of a [`WebSocketPool`].
+///
+/// It is a [`Sink`] and [`Stream`] of messages like a [`WebSocket`]: the messages it sends go
+/// over the shared connection, and it receives every message of the connection, along with its
+/// errors and close event. Dropping it gives up the connection, which closes once every handle
+/// is dropped.
+#[must_use = "streams do nothing unless polled"]
+pub struct PooledWebSocket {
+    id: u64,
+    connection: Rc<Connection>,
+    receiver: mpsc::UnboundedReceiver<Result<Message, WebSocketError>>,
+}
+
+impl PooledWebSocket {
+    fn new(connection: Rc<Connection>) -> Self {
+        let routes = &connection.routes;
+        let id = routes.next_id.get();
+        routes.next_id.set(id + 1);
+        let (sender, receiver) = mpsc::unbounded();
+        routes.handles.borrow_mut().insert(id, sender);
+        Self {
+            id,
+            connection,
+            receiver,
+        }
+    }
+
+    /// The URL of the connection, see [`WebSocket::url`].
+    pub fn url(&self) -> String {
+        self.connection.sender.borrow().url()
+    }
+
+    /// The current state of the connection.
+    pub fn state(&self) -> State {
+        self.connection.sender.borrow().state()
+    }
+
+    /// The number of handles sharing the connection, this one included.
+    pub fn handles(&self) -> usize {
+        Rc::strong_count(&self.connection)
+    }
+}
+
+impl Stream for PooledWebSocket {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Sink<Message> for PooledWebSocket {
+    type Error = WebSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.state() == State::Connecting {
+            let mut wakers = self.connection.routes.ready_wakers.borrow_mut();
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            return Poll::Pending;
+        }
+        Pin::new(&mut *self.connection.sender.borrow_mut()).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut *self.connection.sender.borrow_mut()).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *self.connection.sender.borrow_mut()).poll_flush(cx)
+    }
+
+    /// Closing a handle flushes the messages it sent, but doesn't close the shared connection,
+    /// dropping every handle does.
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl Drop for PooledWebSocket {
+    fn drop(&mut self) {
+        self.connection.routes.handles.borrow_mut().remove(&self.id);
+    }
+}
+
+impl fmt::Debug for PooledWebSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledWebSocket")
+            .field("url", &self.url())
+            .field("state", &self.state())
+            .field("handles", &self.handles())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Wakes the handles waiting once the connection opens or fails, then reads it until it closes,
+/// passing every item on to the handles.
+async fn read(mut receiver: WebSocketReceiver, mut states: ConnectionStates, routes: Rc<Routes>) {
+    std::future::poll_fn(|cx| Pin::new(&mut states).poll_next(cx)).await;
+    for waker in routes.ready_wakers.take() {
+        waker.wake();
+    }
+    while let Some(item) = std::future::poll_fn(|cx| Pin::new(&mut receiver).poll_next(cx)).await {
+        let handles = routes.handles.borrow();
+        for handle in handles.values() {
+            let item = match &item {
+                Ok(message) => Ok(message.clone()),
+                Err(error) => Err(error.duplicate()),
+            };
+            let _ = handle.unbounded_send(item);
+        }
+    }
+    // dropping the senders ends the streams of the handles
+    routes.handles.borrow_mut().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::websocket::ConnectionState;
+    use futures::{SinkExt, StreamExt};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn states(handle: &PooledWebSocket) -> ConnectionStates {
+        handle.connection.sender.borrow().states()
+    }
+
+    #[wasm_bindgen_test]
+    async fn handles_share_a_connection() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let pool = WebSocketPool::new();
+        let mut chat = pool.open(ws_echo_server_url).unwrap();
+        let mut notifications = pool.clone().open(ws_echo_server_url).unwrap();
+        let other = pool
+            .open_with_protocols(ws_echo_server_url, &["chat"])
+            .unwrap();
+        assert!(Rc::ptr_eq(&chat.connection, &notifications.connection));
+        assert!(!Rc::ptr_eq(&chat.connection, &other.connection));
+        assert_eq!(chat.handles(), 2);
+        assert_eq!(other.handles(), 1);
+        assert_eq!(pool.len(), 2);
+
+        drop(other);
+        assert_eq!(pool.len(), 1);
+
+        // the echo-server sends its info in the first message
+        let _ = chat.next().await;
+        let _ = notifications.next().await;
+        chat.send(Message::Text("hello".to_string())).await.unwrap();
+        for handle in [&mut chat, &mut notifications] {
+            assert_eq!(
+                handle.next().await.unwrap().unwrap(),
+                Message::Text("hello".to_string())
+            );
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn dropping_the_last_handle_closes_the_connection() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let pool = WebSocketPool::new();
+        let first = pool.open(ws_echo_server_url).unwrap();
+        let second = pool.open(ws_echo_server_url).unwrap();
+        let mut states = states(&first);
+        assert_eq!(states.next().await, Some(ConnectionState::Open));
+
+        drop(first);
+        assert_eq!(second.handles(), 1);
+        assert_eq!(second.state(), State::Open);
+
+        drop(second);
+        assert!(pool.is_empty());
+        assert!(pool.connections.borrow().is_empty());
+        assert_eq!(
+            states.next().await,
+            Some(ConnectionState::Closed { code: 1000 })
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn closed_connections_are_reopened() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let pool = WebSocketPool::new();
+        let closed = pool.open(ws_echo_server_url).unwrap();
+        let mut states = states(&closed);
+        assert_eq!(states.next().await, Some(ConnectionState::Open));
+        closed
+            .connection
+            .sender
+            .borrow()
+            .close_raw(Some(1000), None)
+            .unwrap();
+        assert_eq!(
+            states.next().await,
+            Some(ConnectionState::Closed { code: 1000 })
+        );
+
+        let mut reopened = pool.open(ws_echo_server_url).unwrap();
+        assert!(!Rc::ptr_eq(&closed.connection, &reopened.connection));
+        assert_eq!(reopened.handles(), 1);
+        assert_eq!(pool.len(), 1);
+
+        let _ = reopened.next().await;
+        reopened
+            .send(Message::Text("again".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            reopened.next().await.unwrap().unwrap(),
+            Message::Text("again".to_string())
+        );
+    }
+}
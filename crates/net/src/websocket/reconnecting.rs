@@ -0,0 +1,730 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+use futures_sink::Sink;
+use gloo_utils::errors::JsError;
+
+use crate::websocket::events::ErrorEvent;
+use crate::websocket::futures::WebSocket;
+use crate::websocket::heartbeat::{Beat, Pulse};
+use crate::websocket::metrics::{message_size, Counters};
+use crate::websocket::{ConnectionState, Heartbeat, Message, Metrics, State, WebSocketError};
+
+/// Builds the messages sent each time the connection opens.
+type OnOpen = Box<dyn Fn() -> Vec<Message>>;
+
+/// A WebSocket which opens the connection again whenever it closes or fails, with exponential
+/// backoff.
+///
+/// It is a [`Sink`] and [`Stream`] of messages like [`WebSocket`], whose stream only ends once
+/// the connection is closed for good: after [`max_retries`](ReconnectingWebSocketBuilder::max_retries)
+/// failed attempts in a row, or when this is dropped. The connection errors in between aren't
+/// yielded: [`states`](Self::states) tells when the connection goes down and when it is opened
+/// again, and only the error which ends the connection for good is yielded, before the stream
+/// ends.
+///
+/// Messages sent while the connection is down wait to be sent until it is open again, as many as
+/// they are unless [buffered](ReconnectingWebSocketBuilder::buffer) up to a capacity. A message
+/// which was being sent as the connection broke can be lost, so protocols which can't afford
+/// that must acknowledge their messages.
+///
+/// # Example
+///
+/// ```
+/// use futures::{SinkExt, StreamExt};
+/// use gloo_net::websocket::{Message, ReconnectingWebSocket};
+/// use std::time::Duration;
+///
+/// # async fn no_run() {
+/// let mut ws = ReconnectingWebSocket::builder("wss://example.com/live")
+///     .max_backoff(Duration::from_secs(10))
+///     // subscribe again on every new connection
+///     .on_open(|| vec![Message::Text(r#"{"subscribe":"prices"}"#.to_string())])
+///     .open()
+///     .unwrap();
+/// while let Some(Ok(message)) = ws.next().await {
+///     // ...
+/// }
+/// # }
+/// ```
+pub struct ReconnectingWebSocket {
+    shared: Rc<Shared>,
+    incoming: mpsc::UnboundedReceiver<Result<Message, WebSocketError>>,
+}
+
+impl ReconnectingWebSocket {
+    /// Connects to `url`, reconnecting forever with the default backoff.
+    pub fn open(url: &str) -> Result<Self, JsError> {
+        Self::builder(url).open()
+    }
+
+    /// Starts configuring a connection to `url`.
+    pub fn builder(url: &str) -> ReconnectingWebSocketBuilder {
+        ReconnectingWebSocketBuilder {
+            url: url.to_string(),
+            backoff: Backoff {
+                initial: Duration::from_millis(500),
+                max: Duration::from_secs(30),
+            },
+            max_retries: None,
+            on_open: None,
+            heartbeat: None,
+            buffer: None,
+            open_timeout: None,
+        }
+    }
+
+    /// The URL the connection is opened to.
+    pub fn url(&self) -> &str {
+        &self.shared.url
+    }
+
+    /// The current state of the connection.
+    pub fn state(&self) -> ConnectionState {
+        self.shared.state.get()
+    }
+
+    /// The changes of the [state](Self::state) of the connection, until it is closed for good.
+    pub fn states(&self) -> ConnectionStates {
+        let (sender, receiver) = mpsc::unbounded();
+        if !matches!(self.state(), ConnectionState::Closed { .. }) {
+            self.shared.listeners.borrow_mut().push(sender);
+        }
+        ConnectionStates::new(receiver)
+    }
+
+    /// A snapshot of the counters of the connection, e.g. for a debugging overlay.
+    ///
+    /// The messages are counted across all the connections, the keepalive messages of the
+    /// [`Heartbeat`] included, and the uptime is the one of the current connection.
+    pub fn metrics(&self) -> Metrics {
+        self.shared.counters.snapshot(js_sys::Date::now())
+    }
+}
+
+impl Sink<Message> for ReconnectingWebSocket {
+    type Error = WebSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.shared.closed.get() {
+            Poll::Ready(Err(self.shared.failed()))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        if self.shared.closed.get() {
+            return Err(self.shared.failed());
+        }
+        self.shared.push(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.shared.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for ReconnectingWebSocket {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.incoming).poll_next(cx)
+    }
+}
+
+impl Drop for ReconnectingWebSocket {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+impl fmt::Debug for ReconnectingWebSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectingWebSocket")
+            .field("url", &self.shared.url)
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Configures a [`ReconnectingWebSocket`].
+///
+/// Between two attempts to connect, it waits for an exponentially growing backoff with "full
+/// jitter": a random duration between zero and `initial_backoff * 2^attempt`, capped at
+/// `max_backoff`. The attempts are counted again from zero once the connection opens.
+pub struct ReconnectingWebSocketBuilder {
+    url: String,
+    backoff: Backoff,
+    max_retries: Option<u32>,
+    on_open: Option<OnOpen>,
+    heartbeat: Option<Heartbeat>,
+    buffer: Option<Buffer>,
+    open_timeout: Option<Duration>,
+}
+
+impl ReconnectingWebSocketBuilder {
+    /// Sets the backoff before the first attempt to reconnect, which doubles with every further
+    /// attempt. Defaults to 500ms.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff.initial = backoff;
+        self
+    }
+
+    /// Sets the longest time to wait between two attempts. Defaults to 30s.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff.max = backoff;
+        self
+    }
+
+    /// Gives up after `max_retries` failed attempts in a row to reconnect. By default, it tries
+    /// forever.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Sends the messages built by `on_open` each time the connection opens, the first time
+    /// included, before any other message, e.g. to authenticate or to subscribe again.
+    pub fn on_open(mut self, on_open: impl Fn() -> Vec<Message> + 'static) -> Self {
+        self.on_open = Some(Box::new(on_open));
+        self
+    }
+
+    /// Checks that the connection is alive with `heartbeat`, and opens it again when it isn't.
+    pub fn heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Gives up on an attempt to connect which didn't open within `timeout`, and tries again
+    /// after a backoff, see [`WebSocket::open_timeout`]. By default, it waits for as long as
+    /// the browser does.
+    pub fn open_timeout(mut self, timeout: Duration) -> Self {
+        self.open_timeout = Some(timeout);
+        self
+    }
+
+    /// Keeps at most `capacity` messages waiting to be sent, e.g. while the connection is down,
+    /// and applies `overflow` to the messages sent beyond that. By default, every message waits.
+    ///
+    /// The waiting messages are sent in order once the connection opens again.
+    pub fn buffer(mut self, capacity: usize, overflow: OverflowPolicy) -> Self {
+        self.buffer = Some(Buffer {
+            capacity: capacity.max(1),
+            overflow,
+        });
+        self
+    }
+
+    /// Connects, and keeps connecting again in the background until the returned
+    /// [`ReconnectingWebSocket`] is dropped.
+    ///
+    /// This fails like [`WebSocket::open`], when the URL is invalid or its port is blocked.
+    pub fn open(self) -> Result<ReconnectingWebSocket, JsError> {
+        let ws = WebSocket::open(&self.url)?;
+        let shared = Rc::new(Shared::new(self.url.clone(), self.buffer));
+        let (incoming_sender, incoming) = mpsc::unbounded();
+        let driver = Driver {
+            shared: Rc::clone(&shared),
+            config: self,
+            incoming: incoming_sender,
+            opened_before: false,
+        };
+        wasm_bindgen_futures::spawn_local(driver.run(ws));
+        Ok(ReconnectingWebSocket { shared, incoming })
+    }
+}
+
+impl fmt::Debug for ReconnectingWebSocketBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectingWebSocketBuilder")
+            .field("url", &self.url)
+            .field("backoff", &self.backoff)
+            .field("max_retries", &self.max_retries)
+            .field("heartbeat", &self.heartbeat)
+            .field("buffer", &self.buffer)
+            .field("open_timeout", &self.open_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`Stream`] of the [`ConnectionState`]s of a connection, see
+/// [`ReconnectingWebSocket::states`] and [`WebSocket::states`].
+///
+/// [`WebSocket::states`]: crate::websocket::futures::WebSocket::states
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct ConnectionStates {
+    receiver: mpsc::UnboundedReceiver<ConnectionState>,
+}
+
+impl ConnectionStates {
+    pub(super) fn new(receiver: mpsc::UnboundedReceiver<ConnectionState>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl Stream for ConnectionStates {
+    type Item = ConnectionState;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+/// What to do with a message sent while the [buffer](ReconnectingWebSocketBuilder::buffer) of
+/// a [`ReconnectingWebSocket`] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the message which waited the longest to make room, keeping the latest messages,
+    /// e.g. for state updates superseding each other.
+    DropOldest,
+    /// Drop the message sent.
+    DropNewest,
+    /// Fail to send the message with [`WebSocketError::BufferFull`].
+    Error,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Buffer {
+    capacity: usize,
+    overflow: OverflowPolicy,
+}
+
+/// The state shared between a [`ReconnectingWebSocket`] and its [`Driver`].
+struct Shared {
+    url: String,
+    state: Cell<ConnectionState>,
+    listeners: RefCell<Vec<mpsc::UnboundedSender<ConnectionState>>>,
+    counters: Counters,
+    /// The messages waiting to be sent.
+    outgoing: RefCell<VecDeque<Message>>,
+    buffer: Option<Buffer>,
+    /// Wakes the driver up once a message is sent, or the connection closed.
+    driver_waker: RefCell<Option<Waker>>,
+    /// Whether the connection was closed on purpose, and shouldn't be opened again.
+    closed: Cell<bool>,
+}
+
+impl Shared {
+    fn new(url: String, buffer: Option<Buffer>) -> Self {
+        Self {
+            url,
+            state: Cell::new(ConnectionState::Connecting),
+            listeners: RefCell::default(),
+            counters: Counters::default(),
+            outgoing: RefCell::default(),
+            buffer,
+            driver_waker: RefCell::default(),
+            closed: Cell::new(false),
+        }
+    }
+
+    /// The error of a connection failing, or closed, without an event telling more.
+    fn failed(&self) -> WebSocketError {
+        WebSocketError::ConnectionError(ErrorEvent::unknown(self.url.clone()))
+    }
+
+    /// The error of a connection which didn't answer the heartbeat.
+    fn stale(&self) -> WebSocketError {
+        WebSocketError::StaleConnection {
+            url: self.url.clone(),
+        }
+    }
+
+    /// Queues `message` to be sent, making room for it as the buffer says.
+    fn push(&self, message: Message) -> Result<(), WebSocketError> {
+        let mut outgoing = self.outgoing.borrow_mut();
+        if let Some(buffer) = self.buffer {
+            if outgoing.len() >= buffer.capacity {
+                match buffer.overflow {
+                    OverflowPolicy::DropOldest => {
+                        outgoing.pop_front();
+                    }
+                    OverflowPolicy::DropNewest => return Ok(()),
+                    OverflowPolicy::Error => {
+                        return Err(WebSocketError::BufferFull {
+                            url: self.url.clone(),
+                        })
+                    }
+                }
+            }
+        }
+        outgoing.push_back(message);
+        drop(outgoing);
+        self.wake_driver();
+        Ok(())
+    }
+
+    /// The next message to send, or `None` once the connection was closed on purpose and every
+    /// message was sent.
+    fn poll_outgoing(&self, cx: &mut Context<'_>) -> Poll<Option<Message>> {
+        if let Some(message) = self.outgoing.borrow_mut().pop_front() {
+            return Poll::Ready(Some(message));
+        }
+        if self.closed.get() {
+            return Poll::Ready(None);
+        }
+        *self.driver_waker.borrow_mut() = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn close(&self) {
+        self.closed.set(true);
+        self.wake_driver();
+    }
+
+    fn wake_driver(&self) {
+        if let Some(waker) = self.driver_waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        self.state.set(state);
+        match state {
+            ConnectionState::Open => self.counters.opened(js_sys::Date::now()),
+            _ => self.counters.closed(),
+        }
+        self.listeners
+            .borrow_mut()
+            .retain(|listener| listener.unbounded_send(state).is_ok());
+        if let ConnectionState::Closed { .. } = state {
+            self.listeners.borrow_mut().clear();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    /// The backoff before attempt number `attempt + 1`, given a random number in `[0, 1)`.
+    fn delay(&self, attempt: u32, random: f64) -> Duration {
+        let exponential = self
+            .initial
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max);
+        Duration::min(exponential, self.max).mul_f64(random)
+    }
+}
+
+/// What happened on an open connection.
+enum Event {
+    Incoming(Option<Result<Message, WebSocketError>>),
+    Outgoing(Option<Message>),
+    Heartbeat(Beat),
+}
+
+/// Runs a [`ReconnectingWebSocket`]: forwards the messages between it and the current
+/// connection, and replaces the connection when it closes.
+struct Driver {
+    shared: Rc<Shared>,
+    config: ReconnectingWebSocketBuilder,
+    incoming: mpsc::UnboundedSender<Result<Message, WebSocketError>>,
+    /// Whether a connection was open before, so that opening one counts as reconnecting.
+    opened_before: bool,
+}
+
+impl Driver {
+    async fn run(mut self, ws: WebSocket) {
+        let mut ws = Some(ws);
+        let mut failures = 0;
+        // the close code of the last connection, or 1006 (abnormal closure) without one
+        let mut code = 1006;
+        while !self.shared.closed.get() {
+            let connection = match ws.take() {
+                Some(ws) => Ok(ws),
+                None => WebSocket::open(&self.config.url),
+            };
+            let error = match connection {
+                Ok(ws) => match self.connected(ws).await {
+                    // the connection was open, start counting the failures again
+                    (true, error) => {
+                        failures = 0;
+                        error
+                    }
+                    (false, error) => error,
+                },
+                Err(_) => self.shared.failed(),
+            };
+            if let WebSocketError::ConnectionClose(event) = &error {
+                code = event.code;
+            }
+            if self.shared.closed.get() {
+                break;
+            }
+            if self.config.max_retries.is_some_and(|max| failures >= max) {
+                let _ = self.incoming.unbounded_send(Err(error));
+                break;
+            }
+            failures += 1;
+            self.shared
+                .set_state(ConnectionState::Reconnecting { attempt: failures });
+            let delay = self
+                .config
+                .backoff
+                .delay(failures - 1, js_sys::Math::random());
+            gloo_timers::future::sleep(delay).await;
+        }
+        if self.shared.closed.get() {
+            code = 1000;
+        }
+        self.shared.closed.set(true);
+        self.shared.set_state(ConnectionState::Closed { code });
+    }
+
+    /// Runs `ws` until it closes, returning whether it was open, and why it closed.
+    async fn connected(&mut self, mut ws: WebSocket) -> (bool, WebSocketError) {
+        self.shared.set_state(ConnectionState::Connecting);
+        if let Some(timeout) = self.config.open_timeout {
+            ws = ws.open_timeout(timeout);
+        }
+        let opened = std::future::poll_fn(|cx| Pin::new(&mut ws).poll_ready(cx)).await;
+        if opened.is_err() || !matches!(ws.state(), State::Open) {
+            let error = self.shared.failed();
+            return (false, next_error(&mut ws, error).await);
+        }
+        self.shared.set_state(ConnectionState::Open);
+        if self.opened_before {
+            self.shared.counters.reconnected();
+        }
+        self.opened_before = true;
+        let shared = Rc::clone(&self.shared);
+        let counters = &shared.counters;
+        if let Some(on_open) = &self.config.on_open {
+            for message in on_open() {
+                if let Err(e) = send(&mut ws, message, counters).await {
+                    return (true, e);
+                }
+            }
+        }
+        let mut pulse = self.config.heartbeat.as_ref().map(Heartbeat::start);
+        loop {
+            let event = std::future::poll_fn(|cx| {
+                if let Poll::Ready(message) = Pin::new(&mut ws).poll_next(cx) {
+                    return Poll::Ready(Event::Incoming(message));
+                }
+                if let Poll::Ready(message) = shared.poll_outgoing(cx) {
+                    return Poll::Ready(Event::Outgoing(message));
+                }
+                match &mut pulse {
+                    Some(pulse) => pulse.poll(cx).map(Event::Heartbeat),
+                    None => Poll::Pending,
+                }
+            })
+            .await;
+            match event {
+                Event::Incoming(Some(Ok(message))) => {
+                    counters.received(message_size(&message));
+                    let is_reply = pulse.as_mut().is_some_and(|pulse| pulse.received(&message));
+                    if let Some(rtt) = pulse.as_ref().and_then(Pulse::last_rtt) {
+                        counters.set_rtt(rtt);
+                    }
+                    if !is_reply {
+                        let _ = self.incoming.unbounded_send(Ok(message));
+                    }
+                }
+                Event::Incoming(Some(Err(e))) => {
+                    // the close event follows the error event
+                    return match e {
+                        e @ WebSocketError::ConnectionError(_) => {
+                            (true, next_error(&mut ws, e).await)
+                        }
+                        e => (true, e),
+                    };
+                }
+                Event::Incoming(None) => return (true, self.shared.failed()),
+                Event::Outgoing(Some(message)) => {
+                    if let Err(e) = send(&mut ws, message, counters).await {
+                        return (true, e);
+                    }
+                }
+                Event::Heartbeat(Beat::Send(message)) => {
+                    if let Err(e) = send(&mut ws, message, counters).await {
+                        return (true, e);
+                    }
+                }
+                Event::Heartbeat(Beat::Missed) => {
+                    let reconnect = self
+                        .config
+                        .heartbeat
+                        .as_ref()
+                        .is_some_and(Heartbeat::reconnects);
+                    if reconnect {
+                        // dropping the connection closes it
+                        return (true, self.shared.stale());
+                    }
+                    let _ = self.incoming.unbounded_send(Err(self.shared.stale()));
+                }
+                Event::Outgoing(None) => {
+                    self.shared.closed.set(true);
+                    let _ = ws.close(Some(1000), None);
+                    return (true, self.shared.failed());
+                }
+            }
+        }
+    }
+}
+
+async fn send(
+    ws: &mut WebSocket,
+    message: Message,
+    counters: &Counters,
+) -> Result<(), WebSocketError> {
+    std::future::poll_fn(|cx| Pin::new(&mut *ws).poll_ready(cx)).await?;
+    let size = message_size(&message);
+    Pin::new(ws).start_send(message)?;
+    counters.sent(size);
+    Ok(())
+}
+
+/// The error closing `ws`, preferring its close event over the `error` before it.
+async fn next_error(ws: &mut WebSocket, mut error: WebSocketError) -> WebSocketError {
+    while let Some(item) = std::future::poll_fn(|cx| Pin::new(&mut *ws).poll_next(cx)).await {
+        if let Err(e) = item {
+            error = e;
+            if let WebSocketError::ConnectionClose(_) = error {
+                break;
+            }
+        }
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn shared(capacity: usize, overflow: OverflowPolicy) -> Shared {
+        let buffer = Buffer { capacity, overflow };
+        let shared = Shared::new("wss://example.com".to_string(), Some(buffer));
+        for i in 0..capacity {
+            shared.push(Message::Text(i.to_string())).unwrap();
+        }
+        shared
+    }
+
+    fn waiting(shared: &Shared) -> Vec<Message> {
+        shared.outgoing.borrow().iter().cloned().collect()
+    }
+
+    #[test]
+    fn buffer_overflows_as_configured() {
+        let text = |text: &str| Message::Text(text.to_string());
+
+        let oldest = shared(2, OverflowPolicy::DropOldest);
+        oldest.push(text("2")).unwrap();
+        assert_eq!(waiting(&oldest), vec![text("1"), text("2")]);
+
+        let newest = shared(2, OverflowPolicy::DropNewest);
+        newest.push(text("2")).unwrap();
+        assert_eq!(waiting(&newest), vec![text("0"), text("1")]);
+
+        let error = shared(2, OverflowPolicy::Error);
+        assert!(matches!(
+            error.push(text("2")),
+            Err(WebSocketError::BufferFull { .. })
+        ));
+        assert_eq!(waiting(&error), vec![text("0"), text("1")]);
+    }
+
+    /// Records the `WebSocket`s the page opens, so that a test can close them as if the server
+    /// did, until the returned guard is dropped.
+    fn record_websockets() -> impl Drop {
+        struct Restore;
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                js_sys::eval("globalThis.WebSocket = globalThis.NativeWebSocket").unwrap();
+            }
+        }
+        js_sys::eval(
+            "globalThis.NativeWebSocket = globalThis.WebSocket;
+             globalThis.openedWebSockets = [];
+             globalThis.WebSocket = class extends globalThis.NativeWebSocket {
+                 constructor(...args) {
+                     super(...args);
+                     globalThis.openedWebSockets.push(this);
+                 }
+             };",
+        )
+        .unwrap();
+        Restore
+    }
+
+    async fn next_state(states: &mut ConnectionStates, expected: ConnectionState) {
+        while let Some(state) = states.next().await {
+            if state == expected {
+                return;
+            }
+        }
+        panic!("the connection never reached {:?}", expected);
+    }
+
+    #[wasm_bindgen_test]
+    async fn reconnects_and_sends_the_waiting_messages() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let _recording = record_websockets();
+        let mut ws = ReconnectingWebSocket::builder(ws_echo_server_url)
+            .initial_backoff(Duration::from_millis(10))
+            .max_backoff(Duration::from_millis(20))
+            .open()
+            .unwrap();
+        let mut states = ws.states();
+        next_state(&mut states, ConnectionState::Open).await;
+        // the echo-server sends its info in the first message
+        let _ = ws.next().await;
+
+        js_sys::eval("globalThis.openedWebSockets[0].close()").unwrap();
+        next_state(&mut states, ConnectionState::Reconnecting { attempt: 1 }).await;
+        ws.send(Message::Text("sent while down".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(ws.shared.outgoing.borrow().len(), 1);
+
+        next_state(&mut states, ConnectionState::Open).await;
+        let _ = ws.next().await;
+        assert_eq!(
+            ws.next().await.unwrap().unwrap(),
+            Message::Text("sent while down".to_string())
+        );
+        assert_eq!(ws.metrics().reconnects, 1);
+        let opened = js_sys::eval("globalThis.openedWebSockets.length").unwrap();
+        assert_eq!(opened.as_f64(), Some(2.0));
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+        };
+        assert_eq!(backoff.delay(0, 0.5), Duration::from_millis(50));
+        assert_eq!(backoff.delay(2, 0.5), Duration::from_millis(200));
+        assert_eq!(backoff.delay(10, 0.5), Duration::from_millis(500));
+        assert_eq!(backoff.delay(u32::MAX, 0.5), Duration::from_millis(500));
+        assert_eq!(backoff.delay(3, 0.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,463 @@
+//! Calls over a WebSocket, speaking [JSON-RPC 2.0](https://www.jsonrpc.org/specification).
+//!
+//! Every [call](RpcClient::call) is sent with an id, and the future it returns resolves with the
+//! reply carrying the same id, however many calls are in flight. The messages which aren't
+//! replies, like the notifications pushed by the server, are passed on to the [`Notifications`]
+//! stream.
+//!
+//! # Example
+//!
+//! ```
+//! use futures::StreamExt;
+//! use gloo_net::websocket::futures::WebSocket;
+//! use gloo_net::websocket::rpc::RpcClient;
+//! use std::time::Duration;
+//! use wasm_bindgen_futures::spawn_local;
+//!
+//! # async fn no_run() -> Result<(), gloo_net::websocket::rpc::RpcError> {
+//! let ws = WebSocket::open("wss://example.com/rpc").unwrap();
+//! let (rpc, mut notifications) = RpcClient::new(ws);
+//!
+//! spawn_local(async move {
+//!     while let Some(Ok(notification)) = notifications.next().await {
+//!         if notification.method == "priceChanged" {
+//!             let (symbol, price): (String, f64) = notification.params_as().unwrap();
+//!         }
+//!     }
+//! });
+//!
+//! let sum: i64 = rpc.call("add", &(1, 2)).await?;
+//! let balance: f64 = rpc
+//!     .call_with_timeout("balance", &["savings"], Duration::from_secs(5))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::Stream;
+use futures_sink::Sink;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error as ThisError;
+
+use crate::websocket::futures::{WebSocket, WebSocketReceiver, WebSocketSender};
+use crate::websocket::{Message, WebSocketError};
+
+/// The error of a call, or of the stream of [`Notifications`].
+#[derive(Debug, ThisError)]
+#[non_exhaustive]
+pub enum RpcError {
+    /// The server answered the call with an error.
+    #[error("{0}")]
+    Remote(RemoteError),
+    /// No reply arrived within the timeout of the call.
+    #[error("the call timed out")]
+    Timeout,
+    /// The connection closed before the reply arrived.
+    #[error("the connection closed before the reply")]
+    Closed,
+    /// The connection failed.
+    #[error("{0}")]
+    WebSocket(WebSocketError),
+    /// The parameters couldn't be encoded, or the result decoded, as JSON.
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    /// The server sent a message which is neither a reply nor a notification.
+    #[error("unexpected message: {0:?}")]
+    UnexpectedMessage(Message),
+}
+
+/// The error object of a reply, telling why the server failed a call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ThisError)]
+#[error("RPC error {code}: {message}")]
+pub struct RemoteError {
+    /// The error code, like `-32601` for an unknown method.
+    pub code: i64,
+    /// A short description of the error.
+    pub message: String,
+    /// Details of the error, if the server gave any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// A message of the server which isn't a reply to a call.
+///
+/// Requests the server sends with an id of their own are passed on as notifications too, and
+/// aren't answered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// The method the server called, like `priceChanged`.
+    pub method: String,
+    /// The parameters, `null` when the server sent none.
+    pub params: Value,
+}
+
+impl Notification {
+    /// Decodes the parameters into `T`.
+    pub fn params_as<T: DeserializeOwned>(&self) -> Result<T, RpcError> {
+        Ok(T::deserialize(&self.params)?)
+    }
+}
+
+/// Makes calls over a WebSocket, see the [module documentation](self).
+///
+/// Clones make calls over the same connection, which is closed once every clone is dropped.
+#[derive(Clone)]
+pub struct RpcClient {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    sender: RefCell<Option<WebSocketSender>>,
+    calls: Rc<Calls>,
+    next_id: Cell<u64>,
+}
+
+/// The calls waiting for a reply, shared with the task reading the connection.
+#[derive(Default)]
+struct Calls {
+    pending: RefCell<HashMap<u64, oneshot::Sender<Result<Value, RemoteError>>>>,
+    closed: Cell<bool>,
+}
+
+impl RpcClient {
+    /// Makes calls over `ws`, returning the client and the stream of the [`Notification`]s of
+    /// the server.
+    ///
+    /// A task reads the connection until it closes, passing on replies and notifications, so
+    /// the notifications can be left unread. This must be called in a wasm-bindgen-futures
+    /// context.
+    pub fn new(ws: WebSocket) -> (Self, Notifications) {
+        let (sender, receiver) = ws.into_halves();
+        let calls = Rc::new(Calls::default());
+        let (notifications, notifications_receiver) = mpsc::unbounded();
+        wasm_bindgen_futures::spawn_local(read(receiver, Rc::clone(&calls), notifications));
+        let client = Self {
+            inner: Rc::new(Inner {
+                sender: RefCell::new(Some(sender)),
+                calls,
+                next_id: Cell::new(1),
+            }),
+        };
+        (
+            client,
+            Notifications {
+                receiver: notifications_receiver,
+            },
+        )
+    }
+
+    /// Calls `method` with `params`, and waits for its result, however long it takes.
+    ///
+    /// `params` is usually a tuple or a slice, sent as an array, or a struct, sent as an object.
+    pub async fn call<P, R>(&self, method: &str, params: &P) -> Result<R, RpcError>
+    where
+        P: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        self.call_inner(method, params, None).await
+    }
+
+    /// Calls `method` with `params`, and waits for its result, failing with
+    /// [`RpcError::Timeout`] if it doesn't arrive within `timeout`.
+    pub async fn call_with_timeout<P, R>(
+        &self,
+        method: &str,
+        params: &P,
+        timeout: Duration,
+    ) -> Result<R, RpcError>
+    where
+        P: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        self.call_inner(method, params, Some(timeout)).await
+    }
+
+    /// Sends a notification, a call without reply, of `method` with `params`.
+    pub async fn notify<P: Serialize + ?Sized>(
+        &self,
+        method: &str,
+        params: &P,
+    ) -> Result<(), RpcError> {
+        self.send(&Request {
+            jsonrpc: "2.0",
+            id: None,
+            method,
+            params,
+        })
+        .await
+    }
+
+    async fn call_inner<P, R>(
+        &self,
+        method: &str,
+        params: &P,
+        timeout: Option<Duration>,
+    ) -> Result<R, RpcError>
+    where
+        P: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let calls = &self.inner.calls;
+        if calls.closed.get() {
+            return Err(RpcError::Closed);
+        }
+        let id = self.inner.next_id.get();
+        self.inner.next_id.set(id + 1);
+        let (reply, mut replied) = oneshot::channel();
+        calls.pending.borrow_mut().insert(id, reply);
+        // forgets the call however this returns, including when this future is dropped
+        let _pending = Pending { calls, id };
+
+        self.send(&Request {
+            jsonrpc: "2.0",
+            id: Some(id),
+            method,
+            params,
+        })
+        .await?;
+
+        let mut timer = timeout.map(gloo_timers::future::sleep);
+        let result = std::future::poll_fn(|cx| {
+            if let Poll::Ready(result) = Pin::new(&mut replied).poll(cx) {
+                return Poll::Ready(result.map_err(|_| RpcError::Closed));
+            }
+            let timed_out = timer
+                .as_mut()
+                .is_some_and(|timer| Pin::new(timer).poll(cx).is_ready());
+            if timed_out {
+                Poll::Ready(Err(RpcError::Timeout))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await?;
+        Ok(R::deserialize(result.map_err(RpcError::Remote)?)?)
+    }
+
+    async fn send<P: Serialize + ?Sized>(&self, request: &Request<'_, P>) -> Result<(), RpcError> {
+        let message = Message::Text(serde_json::to_string(request)?);
+        std::future::poll_fn(|cx| match &mut *self.inner.sender.borrow_mut() {
+            Some(sender) => Pin::new(sender).poll_ready(cx),
+            None => Poll::Ready(Ok(())),
+        })
+        .await
+        .map_err(RpcError::WebSocket)?;
+        if self.inner.calls.closed.get() {
+            return Err(RpcError::Closed);
+        }
+        match &mut *self.inner.sender.borrow_mut() {
+            Some(sender) => Pin::new(sender)
+                .start_send(message)
+                .map_err(RpcError::WebSocket),
+            None => Err(RpcError::Closed),
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.get_mut().take() {
+            let _ = sender.close(Some(1000), None);
+        }
+    }
+}
+
+impl fmt::Debug for RpcClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RpcClient")
+            .field("pending", &self.inner.calls.pending.borrow().len())
+            .field("closed", &self.inner.calls.closed.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A call waiting for its reply, forgotten when dropped.
+struct Pending<'a> {
+    calls: &'a Calls,
+    id: u64,
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.calls.pending.borrow_mut().remove(&self.id);
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a, P: ?Sized> {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    method: &'a str,
+    params: &'a P,
+}
+
+/// The messages of the server which aren't replies, see [`RpcClient::new`].
+///
+/// The stream reports messages it can't make sense of, and the failure of the connection, as
+/// errors, and ends when the connection closes.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Notifications {
+    receiver: mpsc::UnboundedReceiver<Result<Notification, RpcError>>,
+}
+
+impl Stream for Notifications {
+    type Item = Result<Notification, RpcError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+/// A message of the server.
+#[derive(Debug, PartialEq)]
+enum Incoming {
+    Reply {
+        id: u64,
+        result: Result<Value, RemoteError>,
+    },
+    Notification(Notification),
+}
+
+impl Incoming {
+    fn parse(text: &str) -> Option<Self> {
+        let mut message = match serde_json::from_str::<Value>(text).ok()? {
+            Value::Object(message) => message,
+            _ => return None,
+        };
+        if let Some(Value::String(method)) = message.remove("method") {
+            return Some(Self::Notification(Notification {
+                method,
+                params: message.remove("params").unwrap_or(Value::Null),
+            }));
+        }
+        let id = message.get("id")?.as_u64()?;
+        let result = match (message.remove("result"), message.remove("error")) {
+            (_, Some(error)) => Err(serde_json::from_value(error).ok()?),
+            (Some(result), None) => Ok(result),
+            (None, None) => return None,
+        };
+        Some(Self::Reply { id, result })
+    }
+}
+
+/// Reads the connection until it closes, passing on replies and notifications.
+async fn read(
+    mut receiver: WebSocketReceiver,
+    calls: Rc<Calls>,
+    notifications: mpsc::UnboundedSender<Result<Notification, RpcError>>,
+) {
+    while let Some(item) = std::future::poll_fn(|cx| Pin::new(&mut receiver).poll_next(cx)).await {
+        let message = match item {
+            Ok(message) => message,
+            Err(WebSocketError::ConnectionClose(_)) => break,
+            Err(error) => {
+                let _ = notifications.unbounded_send(Err(RpcError::WebSocket(error)));
+                continue;
+            }
+        };
+        let incoming = match &message {
+            Message::Text(text) => Incoming::parse(text),
+            Message::Bytes(bytes) => std::str::from_utf8(bytes).ok().and_then(Incoming::parse),
+        };
+        match incoming {
+            Some(Incoming::Reply { id, result }) => {
+                if let Some(reply) = calls.pending.borrow_mut().remove(&id) {
+                    let _ = reply.send(result);
+                }
+            }
+            Some(Incoming::Notification(notification)) => {
+                let _ = notifications.unbounded_send(Ok(notification));
+            }
+            None => {
+                let _ = notifications.unbounded_send(Err(RpcError::UnexpectedMessage(message)));
+            }
+        }
+    }
+    calls.closed.set(true);
+    // dropping the senders fails the calls still waiting with `RpcError::Closed`
+    calls.pending.borrow_mut().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_incoming_messages() {
+        assert_eq!(
+            Incoming::parse(r#"{"jsonrpc":"2.0","id":3,"result":[1,2]}"#),
+            Some(Incoming::Reply {
+                id: 3,
+                result: Ok(json!([1, 2]))
+            })
+        );
+        assert_eq!(
+            Incoming::parse(
+                r#"{"jsonrpc":"2.0","id":4,"error":{"code":-32601,"message":"Method not found"}}"#
+            ),
+            Some(Incoming::Reply {
+                id: 4,
+                result: Err(RemoteError {
+                    code: -32601,
+                    message: "Method not found".to_string(),
+                    data: None,
+                })
+            })
+        );
+        assert_eq!(
+            Incoming::parse(r#"{"jsonrpc":"2.0","method":"tick","params":{"n":1}}"#),
+            Some(Incoming::Notification(Notification {
+                method: "tick".to_string(),
+                params: json!({"n": 1}),
+            }))
+        );
+        assert_eq!(
+            Incoming::parse(r#"{"jsonrpc":"2.0","method":"ping"}"#),
+            Some(Incoming::Notification(Notification {
+                method: "ping".to_string(),
+                params: Value::Null,
+            }))
+        );
+        assert_eq!(Incoming::parse(r#"{"id":5}"#), None);
+        assert_eq!(Incoming::parse("hello"), None);
+    }
+
+    #[test]
+    fn encodes_requests() {
+        let request = Request {
+            jsonrpc: "2.0",
+            id: Some(1),
+            method: "add",
+            params: &(1, 2),
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]})
+        );
+        let notification = Request {
+            jsonrpc: "2.0",
+            id: None,
+            method: "log",
+            params: &["hi"],
+        };
+        assert_eq!(
+            serde_json::to_value(&notification).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "log", "params": ["hi"]})
+        );
+    }
+}
@@ -0,0 +1,659 @@
+//! One WebSocket shared by every tab of an origin, living in a `SharedWorker`.
+//!
+//! Each tab opening a [`WebSocket`] opens its own connection to the server, so a user with ten
+//! tabs holds ten connections. With a [`SharedWebSocket`], the connection lives in a
+//! `SharedWorker` instead, and each tab talks to it over a `MessagePort`: the first tab opening
+//! a URL opens the connection, later tabs join it, every tab receives every message, and the
+//! connection closes once the last tab leaves.
+//!
+//! The worker script runs the wasm of the app and calls [`listen`]. Tabs then open
+//! connections through it with [`SharedWebSocket::open`], passing the URL of that script.
+//!
+//! # Example
+//!
+//! ```
+//! use futures::{SinkExt, StreamExt};
+//! use gloo_net::websocket::shared::{self, SharedWebSocket};
+//! use gloo_net::websocket::Message;
+//!
+//! // in the shared worker
+//! # fn worker() {
+//! shared::listen().forget();
+//! # }
+//!
+//! // in every tab
+//! # async fn no_run() {
+//! let mut ws = SharedWebSocket::open("/socket-worker.js", "wss://example.com/live").unwrap();
+//! ws.send(Message::Text("hello".to_string())).await.unwrap();
+//! while let Some(Ok(message)) = ws.next().await {
+//!     // every tab receives the message
+//! }
+//! # }
+//! ```
+//!
+//! [`WebSocket`]: crate::websocket::futures::WebSocket
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+use futures_sink::Sink;
+use gloo_utils::errors::JsError;
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{MessageEvent, MessagePort, SharedWorker, SharedWorkerGlobalScope};
+
+use crate::js_to_js_error;
+use crate::websocket::events::{CloseEvent, ErrorEvent};
+use crate::websocket::futures::{WebSocket, WebSocketSender};
+use crate::websocket::{Message, State, WebSocketError};
+
+/// A WebSocket living in a `SharedWorker`, see the [module documentation](self).
+///
+/// It sends and receives like a [`WebSocket`], whose messages, errors and close event it
+/// passes on. Sending waits until the connection is open.
+///
+/// Dropping it leaves the connection, which the worker closes once every tab left. Browsers
+/// don't tell a worker when a tab closes, so tabs which go away without dropping theirs keep
+/// the connection open until the worker stops, along with the last tab.
+///
+/// [`WebSocket`]: crate::websocket::futures::WebSocket
+#[must_use = "streams do nothing unless polled"]
+pub struct SharedWebSocket {
+    worker: SharedWorker,
+    port: MessagePort,
+    shared: Rc<TabState>,
+    receiver: mpsc::UnboundedReceiver<Result<Message, WebSocketError>>,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+    _onerror: Closure<dyn FnMut(JsValue)>,
+}
+
+/// What a tab knows of the connection, updated by the messages of the worker.
+struct TabState {
+    url: String,
+    state: Cell<State>,
+    protocol: RefCell<String>,
+    extensions: RefCell<String>,
+    sink_waker: RefCell<Option<Waker>>,
+}
+
+impl SharedWebSocket {
+    /// Opens a connection to `url` through the shared worker running the script at
+    /// `worker_url`, or joins the one the worker already holds.
+    ///
+    /// This errors if the browser doesn't support `SharedWorker`, like Chrome on Android. A
+    /// worker which fails to start, e.g. because its script can't be loaded, and a connection
+    /// failing to open, are reported as a [`WebSocketError::ConnectionError`], followed by the
+    /// close event.
+    pub fn open(worker_url: &str, url: &str) -> Result<Self, JsError> {
+        Self::open_with_protocols::<&str>(worker_url, url, &[])
+    }
+
+    /// Opens a connection to `url` with sub-`protocols`, see [`open`](Self::open).
+    ///
+    /// Tabs only share the connections they open with the same URL and protocols.
+    pub fn open_with_protocols<S: AsRef<str>>(
+        worker_url: &str,
+        url: &str,
+        protocols: &[S],
+    ) -> Result<Self, JsError> {
+        let worker = SharedWorker::new(worker_url).map_err(js_to_js_error)?;
+        let port = worker.port();
+        let shared = Rc::new(TabState {
+            url: url.to_string(),
+            state: Cell::new(State::Connecting),
+            protocol: RefCell::default(),
+            extensions: RefCell::default(),
+            sink_waker: RefCell::default(),
+        });
+        let (sender, receiver) = mpsc::unbounded();
+        // the `error` event of the worker, which failed to load or start
+        let onerror = {
+            let shared = Rc::clone(&shared);
+            let sender = sender.clone();
+            Closure::<dyn FnMut(JsValue)>::new(move |_event: JsValue| {
+                if shared.state.get() == State::Closed {
+                    return;
+                }
+                shared.set_state(State::Closed);
+                let event = ErrorEvent::new(
+                    shared.url.clone(),
+                    Some("the shared worker couldn't be started".to_string()),
+                    None,
+                    false,
+                );
+                let _ = sender.unbounded_send(Err(WebSocketError::ConnectionError(event)));
+                let event = CloseEvent {
+                    url: shared.url.clone(),
+                    code: 1006,
+                    reason: String::new(),
+                    was_clean: false,
+                };
+                let _ = sender.unbounded_send(Err(WebSocketError::ConnectionClose(event)));
+                sender.close_channel();
+            })
+        };
+        worker.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+        let onmessage = {
+            let shared = Rc::clone(&shared);
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                let data = event.data();
+                match field(&data, "type").as_string().as_deref() {
+                    Some("open") => {
+                        *shared.protocol.borrow_mut() =
+                            field(&data, "protocol").as_string().unwrap_or_default();
+                        *shared.extensions.borrow_mut() =
+                            field(&data, "extensions").as_string().unwrap_or_default();
+                        shared.set_state(State::Open);
+                    }
+                    Some("message") => {
+                        if let Some(message) = decode(&field(&data, "data")) {
+                            let _ = sender.unbounded_send(Ok(message));
+                        }
+                    }
+                    Some("error") => {
+                        let event = ErrorEvent::new(
+                            shared.url.clone(),
+                            field(&data, "message").as_string(),
+                            None,
+                            shared.state.get() == State::Open,
+                        );
+                        let _ = sender.unbounded_send(Err(WebSocketError::ConnectionError(event)));
+                    }
+                    Some("close") => {
+                        shared.set_state(State::Closed);
+                        let event = CloseEvent {
+                            url: shared.url.clone(),
+                            code: field(&data, "code").as_f64().unwrap_or(1006.0) as u16,
+                            reason: field(&data, "reason").as_string().unwrap_or_default(),
+                            was_clean: field(&data, "wasClean").is_truthy(),
+                        };
+                        let _ = sender.unbounded_send(Err(WebSocketError::ConnectionClose(event)));
+                        sender.close_channel();
+                    }
+                    _ => {}
+                }
+            })
+        };
+        port.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+        let protocols = protocols
+            .iter()
+            .map(|protocol| JsValue::from_str(protocol.as_ref()))
+            .collect::<Array>();
+        post(
+            &port,
+            "open",
+            &[
+                ("url", JsValue::from_str(url)),
+                ("protocols", protocols.into()),
+            ],
+        );
+        Ok(Self {
+            worker,
+            port,
+            shared,
+            receiver,
+            _onmessage: onmessage,
+            _onerror: onerror,
+        })
+    }
+
+    /// The state of the connection, as last reported by the worker.
+    pub fn state(&self) -> State {
+        self.shared.state.get()
+    }
+
+    /// The sub-protocol the server selected, empty until the connection is open, or when the
+    /// server selected none.
+    pub fn protocol(&self) -> String {
+        self.shared.protocol.borrow().clone()
+    }
+
+    /// The extensions in use, see [`WebSocket::extensions`].
+    ///
+    /// [`WebSocket::extensions`]: crate::websocket::futures::WebSocket::extensions
+    pub fn extensions(&self) -> String {
+        self.shared.extensions.borrow().clone()
+    }
+}
+
+impl TabState {
+    fn set_state(&self, state: State) {
+        self.state.set(state);
+        if let Some(waker) = self.sink_waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Stream for SharedWebSocket {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Sink<Message> for SharedWebSocket {
+    type Error = WebSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.shared.state.get() {
+            State::Connecting => {
+                *self.shared.sink_waker.borrow_mut() = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            State::Open => Poll::Ready(Ok(())),
+            State::Closing | State::Closed => Poll::Ready(Err(WebSocketError::ConnectionError(
+                ErrorEvent::unknown(self.shared.url.clone()),
+            ))),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        post(&self.port, "send", &[("data", encode(&item))]);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for SharedWebSocket {
+    fn drop(&mut self) {
+        post(&self.port, "leave", &[]);
+        self.worker.set_onerror(None);
+        self.port.set_onmessage(None);
+        self.port.close();
+    }
+}
+
+impl fmt::Debug for SharedWebSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedWebSocket")
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Starts holding the connections of the tabs, in the shared worker, until the returned
+/// listener is dropped.
+///
+/// See the [module documentation](self).
+pub fn listen() -> SharedWorkerListener {
+    let scope: SharedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let hub = Rc::new(Hub::default());
+    let onconnect = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+        if let Ok(port) = event.ports().get(0).dyn_into::<MessagePort>() {
+            hub.accept(port);
+        }
+    });
+    scope.set_onconnect(Some(onconnect.as_ref().unchecked_ref()));
+    SharedWorkerListener {
+        scope,
+        _onconnect: onconnect,
+    }
+}
+
+/// Holds the connections of the tabs, until it is dropped.
+///
+/// See [`listen`].
+#[must_use = "the connections are only held until this is dropped"]
+pub struct SharedWorkerListener {
+    scope: SharedWorkerGlobalScope,
+    _onconnect: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl SharedWorkerListener {
+    /// Keeps holding the connections for the lifetime of the worker.
+    pub fn forget(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for SharedWorkerListener {
+    fn drop(&mut self) {
+        self.scope.set_onconnect(None);
+    }
+}
+
+impl fmt::Debug for SharedWorkerListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedWorkerListener")
+            .finish_non_exhaustive()
+    }
+}
+
+/// The connections of the worker, by URL and protocols, and the ports of the tabs.
+#[derive(Default)]
+struct Hub {
+    connections: RefCell<HashMap<String, Connection>>,
+    ports: RefCell<HashMap<u64, Port>>,
+    next_id: Cell<u64>,
+}
+
+struct Connection {
+    id: u64,
+    /// The sending half, once the connection is open.
+    sender: Option<WebSocketSender>,
+    /// The messages sent by tabs before the connection opened.
+    waiting: Vec<Message>,
+    /// The ports of the tabs sharing the connection.
+    ports: Vec<u64>,
+    /// The `open` message, once the connection is open, for the tabs joining later.
+    opened: Option<JsValue>,
+}
+
+struct Port {
+    port: MessagePort,
+    /// The connection of the tab, once it asked for one.
+    key: Option<String>,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl Hub {
+    fn next_id(&self) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    /// Listens to the messages of the tab at the other end of `port`.
+    fn accept(self: &Rc<Self>, port: MessagePort) {
+        let id = self.next_id();
+        let onmessage = {
+            let hub = Rc::downgrade(self);
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                if let Some(hub) = hub.upgrade() {
+                    hub.receive(id, event.data());
+                }
+            })
+        };
+        port.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        self.ports.borrow_mut().insert(
+            id,
+            Port {
+                port,
+                key: None,
+                _onmessage: onmessage,
+            },
+        );
+    }
+
+    /// Handles a message of the tab of port `id`.
+    fn receive(self: &Rc<Self>, id: u64, data: JsValue) {
+        match field(&data, "type").as_string().as_deref() {
+            Some("open") => {
+                let url = field(&data, "url").as_string().unwrap_or_default();
+                let protocols: Vec<String> = Array::from(&field(&data, "protocols"))
+                    .iter()
+                    .filter_map(|protocol| protocol.as_string())
+                    .collect();
+                self.join(id, url, protocols);
+            }
+            Some("send") => {
+                let Some(message) = decode(&field(&data, "data")) else {
+                    return;
+                };
+                let key = self
+                    .ports
+                    .borrow()
+                    .get(&id)
+                    .and_then(|port| port.key.clone());
+                let mut connections = self.connections.borrow_mut();
+                let Some(connection) = key.and_then(|key| connections.get_mut(&key)) else {
+                    return;
+                };
+                match &mut connection.sender {
+                    Some(sender) => {
+                        let _ = Pin::new(sender).start_send(message);
+                    }
+                    None => connection.waiting.push(message),
+                }
+            }
+            Some("leave") => {
+                // the closure running this is dropped along with the port, so not right away
+                let hub = Rc::clone(self);
+                wasm_bindgen_futures::spawn_local(async move { hub.leave(id) });
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds the tab of port `id` to the connection to `url` with `protocols`, opening it if
+    /// no tab did.
+    fn join(self: &Rc<Self>, id: u64, url: String, protocols: Vec<String>) {
+        let key = format!("{} {}", protocols.join(","), url);
+        let Some(port) = self.ports.borrow_mut().get_mut(&id).map(|port| {
+            port.key = Some(key.clone());
+            port.port.clone()
+        }) else {
+            return;
+        };
+        if let Some(connection) = self.connections.borrow_mut().get_mut(&key) {
+            connection.ports.push(id);
+            if let Some(opened) = &connection.opened {
+                let _ = port.post_message(opened);
+            }
+            return;
+        }
+
+        let ws = match WebSocket::open_with_protocols(&url, &protocols) {
+            Ok(ws) => ws,
+            Err(_) => {
+                post(&port, "error", &[]);
+                post(&port, "close", &[("code", JsValue::from(1006))]);
+                return;
+            }
+        };
+        let connection = Connection {
+            id: self.next_id(),
+            sender: None,
+            waiting: Vec::new(),
+            ports: vec![id],
+            opened: None,
+        };
+        let connection_id = connection.id;
+        self.connections
+            .borrow_mut()
+            .insert(key.clone(), connection);
+        wasm_bindgen_futures::spawn_local(Rc::clone(self).run(ws, key, connection_id));
+    }
+
+    /// Removes the tab of port `id`, closing its connection if it was the last tab.
+    fn leave(&self, id: u64) {
+        let Some(port) = self.ports.borrow_mut().remove(&id) else {
+            return;
+        };
+        port.port.close();
+        let Some(key) = port.key else {
+            return;
+        };
+        let mut connections = self.connections.borrow_mut();
+        let Some(connection) = connections.get_mut(&key) else {
+            return;
+        };
+        connection.ports.retain(|port| *port != id);
+        if connection.ports.is_empty() {
+            if let Some(connection) = connections.remove(&key) {
+                if let Some(sender) = connection.sender {
+                    let _ = sender.close(Some(1000), None);
+                }
+            }
+        }
+    }
+
+    /// Posts `message` to the tabs of the connection `key`.
+    fn broadcast(&self, key: &str, message: &JsValue) {
+        let connections = self.connections.borrow();
+        let Some(connection) = connections.get(key) else {
+            return;
+        };
+        let ports = self.ports.borrow();
+        for id in &connection.ports {
+            if let Some(port) = ports.get(id) {
+                let _ = port.port.post_message(message);
+            }
+        }
+    }
+
+    /// Opens `ws`, then passes its messages on to the tabs until it closes.
+    async fn run(self: Rc<Self>, mut ws: WebSocket, key: String, id: u64) {
+        let opened = std::future::poll_fn(|cx| Pin::new(&mut ws).poll_ready(cx)).await;
+        let is_current = |hub: &Self| {
+            hub.connections
+                .borrow()
+                .get(&key)
+                .is_some_and(|connection| connection.id == id)
+        };
+        // every tab left while connecting, dropping `ws` closes it
+        if !is_current(&self) {
+            return;
+        }
+        if opened.is_ok() && ws.state() == State::Open {
+            let message = object(
+                "open",
+                &[
+                    ("protocol", JsValue::from(ws.protocol())),
+                    ("extensions", JsValue::from(ws.extensions())),
+                ],
+            );
+            let (mut sender, mut receiver) = ws.into_halves();
+            if let Some(connection) = self.connections.borrow_mut().get_mut(&key) {
+                for message in connection.waiting.drain(..) {
+                    let _ = Pin::new(&mut sender).start_send(message);
+                }
+                connection.sender = Some(sender);
+                connection.opened = Some(message.clone());
+            }
+            self.broadcast(&key, &message);
+
+            let mut closed = false;
+            while let Some(item) =
+                std::future::poll_fn(|cx| Pin::new(&mut receiver).poll_next(cx)).await
+            {
+                if !is_current(&self) {
+                    return;
+                }
+                let message = match item {
+                    Ok(message) => object("message", &[("data", encode(&message))]),
+                    Err(WebSocketError::ConnectionClose(event)) => {
+                        closed = true;
+                        close_message(&event)
+                    }
+                    Err(WebSocketError::ConnectionError(event)) => {
+                        let message = event.message.map(JsValue::from).unwrap_or_default();
+                        object("error", &[("message", message)])
+                    }
+                    Err(_) => object("error", &[]),
+                };
+                self.broadcast(&key, &message);
+            }
+            if !closed && is_current(&self) {
+                self.broadcast(&key, &object("close", &[("code", JsValue::from(1006))]));
+            }
+        } else {
+            self.broadcast(&key, &object("error", &[]));
+            self.broadcast(&key, &object("close", &[("code", JsValue::from(1006))]));
+        }
+        if is_current(&self) {
+            self.connections.borrow_mut().remove(&key);
+        }
+    }
+}
+
+fn close_message(event: &CloseEvent) -> JsValue {
+    object(
+        "close",
+        &[
+            ("code", JsValue::from(event.code)),
+            ("reason", JsValue::from(event.reason.as_str())),
+            ("wasClean", JsValue::from(event.was_clean)),
+        ],
+    )
+}
+
+/// Builds a message of the port protocol: an object with a `type`, and `fields`.
+fn object(kind: &str, fields: &[(&str, JsValue)]) -> JsValue {
+    let object = Object::new();
+    let _ = Reflect::set(&object, &"type".into(), &kind.into());
+    for (name, value) in fields {
+        let _ = Reflect::set(&object, &JsValue::from_str(name), value);
+    }
+    object.into()
+}
+
+fn post(port: &MessagePort, kind: &str, fields: &[(&str, JsValue)]) {
+    let _ = port.post_message(&object(kind, fields));
+}
+
+fn field(object: &JsValue, name: &str) -> JsValue {
+    Reflect::get(object, &JsValue::from_str(name)).unwrap_or(JsValue::UNDEFINED)
+}
+
+/// Text messages cross the port as strings, and binary ones as `Uint8Array`s.
+fn encode(message: &Message) -> JsValue {
+    match message {
+        Message::Text(text) => JsValue::from_str(text),
+        Message::Bytes(bytes) => Uint8Array::from(bytes.as_slice()).into(),
+    }
+}
+
+fn decode(data: &JsValue) -> Option<Message> {
+    if let Some(text) = data.as_string() {
+        return Some(Message::Text(text));
+    }
+    data.dyn_ref::<Uint8Array>()
+        .map(|bytes| Message::Bytes(bytes.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn messages_cross_the_port() {
+        for message in [
+            Message::Text("hello".to_string()),
+            Message::Bytes(vec![1, 2, 3]),
+        ] {
+            assert_eq!(decode(&encode(&message)), Some(message));
+        }
+        assert_eq!(decode(&JsValue::from(1)), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn worker_failing_to_start_is_reported() {
+        let mut ws = SharedWebSocket::open("/no-such-worker.js", "wss://example.com/live").unwrap();
+
+        match ws.next().await {
+            Some(Err(WebSocketError::ConnectionError(event))) => {
+                assert_eq!(event.url, "wss://example.com/live");
+            }
+            item => panic!("expected a connection error, got {:?}", item),
+        }
+        match ws.next().await {
+            Some(Err(WebSocketError::ConnectionClose(event))) => assert_eq!(event.code, 1006),
+            item => panic!("expected the close event, got {:?}", item),
+        }
+        assert!(ws.next().await.is_none());
+        assert_eq!(ws.state(), State::Closed);
+        assert!(ws.send(Message::Text("hello".to_string())).await.is_err());
+    }
+}
@@ -0,0 +1,957 @@
+//! A [Socket.IO](https://socket.io/docs/v4/) client, speaking version 5 of the protocol over
+//! Engine.IO 4.
+//!
+//! A [`SocketIo`] client holds one connection to the server, opened again whenever it drops,
+//! like a [`ReconnectingWebSocket`]. Each [`Socket`] joins a namespace over that connection, and
+//! joins it again on every new connection. Events are [emitted](Socket::emit), optionally
+//! waiting for the server to [acknowledge](Socket::emit_with_ack) them, and the events of the
+//! server are received as [streams](Socket::on).
+//!
+//! Only the WebSocket transport of Engine.IO is implemented. Socket.IO servers accept it unless
+//! configured otherwise, but there is no fallback to the HTTP long-polling transport: a client
+//! behind a proxy blocking WebSockets, or a server only allowing long-polling, can't connect.
+//! Events with binary attachments aren't supported, and are dropped.
+//!
+//! # Example
+//!
+//! ```
+//! use futures::StreamExt;
+//! use gloo_net::websocket::socketio::SocketIo;
+//! use std::time::Duration;
+//!
+//! # async fn no_run() -> Result<(), gloo_net::websocket::socketio::SocketIoError> {
+//! let io = SocketIo::connect("https://example.com").unwrap();
+//! let chat = io.socket("/chat");
+//!
+//! let mut messages = chat.on("message");
+//! chat.emit("join", &("lobby",))?;
+//! let members: Vec<String> = chat
+//!     .emit_with_ack("members", &("lobby",), Duration::from_secs(5))
+//!     .await?;
+//!
+//! while let Some(Ok(event)) = messages.next().await {
+//!     let (author, text): (String, String) = event.args_as()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ReconnectingWebSocket`]: crate::websocket::ReconnectingWebSocket
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::Stream;
+use futures_sink::Sink;
+use gloo_utils::errors::JsError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error as ThisError;
+
+use crate::websocket::futures::websocket_url;
+use crate::websocket::{ConnectionState, Message, ReconnectingWebSocket, WebSocketError};
+
+/// The error of an emit, or of a stream of events.
+#[derive(Debug, ThisError)]
+#[non_exhaustive]
+pub enum SocketIoError {
+    /// The server refused to let the socket join its namespace, e.g. because the credentials
+    /// are invalid.
+    #[error("the server refused to join the namespace: {message}")]
+    Refused {
+        /// Why the server refused.
+        message: String,
+        /// Details the server gave, if any.
+        data: Option<Value>,
+    },
+    /// The acknowledgement didn't arrive within the timeout of the emit.
+    #[error("the acknowledgement timed out")]
+    Timeout,
+    /// The client was dropped, the connection closed for good, or the socket left its
+    /// namespace.
+    #[error("the socket is closed")]
+    Closed,
+    /// The connection failed.
+    #[error("{0}")]
+    WebSocket(WebSocketError),
+    /// The arguments couldn't be encoded, or decoded, as JSON.
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A Socket.IO client, see the [module documentation](self).
+///
+/// Clones share the same connection, which is closed once every clone and every [`Socket`] are
+/// dropped.
+#[derive(Clone)]
+pub struct SocketIo {
+    client: Rc<Client>,
+}
+
+impl SocketIo {
+    /// Connects to the Socket.IO server at `url`, like `https://example.com`, at the default
+    /// path `/socket.io/`.
+    pub fn connect(url: &str) -> Result<Self, JsError> {
+        Self::builder(url).connect()
+    }
+
+    /// Starts configuring a connection to the Socket.IO server at `url`.
+    pub fn builder(url: &str) -> SocketIoBuilder {
+        SocketIoBuilder {
+            url: url.to_string(),
+            path: "/socket.io/".to_string(),
+            auth: None,
+        }
+    }
+
+    /// Joins `namespace`, like `/` or `/chat`, unless a socket of this client already did.
+    ///
+    /// The events emitted before the server accepted the socket wait to be sent until it does.
+    pub fn socket(&self, namespace: &str) -> Socket {
+        let shared = &self.client.shared;
+        let joined = shared.namespaces.borrow().contains_key(namespace);
+        if !joined {
+            shared
+                .namespaces
+                .borrow_mut()
+                .insert(namespace.to_string(), Namespace::default());
+            if shared.engine_open.get() {
+                let packet = Packet::connect(namespace, shared.auth.clone());
+                let _ = shared.outgoing.unbounded_send(packet.into_message());
+            }
+        }
+        Socket {
+            client: Rc::clone(&self.client),
+            namespace: namespace.to_string(),
+        }
+    }
+}
+
+impl fmt::Debug for SocketIo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocketIo")
+            .field("namespaces", &self.client.shared.namespaces.borrow().len())
+            .field("closed", &self.client.shared.closed.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Configures a [`SocketIo`] client.
+#[derive(Debug)]
+pub struct SocketIoBuilder {
+    url: String,
+    path: String,
+    auth: Option<Value>,
+}
+
+impl SocketIoBuilder {
+    /// Sets the path the server is listening at. Defaults to `/socket.io/`.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Sends `auth` to the server every time a socket joins a namespace, like a token.
+    pub fn auth(mut self, auth: Value) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Connects, and keeps connecting again in the background until the client is dropped.
+    ///
+    /// This fails like [`ReconnectingWebSocket::open`]. It must be called in a
+    /// wasm-bindgen-futures context.
+    pub fn connect(self) -> Result<SocketIo, JsError> {
+        let url = format!(
+            "{}/{}?EIO=4&transport=websocket",
+            websocket_url(&self.url).trim_end_matches('/'),
+            self.path.trim_start_matches('/'),
+        );
+        let ws = ReconnectingWebSocket::open(&url)?;
+        let (outgoing, outgoing_receiver) = mpsc::unbounded();
+        let shared = Rc::new(Shared {
+            auth: self.auth,
+            outgoing,
+            namespaces: RefCell::default(),
+            acks: RefCell::default(),
+            next_ack: Cell::new(0),
+            engine_open: Cell::new(false),
+            closed: Cell::new(false),
+        });
+        wasm_bindgen_futures::spawn_local(drive(ws, outgoing_receiver, Rc::clone(&shared)));
+        Ok(SocketIo {
+            client: Rc::new(Client { shared }),
+        })
+    }
+}
+
+/// A socket joining one namespace of the server, see [`SocketIo::socket`].
+///
+/// Clones are handles to the same socket.
+#[derive(Clone)]
+pub struct Socket {
+    client: Rc<Client>,
+    namespace: String,
+}
+
+impl Socket {
+    /// The namespace of the socket.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// The id the server gave the socket, or `None` while it didn't accept the socket.
+    pub fn id(&self) -> Option<String> {
+        let namespaces = self.client.shared.namespaces.borrow();
+        namespaces.get(&self.namespace)?.sid.clone()
+    }
+
+    /// Whether the server accepted the socket in its namespace, over the current connection.
+    pub fn is_connected(&self) -> bool {
+        self.id().is_some()
+    }
+
+    /// Emits `event` with `args`, without waiting for the server to acknowledge it.
+    ///
+    /// `args` is usually a tuple of the arguments, like `("lobby",)`, sent as separate
+    /// arguments. Any other value is sent as the one argument, except `()`, which sends none.
+    /// A list meant as one argument is wrapped in a tuple, like `(&list,)`.
+    pub fn emit<A: Serialize + ?Sized>(&self, event: &str, args: &A) -> Result<(), SocketIoError> {
+        let packet = Packet::event(&self.namespace, None, event, args)?;
+        self.client.send(&self.namespace, packet.into_message())
+    }
+
+    /// Emits `event` with `args`, see [`emit`](Self::emit), and waits for the arguments of the
+    /// acknowledgement of the server, failing with [`SocketIoError::Timeout`] if it doesn't
+    /// arrive within `timeout`.
+    ///
+    /// The acknowledgement arguments are decoded as a tuple, or as the one value when `R`
+    /// isn't a tuple or a list.
+    pub async fn emit_with_ack<A, R>(
+        &self,
+        event: &str,
+        args: &A,
+        timeout: Duration,
+    ) -> Result<R, SocketIoError>
+    where
+        A: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let shared = &self.client.shared;
+        let id = shared.next_ack.get();
+        shared.next_ack.set(id + 1);
+        let packet = Packet::event(&self.namespace, Some(id), event, args)?;
+        let (ack, mut acked) = oneshot::channel();
+        shared.acks.borrow_mut().insert(id, ack);
+        // forgets the ack however this returns, including when this future is dropped
+        let _pending = PendingAck { shared, id };
+        self.client.send(&self.namespace, packet.into_message())?;
+
+        let mut timer = gloo_timers::future::sleep(timeout);
+        let args = std::future::poll_fn(|cx| {
+            if let Poll::Ready(args) = Pin::new(&mut acked).poll(cx) {
+                return Poll::Ready(args.map_err(|_| SocketIoError::Closed));
+            }
+            match Pin::new(&mut timer).poll(cx) {
+                Poll::Ready(()) => Poll::Ready(Err(SocketIoError::Timeout)),
+                Poll::Pending => Poll::Pending,
+            }
+        })
+        .await?;
+        decode_args(args)
+    }
+
+    /// The stream of the events named `event` the server emits to the socket.
+    pub fn on(&self, event: &str) -> Events {
+        self.listen(Some(event.to_string()))
+    }
+
+    /// The stream of all the events the server emits to the socket.
+    pub fn events(&self) -> Events {
+        self.listen(None)
+    }
+
+    fn listen(&self, event: Option<String>) -> Events {
+        let (sender, receiver) = mpsc::unbounded();
+        if let Some(namespace) = self
+            .client
+            .shared
+            .namespaces
+            .borrow_mut()
+            .get_mut(&self.namespace)
+        {
+            namespace.listeners.push(Listener { event, sender });
+        }
+        Events { receiver }
+    }
+
+    /// Leaves the namespace, which ends the streams of events of the socket and its clones.
+    pub fn disconnect(self) {
+        let namespace = self
+            .client
+            .shared
+            .namespaces
+            .borrow_mut()
+            .remove(&self.namespace);
+        if namespace.is_some_and(|namespace| namespace.sid.is_some()) {
+            let packet = Packet::new(PacketType::Disconnect, &self.namespace);
+            let _ = self
+                .client
+                .shared
+                .outgoing
+                .unbounded_send(packet.into_message());
+        }
+    }
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Socket")
+            .field("namespace", &self.namespace)
+            .field("id", &self.id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// An event the server emitted.
+#[derive(Debug)]
+pub struct Event {
+    /// The name of the event, like `message`.
+    pub name: String,
+    /// The arguments of the event.
+    pub args: Vec<Value>,
+    ack: Option<Ack>,
+}
+
+/// How to acknowledge an event.
+#[derive(Debug)]
+struct Ack {
+    namespace: String,
+    id: u64,
+    outgoing: mpsc::UnboundedSender<Message>,
+}
+
+impl Event {
+    /// Decodes the arguments as a tuple, or as the one value when `T` isn't a tuple or a list.
+    pub fn args_as<T: DeserializeOwned>(&self) -> Result<T, SocketIoError> {
+        decode_args(self.args.clone())
+    }
+
+    /// Whether the server waits for the event to be [acknowledged](Self::ack).
+    pub fn wants_ack(&self) -> bool {
+        self.ack.is_some()
+    }
+
+    /// Acknowledges the event with `args`, passed like those of [`Socket::emit`]. This does
+    /// nothing when the server doesn't wait for it.
+    pub fn ack<A: Serialize + ?Sized>(self, args: &A) -> Result<(), SocketIoError> {
+        let Some(ack) = self.ack else {
+            return Ok(());
+        };
+        let packet = Packet {
+            kind: PacketType::Ack,
+            namespace: ack.namespace,
+            id: Some(ack.id),
+            data: Some(Value::Array(encode_args(args)?)),
+        };
+        ack.outgoing
+            .unbounded_send(packet.into_message())
+            .map_err(|_| SocketIoError::Closed)
+    }
+}
+
+/// The events the server emits to a socket, see [`Socket::on`].
+///
+/// The stream reports the failure of the connection, and the refusal of the server to let the
+/// socket join its namespace, as errors. It ends once the socket leaves its namespace, or the
+/// connection closes for good.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Events {
+    receiver: mpsc::UnboundedReceiver<Result<Event, SocketIoError>>,
+}
+
+impl Stream for Events {
+    type Item = Result<Event, SocketIoError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+/// The handles of a client, closing the connection once dropped.
+struct Client {
+    shared: Rc<Shared>,
+}
+
+impl Client {
+    /// Sends `message` to `namespace`, or holds it until the server accepted the socket.
+    fn send(&self, namespace: &str, message: Message) -> Result<(), SocketIoError> {
+        if self.shared.closed.get() {
+            return Err(SocketIoError::Closed);
+        }
+        let mut namespaces = self.shared.namespaces.borrow_mut();
+        let namespace = namespaces.get_mut(namespace).ok_or(SocketIoError::Closed)?;
+        if namespace.sid.is_some() {
+            self.shared
+                .outgoing
+                .unbounded_send(message)
+                .map_err(|_| SocketIoError::Closed)
+        } else {
+            namespace.waiting.push(message);
+            Ok(())
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.shared.outgoing.close_channel();
+    }
+}
+
+/// The state shared with the task driving the connection.
+struct Shared {
+    auth: Option<Value>,
+    /// The messages to send, once the socket of their namespace was accepted.
+    outgoing: mpsc::UnboundedSender<Message>,
+    namespaces: RefCell<HashMap<String, Namespace>>,
+    acks: RefCell<HashMap<u64, oneshot::Sender<Vec<Value>>>>,
+    next_ack: Cell<u64>,
+    /// Whether the Engine.IO session of the current connection is open.
+    engine_open: Cell<bool>,
+    closed: Cell<bool>,
+}
+
+#[derive(Default)]
+struct Namespace {
+    /// The id of the socket, once the server accepted it over the current connection.
+    sid: Option<String>,
+    /// The messages emitted before the server accepted the socket.
+    waiting: Vec<Message>,
+    listeners: Vec<Listener>,
+}
+
+impl Namespace {
+    /// Passes the item built by `item` on to the listeners of the events named `name`, or to
+    /// every listener without a name.
+    fn dispatch(&mut self, name: Option<&str>, item: impl Fn() -> Result<Event, SocketIoError>) {
+        self.listeners.retain(|listener| {
+            let matches = match (&listener.event, name) {
+                (Some(wanted), Some(name)) => wanted == name,
+                (Some(_), None) | (None, _) => true,
+            };
+            !matches || listener.sender.unbounded_send(item()).is_ok()
+        });
+    }
+}
+
+struct Listener {
+    /// The name of the events listened to, or `None` for all of them.
+    event: Option<String>,
+    sender: mpsc::UnboundedSender<Result<Event, SocketIoError>>,
+}
+
+/// An emit waiting for its acknowledgement, forgotten when dropped.
+struct PendingAck<'a> {
+    shared: &'a Shared,
+    id: u64,
+}
+
+impl Drop for PendingAck<'_> {
+    fn drop(&mut self) {
+        self.shared.acks.borrow_mut().remove(&self.id);
+    }
+}
+
+/// Encodes `args` as the list of the arguments of an event.
+fn encode_args<A: Serialize + ?Sized>(args: &A) -> Result<Vec<Value>, serde_json::Error> {
+    Ok(match serde_json::to_value(args)? {
+        Value::Array(args) => args,
+        Value::Null => Vec::new(),
+        arg => vec![arg],
+    })
+}
+
+/// Decodes the arguments of an event, as a tuple, or as the one value.
+fn decode_args<T: DeserializeOwned>(mut args: Vec<Value>) -> Result<T, SocketIoError> {
+    if args.len() == 1 {
+        if let Ok(value) = T::deserialize(&args[0]) {
+            return Ok(value);
+        }
+    }
+    if args.is_empty() {
+        if let Ok(value) = T::deserialize(Value::Null) {
+            return Ok(value);
+        }
+    }
+    let args = Value::Array(std::mem::take(&mut args));
+    Ok(T::deserialize(args)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketType {
+    Connect = 0,
+    Disconnect = 1,
+    Event = 2,
+    Ack = 3,
+    ConnectError = 4,
+    BinaryEvent = 5,
+    BinaryAck = 6,
+}
+
+/// A Socket.IO packet, carried by an Engine.IO message.
+#[derive(Debug, PartialEq)]
+struct Packet {
+    kind: PacketType,
+    namespace: String,
+    id: Option<u64>,
+    data: Option<Value>,
+}
+
+impl Packet {
+    fn new(kind: PacketType, namespace: &str) -> Self {
+        Self {
+            kind,
+            namespace: namespace.to_string(),
+            id: None,
+            data: None,
+        }
+    }
+
+    fn connect(namespace: &str, auth: Option<Value>) -> Self {
+        Self {
+            data: auth,
+            ..Self::new(PacketType::Connect, namespace)
+        }
+    }
+
+    fn event<A: Serialize + ?Sized>(
+        namespace: &str,
+        id: Option<u64>,
+        event: &str,
+        args: &A,
+    ) -> Result<Self, serde_json::Error> {
+        let mut data = vec![Value::String(event.to_string())];
+        data.extend(encode_args(args)?);
+        Ok(Self {
+            id,
+            data: Some(Value::Array(data)),
+            ..Self::new(PacketType::Event, namespace)
+        })
+    }
+
+    /// Encodes the packet as an Engine.IO message: `4`, the packet type, the namespace unless
+    /// it is `/`, the ack id, then the JSON data.
+    fn into_message(self) -> Message {
+        let mut message = format!("4{}", self.kind as u8);
+        if self.namespace != "/" {
+            message.push_str(&self.namespace);
+            message.push(',');
+        }
+        if let Some(id) = self.id {
+            message.push_str(&id.to_string());
+        }
+        if let Some(data) = self.data {
+            message.push_str(&data.to_string());
+        }
+        Message::Text(message)
+    }
+
+    /// Parses a packet, without the `4` of the Engine.IO message carrying it.
+    fn parse(packet: &str) -> Option<Self> {
+        let kind = match packet.as_bytes().first()? {
+            b'0' => PacketType::Connect,
+            b'1' => PacketType::Disconnect,
+            b'2' => PacketType::Event,
+            b'3' => PacketType::Ack,
+            b'4' => PacketType::ConnectError,
+            b'5' => PacketType::BinaryEvent,
+            b'6' => PacketType::BinaryAck,
+            _ => return None,
+        };
+        let mut rest = &packet[1..];
+        if let PacketType::BinaryEvent | PacketType::BinaryAck = kind {
+            // the number of attachments
+            rest = &rest[rest.find('-')? + 1..];
+        }
+        let mut namespace = "/";
+        if rest.starts_with('/') {
+            let end = rest.find(',').unwrap_or(rest.len());
+            namespace = &rest[..end];
+            rest = rest.get(end + 1..).unwrap_or("");
+        }
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let id = match digits {
+            0 => None,
+            _ => Some(rest[..digits].parse().ok()?),
+        };
+        let data = match &rest[digits..] {
+            "" => None,
+            data => Some(serde_json::from_str(data).ok()?),
+        };
+        Some(Self {
+            id,
+            data,
+            ..Self::new(kind, namespace)
+        })
+    }
+}
+
+/// What happened on the connection.
+enum Step {
+    Incoming(Option<Result<Message, WebSocketError>>),
+    State(Option<ConnectionState>),
+    Outgoing(Option<Message>),
+}
+
+/// Runs the Engine.IO session over `ws`, passing packets between it and the sockets.
+async fn drive(
+    mut ws: ReconnectingWebSocket,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    shared: Rc<Shared>,
+) {
+    let mut states = ws.states();
+    loop {
+        let step = std::future::poll_fn(|cx| {
+            if let Poll::Ready(state) = Pin::new(&mut states).poll_next(cx) {
+                return Poll::Ready(Step::State(state));
+            }
+            if let Poll::Ready(item) = Pin::new(&mut ws).poll_next(cx) {
+                return Poll::Ready(Step::Incoming(item));
+            }
+            Pin::new(&mut outgoing).poll_next(cx).map(Step::Outgoing)
+        })
+        .await;
+        match step {
+            Step::Incoming(Some(Ok(Message::Text(text)))) => match text.as_bytes().first() {
+                // the server opened the session, the sockets join their namespaces again
+                Some(b'0') => {
+                    shared.engine_open.set(true);
+                    for namespace in shared.namespaces.borrow().keys() {
+                        let packet = Packet::connect(namespace, shared.auth.clone());
+                        send(&mut ws, packet.into_message());
+                    }
+                }
+                // ping, answered with a pong carrying the same payload
+                Some(b'2') => send(&mut ws, Message::Text(format!("3{}", &text[1..]))),
+                Some(b'4') => {
+                    if let Some(packet) = Packet::parse(&text[1..]) {
+                        for message in receive(packet, &shared) {
+                            send(&mut ws, message);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Step::Incoming(Some(Ok(Message::Bytes(_)))) => {}
+            Step::Incoming(Some(Err(error))) => {
+                for namespace in shared.namespaces.borrow_mut().values_mut() {
+                    namespace.dispatch(None, || Err(SocketIoError::WebSocket(error.duplicate())));
+                }
+            }
+            Step::Incoming(None) | Step::State(None) => break,
+            Step::State(Some(ConnectionState::Open)) => {}
+            Step::State(Some(_)) => {
+                shared.engine_open.set(false);
+                for namespace in shared.namespaces.borrow_mut().values_mut() {
+                    namespace.sid = None;
+                }
+            }
+            Step::Outgoing(Some(message)) => send(&mut ws, message),
+            Step::Outgoing(None) => {
+                let _ = std::future::poll_fn(|cx| Pin::new(&mut ws).poll_close(cx)).await;
+                break;
+            }
+        }
+    }
+    shared.closed.set(true);
+    shared.engine_open.set(false);
+    // dropping the senders ends the streams, and fails the emits waiting for an ack
+    shared.namespaces.borrow_mut().clear();
+    shared.acks.borrow_mut().clear();
+}
+
+fn send(ws: &mut ReconnectingWebSocket, message: Message) {
+    // a reconnecting websocket holds the message until it can send it
+    let _ = Pin::new(ws).start_send(message);
+}
+
+/// Handles a packet of the server, resolving to the messages to send in reply.
+fn receive(packet: Packet, shared: &Shared) -> Vec<Message> {
+    let Packet {
+        kind,
+        namespace: path,
+        id,
+        data,
+    } = packet;
+    let mut namespaces = shared.namespaces.borrow_mut();
+    let Some(namespace) = namespaces.get_mut(&path) else {
+        return Vec::new();
+    };
+    match (kind, data) {
+        (PacketType::Connect, data) => {
+            let sid = data.as_ref().and_then(|data| data.get("sid"));
+            namespace.sid = Some(sid.and_then(Value::as_str).unwrap_or_default().to_string());
+            return std::mem::take(&mut namespace.waiting);
+        }
+        (PacketType::ConnectError, data) => {
+            let message = data
+                .as_ref()
+                .and_then(|data| data.get("message"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let data = data.and_then(|mut data| data.get_mut("data").map(Value::take));
+            namespace.dispatch(None, || {
+                Err(SocketIoError::Refused {
+                    message: message.clone(),
+                    data: data.clone(),
+                })
+            });
+            namespaces.remove(&path);
+        }
+        (PacketType::Disconnect, _) => {
+            namespaces.remove(&path);
+        }
+        (PacketType::Event, Some(Value::Array(mut args))) if !args.is_empty() => {
+            let Value::String(name) = args.remove(0) else {
+                return Vec::new();
+            };
+            namespace.dispatch(Some(&name), || {
+                Ok(Event {
+                    name: name.clone(),
+                    args: args.clone(),
+                    ack: id.map(|id| Ack {
+                        namespace: path.clone(),
+                        id,
+                        outgoing: shared.outgoing.clone(),
+                    }),
+                })
+            });
+        }
+        (PacketType::Ack, Some(Value::Array(args))) => {
+            if let Some(ack) = packet
+                .id
+                .and_then(|id| shared.acks.borrow_mut().remove(&id))
+            {
+                let _ = ack.send(args);
+            }
+        }
+        _ => {}
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(message: Message) -> String {
+        match message {
+            Message::Text(text) => text,
+            Message::Bytes(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn encodes_packets() {
+        let connect = Packet::connect("/", Some(json!({"token": "abc"})));
+        assert_eq!(text(connect.into_message()), r#"40{"token":"abc"}"#);
+        let event = Packet::event("/chat", Some(12), "join", &("lobby", 2)).unwrap();
+        assert_eq!(
+            text(event.into_message()),
+            r#"42/chat,12["join","lobby",2]"#
+        );
+        let event = Packet::event("/", None, "ping", &()).unwrap();
+        assert_eq!(text(event.into_message()), r#"42["ping"]"#);
+        let leave = Packet::new(PacketType::Disconnect, "/chat");
+        assert_eq!(text(leave.into_message()), "41/chat,");
+    }
+
+    #[test]
+    fn parses_packets() {
+        assert_eq!(
+            Packet::parse(r#"0{"sid":"wZX3oN0bSVIhsaknAAAI"}"#),
+            Some(Packet {
+                data: Some(json!({"sid": "wZX3oN0bSVIhsaknAAAI"})),
+                ..Packet::new(PacketType::Connect, "/")
+            })
+        );
+        assert_eq!(
+            Packet::parse(r#"2/admin,["tick",{"n":1}]"#),
+            Some(Packet {
+                data: Some(json!(["tick", {"n": 1}])),
+                ..Packet::new(PacketType::Event, "/admin")
+            })
+        );
+        assert_eq!(
+            Packet::parse(r#"3/chat,12["a","b"]"#),
+            Some(Packet {
+                id: Some(12),
+                data: Some(json!(["a", "b"])),
+                ..Packet::new(PacketType::Ack, "/chat")
+            })
+        );
+        assert_eq!(
+            Packet::parse("1/chat,"),
+            Some(Packet::new(PacketType::Disconnect, "/chat"))
+        );
+        assert_eq!(
+            Packet::parse(r#"51-["upload",{"_placeholder":true,"num":0}]"#).map(|p| p.kind),
+            Some(PacketType::BinaryEvent)
+        );
+        assert_eq!(Packet::parse("9"), None);
+        assert_eq!(Packet::parse("2[oops"), None);
+    }
+
+    /// A client without a connection, along with the messages it sends.
+    fn client(namespaces: &[&str]) -> (Shared, mpsc::UnboundedReceiver<Message>) {
+        let (outgoing, sent) = mpsc::unbounded();
+        let shared = Shared {
+            auth: None,
+            outgoing,
+            namespaces: RefCell::default(),
+            acks: RefCell::default(),
+            next_ack: Cell::new(0),
+            engine_open: Cell::new(true),
+            closed: Cell::new(false),
+        };
+        for namespace in namespaces {
+            shared
+                .namespaces
+                .borrow_mut()
+                .insert(namespace.to_string(), Namespace::default());
+        }
+        (shared, sent)
+    }
+
+    fn listen(
+        shared: &Shared,
+        namespace: &str,
+        event: Option<&str>,
+    ) -> mpsc::UnboundedReceiver<Result<Event, SocketIoError>> {
+        let (sender, receiver) = mpsc::unbounded();
+        let mut namespaces = shared.namespaces.borrow_mut();
+        namespaces
+            .get_mut(namespace)
+            .unwrap()
+            .listeners
+            .push(Listener {
+                event: event.map(str::to_string),
+                sender,
+            });
+        receiver
+    }
+
+    fn packet(packet: &str) -> Packet {
+        Packet::parse(packet).unwrap()
+    }
+
+    #[test]
+    fn events_go_to_the_listeners_of_their_namespace() {
+        let (shared, mut sent) = client(&["/", "/chat"]);
+        let mut root = listen(&shared, "/", None);
+        let mut messages = listen(&shared, "/chat", Some("message"));
+        let mut others = listen(&shared, "/chat", Some("typing"));
+        let mut all = listen(&shared, "/chat", None);
+
+        assert!(receive(packet(r#"2/chat,5["message","hi"]"#), &shared).is_empty());
+        assert!(root.try_next().is_err());
+        assert!(others.try_next().is_err());
+        assert_eq!(all.try_next().unwrap().unwrap().unwrap().name, "message");
+        let event = messages.try_next().unwrap().unwrap().unwrap();
+        assert_eq!(event.args, vec![json!("hi")]);
+        assert!(event.wants_ack());
+
+        event.ack(&("ok",)).unwrap();
+        assert_eq!(
+            text(sent.try_next().unwrap().unwrap()),
+            r#"43/chat,5["ok"]"#
+        );
+    }
+
+    #[test]
+    fn acks_resolve_the_emits_waiting_for_them() {
+        let (shared, _sent) = client(&["/chat"]);
+        let (ack, mut acked) = oneshot::channel();
+        shared.acks.borrow_mut().insert(7, ack);
+
+        receive(packet(r#"3/chat,7["done"]"#), &shared);
+        assert_eq!(acked.try_recv().unwrap(), Some(vec![json!("done")]));
+        assert!(shared.acks.borrow().is_empty());
+    }
+
+    #[test]
+    fn joining_sends_the_waiting_messages() {
+        let (shared, _sent) = client(&["/chat"]);
+        let waiting = Packet::event("/chat", None, "join", &("lobby",)).unwrap();
+        shared
+            .namespaces
+            .borrow_mut()
+            .get_mut("/chat")
+            .unwrap()
+            .waiting
+            .push(waiting.into_message());
+
+        let replies = receive(packet(r#"0/chat,{"sid":"abc"}"#), &shared);
+        assert_eq!(
+            replies.into_iter().map(text).collect::<Vec<_>>(),
+            [r#"42/chat,["join","lobby"]"#]
+        );
+        assert_eq!(
+            shared.namespaces.borrow()["/chat"].sid.as_deref(),
+            Some("abc")
+        );
+    }
+
+    #[test]
+    fn refusals_end_the_namespace() {
+        let (shared, _sent) = client(&["/admin"]);
+        let mut events = listen(&shared, "/admin", Some("tick"));
+
+        receive(
+            packet(r#"4/admin,{"message":"Not authorized","data":{"code":401}}"#),
+            &shared,
+        );
+        match events.try_next().unwrap().unwrap() {
+            Err(SocketIoError::Refused { message, data }) => {
+                assert_eq!(message, "Not authorized");
+                assert_eq!(data, Some(json!({"code": 401})));
+            }
+            item => panic!("expected a refusal, got {:?}", item),
+        }
+        // the listener was dropped along with the namespace
+        assert!(events.try_next().unwrap().is_none());
+        assert!(shared.namespaces.borrow().is_empty());
+    }
+
+    #[test]
+    fn decodes_args() {
+        let one: String = decode_args(vec![json!("hi")]).unwrap();
+        assert_eq!(one, "hi");
+        let two: (String, u32) = decode_args(vec![json!("hi"), json!(2)]).unwrap();
+        assert_eq!(two, ("hi".to_string(), 2));
+        let none: () = decode_args(Vec::new()).unwrap();
+        assert_eq!(none, ());
+        let list: Vec<u32> = decode_args(vec![json!([1, 2])]).unwrap();
+        assert_eq!(list, vec![1, 2]);
+        assert_eq!(encode_args(&("a", 1)).unwrap(), vec![json!("a"), json!(1)]);
+        assert_eq!(
+            encode_args(&json!({"x": 1})).unwrap(),
+            vec![json!({"x": 1})]
+        );
+        assert_eq!(encode_args(&()).unwrap(), Vec::<Value>::new());
+    }
+}
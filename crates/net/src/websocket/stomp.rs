@@ -0,0 +1,900 @@
+//! A [STOMP 1.2](https://stomp.github.io/stomp-specification-1.2.html) client, talking to the
+//! WebSocket endpoints of message brokers like RabbitMQ, ActiveMQ or Spring.
+//!
+//! A [`StompClient`] connects over a [`WebSocket`], then [sends](StompClient::send) messages to
+//! destinations and [subscribes](StompClient::subscribe) to them, each subscription being a
+//! stream of its messages. Messages can be decoded with a [`Codec`], both the ones received
+//! with [typed subscriptions](Subscription::typed) and the ones sent with
+//! [`send_as`](StompClient::send_as).
+//!
+//! The heart-beats asked for in the [`ConnectOptions`] are negotiated with the server: the
+//! client sends its own, and fails the connection with [`StompError::HeartbeatTimeout`] when
+//! the server's stop arriving.
+//!
+//! # Example
+//!
+//! ```
+//! use futures::StreamExt;
+//! use gloo_net::websocket::futures::WebSocket;
+//! use gloo_net::websocket::stomp::{AckMode, ConnectOptions, StompClient};
+//! use gloo_net::websocket::Json;
+//! use serde::Deserialize;
+//! use std::time::Duration;
+//!
+//! #[derive(Deserialize)]
+//! struct Order {
+//!     id: u64,
+//! }
+//!
+//! # async fn no_run() -> Result<(), gloo_net::websocket::stomp::StompError> {
+//! let ws = WebSocket::open("wss://example.com/ws").unwrap();
+//! let options = ConnectOptions::new("/")
+//!     .credentials("guest", "guest")
+//!     .heartbeat(Duration::from_secs(10), Duration::from_secs(10));
+//! let client = StompClient::connect(ws, options).await?;
+//!
+//! let mut orders = client
+//!     .subscribe("/queue/orders", AckMode::Client)?
+//!     .typed::<Order, Json>();
+//! client.send("/topic/chat", "hello")?;
+//!
+//! while let Some(order) = orders.next().await {
+//!     let (order, message) = order?;
+//!     // process the order
+//!     client.ack(&message)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::{ready, Stream};
+use futures_sink::Sink;
+use gloo_timers::future::sleep;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error as ThisError;
+
+use crate::websocket::events::ErrorEvent;
+use crate::websocket::futures::{WebSocket, WebSocketReceiver, WebSocketSender};
+use crate::websocket::{Codec, CodecError, Message, WebSocketError};
+
+/// The error of a STOMP client, or of a subscription.
+#[derive(Debug, ThisError)]
+#[non_exhaustive]
+pub enum StompError {
+    /// The server sent an `ERROR` frame, after which it closes the connection.
+    #[error("STOMP server error: {message}")]
+    Server {
+        /// The `message` header, a short description of the error.
+        message: String,
+        /// The body of the frame, which may tell more.
+        body: String,
+    },
+    /// The server answered the `CONNECT` frame with something else than `CONNECTED`.
+    #[error("unexpected {0} frame")]
+    UnexpectedFrame(String),
+    /// No frame, not even a heart-beat, arrived from the server in time.
+    #[error("the server stopped sending heart-beats")]
+    HeartbeatTimeout,
+    /// The connection closed.
+    #[error("the connection is closed")]
+    Closed,
+    /// The connection failed.
+    #[error("{0}")]
+    WebSocket(WebSocketError),
+    /// A message body couldn't be encoded or decoded.
+    #[error("{0}")]
+    Codec(CodecError),
+}
+
+/// How the messages of a subscription are acknowledged, see [`StompClient::ack`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckMode {
+    /// The server considers messages acknowledged as soon as it sends them.
+    Auto,
+    /// Acknowledging a message acknowledges every message received before it on the
+    /// subscription.
+    Client,
+    /// Every message is acknowledged on its own.
+    ClientIndividual,
+}
+
+impl AckMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            AckMode::Auto => "auto",
+            AckMode::Client => "client",
+            AckMode::ClientIndividual => "client-individual",
+        }
+    }
+}
+
+/// The headers of the `CONNECT` frame, see [`StompClient::connect`].
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    host: String,
+    credentials: Option<(String, String)>,
+    heartbeat: (Duration, Duration),
+    headers: Vec<(String, String)>,
+}
+
+impl ConnectOptions {
+    /// Connects to the virtual host `host`, like `/` for RabbitMQ, without credentials nor
+    /// heart-beats.
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            credentials: None,
+            heartbeat: (Duration::ZERO, Duration::ZERO),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Logs in with `login` and `passcode`.
+    pub fn credentials(mut self, login: &str, passcode: &str) -> Self {
+        self.credentials = Some((login.to_string(), passcode.to_string()));
+        self
+    }
+
+    /// Offers to send a heart-beat every `send`, and asks for one every `receive`, zero
+    /// meaning none. The server may pick longer intervals.
+    pub fn heartbeat(mut self, send: Duration, receive: Duration) -> Self {
+        self.heartbeat = (send, receive);
+        self
+    }
+
+    /// Adds a header to the `CONNECT` frame, like a token the broker expects.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn frame(&self) -> Frame {
+        let (send, receive) = self.heartbeat;
+        let mut frame = Frame::new("CONNECT")
+            .header("accept-version", "1.2")
+            .header("host", &self.host)
+            .header(
+                "heart-beat",
+                &format!("{},{}", send.as_millis(), receive.as_millis()),
+            );
+        if let Some((login, passcode)) = &self.credentials {
+            frame = frame.header("login", login).header("passcode", passcode);
+        }
+        for (name, value) in &self.headers {
+            frame = frame.header(name, value);
+        }
+        frame
+    }
+}
+
+/// A STOMP client, see the [module documentation](self).
+///
+/// Clones share the same connection, which is closed once every clone is dropped.
+#[derive(Clone)]
+pub struct StompClient {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    sender: RefCell<Option<WebSocketSender>>,
+    routes: Rc<Routes>,
+    next_id: Cell<u64>,
+}
+
+/// Where the frames of the server go, shared with the task reading the connection.
+#[derive(Default)]
+struct Routes {
+    subscriptions:
+        RefCell<HashMap<String, mpsc::UnboundedSender<Result<StompMessage, StompError>>>>,
+    receipts: RefCell<HashMap<String, oneshot::Sender<()>>>,
+    closed: Cell<bool>,
+}
+
+impl StompClient {
+    /// Sends the `CONNECT` frame over `ws`, and waits for the server to accept it.
+    ///
+    /// A task then reads the connection until it closes. This must be called in a
+    /// wasm-bindgen-futures context.
+    pub async fn connect(ws: WebSocket, options: ConnectOptions) -> Result<Self, StompError> {
+        let (mut sender, mut receiver) = ws.into_halves();
+        std::future::poll_fn(|cx| Pin::new(&mut sender).poll_ready(cx))
+            .await
+            .map_err(StompError::WebSocket)?;
+        Pin::new(&mut sender)
+            .start_send(options.frame().into_message())
+            .map_err(StompError::WebSocket)?;
+
+        let connected = loop {
+            let message =
+                match std::future::poll_fn(|cx| Pin::new(&mut receiver).poll_next(cx)).await {
+                    Some(Ok(message)) => message,
+                    Some(Err(WebSocketError::ConnectionClose(_))) | None => {
+                        return Err(StompError::Closed)
+                    }
+                    Some(Err(error)) => return Err(StompError::WebSocket(error)),
+                };
+            // heart-beats may come first
+            if let Some(frame) =
+                parse_frames(&message_bytes(message)).and_then(|f| f.into_iter().next())
+            {
+                break frame;
+            }
+        };
+        match connected.command.as_str() {
+            "CONNECTED" => {}
+            "ERROR" => return Err(connected.error()),
+            command => return Err(StompError::UnexpectedFrame(command.to_string())),
+        }
+
+        let (send, receive) = negotiate(options.heartbeat, connected.get("heart-beat"));
+        let inner = Rc::new(Inner {
+            sender: RefCell::new(Some(sender)),
+            routes: Rc::new(Routes::default()),
+            next_id: Cell::new(0),
+        });
+        wasm_bindgen_futures::spawn_local(read(
+            receiver,
+            Rc::clone(&inner.routes),
+            Rc::downgrade(&inner),
+            receive,
+        ));
+        if let Some(interval) = send {
+            wasm_bindgen_futures::spawn_local(beat(Rc::downgrade(&inner), interval));
+        }
+        Ok(Self { inner })
+    }
+
+    /// Sends `body` to `destination`.
+    pub fn send(&self, destination: &str, body: impl Into<Vec<u8>>) -> Result<(), StompError> {
+        self.send_with_headers(destination, &[], body)
+    }
+
+    /// Sends `value` to `destination`, encoded with `C`.
+    pub fn send_as<T: Serialize, C: Codec>(
+        &self,
+        destination: &str,
+        value: &T,
+    ) -> Result<(), StompError> {
+        let body = message_bytes(C::encode(value).map_err(StompError::Codec)?);
+        self.send(destination, body)
+    }
+
+    /// Sends `body` to `destination`, with extra headers like `content-type`.
+    pub fn send_with_headers(
+        &self,
+        destination: &str,
+        headers: &[(&str, &str)],
+        body: impl Into<Vec<u8>>,
+    ) -> Result<(), StompError> {
+        self.inner
+            .send(send_frame(destination, headers, body.into()))
+    }
+
+    /// Sends `body` to `destination` like [`send_with_headers`](Self::send_with_headers), and
+    /// waits for the server to confirm it processed the message.
+    pub async fn send_with_receipt(
+        &self,
+        destination: &str,
+        headers: &[(&str, &str)],
+        body: impl Into<Vec<u8>>,
+    ) -> Result<(), StompError> {
+        let frame = send_frame(destination, headers, body.into());
+        self.inner.send_with_receipt(frame).await
+    }
+
+    /// Subscribes to `destination`, returning the stream of its messages.
+    ///
+    /// Dropping the subscription unsubscribes.
+    pub fn subscribe(&self, destination: &str, ack: AckMode) -> Result<Subscription, StompError> {
+        let id = format!("sub-{}", self.inner.next_id());
+        let (sender, receiver) = mpsc::unbounded();
+        self.inner
+            .routes
+            .subscriptions
+            .borrow_mut()
+            .insert(id.clone(), sender);
+        let frame = Frame::new("SUBSCRIBE")
+            .header("id", &id)
+            .header("destination", destination)
+            .header("ack", ack.as_str());
+        if let Err(error) = self.inner.send(frame) {
+            self.inner.routes.subscriptions.borrow_mut().remove(&id);
+            return Err(error);
+        }
+        Ok(Subscription {
+            id,
+            receiver,
+            client: Rc::downgrade(&self.inner),
+        })
+    }
+
+    /// Acknowledges `message`, received on a subscription in a client [`AckMode`]. This does
+    /// nothing for the messages which don't need it.
+    pub fn ack(&self, message: &StompMessage) -> Result<(), StompError> {
+        self.acknowledge("ACK", message)
+    }
+
+    /// Tells the server `message` wasn't processed, see [`ack`](Self::ack).
+    pub fn nack(&self, message: &StompMessage) -> Result<(), StompError> {
+        self.acknowledge("NACK", message)
+    }
+
+    fn acknowledge(&self, command: &str, message: &StompMessage) -> Result<(), StompError> {
+        match &message.ack {
+            Some(id) => self.inner.send(Frame::new(command).header("id", id)),
+            None => Ok(()),
+        }
+    }
+
+    /// Disconnects gracefully: waits for the server to confirm it received every frame sent
+    /// before, then closes the connection.
+    pub async fn disconnect(self) -> Result<(), StompError> {
+        let result = self.inner.send_with_receipt(Frame::new("DISCONNECT")).await;
+        self.inner.close();
+        result
+    }
+}
+
+impl Inner {
+    fn next_id(&self) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    fn send(&self, frame: Frame) -> Result<(), StompError> {
+        if self.routes.closed.get() {
+            return Err(StompError::Closed);
+        }
+        // the connection is open, which is all sending needs
+        match &mut *self.sender.borrow_mut() {
+            Some(sender) => Pin::new(sender)
+                .start_send(frame.into_message())
+                .map_err(StompError::WebSocket),
+            None => Err(StompError::Closed),
+        }
+    }
+
+    async fn send_with_receipt(&self, frame: Frame) -> Result<(), StompError> {
+        let id = format!("receipt-{}", self.next_id());
+        let (receipt, received) = oneshot::channel();
+        self.routes
+            .receipts
+            .borrow_mut()
+            .insert(id.clone(), receipt);
+        if let Err(error) = self.send(frame.header("receipt", &id)) {
+            self.routes.receipts.borrow_mut().remove(&id);
+            return Err(error);
+        }
+        received.await.map_err(|_| StompError::Closed)
+    }
+
+    fn close(&self) {
+        if let Some(sender) = self.sender.borrow_mut().take() {
+            let _ = sender.close(Some(1000), None);
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl fmt::Debug for StompClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let routes = &self.inner.routes;
+        f.debug_struct("StompClient")
+            .field("subscriptions", &routes.subscriptions.borrow().len())
+            .field("closed", &routes.closed.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message received on a [`Subscription`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StompMessage {
+    /// The destination the message was sent to.
+    pub destination: String,
+    /// All the headers of the `MESSAGE` frame.
+    pub headers: Vec<(String, String)>,
+    /// The body.
+    pub body: Vec<u8>,
+    /// The id to acknowledge the message with, if it needs to be.
+    ack: Option<String>,
+}
+
+impl StompMessage {
+    /// The value of the header `name`.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+
+    /// The body as text, if it is UTF-8.
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.body).ok()
+    }
+
+    /// Decodes the body with `C`.
+    pub fn body_as<T: DeserializeOwned, C: Codec>(&self) -> Result<T, StompError> {
+        C::decode(Message::Bytes(self.body.clone())).map_err(StompError::Codec)
+    }
+}
+
+/// The messages of a subscription, see [`StompClient::subscribe`].
+///
+/// The stream reports the failure of the connection as an error, and ends once it closes.
+#[must_use = "streams do nothing unless polled"]
+pub struct Subscription {
+    id: String,
+    receiver: mpsc::UnboundedReceiver<Result<StompMessage, StompError>>,
+    client: Weak<Inner>,
+}
+
+impl Subscription {
+    /// The id of the subscription.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Decodes the bodies of the messages with `C`, yielding each value along with its
+    /// message, to [acknowledge](StompClient::ack) it.
+    pub fn typed<T: DeserializeOwned, C: Codec>(self) -> TypedSubscription<T, C> {
+        TypedSubscription {
+            inner: self,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<StompMessage, StompError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.upgrade() {
+            client.routes.subscriptions.borrow_mut().remove(&self.id);
+            let _ = client.send(Frame::new("UNSUBSCRIBE").header("id", &self.id));
+        }
+    }
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The messages of a subscription, decoded with `C`, see [`Subscription::typed`].
+#[must_use = "streams do nothing unless polled"]
+pub struct TypedSubscription<T, C> {
+    inner: Subscription,
+    _marker: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T, C> TypedSubscription<T, C> {
+    /// The subscription the messages are decoded from.
+    pub fn into_inner(self) -> Subscription {
+        self.inner
+    }
+}
+
+impl<T: DeserializeOwned, C: Codec> Stream for TypedSubscription<T, C> {
+    type Item = Result<(T, StompMessage), StompError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = ready!(Pin::new(&mut self.inner).poll_next(cx));
+        Poll::Ready(item.map(|message| {
+            let message = message?;
+            Ok((message.body_as::<T, C>()?, message))
+        }))
+    }
+}
+
+impl<T, C> fmt::Debug for TypedSubscription<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedSubscription")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// What happened while reading the connection.
+enum Step {
+    Incoming(Option<Result<Message, WebSocketError>>),
+    HeartbeatMissed,
+}
+
+/// Reads the connection until it closes, passing on the frames of the server.
+async fn read(
+    mut receiver: WebSocketReceiver,
+    routes: Rc<Routes>,
+    client: Weak<Inner>,
+    heartbeat: Option<Duration>,
+) {
+    let mut deadline = heartbeat.map(sleep);
+    let url = receiver.url();
+    let error = loop {
+        let step = std::future::poll_fn(|cx| {
+            if let Poll::Ready(item) = Pin::new(&mut receiver).poll_next(cx) {
+                return Poll::Ready(Step::Incoming(item));
+            }
+            match &mut deadline {
+                Some(deadline) => Pin::new(deadline).poll(cx).map(|()| Step::HeartbeatMissed),
+                None => Poll::Pending,
+            }
+        })
+        .await;
+        let message = match step {
+            Step::Incoming(Some(Ok(message))) => message,
+            Step::Incoming(Some(Err(WebSocketError::ConnectionClose(_))))
+            | Step::Incoming(None) => break None,
+            Step::Incoming(Some(Err(error))) => break Some(StompError::WebSocket(error)),
+            Step::HeartbeatMissed => break Some(StompError::HeartbeatTimeout),
+        };
+        // any frame counts as a heart-beat
+        deadline = heartbeat.map(sleep);
+        let reported = parse_frames(&message_bytes(message))
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|frame| routes.dispatch(frame));
+        if reported.is_some() {
+            break reported;
+        }
+    };
+    if let Some(client) = client.upgrade() {
+        client.close();
+    }
+    routes.closed.set(true);
+    for (_, subscription) in routes.subscriptions.borrow_mut().drain() {
+        let error = match &error {
+            Some(StompError::Server { message, body }) => StompError::Server {
+                message: message.clone(),
+                body: body.clone(),
+            },
+            Some(StompError::HeartbeatTimeout) => StompError::HeartbeatTimeout,
+            Some(StompError::WebSocket(error)) => StompError::WebSocket(error.duplicate()),
+            Some(_) => StompError::WebSocket(WebSocketError::ConnectionError(ErrorEvent::unknown(
+                url.clone(),
+            ))),
+            None => continue,
+        };
+        let _ = subscription.unbounded_send(Err(error));
+    }
+    // dropping the senders fails the receipts still waiting with `StompError::Closed`
+    routes.receipts.borrow_mut().clear();
+}
+
+impl Routes {
+    /// Passes `frame` on, returning the error it reports, if any.
+    fn dispatch(&self, frame: Frame) -> Option<StompError> {
+        match frame.command.as_str() {
+            "MESSAGE" => {
+                let subscriptions = self.subscriptions.borrow();
+                let subscription = subscriptions.get(frame.get("subscription")?)?;
+                let message = StompMessage {
+                    destination: frame.get("destination").unwrap_or_default().to_string(),
+                    ack: frame.get("ack").map(str::to_string),
+                    headers: frame.headers,
+                    body: frame.body,
+                };
+                let _ = subscription.unbounded_send(Ok(message));
+            }
+            "RECEIPT" => {
+                let id = frame.get("receipt-id")?;
+                if let Some(receipt) = self.receipts.borrow_mut().remove(id) {
+                    let _ = receipt.send(());
+                }
+            }
+            "ERROR" => return Some(frame.error()),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Sends a heart-beat every `interval`, until the client is dropped.
+async fn beat(client: Weak<Inner>, interval: Duration) {
+    loop {
+        sleep(interval).await;
+        let Some(client) = client.upgrade() else {
+            break;
+        };
+        if client.routes.closed.get() {
+            break;
+        }
+        let mut sender = client.sender.borrow_mut();
+        if let Some(sender) = &mut *sender {
+            let _ = Pin::new(sender).start_send(Message::Text("\n".to_string()));
+        }
+    }
+}
+
+/// The intervals at which heart-beats are sent and expected, given the ones asked for and the
+/// `heart-beat` header of the server.
+fn negotiate(
+    (send, receive): (Duration, Duration),
+    server: Option<&str>,
+) -> (Option<Duration>, Option<Duration>) {
+    let (server_send, server_receive) = server
+        .and_then(|header| header.split_once(','))
+        .and_then(|(send, receive)| Some((send.trim().parse().ok()?, receive.trim().parse().ok()?)))
+        .unwrap_or((0, 0));
+    let pick = |ours: Duration, theirs: u64| {
+        if ours.is_zero() || theirs == 0 {
+            None
+        } else {
+            Some(ours.max(Duration::from_millis(theirs)))
+        }
+    };
+    // heart-beats missing for twice the interval tell a dead connection from a slow network
+    (
+        pick(send, server_receive),
+        pick(receive, server_send).map(|interval| interval * 2),
+    )
+}
+
+fn send_frame(destination: &str, headers: &[(&str, &str)], body: Vec<u8>) -> Frame {
+    let mut frame = Frame::new("SEND").header("destination", destination);
+    for (name, value) in headers {
+        frame = frame.header(name, value);
+    }
+    frame.body = body;
+    frame
+}
+
+fn message_bytes(message: Message) -> Vec<u8> {
+    match message {
+        Message::Text(text) => text.into_bytes(),
+        Message::Bytes(bytes) => bytes,
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    // the first of repeated headers wins
+    headers
+        .iter()
+        .find(|(header, _)| header == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Frame {
+    command: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Frame {
+    fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+
+    fn error(&self) -> StompError {
+        StompError::Server {
+            message: self.get("message").unwrap_or_default().to_string(),
+            body: String::from_utf8_lossy(&self.body).into_owned(),
+        }
+    }
+
+    /// The headers of `CONNECT` and `CONNECTED` frames aren't escaped, for older servers.
+    fn escapes(command: &str) -> bool {
+        !matches!(command, "CONNECT" | "CONNECTED")
+    }
+
+    /// Encodes the frame, as a text message when its body is UTF-8.
+    fn into_message(self) -> Message {
+        let mut frame = self.command.clone();
+        frame.push('\n');
+        for (name, value) in &self.headers {
+            if Self::escapes(&self.command) {
+                frame.push_str(&escape(name));
+                frame.push(':');
+                frame.push_str(&escape(value));
+            } else {
+                frame.push_str(name);
+                frame.push(':');
+                frame.push_str(value);
+            }
+            frame.push('\n');
+        }
+        if !self.body.is_empty() {
+            frame.push_str(&format!("content-length:{}\n", self.body.len()));
+        }
+        frame.push('\n');
+        let mut frame = frame.into_bytes();
+        frame.extend_from_slice(&self.body);
+        frame.push(0);
+        match String::from_utf8(frame) {
+            Ok(text) => Message::Text(text),
+            Err(error) => Message::Bytes(error.into_bytes()),
+        }
+    }
+
+    /// Parses the frame at the start of `data`, returning it and the data after it.
+    fn parse(data: &[u8]) -> Option<(Self, &[u8])> {
+        let (command, mut rest) = line(data)?;
+        let mut frame = Frame::new(command);
+        loop {
+            let (line, after) = line(rest)?;
+            rest = after;
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line.split_once(':')?;
+            if Self::escapes(command) {
+                frame.headers.push((unescape(name)?, unescape(value)?));
+            } else {
+                frame.headers.push((name.to_string(), value.to_string()));
+            }
+        }
+        let length = frame
+            .get("content-length")
+            .and_then(|length| length.parse().ok());
+        let end = match length {
+            Some(length) if rest.get(length) == Some(&0) => length,
+            Some(_) => return None,
+            None => rest.iter().position(|&byte| byte == 0)?,
+        };
+        frame.body = rest[..end].to_vec();
+        Some((frame, &rest[end + 1..]))
+    }
+}
+
+/// Parses the frames of a message, skipping the heart-beats, which are bare line endings.
+fn parse_frames(mut data: &[u8]) -> Option<Vec<Frame>> {
+    let mut frames = Vec::new();
+    loop {
+        while let Some(rest) = data
+            .strip_prefix(b"\r\n")
+            .or_else(|| data.strip_prefix(b"\n"))
+        {
+            data = rest;
+        }
+        if data.is_empty() {
+            return Some(frames);
+        }
+        let (frame, rest) = Frame::parse(data)?;
+        frames.push(frame);
+        data = rest;
+    }
+}
+
+/// Splits the line at the start of `data`, ended by `\n` or `\r\n`, from the data after it.
+fn line(data: &[u8]) -> Option<(&str, &[u8])> {
+    let end = data.iter().position(|&byte| byte == b'\n')?;
+    let line = &data[..end];
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    Some((std::str::from_utf8(line).ok()?, &data[end + 1..]))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            ':' => escaped.push_str("\\c"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            'r' => '\r',
+            'n' => '\n',
+            'c' => ':',
+            // undefined escapes are a fatal protocol error
+            _ => return None,
+        });
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(message: Message) -> String {
+        match message {
+            Message::Text(text) => text,
+            Message::Bytes(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn encodes_frames() {
+        let frame = send_frame(
+            "/queue/a:b",
+            &[("content-type", "text/plain")],
+            b"hi".to_vec(),
+        );
+        assert_eq!(
+            text(frame.into_message()),
+            "SEND\ndestination:/queue/a\\cb\ncontent-type:text/plain\ncontent-length:2\n\nhi\0"
+        );
+        let connect = ConnectOptions::new("/")
+            .credentials("guest", "pass:word")
+            .heartbeat(Duration::from_secs(10), Duration::ZERO)
+            .frame();
+        assert_eq!(
+            text(connect.into_message()),
+            "CONNECT\naccept-version:1.2\nhost:/\nheart-beat:10000,0\nlogin:guest\npasscode:pass:word\n\n\0"
+        );
+        let binary = send_frame("/queue/b", &[], vec![0xff, 0]);
+        assert!(matches!(binary.into_message(), Message::Bytes(_)));
+    }
+
+    #[test]
+    fn parses_frames() {
+        let data = b"\nMESSAGE\r\nsubscription:sub-0\nmessage-id:1\ndestination:/topic/a\\cb\ncontent-length:3\n\na\0b\0\n\nRECEIPT\nreceipt-id:receipt-1\n\n\0\n";
+        let frames = parse_frames(data).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].command, "MESSAGE");
+        assert_eq!(frames[0].get("destination"), Some("/topic/a:b"));
+        assert_eq!(frames[0].body, b"a\0b");
+        assert_eq!(frames[1].get("receipt-id"), Some("receipt-1"));
+
+        let connected = parse_frames(b"CONNECTED\nversion:1.2\nserver:a:b\n\n\0").unwrap();
+        assert_eq!(connected[0].get("server"), Some("a:b"));
+
+        assert_eq!(parse_frames(b"\n\r\n"), Some(Vec::new()));
+        assert_eq!(parse_frames(b"MESSAGE\nbad:\\t\n\n\0"), None);
+        assert_eq!(parse_frames(b"MESSAGE\n\nno end"), None);
+    }
+
+    #[test]
+    fn negotiates_heartbeats() {
+        let ten = Duration::from_secs(10);
+        assert_eq!(
+            negotiate((ten, ten), Some("5000,20000")),
+            (Some(Duration::from_secs(20)), Some(Duration::from_secs(20)))
+        );
+        assert_eq!(negotiate((ten, ten), Some("0,0")), (None, None));
+        assert_eq!(negotiate((Duration::ZERO, ten), None), (None, None));
+    }
+}
@@ -0,0 +1,500 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{ready, Stream};
+use futures_sink::Sink;
+use js_sys::{Promise, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{ReadableStream, ReadableStreamDefaultReader, WritableStreamDefaultWriter};
+
+use crate::js_to_js_error;
+use crate::websocket::events::{CloseCode, CloseEvent, ErrorEvent};
+use crate::websocket::futures::WebSocket;
+use crate::websocket::{Extensions, Message, WebSocketError};
+
+#[wasm_bindgen]
+extern "C" {
+    /// The WHATWG `WebSocketStream`.
+    #[wasm_bindgen(js_name = WebSocketStream)]
+    type RawWebSocketStream;
+
+    #[wasm_bindgen(constructor, js_class = "WebSocketStream", catch)]
+    fn new(url: &str, options: &JsValue) -> Result<RawWebSocketStream, JsValue>;
+
+    #[wasm_bindgen(method, getter)]
+    fn opened(this: &RawWebSocketStream) -> Promise;
+
+    #[wasm_bindgen(method, getter)]
+    fn closed(this: &RawWebSocketStream) -> Promise;
+
+    #[wasm_bindgen(method, catch)]
+    fn close(this: &RawWebSocketStream, options: &JsValue) -> Result<(), JsValue>;
+}
+
+/// A WebSocket connection backed by the
+/// [`WebSocketStream`](https://developer.chrome.com/docs/capabilities/web-apis/websocketstream)
+/// API where the browser supports it, and by a classic [`WebSocket`] elsewhere.
+///
+/// With `WebSocketStream`, messages are only read as the stream is polled, so a consumer which
+/// falls behind makes the browser stop reading from the network instead of buffering messages
+/// without bound, and the sink waits for the browser to be ready for more messages. The
+/// connection is opened with an `async` [`connect`](Self::connect), and closed with an `async`
+/// [`close`](Self::close) resolving once the closing handshake is done.
+///
+/// # Example
+///
+/// ```
+/// use futures::{SinkExt, StreamExt};
+/// use gloo_net::websocket::{events::CloseCode, Message, WebSocketStream};
+///
+/// # async fn no_run() -> Result<(), gloo_net::websocket::WebSocketError> {
+/// let mut ws = WebSocketStream::connect("wss://example.com/feed").await?;
+/// ws.send(Message::Text("subscribe".to_string())).await?;
+/// while let Some(message) = ws.next().await {
+///     let message = message?;
+///     // processing slowly holds the next messages back in the network
+/// }
+/// let close = ws.close(CloseCode::Normal, "done").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct WebSocketStream {
+    inner: Inner,
+}
+
+enum Inner {
+    Native(Native),
+    Classic(WebSocket),
+}
+
+struct Native {
+    url: String,
+    raw: RawWebSocketStream,
+    reader: ReadableStreamDefaultReader,
+    writer: WritableStreamDefaultWriter,
+    protocol: String,
+    extensions: String,
+    reading: Option<JsFuture>,
+    writer_ready: Option<JsFuture>,
+    /// The writes not known to be done yet, as a promise combining them, and its future.
+    writing: Option<(Promise, JsFuture)>,
+    /// Closing the writable side, which closes the connection.
+    closing_writer: Option<JsFuture>,
+    writer_closed: bool,
+    /// Waiting for the `closed` promise, once the messages ended.
+    closing: Option<JsFuture>,
+    finished: bool,
+}
+
+impl WebSocketStream {
+    /// Opens a connection, waiting for the handshake.
+    ///
+    /// This errors with [`WebSocketError::OpenError`] if the URL is invalid or the port is
+    /// blocked, and with [`WebSocketError::ConnectionError`] if the connection couldn't be
+    /// established.
+    pub async fn connect(url: &str) -> Result<Self, WebSocketError> {
+        Self::connect_with_protocols::<&str>(url, &[]).await
+    }
+
+    /// Opens a connection asking for one of the sub-protocols `protocols`, waiting for the
+    /// handshake.
+    ///
+    /// See [`connect`](Self::connect).
+    pub async fn connect_with_protocols<S: AsRef<str>>(
+        url: &str,
+        protocols: &[S],
+    ) -> Result<Self, WebSocketError> {
+        let inner = if is_supported() {
+            Inner::Native(Native::connect(url, protocols).await?)
+        } else {
+            let mut ws = if protocols.is_empty() {
+                WebSocket::open(url)
+            } else {
+                WebSocket::open_with_protocols(url, protocols)
+            }
+            .map_err(|error| WebSocketError::OpenError {
+                url: url.to_string(),
+                error,
+            })?;
+            ws.negotiated_protocol().await?;
+            Inner::Classic(ws)
+        };
+        Ok(Self { inner })
+    }
+
+    /// Whether the connection is backed by a `WebSocketStream`, rather than a classic
+    /// `WebSocket`.
+    pub fn is_native(&self) -> bool {
+        matches!(self.inner, Inner::Native(_))
+    }
+
+    /// The sub-protocol the server selected, empty when it selected none.
+    pub fn protocol(&self) -> String {
+        match &self.inner {
+            Inner::Native(native) => native.protocol.clone(),
+            Inner::Classic(ws) => ws.protocol(),
+        }
+    }
+
+    /// The extensions in use.
+    pub fn extensions(&self) -> String {
+        match &self.inner {
+            Inner::Native(native) => native.extensions.clone(),
+            Inner::Classic(ws) => ws.extensions(),
+        }
+    }
+
+    /// The extensions in use, parsed, see [`WebSocket::negotiated_extensions`].
+    pub fn negotiated_extensions(&self) -> Extensions {
+        Extensions::parse(&self.extensions())
+    }
+
+    /// Closes the connection, resolving with the close event once the closing handshake is
+    /// done.
+    ///
+    /// This errors if the code isn't [sendable](CloseCode::is_sendable), or the reason is longer
+    /// than 123 bytes.
+    pub async fn close(self, code: CloseCode, reason: &str) -> Result<CloseEvent, WebSocketError> {
+        match self.inner {
+            Inner::Native(mut native) => {
+                let options = js_sys::Object::new();
+                let _ = Reflect::set(
+                    &options,
+                    &JsValue::from_str("closeCode"),
+                    &JsValue::from(u16::from(code)),
+                );
+                let _ = Reflect::set(
+                    &options,
+                    &JsValue::from_str("reason"),
+                    &JsValue::from_str(reason),
+                );
+                native
+                    .raw
+                    .close(&options)
+                    .map_err(|error| WebSocketError::MessageSendError {
+                        url: native.url.clone(),
+                        error: js_to_js_error(error),
+                    })?;
+                let closed = JsFuture::from(native.raw.closed()).await;
+                native.finished = true;
+                Ok(close_event(&native.url, closed))
+            }
+            Inner::Classic(mut ws) => {
+                ws.close_raw(Some(code.into()), Some(reason))
+                    .map_err(|error| WebSocketError::MessageSendError {
+                        url: ws.url(),
+                        error,
+                    })?;
+                Ok(ws.closed().await)
+            }
+        }
+    }
+}
+
+impl Native {
+    async fn connect<S: AsRef<str>>(url: &str, protocols: &[S]) -> Result<Self, WebSocketError> {
+        let options = js_sys::Object::new();
+        if !protocols.is_empty() {
+            let protocols = protocols
+                .iter()
+                .map(|protocol| JsValue::from_str(protocol.as_ref()))
+                .collect::<js_sys::Array>();
+            let _ = Reflect::set(&options, &JsValue::from_str("protocols"), &protocols);
+        }
+        let raw =
+            RawWebSocketStream::new(url, &options).map_err(|error| WebSocketError::OpenError {
+                url: url.to_string(),
+                error: js_to_js_error(error),
+            })?;
+        let opened = JsFuture::from(raw.opened())
+            .await
+            .map_err(|error| connection_error(url, &error, false))?;
+        let get = |key: &str| Reflect::get(&opened, &JsValue::from_str(key)).unwrap_or_default();
+        let readable: ReadableStream = get("readable").unchecked_into();
+        let writer = get("writable")
+            .unchecked_into::<web_sys::WritableStream>()
+            .get_writer()
+            .map_err(|error| WebSocketError::OpenError {
+                url: url.to_string(),
+                error: js_to_js_error(error),
+            })?;
+        Ok(Self {
+            url: url.to_string(),
+            reader: readable.get_reader().unchecked_into(),
+            writer,
+            protocol: get("protocol").as_string().unwrap_or_default(),
+            extensions: get("extensions").as_string().unwrap_or_default(),
+            raw,
+            reading: None,
+            writer_ready: None,
+            writing: None,
+            closing_writer: None,
+            writer_closed: false,
+            closing: None,
+            finished: false,
+        })
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Message, WebSocketError>>> {
+        loop {
+            if self.finished {
+                return Poll::Ready(None);
+            }
+            if let Some(closing) = &mut self.closing {
+                let closed = ready!(Pin::new(closing).poll(cx));
+                self.closing = None;
+                self.finished = true;
+                return Poll::Ready(Some(Err(WebSocketError::ConnectionClose(close_event(
+                    &self.url, closed,
+                )))));
+            }
+            let reader = &self.reader;
+            let reading = self
+                .reading
+                .get_or_insert_with(|| JsFuture::from(reader.read()));
+            let read = ready!(Pin::new(reading).poll(cx));
+            self.reading = None;
+            let chunk = match read {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    self.closing = Some(JsFuture::from(self.raw.closed()));
+                    return Poll::Ready(Some(Err(connection_error(&self.url, &error, true))));
+                }
+            };
+            let get = |key: &str| Reflect::get(&chunk, &JsValue::from_str(key)).unwrap_or_default();
+            if get("done").is_truthy() {
+                self.closing = Some(JsFuture::from(self.raw.closed()));
+                continue;
+            }
+            let value = get("value");
+            let message = match value.as_string() {
+                Some(text) => Message::Text(text),
+                // binary messages are `ArrayBuffer`s, or `Uint8Array`s in newer browsers
+                None => Message::Bytes(Uint8Array::new(&value).to_vec()),
+            };
+            return Poll::Ready(Some(Ok(message)));
+        }
+    }
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), WebSocketError>> {
+        // the error of the previous write is reported here, rather than lost
+        ready!(self.poll_flush(cx))?;
+        let writer = &self.writer;
+        let writer_ready = self
+            .writer_ready
+            .get_or_insert_with(|| JsFuture::from(writer.ready()));
+        let result = ready!(Pin::new(writer_ready).poll(cx));
+        self.writer_ready = None;
+        Poll::Ready(
+            result
+                .map(|_| ())
+                .map_err(|error| connection_error(&self.url, &error, true)),
+        )
+    }
+
+    fn start_send(&mut self, message: Message) {
+        let chunk = match message {
+            Message::Text(text) => JsValue::from_str(&text),
+            Message::Bytes(bytes) => Uint8Array::from(bytes.as_slice()).into(),
+        };
+        let write = self.writer.write_with_chunk(&chunk);
+        // without `poll_ready`, the previous write may still be pending
+        let write = match self.writing.take() {
+            Some((previous, _)) => Promise::all(&js_sys::Array::of2(&previous, &write)),
+            None => write,
+        };
+        self.writing = Some((write.clone(), JsFuture::from(write)));
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), WebSocketError>> {
+        if let Some((_, writing)) = &mut self.writing {
+            let result = ready!(Pin::new(writing).poll(cx));
+            self.writing = None;
+            result.map_err(|error| connection_error(&self.url, &error, true))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), WebSocketError>> {
+        ready!(self.poll_flush(cx))?;
+        if self.writer_closed {
+            return Poll::Ready(Ok(()));
+        }
+        let writer = &self.writer;
+        let closing = self
+            .closing_writer
+            .get_or_insert_with(|| JsFuture::from(writer.close()));
+        let result = ready!(Pin::new(closing).poll(cx));
+        self.closing_writer = None;
+        self.writer_closed = true;
+        Poll::Ready(
+            result
+                .map(|_| ())
+                .map_err(|error| connection_error(&self.url, &error, true)),
+        )
+    }
+}
+
+impl Drop for Native {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.raw.close(&JsValue::UNDEFINED);
+        }
+    }
+}
+
+impl Stream for WebSocketStream {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match &mut self.inner {
+            Inner::Native(native) => native.poll_next(cx),
+            Inner::Classic(ws) => Pin::new(ws).poll_next(cx),
+        }
+    }
+}
+
+impl Sink<Message> for WebSocketStream {
+    type Error = WebSocketError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.inner {
+            Inner::Native(native) => native.poll_ready(cx),
+            Inner::Classic(ws) => Pin::new(ws).poll_ready(cx),
+        }
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        match &mut self.inner {
+            Inner::Native(native) => {
+                native.start_send(item);
+                Ok(())
+            }
+            Inner::Classic(ws) => Pin::new(ws).start_send(item),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.inner {
+            Inner::Native(native) => native.poll_flush(cx),
+            Inner::Classic(ws) => Pin::new(ws).poll_flush(cx),
+        }
+    }
+
+    /// Closes the connection with a normal closure once the messages sent were written.
+    ///
+    /// Unlike [`close`](WebSocketStream::close), this doesn't wait for the closing handshake.
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.inner {
+            Inner::Native(native) => native.poll_close(cx),
+            Inner::Classic(ws) => {
+                ready!(Pin::new(&mut *ws).poll_close(cx))?;
+                Poll::Ready(
+                    ws.close_raw(Some(CloseCode::Normal.into()), None)
+                        .map_err(|error| WebSocketError::MessageSendError {
+                            url: ws.url(),
+                            error,
+                        }),
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WebSocketStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketStream")
+            .field("native", &self.is_native())
+            .field("protocol", &self.protocol())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether the browser supports `WebSocketStream`.
+fn is_supported() -> bool {
+    Reflect::has(&js_sys::global(), &JsValue::from_str("WebSocketStream")).unwrap_or(false)
+}
+
+/// The close event of the `closed` promise of a `WebSocketStream`, which rejects when the
+/// connection failed.
+fn close_event(url: &str, closed: Result<JsValue, JsValue>) -> CloseEvent {
+    match closed {
+        Ok(info) => {
+            let get = |key: &str| Reflect::get(&info, &JsValue::from_str(key)).unwrap_or_default();
+            CloseEvent {
+                url: url.to_string(),
+                code: get("closeCode").as_f64().map_or(1005, |code| code as u16),
+                reason: get("reason").as_string().unwrap_or_default(),
+                was_clean: true,
+            }
+        }
+        Err(_) => CloseEvent {
+            url: url.to_string(),
+            code: 1006,
+            reason: String::new(),
+            was_clean: false,
+        },
+    }
+}
+
+/// The error of a promise of a `WebSocketStream` rejecting, whose `WebSocketError` exception
+/// has a message, unlike the error events of a classic `WebSocket`.
+fn connection_error(url: &str, error: &JsValue, was_open: bool) -> WebSocketError {
+    let message = error
+        .dyn_ref::<js_sys::Error>()
+        .map(|error| String::from(error.message()))
+        .filter(|message| !message.is_empty());
+    WebSocketError::ConnectionError(ErrorEvent::new(url.to_string(), message, None, was_open))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn messages_are_echoed() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let mut ws = WebSocketStream::connect(ws_echo_server_url).await.unwrap();
+        // ignore the info message of the echo-server
+        let _ = ws.next().await;
+
+        ws.send(Message::Text("test 1".to_string())).await.unwrap();
+        ws.send(Message::Bytes(vec![1, 2, 3])).await.unwrap();
+        assert_eq!(
+            ws.next().await.unwrap().unwrap(),
+            Message::Text("test 1".to_string())
+        );
+        assert_eq!(
+            ws.next().await.unwrap().unwrap(),
+            Message::Bytes(vec![1, 2, 3])
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn closing_the_sink_closes_the_connection() {
+        let ws_echo_server_url =
+            option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?");
+
+        let mut ws = WebSocketStream::connect(ws_echo_server_url).await.unwrap();
+        ws.feed(Message::Text("last".to_string())).await.unwrap();
+        SinkExt::close(&mut ws).await.unwrap();
+
+        let mut closed = false;
+        while let Some(item) = ws.next().await {
+            if let Err(WebSocketError::ConnectionClose(_)) = item {
+                closed = true;
+            }
+        }
+        assert!(closed);
+    }
+}
@@ -0,0 +1,87 @@
+//! Conversions between the messages of this crate and those of [`tungstenite`], so that
+//! protocol code written against `tungstenite::Message` runs in the browser too.
+
+use std::convert::TryFrom;
+
+use tungstenite::protocol::frame::coding::CloseCode as TungsteniteCloseCode;
+use tungstenite::protocol::CloseFrame;
+
+use crate::websocket::events::{CloseCode, CloseEvent};
+use crate::websocket::Message;
+
+impl From<Message> for tungstenite::Message {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::Text(text) => tungstenite::Message::Text(text),
+            Message::Bytes(bytes) => tungstenite::Message::Binary(bytes),
+        }
+    }
+}
+
+/// Browsers handle the control frames of the protocol themselves, so only text and binary
+/// messages convert, and the other ones are given back.
+impl TryFrom<tungstenite::Message> for Message {
+    type Error = tungstenite::Message;
+
+    fn try_from(message: tungstenite::Message) -> Result<Self, Self::Error> {
+        match message {
+            tungstenite::Message::Text(text) => Ok(Message::Text(text)),
+            tungstenite::Message::Binary(bytes) => Ok(Message::Bytes(bytes)),
+            message => Err(message),
+        }
+    }
+}
+
+impl From<CloseCode> for TungsteniteCloseCode {
+    fn from(code: CloseCode) -> Self {
+        u16::from(code).into()
+    }
+}
+
+impl From<TungsteniteCloseCode> for CloseCode {
+    fn from(code: TungsteniteCloseCode) -> Self {
+        u16::from(code).into()
+    }
+}
+
+/// The close event as the `Close` message a native client receives.
+impl From<CloseEvent> for tungstenite::Message {
+    fn from(event: CloseEvent) -> Self {
+        tungstenite::Message::Close(Some(CloseFrame {
+            code: event.code.into(),
+            reason: event.reason.into(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_convert_both_ways() {
+        for message in [
+            Message::Text("hello".to_string()),
+            Message::Bytes(vec![1, 2, 3]),
+        ] {
+            let converted = tungstenite::Message::from(message.clone());
+            assert_eq!(Message::try_from(converted).unwrap(), message);
+        }
+        assert_eq!(
+            Message::try_from(tungstenite::Message::Ping(vec![1])),
+            Err(tungstenite::Message::Ping(vec![1]))
+        );
+    }
+
+    #[test]
+    fn close_codes_convert_both_ways() {
+        assert_eq!(
+            TungsteniteCloseCode::from(CloseCode::GoingAway),
+            TungsteniteCloseCode::Away
+        );
+        assert_eq!(
+            CloseCode::from(TungsteniteCloseCode::Library(4001)),
+            CloseCode::Application(4001)
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{ready, Stream};
+use futures_sink::Sink;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::websocket::futures::{WebSocket, WebSocketReceiver, WebSocketSender};
+use crate::websocket::{Message, WebSocketError};
+
+/// The error of a [`Codec`].
+pub type CodecError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A format the messages of a [`WebSocket::typed`] connection are encoded in.
+///
+/// [`Json`] is sent as text messages, and the binary formats as binary messages. Implementing it
+/// plugs other formats in.
+///
+/// # Example
+///
+/// ```
+/// use gloo_net::websocket::{Codec, CodecError, Message};
+/// use serde::{de::DeserializeOwned, Serialize};
+///
+/// /// JSON, pretty-printed.
+/// struct PrettyJson;
+///
+/// impl Codec for PrettyJson {
+///     fn encode<T: Serialize>(value: &T) -> Result<Message, CodecError> {
+///         Ok(Message::Text(serde_json::to_string_pretty(value)?))
+///     }
+///
+///     fn decode<T: DeserializeOwned>(message: Message) -> Result<T, CodecError> {
+///         match message {
+///             Message::Text(text) => Ok(serde_json::from_str(&text)?),
+///             Message::Bytes(bytes) => Ok(serde_json::from_slice(&bytes)?),
+///         }
+///     }
+/// }
+/// ```
+pub trait Codec {
+    /// Encodes `value` into a message.
+    fn encode<T: Serialize>(value: &T) -> Result<Message, CodecError>;
+
+    /// Decodes a value from `message`.
+    fn decode<T: DeserializeOwned>(message: Message) -> Result<T, CodecError>;
+}
+
+/// Messages encoded as JSON, sent as text messages.
+///
+/// Binary messages are decoded as UTF-8 JSON too.
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    fn encode<T: Serialize>(value: &T) -> Result<Message, CodecError> {
+        Ok(Message::Text(serde_json::to_string(value)?))
+    }
+
+    fn decode<T: DeserializeOwned>(message: Message) -> Result<T, CodecError> {
+        match message {
+            Message::Text(text) => Ok(serde_json::from_str(&text)?),
+            Message::Bytes(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        }
+    }
+}
+
+/// Messages encoded as CBOR, sent as binary messages.
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Codec for Cbor {
+    fn encode<T: Serialize>(value: &T) -> Result<Message, CodecError> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(value, &mut bytes)?;
+        Ok(Message::Bytes(bytes))
+    }
+
+    fn decode<T: DeserializeOwned>(message: Message) -> Result<T, CodecError> {
+        Ok(ciborium::de::from_reader(
+            binary(message, "CBOR")?.as_slice(),
+        )?)
+    }
+}
+
+/// Messages encoded as MessagePack, sent as binary messages.
+///
+/// Structs are encoded as maps with named fields, which other MessagePack implementations can
+/// decode.
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPack;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MsgPack {
+    fn encode<T: Serialize>(value: &T) -> Result<Message, CodecError> {
+        Ok(Message::Bytes(rmp_serde::to_vec_named(value)?))
+    }
+
+    fn decode<T: DeserializeOwned>(message: Message) -> Result<T, CodecError> {
+        Ok(rmp_serde::from_slice(&binary(message, "MessagePack")?)?)
+    }
+}
+
+/// Messages encoded with `bincode`, sent as binary messages, for servers written in Rust.
+///
+/// Only use this with a server which encodes the messages with the same version and
+/// configuration of `bincode`, typically one sharing the type definitions.
+#[cfg(feature = "bincode")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bincode")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Codec for Bincode {
+    fn encode<T: Serialize>(value: &T) -> Result<Message, CodecError> {
+        Ok(Message::Bytes(bincode::serialize(value)?))
+    }
+
+    fn decode<T: DeserializeOwned>(message: Message) -> Result<T, CodecError> {
+        Ok(bincode::deserialize(&binary(message, "bincode")?)?)
+    }
+}
+
+/// The bytes of a binary message, as the binary formats expect.
+#[cfg(any(feature = "cbor", feature = "msgpack", feature = "bincode"))]
+fn binary(message: Message, format: &str) -> Result<Vec<u8>, CodecError> {
+    match message {
+        Message::Bytes(bytes) => Ok(bytes),
+        Message::Text(_) => Err(format!("expected a binary {} message, got text", format).into()),
+    }
+}
+
+impl WebSocket {
+    /// Splits the connection into a [`Sink`] of `Tx` and a [`Stream`] of `Rx`, encoded in the
+    /// messages with the codec `C`.
+    ///
+    /// A message which can't be decoded is reported as a [`WebSocketError::CodecError`] on the
+    /// stream, which then goes on with the next message. Like with [`WebSocket::into_halves`], the
+    /// connection is closed once both halves are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::{SinkExt, StreamExt};
+    /// use gloo_net::websocket::{futures::WebSocket, Json};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize)]
+    /// enum Command {
+    ///     Subscribe { channel: String },
+    /// }
+    ///
+    /// #[derive(Deserialize)]
+    /// enum Event {
+    ///     Price { symbol: String, price: f64 },
+    /// }
+    ///
+    /// # async fn no_run() -> Result<(), gloo_net::websocket::WebSocketError> {
+    /// let ws = WebSocket::open("wss://example.com/prices").unwrap();
+    /// let (mut commands, mut events) = ws.typed::<Command, Event, Json>();
+    ///
+    /// commands
+    ///     .send(Command::Subscribe { channel: "BTC".to_string() })
+    ///     .await?;
+    /// while let Some(event) = events.next().await {
+    ///     let Event::Price { symbol, price } = event?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn typed<Tx, Rx, C>(self) -> (TypedSink<Tx, C>, TypedStream<Rx, C>)
+    where
+        Tx: Serialize,
+        Rx: DeserializeOwned,
+        C: Codec,
+    {
+        let (sender, receiver) = self.into_halves();
+        (
+            TypedSink {
+                sender,
+                _marker: PhantomData,
+            },
+            TypedStream {
+                receiver,
+                _marker: PhantomData,
+            },
+        )
+    }
+}
+
+/// The sending half of a [`WebSocket::typed`] connection, encoding `Tx` with the codec `C`.
+#[must_use = "sinks do nothing unless polled"]
+pub struct TypedSink<Tx, C> {
+    sender: WebSocketSender,
+    _marker: PhantomData<fn(Tx) -> C>,
+}
+
+impl<Tx: Serialize, C: Codec> Sink<Tx> for TypedSink<Tx, C> {
+    type Error = WebSocketError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.sender).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Tx) -> Result<(), Self::Error> {
+        let message = C::encode(&item).map_err(|source| WebSocketError::CodecError {
+            url: self.sender.url(),
+            source,
+        })?;
+        Pin::new(&mut self.sender).start_send(message)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.sender).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.sender).poll_close(cx)
+    }
+}
+
+impl<Tx, C> fmt::Debug for TypedSink<Tx, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedSink").finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a [`WebSocket::typed`] connection, decoding `Rx` with the codec `C`.
+#[must_use = "streams do nothing unless polled"]
+pub struct TypedStream<Rx, C> {
+    receiver: WebSocketReceiver,
+    _marker: PhantomData<fn() -> (Rx, C)>,
+}
+
+impl<Rx: DeserializeOwned, C: Codec> Stream for TypedStream<Rx, C> {
+    type Item = Result<Rx, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let message = ready!(Pin::new(&mut self.receiver).poll_next(cx));
+        Poll::Ready(message.map(|message| {
+            message.and_then(|message| {
+                C::decode(message).map_err(|source| WebSocketError::CodecError {
+                    url: self.receiver.url(),
+                    source,
+                })
+            })
+        }))
+    }
+}
+
+impl<Rx, C> fmt::Debug for TypedStream<Rx, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedStream").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Price {
+        symbol: String,
+        price: f64,
+    }
+
+    fn round_trip<C: Codec>() -> Message {
+        let price = Price {
+            symbol: "BTC".to_string(),
+            price: 42.5,
+        };
+        let message = C::encode(&price).unwrap();
+        assert_eq!(C::decode::<Price>(message.clone()).unwrap(), price);
+        assert!(C::decode::<Price>(Message::Bytes(vec![0xc1])).is_err());
+        message
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_messages() {
+        assert_eq!(
+            round_trip::<Json>(),
+            Message::Text(r#"{"symbol":"BTC","price":42.5}"#.to_string())
+        );
+        let bytes = br#"{"symbol":"ETH","price":1.0}"#.to_vec();
+        assert_eq!(
+            Json::decode::<Price>(Message::Bytes(bytes)).unwrap().symbol,
+            "ETH"
+        );
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_messages() {
+        assert!(matches!(round_trip::<Cbor>(), Message::Bytes(_)));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_messages() {
+        assert!(matches!(round_trip::<MsgPack>(), Message::Bytes(_)));
+        assert!(MsgPack::decode::<Price>(Message::Text("{}".to_string())).is_err());
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_messages() {
+        assert!(matches!(round_trip::<Bincode>(), Message::Bytes(_)));
+    }
+}
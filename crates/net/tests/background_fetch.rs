@@ -0,0 +1,116 @@
+#![cfg(feature = "background-fetch")]
+
+use gloo_net::http::{
+    BackgroundFetch, BackgroundFetchIcon, BackgroundFetchOptions, BackgroundFetchResult, Request,
+};
+use js_sys::Reflect;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// A service worker registration whose `backgroundFetch` records what it is asked to fetch in
+/// `globalThis.backgroundFetched`, rather than downloading it.
+fn registration() -> web_sys::ServiceWorkerRegistration {
+    js_sys::eval(
+        "({
+            backgroundFetch: {
+                fetch(id, requests, options) {
+                    globalThis.backgroundFetched = { id, requests, options };
+                    return Promise.resolve({
+                        id,
+                        result: '',
+                        failureReason: '',
+                        downloaded: 0,
+                        downloadTotal: options.downloadTotal || 0,
+                    });
+                },
+            },
+        })",
+    )
+    .unwrap()
+    .unchecked_into()
+}
+
+fn get(target: &JsValue, path: &str) -> JsValue {
+    path.split('.').fold(target.clone(), |target, name| {
+        Reflect::get(&target, &name.into()).unwrap()
+    })
+}
+
+#[wasm_bindgen_test]
+async fn passes_requests_and_options_on() {
+    let fetch = BackgroundFetch::from_registration(&registration()).unwrap();
+    let request = Request::get("/episodes/42.mp4")
+        .header("X-Quality", "hd")
+        .build()
+        .unwrap();
+    let options = BackgroundFetchOptions::new()
+        .title("Episode 42")
+        .icon(
+            BackgroundFetchIcon::new("/icon.png")
+                .sizes("192x192")
+                .mime_type("image/png")
+                .label("Podcast"),
+        )
+        .icon(BackgroundFetchIcon::new("/icon.svg"))
+        .download_total(60_000_000);
+    let episode = fetch
+        .fetch("episode-42", vec![request], options)
+        .await
+        .unwrap();
+    assert_eq!(episode.id(), "episode-42");
+    assert_eq!(episode.result(), BackgroundFetchResult::Pending);
+    assert_eq!(episode.download_total(), Some(60_000_000));
+
+    let fetched = get(&js_sys::global(), "backgroundFetched");
+    assert_eq!(
+        get(&fetched, "id").as_string().as_deref(),
+        Some("episode-42")
+    );
+    let request: web_sys::Request = get(&fetched, "requests.0").unchecked_into();
+    assert!(request.url().ends_with("/episodes/42.mp4"));
+    assert_eq!(
+        request.headers().get("X-Quality").unwrap().as_deref(),
+        Some("hd")
+    );
+
+    let options = get(&fetched, "options");
+    assert_eq!(
+        get(&options, "title").as_string().as_deref(),
+        Some("Episode 42")
+    );
+    assert_eq!(get(&options, "downloadTotal").as_f64(), Some(60_000_000.0));
+    let icon = get(&options, "icons.0");
+    assert_eq!(get(&icon, "src").as_string().as_deref(), Some("/icon.png"));
+    assert_eq!(get(&icon, "sizes").as_string().as_deref(), Some("192x192"));
+    assert_eq!(get(&icon, "type").as_string().as_deref(), Some("image/png"));
+    assert_eq!(get(&icon, "label").as_string().as_deref(), Some("Podcast"));
+    // the fields left out aren't sent
+    let icon = get(&options, "icons.1");
+    assert_eq!(get(&icon, "src").as_string().as_deref(), Some("/icon.svg"));
+    assert!(!Reflect::has(&icon, &"sizes".into()).unwrap());
+    assert!(!Reflect::has(&icon, &"type".into()).unwrap());
+
+    let fetch = BackgroundFetch::from_registration(&registration()).unwrap();
+    fetch
+        .fetch("empty", Vec::new(), BackgroundFetchOptions::new())
+        .await
+        .unwrap();
+    let options = get(&js_sys::global(), "backgroundFetched.options");
+    assert_eq!(
+        js_sys::Object::keys(options.unchecked_ref::<js_sys::Object>()).length(),
+        0
+    );
+}
+
+#[wasm_bindgen_test]
+fn unsupported_browsers_are_reported() {
+    let registration = js_sys::Object::new().unchecked_into();
+    match BackgroundFetch::from_registration(&registration) {
+        Err(gloo_net::Error::GlooError(message)) => {
+            assert_eq!(message, "the Background Fetch API is not available")
+        }
+        result => panic!("expected the API to be unsupported, got {:?}", result),
+    }
+}
@@ -0,0 +1,29 @@
+#![cfg(feature = "cache")]
+
+use gloo_net::cache::Cache;
+use gloo_net::http::{Request, Response};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+async fn put_match_delete() {
+    let cache = Cache::open("gloo-net-test").await.unwrap();
+    let request = Request::get("/cached").build().unwrap();
+    assert!(cache.match_request(&request).await.unwrap().is_none());
+
+    let response = Response::builder()
+        .status(200)
+        .body(Some("cached body"))
+        .unwrap();
+    cache.put(&request, &response).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "cached body");
+
+    let cached = cache.match_request(&request).await.unwrap().unwrap();
+    assert_eq!(cached.text().await.unwrap(), "cached body");
+
+    assert!(cache.delete(&request).await.unwrap());
+    assert!(cache.match_request(&request).await.unwrap().is_none());
+    assert!(Cache::delete_cache("gloo-net-test").await.unwrap());
+    assert!(!Cache::exists("gloo-net-test").await.unwrap());
+}
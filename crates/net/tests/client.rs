@@ -0,0 +1,605 @@
+use gloo_net::http::{Client, FetchFuture, Fetcher, Request, Response};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// Answers every request with its method and URL, remembering the URLs it saw.
+#[derive(Default)]
+struct Echo {
+    seen: Rc<RefCell<Vec<String>>>,
+}
+
+impl Fetcher for Echo {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        self.seen.borrow_mut().push(request.url());
+        Box::pin(async move {
+            let body = format!("{} {}", request.method(), request.url());
+            Response::builder().status(200).body(Some(body.as_str()))
+        })
+    }
+}
+
+#[wasm_bindgen_test]
+async fn client_uses_fetcher() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let client = Client::with_fetcher(Echo { seen: seen.clone() });
+
+    let resp = client
+        .post("https://example.com/items")
+        .body("{}")
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "POST https://example.com/items");
+
+    let request = Request::get("https://example.com/other").build().unwrap();
+    client.send(request).await.unwrap();
+    assert_eq!(
+        *seen.borrow(),
+        vec![
+            "https://example.com/items".to_string(),
+            "https://example.com/other".to_string()
+        ]
+    );
+}
+
+#[cfg(feature = "tower")]
+#[wasm_bindgen_test]
+async fn client_is_tower_service() {
+    use tower_service::Service;
+
+    let mut client = Client::with_fetcher(Echo::default());
+    let request = http::Request::put("https://example.com/items/1")
+        .header("Content-Type", "text/plain")
+        .body("updated".to_string())
+        .unwrap();
+    let resp = client.call(request).await.unwrap();
+    assert_eq!(
+        resp.text().await.unwrap(),
+        "PUT https://example.com/items/1"
+    );
+}
+
+#[wasm_bindgen_test]
+async fn interceptors_wrap_requests() {
+    let client = Client::with_fetcher(Echo::default())
+        .with_interceptor(|request, next| async move {
+            assert_eq!(request.headers().get("X-Trace").as_deref(), Some("1"));
+            next.run(request).await
+        })
+        .with_interceptor(|request, next| async move {
+            request.headers().set("X-Trace", "1");
+            let response = next.run(request).await?;
+            let body = response.text().await?;
+            Response::builder()
+                .status(200)
+                .body(Some(body.to_uppercase().as_str()))
+        });
+
+    let resp = client.get("https://example.com/").send().await.unwrap();
+    assert_eq!(resp.text().await.unwrap(), "GET HTTPS://EXAMPLE.COM/");
+}
+
+#[wasm_bindgen_test]
+async fn retries_server_errors() {
+    use gloo_net::http::{Headers, RetryPolicy};
+    use std::cell::Cell;
+
+    /// Fails with `503 Service Unavailable` until the third attempt.
+    #[derive(Default)]
+    struct Flaky {
+        attempts: Rc<Cell<u32>>,
+    }
+
+    impl Fetcher for Flaky {
+        fn fetch(&self, _request: Request) -> FetchFuture<'_> {
+            self.attempts.set(self.attempts.get() + 1);
+            let attempts = self.attempts.get();
+            Box::pin(async move {
+                let headers = Headers::new();
+                headers.set("Retry-After", "0");
+                let status = if attempts < 3 { 503 } else { 200 };
+                Response::builder()
+                    .status(status)
+                    .headers(headers)
+                    .body(None::<&str>)
+            })
+        }
+    }
+
+    let attempts = Rc::new(Cell::new(0));
+    let client = Client::with_fetcher(Flaky {
+        attempts: attempts.clone(),
+    });
+
+    let resp = client
+        .get("https://example.com/")
+        .retry(RetryPolicy::new())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(attempts.get(), 3);
+
+    attempts.set(0);
+    let resp = client
+        .post("https://example.com/")
+        .retry(RetryPolicy::new())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 503);
+    assert_eq!(attempts.get(), 1);
+}
+
+#[wasm_bindgen_test]
+async fn deduplicates_concurrent_gets() {
+    /// Echoes requests after a delay, so that they overlap.
+    struct Slow(Echo);
+
+    impl Fetcher for Slow {
+        fn fetch(&self, request: Request) -> FetchFuture<'_> {
+            Box::pin(async move {
+                gloo_timers::future::sleep(std::time::Duration::from_millis(10)).await;
+                self.0.fetch(request).await
+            })
+        }
+    }
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let client = Client::with_fetcher(Slow(Echo { seen: seen.clone() })).with_deduplication();
+
+    let (a, b, c) = futures::join!(
+        client.get("https://example.com/a").send(),
+        client.get("https://example.com/a").send(),
+        client.get("https://example.com/b").send(),
+    );
+    assert_eq!(
+        a.unwrap().text().await.unwrap(),
+        "GET https://example.com/a"
+    );
+    assert_eq!(
+        b.unwrap().text().await.unwrap(),
+        "GET https://example.com/a"
+    );
+    assert_eq!(
+        c.unwrap().text().await.unwrap(),
+        "GET https://example.com/b"
+    );
+    assert_eq!(seen.borrow().len(), 2);
+
+    // requests which are not in flight at the same time are both sent
+    client.get("https://example.com/a").send().await.unwrap();
+    assert_eq!(seen.borrow().len(), 3);
+}
+
+#[wasm_bindgen_test]
+async fn limits_concurrent_requests() {
+    use std::cell::Cell;
+
+    /// Records the most requests it had in flight at once.
+    #[derive(Default)]
+    struct Gauge {
+        in_flight: Cell<u32>,
+        max: Rc<Cell<u32>>,
+    }
+
+    impl Fetcher for Gauge {
+        fn fetch(&self, _request: Request) -> FetchFuture<'_> {
+            Box::pin(async move {
+                self.in_flight.set(self.in_flight.get() + 1);
+                self.max.set(u32::max(self.max.get(), self.in_flight.get()));
+                gloo_timers::future::sleep(std::time::Duration::from_millis(10)).await;
+                self.in_flight.set(self.in_flight.get() - 1);
+                Response::builder().status(200).body(None::<&str>)
+            })
+        }
+    }
+
+    let max = Rc::new(Cell::new(0));
+    let client = Client::with_fetcher(Gauge {
+        max: max.clone(),
+        ..Gauge::default()
+    })
+    .max_concurrent(2);
+
+    let responses = futures::future::join_all(
+        (0..5).map(|i| client.get(&format!("https://example.com/{}", i)).send()),
+    )
+    .await;
+    assert!(responses.iter().all(|resp| resp.is_ok()));
+    assert_eq!(max.get(), 2);
+}
+
+#[wasm_bindgen_test]
+async fn circuit_breaker_fails_fast() {
+    use gloo_net::http::{CircuitBreaker, CircuitState};
+    use gloo_net::Error;
+
+    struct Down;
+
+    impl Fetcher for Down {
+        fn fetch(&self, _request: Request) -> FetchFuture<'_> {
+            Box::pin(async { Response::builder().status(503).body(None::<&str>) })
+        }
+    }
+
+    let states = Rc::new(RefCell::new(Vec::new()));
+    let client = Client::with_fetcher(Down).with_circuit_breaker(
+        CircuitBreaker::new().failure_threshold(2).on_state_change({
+            let states = states.clone();
+            move |origin, state| states.borrow_mut().push((origin.to_string(), state))
+        }),
+    );
+
+    for _ in 0..2 {
+        let resp = client.get("https://example.com/").send().await.unwrap();
+        assert_eq!(resp.status(), 503);
+    }
+    let err = client.get("https://example.com/").send().await.unwrap_err();
+    assert!(matches!(err, Error::CircuitOpen(origin) if origin == "https://example.com"));
+    assert_eq!(
+        *states.borrow(),
+        vec![("https://example.com".to_string(), CircuitState::Open)]
+    );
+
+    // other origins are unaffected
+    let resp = client.get("https://example.org/").send().await.unwrap();
+    assert_eq!(resp.status(), 503);
+}
+
+#[wasm_bindgen_test]
+async fn revalidates_with_etag() {
+    use gloo_net::http::Headers;
+
+    /// Serves a body with an `ETag`, answering `304` when it matches.
+    struct Tagged;
+
+    impl Fetcher for Tagged {
+        fn fetch(&self, request: Request) -> FetchFuture<'_> {
+            Box::pin(async move {
+                if request.headers().get("If-None-Match").as_deref() == Some("\"v1\"") {
+                    return Response::builder().status(304).body(None::<&str>);
+                }
+                let headers = Headers::new();
+                headers.set("ETag", "\"v1\"");
+                Response::builder()
+                    .status(200)
+                    .headers(headers)
+                    .body(Some("first"))
+            })
+        }
+    }
+
+    let client = Client::with_fetcher(Tagged).with_revalidation();
+    for _ in 0..3 {
+        let resp = client.get("https://example.com/").send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.text().await.unwrap(), "first");
+    }
+}
+
+#[wasm_bindgen_test]
+async fn try_clone_copies_body() {
+    let request = Request::post("https://example.com/")
+        .body("payload")
+        .unwrap();
+    let copy = request.try_clone().unwrap();
+    assert_eq!(copy.url(), request.url());
+    assert_eq!(copy.text().await.unwrap(), "payload");
+    assert_eq!(request.text().await.unwrap(), "payload");
+    assert!(request.try_clone().is_err());
+}
+
+#[cfg(feature = "url")]
+#[wasm_bindgen_test]
+fn validated_urls() {
+    assert!(Request::try_get("/relative").is_err());
+
+    let url = url::Url::parse("https://example.com/a/../b").unwrap();
+    let request = Request::try_get(url).unwrap().build().unwrap();
+    let parsed = request.parsed_url().unwrap();
+    assert_eq!(parsed.host_str(), Some("example.com"));
+    assert_eq!(parsed.path(), "/b");
+}
+
+#[wasm_bindgen_test]
+async fn resolves_against_base_url() {
+    let client = Client::with_fetcher(Echo::default()).with_base_url("https://example.com/app/");
+    let resp = client
+        .get("items")
+        .query([("page", "2")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.text().await.unwrap(),
+        "GET https://example.com/app/items?page=2"
+    );
+
+    let builder = Request::get("https://example.org/?a=1").query([("b", "2")]);
+    assert_eq!(
+        builder.resolved_url().unwrap(),
+        "https://example.org/?a=1&b=2"
+    );
+}
+
+#[wasm_bindgen_test]
+async fn follows_redirects_manually() {
+    use gloo_net::http::Headers;
+
+    /// Redirects `/old` to `/new` with the status in the query, and echoes anything else.
+    struct Redirecting;
+
+    impl Fetcher for Redirecting {
+        fn fetch(&self, request: Request) -> FetchFuture<'_> {
+            Box::pin(async move {
+                let url = request.url();
+                if let Some(status) = url.strip_prefix("https://example.com/old?status=") {
+                    let headers = Headers::new();
+                    headers.set("Location", "https://example.com/new");
+                    return Response::builder()
+                        .status(status.parse().unwrap())
+                        .headers(headers)
+                        .body(None::<&str>);
+                }
+                let body = format!("{} {} {}", request.method(), url, request.text().await?);
+                Response::builder().status(200).body(Some(body.as_str()))
+            })
+        }
+    }
+
+    for (status, expected) in [
+        (307, "POST https://example.com/new data"),
+        (303, "GET https://example.com/new "),
+    ] {
+        let request = Request::post(&format!("https://example.com/old?status={}", status))
+            .body("data")
+            .unwrap();
+        let resp = request
+            .try_clone()
+            .unwrap()
+            .send_with(&Redirecting)
+            .await
+            .unwrap();
+        assert!(resp.is_redirect());
+        assert_eq!(resp.location().as_deref(), Some("https://example.com/new"));
+
+        let request = resp.follow_manually(request).await.unwrap();
+        let resp = request.send_with(&Redirecting).await.unwrap();
+        assert!(!resp.is_redirect());
+        assert_eq!(resp.text().await.unwrap(), expected);
+    }
+}
+
+/// Serves three pages of items, linking each to the next one.
+struct Paged;
+
+impl Fetcher for Paged {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(async move {
+            let url = web_sys::Url::new(&request.url()).unwrap();
+            let page: u32 = url.search_params().get("page").unwrap().parse().unwrap();
+            let mut builder = Response::builder().status(200);
+            if page < 3 {
+                let link = format!(r#"</items?page={}>; rel="next""#, page + 1);
+                builder = builder.header("Link", &link);
+            }
+            let body = format!("{} {}", request.method(), page);
+            builder.body(Some(body.as_str()))
+        })
+    }
+}
+
+#[wasm_bindgen_test]
+async fn paginates_through_next_links() {
+    use futures::TryStreamExt;
+
+    let client = Client::with_fetcher(Paged);
+    let request = client
+        .get("https://example.com/items?page=1")
+        .build()
+        .unwrap();
+    let pages: Vec<Response> = client.paginate(request).try_collect().await.unwrap();
+    let mut bodies = Vec::new();
+    for page in &pages {
+        bodies.push(page.text().await.unwrap());
+    }
+    assert_eq!(bodies, ["GET 1", "GET 2", "GET 3"]);
+}
+
+#[cfg(feature = "persistent-cache")]
+#[wasm_bindgen_test]
+async fn persistent_cache_survives_clients() {
+    use gloo_net::http::PersistentCache;
+
+    let cache = PersistentCache::new("gloo-net-test");
+    cache.clear().await.unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    let client =
+        Client::with_fetcher(Echo { seen: seen.clone() }).with_persistent_cache(cache.clone());
+    let resp = client.get("https://example.com/a").send().await.unwrap();
+    assert_eq!(resp.text().await.unwrap(), "GET https://example.com/a");
+
+    // a new client, as after a reload, is answered from the database
+    let client = Client::with_fetcher(Echo { seen: seen.clone() }).with_persistent_cache(cache);
+    let resp = client.get("https://example.com/a").send().await.unwrap();
+    assert_eq!(resp.text().await.unwrap(), "GET https://example.com/a");
+    assert_eq!(seen.borrow().len(), 1);
+}
+
+#[cfg(feature = "har")]
+#[wasm_bindgen_test]
+async fn records_har() {
+    use gloo_net::http::HarRecorder;
+
+    let recorder = HarRecorder::new().max_body_size(8);
+    let client = Client::with_fetcher(Echo::default()).with_recorder(recorder.clone());
+    let resp = client
+        .post("https://example.com/items?page=2")
+        .header("Authorization", "Bearer secret")
+        .header("X-Api-Key", "secret")
+        .header("Accept", "text/plain")
+        .body("a request body")
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    resp.text().await.unwrap();
+    // let the copy of the response body be read
+    gloo_timers::future::sleep(std::time::Duration::from_millis(10)).await;
+
+    let har = recorder.to_value();
+    let entry = &har["log"]["entries"][0];
+    assert_eq!(entry["request"]["method"], "POST");
+    assert_eq!(entry["request"]["queryString"][0]["value"], "2");
+    assert_eq!(entry["request"]["postData"]["text"], "a reques");
+    let headers = entry["request"]["headers"].as_array().unwrap();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|header| header["name"] == name)
+            .map(|header| header["value"].clone())
+    };
+    assert_eq!(header("authorization").unwrap(), "[REDACTED]");
+    assert_eq!(header("x-api-key").unwrap(), "[REDACTED]");
+    assert_eq!(header("accept").unwrap(), "text/plain");
+    assert!(!entry.to_string().contains("secret"));
+    assert_eq!(entry["response"]["status"], 200);
+    assert_eq!(
+        entry["response"]["content"]["size"],
+        "POST https://example.com/items?page=2".len()
+    );
+}
+
+/// Answers a JSON array of numbers with their doubles, remembering the size of each batch.
+#[cfg(feature = "json")]
+#[derive(Default)]
+struct Doubler {
+    batches: Rc<RefCell<Vec<usize>>>,
+}
+
+#[cfg(feature = "json")]
+impl Fetcher for Doubler {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        let batches = self.batches.clone();
+        Box::pin(async move {
+            let numbers: Vec<u32> = serde_json::from_str(&request.text().await?)?;
+            batches.borrow_mut().push(numbers.len());
+            let doubled: Vec<u32> = numbers.iter().map(|n| n * 2).collect();
+            Response::builder().json(&doubled)
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+#[wasm_bindgen_test]
+async fn batcher_sends_calls_together() {
+    use gloo_net::http::{Batcher, JsonBatch};
+
+    let batches = Rc::new(RefCell::new(Vec::new()));
+    let client = Client::with_fetcher(Doubler {
+        batches: batches.clone(),
+    });
+    let batcher = Batcher::new(JsonBatch::<u32, u32>::new("/double"))
+        .max_size(3)
+        .client(client);
+
+    let (a, b, c, d) = futures::join!(
+        batcher.call(1),
+        batcher.call(2),
+        batcher.call(3),
+        batcher.call(4)
+    );
+    assert_eq!(
+        (a.unwrap(), b.unwrap(), c.unwrap(), d.unwrap()),
+        (2, 4, 6, 8)
+    );
+    // the first batch was full after 3 calls
+    assert_eq!(*batches.borrow(), [3, 1]);
+}
+
+#[cfg(feature = "json")]
+#[wasm_bindgen_test]
+async fn batcher_splits_calls_beyond_max_size() {
+    use gloo_net::http::{Batcher, JsonBatch};
+
+    let batches = Rc::new(RefCell::new(Vec::new()));
+    let client = Client::with_fetcher(Doubler {
+        batches: batches.clone(),
+    });
+    let batcher = Batcher::new(JsonBatch::<u32, u32>::new("/double"))
+        .max_size(2)
+        .client(client);
+
+    let replies = futures::future::join_all((1..=5).map(|n| batcher.call(n))).await;
+    let replies: Vec<u32> = replies.into_iter().map(Result::unwrap).collect();
+    assert_eq!(replies, [2, 4, 6, 8, 10]);
+    assert_eq!(*batches.borrow(), [2, 2, 1]);
+}
+
+#[wasm_bindgen_test]
+async fn decode_as_custom_format() {
+    use gloo_net::http::{Decode, DecodeFuture};
+
+    struct Csv;
+
+    impl Decode for Csv {
+        type Output = Vec<Vec<String>>;
+
+        fn decode(response: Response) -> DecodeFuture<'static, Self::Output> {
+            Box::pin(async move {
+                let text = response.text().await?;
+                Ok(text
+                    .lines()
+                    .map(|line| line.split(',').map(String::from).collect())
+                    .collect())
+            })
+        }
+    }
+
+    let resp = Response::builder().body(Some("a,b\nc,d")).unwrap();
+    assert_eq!(
+        resp.decode_as::<Csv>().await.unwrap(),
+        vec![vec!["a", "b"], vec!["c", "d"]]
+    );
+    let resp = Response::builder().body(Some("text")).unwrap();
+    assert_eq!(resp.decode_as::<String>().await.unwrap(), "text");
+}
+
+#[wasm_bindgen_test]
+async fn encoded_custom_format() {
+    use gloo_net::http::{Encode, EncodedBody};
+
+    struct Csv(Vec<Vec<&'static str>>);
+
+    impl Encode for Csv {
+        fn encode(&self) -> Result<EncodedBody, gloo_net::Error> {
+            let rows: Vec<String> = self.0.iter().map(|row| row.join(",")).collect();
+            Ok(EncodedBody::new(rows.join("\n"), "text/csv"))
+        }
+    }
+
+    let request = Request::post("/rows")
+        .encoded(&Csv(vec![vec!["a", "b"], vec!["c", "d"]]))
+        .unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("text/csv")
+    );
+    assert_eq!(request.text().await.unwrap(), "a,b\nc,d");
+
+    let request = Request::post("/bytes")
+        .header("Content-Type", "image/png")
+        .encoded(&vec![1u8, 2, 3])
+        .unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("image/png")
+    );
+}
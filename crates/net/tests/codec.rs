@@ -0,0 +1,152 @@
+#![cfg(any(
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode",
+    feature = "prost"
+))]
+
+use gloo_net::http::{FetchFuture, Fetcher, Headers, Request, Response};
+#[cfg(any(feature = "cbor", feature = "msgpack", feature = "bincode"))]
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg(any(feature = "cbor", feature = "msgpack", feature = "bincode"))]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Payload {
+    data: String,
+    num: i16,
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack", feature = "bincode"))]
+fn payload() -> Payload {
+    Payload {
+        data: "data".to_string(),
+        num: 42,
+    }
+}
+
+/// Answers every request with its own body and `Content-Type`.
+struct Mirror;
+
+impl Fetcher for Mirror {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(async move {
+            let headers = Headers::new();
+            if let Some(content_type) = request.headers().get("Content-Type") {
+                headers.set("Content-Type", &content_type);
+            }
+            let mut body = request.binary().await?;
+            Response::builder()
+                .status(200)
+                .headers(headers)
+                .body(Some(body.as_mut_slice()))
+        })
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[wasm_bindgen_test]
+async fn cbor_round_trip() {
+    let request = Request::post("/").cbor(&payload()).unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("application/cbor")
+    );
+    let resp = request.send_with(&Mirror).await.unwrap();
+    assert_eq!(resp.cbor::<Payload>().await.unwrap(), payload());
+
+    let resp = Request::post("/")
+        .content_type("text/plain")
+        .cbor(&payload())
+        .unwrap()
+        .send_with(&Mirror)
+        .await
+        .unwrap();
+    assert!(matches!(
+        resp.cbor::<Payload>().await,
+        Err(gloo_net::Error::UnexpectedMediaType { expected, actual })
+            if expected == "application/cbor" && actual == "text/plain"
+    ));
+}
+
+#[cfg(feature = "msgpack")]
+#[wasm_bindgen_test]
+async fn msgpack_round_trip() {
+    let request = Request::post("/").msgpack(&payload()).unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("application/msgpack")
+    );
+    let resp = request.send_with(&Mirror).await.unwrap();
+    assert_eq!(resp.msgpack::<Payload>().await.unwrap(), payload());
+}
+
+#[cfg(feature = "bincode")]
+#[wasm_bindgen_test]
+async fn bincode_round_trip() {
+    let request = Request::post("/").bincode(&payload()).unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("application/x-bincode")
+    );
+    let resp = request.send_with(&Mirror).await.unwrap();
+    assert_eq!(resp.bincode::<Payload>().await.unwrap(), payload());
+}
+
+#[cfg(feature = "prost")]
+#[wasm_bindgen_test]
+async fn protobuf_round_trip() {
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Message {
+        #[prost(string, tag = "1")]
+        data: String,
+        #[prost(int32, tag = "2")]
+        num: i32,
+    }
+
+    let message = Message {
+        data: "data".to_string(),
+        num: 42,
+    };
+    let request = Request::post("/").protobuf(&message).unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("application/x-protobuf")
+    );
+    let resp = request.send_with(&Mirror).await.unwrap();
+    assert_eq!(resp.protobuf::<Message>().await.unwrap(), message);
+}
+
+#[cfg(feature = "cbor")]
+#[wasm_bindgen_test]
+async fn decode_by_content_type() {
+    let resp = Request::post("/")
+        .cbor(&payload())
+        .unwrap()
+        .send_with(&Mirror)
+        .await
+        .unwrap();
+    assert_eq!(resp.decode::<Payload>().await.unwrap(), payload());
+
+    let resp = Request::post("/")
+        .text("plain")
+        .unwrap()
+        .send_with(&Mirror)
+        .await
+        .unwrap();
+    assert_eq!(resp.decode::<String>().await.unwrap(), "plain");
+
+    let resp = Request::post("/")
+        .content_type("image/png")
+        .body(js_sys::Uint8Array::from(&[0u8; 4][..]))
+        .unwrap()
+        .send_with(&Mirror)
+        .await
+        .unwrap();
+    assert!(matches!(
+        resp.decode::<Payload>().await,
+        Err(gloo_net::Error::UnsupportedMediaType(media_type)) if media_type == "image/png"
+    ));
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "file-system")]
+
+use gloo_net::http::{Request, Response};
+use js_sys::{Function, Object, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// Calls `target[method](...args)` and awaits the returned promise.
+async fn call(target: &JsValue, method: &str, args: &[JsValue]) -> JsValue {
+    let function: Function = Reflect::get(target, &method.into())
+        .unwrap()
+        .unchecked_into();
+    let args: js_sys::Array = args.iter().collect();
+    let promise = function.apply(target, &args).unwrap();
+    JsFuture::from(promise.unchecked_into::<Promise>())
+        .await
+        .unwrap()
+}
+
+/// A file of the origin private file system, which needs no user gesture.
+async fn private_file(name: &str) -> JsValue {
+    let navigator = Reflect::get(&js_sys::global(), &"navigator".into()).unwrap();
+    let storage = Reflect::get(&navigator, &"storage".into()).unwrap();
+    let directory = call(&storage, "getDirectory", &[]).await;
+    let options = Object::new();
+    Reflect::set(&options, &"create".into(), &true.into()).unwrap();
+    call(&directory, "getFileHandle", &[name.into(), options.into()]).await
+}
+
+async fn contents(file: &JsValue) -> Option<String> {
+    let contents = call(file, "getFile", &[]).await;
+    call(&contents, "text", &[]).await.as_string()
+}
+
+#[wasm_bindgen_test]
+async fn saves_body_to_file() {
+    let file = private_file("download.txt").await;
+
+    let resp = Response::builder().body(Some("saved to disk")).unwrap();
+    resp.save_to(file.unchecked_ref()).await.unwrap();
+    assert_eq!(contents(&file).await.as_deref(), Some("saved to disk"));
+}
+
+#[wasm_bindgen_test]
+async fn failed_bodies_leave_the_file_untouched() {
+    let file = private_file("untouched.txt").await;
+    let resp = Response::builder().body(Some("old content")).unwrap();
+    resp.save_to(file.unchecked_ref()).await.unwrap();
+
+    // answer every request with a body failing after its first chunk
+    js_sys::eval(
+        "globalThis.nativeFetch = globalThis.fetch;
+         globalThis.fetch = () => Promise.resolve(new Response(new ReadableStream({
+             start(controller) {
+                 controller.enqueue(new TextEncoder().encode('partial'));
+                 controller.error(new Error('connection lost'));
+             },
+         })));",
+    )
+    .unwrap();
+    // both with the body piped to the file, and written chunk by chunk to report progress
+    let tracked = Request::get("/export").on_download_progress(|_| {});
+    for request in [Request::get("/export"), tracked] {
+        let resp = request.send().await.unwrap();
+        assert!(resp.save_to(file.unchecked_ref()).await.is_err());
+        assert_eq!(contents(&file).await.as_deref(), Some("old content"));
+    }
+    js_sys::eval("globalThis.fetch = globalThis.nativeFetch").unwrap();
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "graphql")]
+
+use gloo_net::graphql::GraphQlClient;
+use gloo_net::http::{Client, FetchFuture, Fetcher, Request, Response};
+use gloo_net::Error;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// Answers a `user` query with the `id` variable echoed back, and any other query with an error.
+struct Server;
+
+impl Fetcher for Server {
+    fn fetch(&self, request: Request) -> FetchFuture<'_> {
+        Box::pin(async move {
+            let body: serde_json::Value = request.json().await?;
+            let response = if body["query"].as_str().unwrap().starts_with("query User") {
+                serde_json::json!({ "data": { "user": { "id": body["variables"]["id"] } } })
+            } else {
+                serde_json::json!({ "data": null, "errors": [{ "message": "unknown query" }] })
+            };
+            Response::builder()
+                .header("Content-Type", "application/graphql-response+json")
+                .json(&response)
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Variables {
+    id: u32,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct User {
+    id: u32,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Data {
+    user: User,
+}
+
+#[wasm_bindgen_test]
+async fn query_returns_data() {
+    let graphql = GraphQlClient::with_client(Client::with_fetcher(Server), "/graphql");
+    let data: Data = graphql
+        .query(
+            "query User($id: ID!) { user(id: $id) { id } }",
+            &Variables { id: 7 },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        data,
+        Data {
+            user: User { id: 7 }
+        }
+    );
+}
+
+#[wasm_bindgen_test]
+async fn query_returns_errors() {
+    let graphql = GraphQlClient::with_client(Client::with_fetcher(Server), "/graphql");
+    let result = graphql
+        .query::<_, Data>("{ users { id } }", &Variables { id: 7 })
+        .await;
+    match result {
+        Err(Error::GraphQlErrors(errors)) => assert_eq!(errors[0].message, "unknown query"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
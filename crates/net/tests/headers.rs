@@ -0,0 +1,93 @@
+use gloo_net::http::{Headers, Request};
+use std::convert::TryFrom;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn multi_value_headers() {
+    let request = Request::get("https://example.com/")
+        .header("Accept", "text/html")
+        .append_header("Accept", "application/json")
+        .build()
+        .unwrap();
+    assert_eq!(
+        request.headers().get_all("Accept"),
+        vec!["text/html", "application/json"]
+    );
+
+    let headers = Headers::new();
+    headers.append("Vary", "Accept");
+    headers.append("Vary", "Origin");
+    let map = http::HeaderMap::try_from(&headers).unwrap();
+    let headers = Headers::try_from(&map).unwrap();
+    assert_eq!(headers.get_all("vary"), vec!["Accept", "Origin"]);
+}
+
+#[wasm_bindgen_test]
+fn default_content_types() {
+    let request = Request::post("/").text("hello").unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("text/plain;charset=UTF-8")
+    );
+
+    let request = Request::post("/").form([("a", "1"), ("b", "2")]).unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("application/x-www-form-urlencoded;charset=UTF-8")
+    );
+
+    let request = Request::post("/")
+        .content_type("text/markdown")
+        .text("# hello")
+        .unwrap();
+    assert_eq!(
+        request.headers().get("Content-Type").as_deref(),
+        Some("text/markdown")
+    );
+}
+
+#[wasm_bindgen_test]
+fn keepalive() {
+    let request = Request::post("/").keepalive(true).text("bye").unwrap();
+    assert!(request.keepalive());
+    assert!(!Request::get("/").build().unwrap().keepalive());
+}
+
+#[wasm_bindgen_test]
+fn stream_body_is_half_duplex() {
+    // Without `duplex: "half"`, browsers refuse to build a request with a stream body.
+    let stream = web_sys::ReadableStream::new().unwrap();
+    let request = Request::post("/upload").body(stream).unwrap();
+    assert!(request.body().is_some());
+}
+
+#[wasm_bindgen_test]
+fn conditional_headers() {
+    use gloo_net::http::{ETag, Response};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let time = UNIX_EPOCH + Duration::from_secs(1_445_412_480);
+    let request = Request::put("/doc")
+        .if_match(&ETag::strong("v1"))
+        .if_none_match(&ETag::weak("v2"))
+        .if_unmodified_since(time)
+        .build()
+        .unwrap();
+    let headers = request.headers();
+    assert_eq!(headers.get("If-Match").as_deref(), Some(r#""v1""#));
+    assert_eq!(headers.get("If-None-Match").as_deref(), Some(r#"W/"v2""#));
+    assert_eq!(
+        headers.get("If-Unmodified-Since").as_deref(),
+        Some("Wed, 21 Oct 2015 07:28:00 GMT")
+    );
+
+    let resp = Response::builder()
+        .header("ETag", r#"W/"v3""#)
+        .header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+        .body(None::<&str>)
+        .unwrap();
+    assert_eq!(resp.etag(), Some(ETag::weak("v3")));
+    assert_eq!(resp.last_modified(), Some(time));
+}
@@ -1,4 +1,5 @@
-use gloo_net::http::Request;
+use gloo_net::http::{Request, Response};
+use gloo_net::Error;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen_test::*;
@@ -42,6 +43,101 @@ async fn auth_valid_bearer() {
     assert_eq!(resp.status(), 200);
 }
 
+#[wasm_bindgen_test]
+async fn auth_helpers() {
+    let resp = Request::get(&format!("{}/basic-auth/user/passwd", *HTTPBIN_URL))
+        .basic_auth("user", "passwd")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let resp = Request::get(&format!("{}/bearer", *HTTPBIN_URL))
+        .bearer_auth("token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+}
+
+#[wasm_bindgen_test]
+async fn error_for_status() {
+    let resp = Request::get(&format!("{}/status/404", *HTTPBIN_URL))
+        .send()
+        .await
+        .unwrap();
+    assert!(!resp.ok());
+    let err = resp.error_for_status().unwrap_err();
+    assert_eq!(err.status(), 404);
+    assert_eq!(err.response().status(), 404);
+    assert!(!err.into_response().body_used());
+
+    let resp = Request::get(&format!("{}/status/204", *HTTPBIN_URL))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.error_for_status().unwrap().status(), 204);
+}
+
+#[wasm_bindgen_test]
+async fn error_for_status_with_body() {
+    let problem = serde_json::json!({ "title": "Out of credit", "detail": "Top up first." });
+    let resp = Response::builder()
+        .status(403)
+        .header("Content-Type", "application/problem+json")
+        .json(&problem)
+        .unwrap();
+    let err = resp.error_for_status_with_body().await.unwrap_err();
+    assert_eq!(err.status(), 403);
+    assert_eq!(err.body(), Some(problem.to_string().as_str()));
+    let details = err.problem_details().unwrap();
+    assert_eq!(details.title.as_deref(), Some("Out of credit"));
+    assert_eq!(details.detail.as_deref(), Some("Top up first."));
+    // the body was read from a copy
+    let resp = err.into_response();
+    let details = resp.problem_details().await.unwrap().unwrap();
+    assert_eq!(details.title.as_deref(), Some("Out of credit"));
+    assert_eq!(resp.status(), 403);
+
+    let resp = Response::builder()
+        .status(500)
+        .body(Some("database unavailable"))
+        .unwrap();
+    let err = resp.error_for_status_with_body().await.unwrap_err();
+    assert_eq!(err.body(), Some("database unavailable"));
+    assert!(err.problem_details().is_none());
+
+    let resp = Response::builder().body(Some("fine")).unwrap();
+    let resp = resp.error_for_status_with_body().await.unwrap();
+    assert_eq!(resp.text().await.unwrap(), "fine");
+}
+
+#[wasm_bindgen_test]
+async fn redirected_response() {
+    let resp = Request::get(&format!("{}/redirect/1", *HTTPBIN_URL))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.redirected());
+    assert_eq!(resp.url(), format!("{}/get", *HTTPBIN_URL));
+    assert!(!resp.is_opaque());
+    assert!(!resp.body_used());
+    resp.text().await.unwrap();
+    assert!(resp.body_used());
+}
+
+#[wasm_bindgen_test]
+async fn low_priority() {
+    use gloo_net::http::Priority;
+
+    let resp = Request::get(&format!("{}/get", *HTTPBIN_URL))
+        .priority(Priority::Low)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+}
+
 #[wasm_bindgen_test]
 async fn gzip_response() {
     #[derive(Deserialize, Debug)]
@@ -101,6 +197,45 @@ async fn post_json() {
     assert_eq!(resp.json.num, 42);
 }
 
+#[wasm_bindgen_test]
+async fn into_builder() {
+    #[derive(Deserialize, Debug)]
+    struct HttpBin {
+        data: String,
+        headers: std::collections::HashMap<String, String>,
+    }
+
+    let request = Request::put(&format!("{}/get", *HTTPBIN_URL))
+        .header("X-First", "1")
+        .text("body")
+        .unwrap();
+    let resp = request
+        .into_builder()
+        .header("X-Second", "2")
+        .build()
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    // httpbin's `/get` only accepts GET, so the method was kept
+    assert_eq!(resp.status(), 405);
+
+    let request = Request::put(&format!("{}/anything", *HTTPBIN_URL))
+        .header("X-First", "1")
+        .text("body")
+        .unwrap();
+    let resp = request
+        .into_builder()
+        .header("X-Second", "2")
+        .send()
+        .await
+        .unwrap();
+    let resp: HttpBin = resp.json().await.unwrap();
+    assert_eq!(resp.data, "body");
+    assert_eq!(resp.headers["X-First"], "1");
+    assert_eq!(resp.headers["X-Second"], "2");
+}
+
 #[wasm_bindgen_test]
 async fn fetch_binary() {
     #[derive(Deserialize, Debug)]
@@ -137,3 +272,230 @@ async fn query_preserve_duplicate_params() {
         .unwrap();
     assert_eq!(resp.url(), format!("{}/get?q=1&q=2", *HTTPBIN_URL));
 }
+
+#[cfg(feature = "io")]
+#[wasm_bindgen_test]
+async fn fetch_async_read() {
+    use futures::io::AsyncReadExt;
+
+    let resp = Request::get(&format!("{}/bytes/1024", *HTTPBIN_URL))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let mut reader = resp.into_async_read().unwrap();
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body).await.unwrap();
+    assert_eq!(body.len(), 1024);
+}
+
+#[wasm_bindgen_test]
+async fn download_progress() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let loaded = Rc::new(Cell::new(0));
+    let resp = Request::get(&format!("{}/bytes/2048", *HTTPBIN_URL))
+        .on_download_progress({
+            let loaded = Rc::clone(&loaded);
+            move |progress| {
+                assert_eq!(progress.total, Some(2048));
+                loaded.set(progress.loaded);
+            }
+        })
+        .send()
+        .await
+        .unwrap();
+    let body = resp.binary().await.unwrap();
+    assert_eq!(body.len(), 2048);
+    assert_eq!(loaded.get(), 2048);
+}
+
+#[wasm_bindgen_test]
+async fn upload_progress() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[derive(Deserialize, Debug)]
+    struct HttpBin {
+        data: String,
+    }
+
+    let loaded = Rc::new(Cell::new(0));
+    let resp = Request::post(&format!("{}/post", *HTTPBIN_URL))
+        .on_upload_progress({
+            let loaded = Rc::clone(&loaded);
+            move |progress| loaded.set(progress.loaded)
+        })
+        .body("a".repeat(4096))
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.url(), format!("{}/post", *HTTPBIN_URL));
+    let json: HttpBin = resp.json().await.unwrap();
+    assert_eq!(json.data.len(), 4096);
+    assert_eq!(loaded.get(), 4096);
+}
+
+#[wasm_bindgen_test]
+async fn upload_progress_keeps_the_abort_handler() {
+    use wasm_bindgen::JsCast;
+
+    let controller = js_sys::eval("new AbortController()").unwrap();
+    let signal: web_sys::AbortSignal = js_sys::Reflect::get(&controller, &"signal".into())
+        .unwrap()
+        .unchecked_into();
+    let handler = js_sys::Function::new_no_args("");
+    signal.set_onabort(Some(&handler));
+
+    let resp = Request::post(&format!("{}/post", *HTTPBIN_URL))
+        .abort_signal(Some(&signal))
+        .on_upload_progress(|_| {})
+        .body("a body")
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(signal.onabort(), Some(handler));
+}
+
+#[wasm_bindgen_test]
+async fn send_with_custom_fetch() {
+    let fetch = js_sys::Function::new_with_args(
+        "request",
+        "request.headers.set('X-Fetched-By', 'custom'); return fetch(request);",
+    );
+    let resp = Request::get(&format!("{}/headers", *HTTPBIN_URL))
+        .send_with(&fetch)
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.text().await.unwrap().contains("custom"));
+}
+
+#[wasm_bindgen_test]
+async fn resource_timing() {
+    let resp = Request::get(&format!("{}/get", *HTTPBIN_URL))
+        .send()
+        .await
+        .unwrap();
+    resp.text().await.unwrap();
+    let timing = resp.timing().expect("no resource timing entry");
+    assert!(timing.duration() > std::time::Duration::ZERO);
+}
+
+#[wasm_bindgen_test]
+async fn network_error() {
+    let err = Request::get("http://127.0.0.1:1/unreachable")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Network(_)), "{:?}", err);
+}
+
+#[wasm_bindgen_test]
+async fn response_builder_headers() {
+    use gloo_net::http::ResponseBuilder;
+
+    assert!(ResponseBuilder::new()
+        .try_header("X-Bad", "line\nbreak")
+        .is_err());
+
+    let resp = ResponseBuilder::new()
+        .try_header("Vary", "Accept")
+        .unwrap()
+        .append_header("Vary", "Accept-Encoding")
+        .header("Content-Type", "application/problem+json")
+        .json(&serde_json::json!({ "title": "Not found" }))
+        .unwrap();
+    assert_eq!(
+        resp.headers().get("Vary").as_deref(),
+        Some("Accept, Accept-Encoding")
+    );
+    assert_eq!(
+        resp.headers().get("Content-Type").as_deref(),
+        Some("application/problem+json")
+    );
+}
+
+#[wasm_bindgen_test]
+async fn redirect_and_error_responses() {
+    use gloo_net::http::Response;
+
+    let resp = Response::redirect("https://example.com/login", 303).unwrap();
+    assert_eq!(resp.status(), 303);
+    assert_eq!(
+        resp.headers().get("Location").as_deref(),
+        Some("https://example.com/login")
+    );
+    assert!(Response::redirect("https://example.com/login", 200).is_err());
+
+    let resp = Response::error();
+    assert_eq!(resp.status(), 0);
+    assert_eq!(resp.type_(), web_sys::ResponseType::Error);
+}
+
+#[wasm_bindgen_test]
+async fn response_try_clone() {
+    let resp = Request::get(&format!("{}/get", *HTTPBIN_URL))
+        .send()
+        .await
+        .unwrap();
+    let copy = resp.try_clone().unwrap();
+    let text = resp.text().await.unwrap();
+    assert!(copy.try_clone().is_ok());
+    assert_eq!(copy.text().await.unwrap(), text);
+}
+
+#[wasm_bindgen_test]
+async fn response_content_length_and_body_used() {
+    let resp = Response::builder()
+        .header("Content-Length", "5")
+        .body(Some("hello"))
+        .unwrap();
+    assert_eq!(resp.content_length(), Some(5));
+    assert!(!resp.body_used());
+    resp.text().await.unwrap();
+    assert!(resp.body_used());
+
+    let resp = Response::builder().body(Some("hello")).unwrap();
+    assert_eq!(resp.content_length(), None);
+}
+
+#[wasm_bindgen_test]
+async fn online_status_resolves_when_online() {
+    let status = gloo_net::online_status();
+    assert!(status.is_online());
+    status.until_online().await;
+}
+
+#[cfg(feature = "mock-api")]
+#[wasm_bindgen_test]
+async fn mock_api_passes_unmatched_posts_through() {
+    use gloo_net::mock::MockApi;
+
+    #[derive(Deserialize, Debug)]
+    struct HttpBin {
+        data: String,
+    }
+
+    let api = MockApi::new()
+        .get("/api/users/:id", |_request, _params| async {
+            Response::builder().body(Some("a user"))
+        })
+        .start()
+        .unwrap();
+    let resp = Request::post(&format!("{}/post", *HTTPBIN_URL))
+        .body("hello")
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: HttpBin = resp.json().await.unwrap();
+    assert_eq!(json.data, "hello");
+    drop(api);
+}
@@ -0,0 +1,17 @@
+use gloo_net::http::Request;
+use once_cell::sync::Lazy;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+static HTTPBIN_URL: Lazy<&'static str> =
+    Lazy::new(|| option_env!("HTTPBIN_URL").expect("Did you set HTTPBIN_URL?"));
+
+#[wasm_bindgen_test]
+async fn fetch_in_worker() {
+    let resp = Request::get(&format!("{}/get", *HTTPBIN_URL))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+}
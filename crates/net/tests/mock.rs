@@ -0,0 +1,575 @@
+#![cfg(feature = "test")]
+
+use gloo_net::http::{Client, Request, Response};
+use gloo_net::test::{Matcher, MockFetch};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+async fn mocks_answer_requests() {
+    let fetch = MockFetch::install();
+    let users = fetch.mock(
+        Matcher::get("/api/users/*"),
+        Response::builder()
+            .status(200)
+            .body(Some("a user"))
+            .unwrap(),
+    );
+    let created = fetch.mock_with(Matcher::post("/api/users"), |request| {
+        assert_eq!(
+            request.headers().get("Content-Type").as_deref(),
+            Some("text/plain")
+        );
+        Response::builder().status(201).body(None::<&str>)
+    });
+
+    for id in 1..=2 {
+        let resp = Request::get(&format!("/api/users/{id}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "a user");
+    }
+    let resp = Client::new()
+        .post("/api/users")
+        .header("Content-Type", "text/plain")
+        .body("Ferris")
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+
+    users.assert_called(2);
+    assert!(users.calls()[1].url().ends_with("/api/users/2"));
+    created.assert_called(1);
+
+    let err = Request::delete("/api/users/1").send().await.unwrap_err();
+    assert!(err.to_string().contains("no mock matches DELETE"));
+}
+
+#[wasm_bindgen_test]
+async fn later_mocks_take_precedence() {
+    let fetch = MockFetch::install();
+    let any = fetch.mock(
+        Matcher::any(),
+        Response::builder().status(200).body(None::<&str>).unwrap(),
+    );
+    let missing = fetch.mock(
+        Matcher::get("/missing"),
+        Response::builder().status(404).body(None::<&str>).unwrap(),
+    );
+
+    assert_eq!(Request::get("/missing").send().await.unwrap().status(), 404);
+    assert_eq!(Request::get("/other").send().await.unwrap().status(), 200);
+    missing.assert_called(1);
+    any.assert_called(1);
+}
+
+#[wasm_bindgen_test]
+async fn polling_skips_unchanged_responses() {
+    use futures::StreamExt;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    let fetch = MockFetch::install();
+    let version = Rc::new(Cell::new(1));
+    let current = version.clone();
+    let stats = fetch.mock_with(Matcher::get("/stats"), move |request| {
+        let etag = format!("\"v{}\"", current.get());
+        if request.headers().get("If-None-Match").as_deref() == Some(etag.as_str()) {
+            return Response::builder().status(304).body(None::<&str>);
+        }
+        Response::builder()
+            .header("ETag", &etag)
+            .body(Some(etag.as_str()))
+    });
+
+    let client = Client::new();
+    let request = client.get("/stats").build().unwrap();
+    let mut updates = client.poll(request, Duration::from_millis(10));
+    let first = updates.next().await.unwrap().unwrap();
+    assert_eq!(first.text().await.unwrap(), "\"v1\"");
+
+    wasm_bindgen_futures::spawn_local(async move {
+        gloo_timers::future::sleep(Duration::from_millis(35)).await;
+        version.set(2);
+    });
+    let second = updates.next().await.unwrap().unwrap();
+    assert_eq!(second.text().await.unwrap(), "\"v2\"");
+    assert!(stats.call_count() > 2);
+}
+
+#[wasm_bindgen_test]
+async fn long_poll_yields_payloads() {
+    use futures::StreamExt;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let fetch = MockFetch::install();
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    fetch.mock_with(Matcher::get("/messages"), move |_| {
+        counter.set(counter.get() + 1);
+        match counter.get() {
+            // the server timed out without news, then failed once
+            1 => Response::builder().status(204).body(None::<&str>),
+            2 => Response::builder().status(503).body(None::<&str>),
+            n => Response::builder().body(Some(format!("message {n}").as_str())),
+        }
+    });
+
+    let client = Client::new();
+    let request = client.get("/messages").build().unwrap();
+    let messages: Vec<_> = client
+        .long_poll::<String>(request)
+        .take(2)
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert_eq!(messages, ["message 3", "message 4"]);
+}
+
+#[wasm_bindgen_test]
+async fn long_poll_backs_off_on_immediate_empty_answers() {
+    use futures::future::{select, Either};
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let fetch = MockFetch::install();
+    let messages = fetch.mock_with(Matcher::get("/messages"), |_| {
+        Response::builder().status(204).body(None::<&str>)
+    });
+
+    let client = Client::new();
+    let request = client.get("/messages").build().unwrap();
+    let mut updates = client.long_poll::<String>(request);
+    let timeout = gloo_timers::future::sleep(Duration::from_millis(250));
+    assert!(matches!(
+        select(updates.next(), Box::pin(timeout)).await,
+        Either::Right(_)
+    ));
+    // the requests are at least 100ms apart
+    assert!((1..=3).contains(&messages.call_count()));
+}
+
+#[cfg(feature = "download")]
+#[wasm_bindgen_test]
+async fn download_manager_resumes_with_range() {
+    use gloo_net::http::{DownloadManager, DownloadStatus, PartialDownload, Priority};
+
+    let fetch = MockFetch::install();
+    let file = fetch.mock_with(Matcher::get("/file.txt"), |request| {
+        assert_eq!(request.headers().get("Range").as_deref(), Some("bytes=6-"));
+        assert_eq!(request.headers().get("If-Range").as_deref(), Some("\"v1\""));
+        Response::builder()
+            .status(206)
+            .header("Content-Range", "bytes 6-10/11")
+            .header("ETag", "\"v1\"")
+            .body(Some("world"))
+    });
+
+    let manager = DownloadManager::new();
+    let download = manager.resume(
+        PartialDownload {
+            url: "/file.txt".to_string(),
+            etag: Some("\"v1\"".to_string()),
+            total: Some(11),
+            bytes: b"hello ".to_vec(),
+        },
+        Priority::Auto,
+    );
+    let blob = download.finish().await.unwrap();
+    assert_eq!(blob.size(), 11.0);
+    assert_eq!(download.status(), DownloadStatus::Completed);
+    assert_eq!(manager.progress().fraction(), Some(1.0));
+    file.assert_called(1);
+}
+
+#[cfg(feature = "download")]
+#[wasm_bindgen_test]
+async fn download_resumed_right_after_pause_runs_once() {
+    use gloo_net::http::{DownloadManager, DownloadStatus, Priority};
+
+    let fetch = MockFetch::install();
+    let file = fetch.mock(
+        Matcher::get("/file.txt"),
+        Response::builder().body(Some("hello world")).unwrap(),
+    );
+
+    let download = DownloadManager::new().download("/file.txt", Priority::Auto);
+    // the first run is still on its way when the second one starts
+    download.pause();
+    download.resume();
+    let blob = download.finish().await.unwrap();
+    assert_eq!(blob.size(), 11.0);
+    assert_eq!(download.status(), DownloadStatus::Completed);
+    file.assert_called(2);
+}
+
+#[cfg(feature = "upload")]
+#[wasm_bindgen_test]
+async fn upload_manager_resumes_from_server_offset() {
+    use gloo_net::http::{Method, UploadManager, UploadStatus};
+
+    let fetch = MockFetch::install();
+    let offset = fetch.mock(
+        Matcher::new(Method::HEAD, "/files/42"),
+        Response::builder()
+            .header("Upload-Offset", "6")
+            .body(None::<&str>)
+            .unwrap(),
+    );
+    let chunk = fetch.mock_with(Matcher::patch("/files/42"), |request| {
+        assert_eq!(request.headers().get("Upload-Offset").as_deref(), Some("6"));
+        assert_eq!(
+            request.headers().get("Content-Type").as_deref(),
+            Some("application/offset+octet-stream")
+        );
+        Response::builder()
+            .status(204)
+            .header("Upload-Offset", "11")
+            .body(None::<&str>)
+    });
+
+    let bytes = js_sys::Uint8Array::from(&b"hello world"[..]);
+    let file = web_sys::Blob::new_with_u8_array_sequence(&js_sys::Array::of1(&bytes)).unwrap();
+    let upload = UploadManager::new("/files/").resume(&file, "/files/42");
+    assert_eq!(upload.finish().await.unwrap(), "/files/42");
+    assert_eq!(upload.status(), UploadStatus::Completed);
+    assert_eq!(upload.progress().fraction(), Some(1.0));
+    offset.assert_called(1);
+    chunk.assert_called(1);
+}
+
+#[cfg(feature = "upload")]
+#[wasm_bindgen_test]
+async fn upload_fails_when_the_offset_is_stuck() {
+    use gloo_net::http::{Method, RetryPolicy, UploadManager, UploadStatus};
+    use std::time::Duration;
+
+    let fetch = MockFetch::install();
+    fetch.mock(
+        Matcher::new(Method::HEAD, "/files/42"),
+        Response::builder()
+            .header("Upload-Offset", "6")
+            .body(None::<&str>)
+            .unwrap(),
+    );
+    // the server never takes more of the upload
+    let chunk = fetch.mock(
+        Matcher::patch("/files/42"),
+        Response::builder()
+            .status(204)
+            .header("Upload-Offset", "6")
+            .body(None::<&str>)
+            .unwrap(),
+    );
+
+    let bytes = js_sys::Uint8Array::from(&b"hello world"[..]);
+    let file = web_sys::Blob::new_with_u8_array_sequence(&js_sys::Array::of1(&bytes)).unwrap();
+    let manager = UploadManager::new("/files/");
+    // configuring a clone doesn't panic
+    let manager = manager.clone().retry(
+        RetryPolicy::new()
+            .max_retries(2)
+            .initial_backoff(Duration::from_millis(1)),
+    );
+    let upload = manager.resume(&file, "/files/42");
+    assert!(upload.finish().await.is_err());
+    assert_eq!(upload.status(), UploadStatus::Failed);
+    chunk.assert_called(3);
+}
+
+#[cfg(feature = "upload")]
+#[wasm_bindgen_test]
+async fn cancelled_upload_stays_cancelled() {
+    use gloo_net::http::{Method, Upload, UploadManager, UploadStatus};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let fetch = MockFetch::install();
+    fetch.mock(
+        Matcher::new(Method::HEAD, "/files/42"),
+        Response::builder()
+            .header("Upload-Offset", "6")
+            .body(None::<&str>)
+            .unwrap(),
+    );
+    let handle: Rc<RefCell<Option<Upload>>> = Rc::default();
+    let cancelling = handle.clone();
+    // the upload is cancelled while its last chunk is being sent
+    fetch.mock_with(Matcher::patch("/files/42"), move |_| {
+        if let Some(upload) = &*cancelling.borrow() {
+            upload.cancel();
+        }
+        Response::builder()
+            .status(204)
+            .header("Upload-Offset", "11")
+            .body(None::<&str>)
+    });
+
+    let bytes = js_sys::Uint8Array::from(&b"hello world"[..]);
+    let file = web_sys::Blob::new_with_u8_array_sequence(&js_sys::Array::of1(&bytes)).unwrap();
+    let upload = UploadManager::new("/files/").resume(&file, "/files/42");
+    *handle.borrow_mut() = Some(upload.clone());
+    assert!(upload.finish().await.is_err());
+    gloo_timers::future::sleep(std::time::Duration::from_millis(10)).await;
+    assert_eq!(upload.status(), UploadStatus::Cancelled);
+}
+
+#[cfg(feature = "reqwest-compat")]
+#[wasm_bindgen_test]
+async fn reqwest_compat_client() {
+    use gloo_net::compat::reqwest::{self, header, StatusCode};
+
+    let fetch = MockFetch::install();
+    let created = fetch.mock_with(Matcher::post("/items?draft=true"), |request| {
+        assert_eq!(request.headers().get("x-app").as_deref(), Some("demo"));
+        assert_eq!(
+            request.headers().get("content-type").as_deref(),
+            Some("application/json")
+        );
+        Response::builder().status(201).body(Some("[1,2]"))
+    });
+    fetch.mock(
+        Matcher::get("/missing"),
+        Response::builder().status(404).body(None::<&str>).unwrap(),
+    );
+
+    let mut headers = header::HeaderMap::new();
+    headers.insert("x-app", header::HeaderValue::from_static("demo"));
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap();
+    let response = client
+        .post("/items")
+        .query(&[("draft", true)])
+        .json(&"item")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.json::<Vec<u32>>().await.unwrap(), [1, 2]);
+    created.assert_called(1);
+
+    let error = client
+        .get("/missing")
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::NOT_FOUND));
+    assert!(error.url().unwrap().path().ends_with("/missing"));
+}
+
+#[cfg(feature = "http-body")]
+#[wasm_bindgen_test]
+async fn response_into_http_body() {
+    use http_body::Body;
+
+    let fetch = MockFetch::install();
+    fetch.mock(
+        Matcher::get("/data"),
+        Response::builder()
+            .header("X-Version", "2")
+            .body(Some("hello"))
+            .unwrap(),
+    );
+
+    let response = Client::new()
+        .get("/data")
+        .send()
+        .await
+        .unwrap()
+        .into_http()
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-version"], "2");
+    let mut body = response.into_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        bytes.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(bytes, b"hello");
+    assert!(body.is_end_stream());
+}
+
+#[cfg(feature = "mock-api")]
+#[wasm_bindgen_test]
+async fn mock_api_answers_page_fetches() {
+    use gloo_net::mock::MockApi;
+
+    let api = MockApi::new()
+        .get("/api/users/:id", |_request, params| {
+            let id = params.get("id").unwrap_or_default().to_string();
+            async move { Response::builder().body(Some(format!("user {}", id).as_str())) }
+        })
+        .strict(true)
+        .start()
+        .unwrap();
+
+    let resp = Request::get("/api/users/7").send().await.unwrap();
+    assert_eq!(resp.text().await.unwrap(), "user 7");
+    assert!(matches!(
+        Request::get("/api/posts").send().await,
+        Err(gloo_net::Error::Network(_))
+    ));
+    drop(api);
+}
+
+#[cfg(feature = "macros")]
+#[wasm_bindgen_test]
+async fn endpoint_macro_builds_requests() {
+    use gloo_net::http::endpoint;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct User {
+        name: String,
+    }
+
+    #[endpoint(GET "users/{name}", query(page, search))]
+    async fn user(
+        client: &Client,
+        name: &str,
+        page: u32,
+        search: Option<&str>,
+    ) -> Result<User, gloo_net::Error>;
+
+    #[endpoint(POST "users", body = user)]
+    async fn create_user(client: &Client, user: &User) -> Result<(), gloo_net::Error>;
+
+    #[endpoint(GET "users/{name}/bio")]
+    async fn bio(client: &Client, name: &str) -> Result<String, gloo_net::Error>;
+
+    #[endpoint(GET "users/{name}/avatar")]
+    async fn avatar(client: &Client, name: &str) -> Result<Vec<u8>, gloo_net::Error>;
+
+    let fetch = MockFetch::install();
+    fetch.mock(
+        Matcher::get("/api/users/a%20b?page=2"),
+        Response::builder()
+            .json(&User { name: "a b".into() })
+            .unwrap(),
+    );
+    let created = fetch.mock_with(Matcher::post("/api/users"), |request| {
+        assert_eq!(
+            request.headers().get("Content-Type").as_deref(),
+            Some("application/json")
+        );
+        Response::builder().status(201).body(None::<&str>)
+    });
+
+    let client = Client::new().with_base_url("/api/");
+    let found = user(&client, "a b", 2, None).await.unwrap();
+    assert_eq!(found.name, "a b");
+    create_user(&client, &found).await.unwrap();
+    assert_eq!(created.calls().len(), 1);
+    assert!(user(&client, "missing", 1, Some("x")).await.is_err());
+
+    fetch.mock(
+        Matcher::get("/api/users/a/bio"),
+        Response::builder().body(Some("hi")).unwrap(),
+    );
+    fetch.mock(
+        Matcher::get("/api/users/a/avatar"),
+        Response::builder()
+            .body(Some(&mut [1u8, 2, 3][..]))
+            .unwrap(),
+    );
+    assert_eq!(bio(&client, "a").await.unwrap(), "hi");
+    assert_eq!(avatar(&client, "a").await.unwrap(), vec![1, 2, 3]);
+}
+
+#[cfg(feature = "offline-queue")]
+#[wasm_bindgen_test]
+async fn offline_queue_replays_and_resolves_conflicts() {
+    use gloo_net::http::{Conflict, OfflineQueue, RequestSnapshot};
+
+    let fetch = MockFetch::install();
+    // the note was changed meanwhile, so only the change based on `v2` applies
+    let note = fetch.mock_with(Matcher::patch("/notes/1"), |request| {
+        match request.headers().get("If-Match").as_deref() {
+            Some("\"v2\"") => Response::builder().body(None::<&str>),
+            _ => Response::builder().status(412).body(None::<&str>),
+        }
+    });
+    let created = fetch.mock(
+        Matcher::post("/notes"),
+        Response::builder().status(201).body(None::<&str>).unwrap(),
+    );
+
+    let queue = OfflineQueue::new("replays-and-resolves-conflicts").on_conflict(
+        |request: RequestSnapshot, _| async move {
+            let mut headers = request.headers.clone();
+            headers.push(("If-Match".to_string(), "\"v2\"".to_string()));
+            Conflict::Retry(RequestSnapshot { headers, ..request })
+        },
+    );
+    queue.clear().await.unwrap();
+    for request in [
+        Request::patch("/notes/1").body("done").unwrap(),
+        Request::post("/notes").body("buy milk").unwrap(),
+    ] {
+        queue
+            .enqueue(&request.snapshot().await.unwrap())
+            .await
+            .unwrap();
+    }
+    assert_eq!(queue.pending().await.unwrap().len(), 2);
+
+    assert_eq!(queue.replay().await.unwrap(), 2);
+    note.assert_called(2);
+    created.assert_called(1);
+    assert!(queue.pending().await.unwrap().is_empty());
+}
+
+#[cfg(feature = "offline-queue")]
+#[wasm_bindgen_test]
+async fn offline_queue_caps_conflict_retries() {
+    use gloo_net::http::{Conflict, OfflineQueue};
+
+    let fetch = MockFetch::install();
+    let note = fetch.mock(
+        Matcher::patch("/notes/1"),
+        Response::builder().status(409).body(None::<&str>).unwrap(),
+    );
+
+    let queue = OfflineQueue::new("caps-conflict-retries")
+        .on_conflict(|request, _| async move { Conflict::Retry(request) })
+        .max_conflict_retries(2);
+    queue.clear().await.unwrap();
+    let request = Request::patch("/notes/1").body("done").unwrap();
+    queue
+        .enqueue(&request.snapshot().await.unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(queue.replay().await.unwrap(), 0);
+    // sent once, then retried twice
+    note.assert_called(3);
+    assert_eq!(queue.pending().await.unwrap().len(), 1);
+}
+
+#[wasm_bindgen_test]
+async fn tracked_text_bodies_must_be_utf8() {
+    let fetch = MockFetch::install();
+    fetch.mock_with(Matcher::get("/latin1"), |_| {
+        Response::builder().body(Some(&mut [b'c', b'a', b'f', 0xe9][..]))
+    });
+
+    let resp = Request::get("/latin1")
+        .on_download_progress(|_| {})
+        .send()
+        .await
+        .unwrap();
+    assert!(matches!(
+        resp.text().await,
+        Err(gloo_net::Error::BodyError { .. })
+    ));
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "snapshot")]
+
+use gloo_net::http::{Request, Response, ResponseSnapshot};
+use std::convert::TryFrom;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+async fn request_snapshot_round_trip() {
+    let request = Request::post("https://example.com/events")
+        .header("X-Trace", "1")
+        .text("clicked")
+        .unwrap();
+    let snapshot = request.snapshot().await.unwrap();
+    assert_eq!(snapshot.method, "POST");
+    assert_eq!(snapshot.body.as_deref(), Some(&b"clicked"[..]));
+    // the original body is left for sending
+    assert_eq!(request.text().await.unwrap(), "clicked");
+
+    let restored = Request::try_from(snapshot.clone()).unwrap();
+    assert_eq!(restored.headers().get("X-Trace").as_deref(), Some("1"));
+    assert_eq!(restored.snapshot().await.unwrap(), snapshot);
+}
+
+#[wasm_bindgen_test]
+async fn response_snapshot_round_trip() {
+    let snapshot = ResponseSnapshot {
+        status: 201,
+        status_text: "Created".to_string(),
+        url: String::new(),
+        headers: vec![("content-type".to_string(), "text/plain".to_string())],
+        body: b"done".to_vec(),
+    };
+    let response = Response::try_from(snapshot.clone()).unwrap();
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.snapshot().await.unwrap(), snapshot);
+    assert_eq!(response.text().await.unwrap(), "done");
+
+    let no_content = ResponseSnapshot {
+        status: 204,
+        body: Vec::new(),
+        ..snapshot
+    };
+    assert!(Response::try_from(no_content).is_ok());
+}
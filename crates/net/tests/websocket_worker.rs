@@ -0,0 +1,48 @@
+#![cfg(feature = "websocket")]
+
+use futures::{SinkExt, StreamExt};
+use gloo_net::websocket::futures::WebSocket;
+use gloo_net::websocket::{ConnectionState, Message, ReconnectingWebSocket};
+use once_cell::sync::Lazy;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+static WS_ECHO_SERVER_URL: Lazy<&'static str> =
+    Lazy::new(|| option_env!("WS_ECHO_SERVER_URL").expect("Did you set WS_ECHO_SERVER_URL?"));
+
+#[wasm_bindgen_test]
+async fn websocket_in_worker() {
+    let mut ws = WebSocket::open(&WS_ECHO_SERVER_URL).unwrap();
+    let mut states = ws.states();
+    // ignore the info message of the echo-server
+    let _ = ws.next().await;
+    assert_eq!(states.next().await, Some(ConnectionState::Open));
+
+    ws.send(Message::Text("from a worker".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(
+        ws.next().await.unwrap().unwrap(),
+        Message::Text("from a worker".to_string())
+    );
+}
+
+#[wasm_bindgen_test]
+async fn reconnecting_websocket_in_worker() {
+    let mut ws = ReconnectingWebSocket::open(&WS_ECHO_SERVER_URL).unwrap();
+    let _ = ws.next().await;
+
+    ws.send(Message::Bytes(vec![1, 2, 3])).await.unwrap();
+    assert_eq!(
+        ws.next().await.unwrap().unwrap(),
+        Message::Bytes(vec![1, 2, 3])
+    );
+}
+
+#[wasm_bindgen_test]
+fn open_relative_in_worker() {
+    // workers have no document, so the path is resolved against their location
+    let ws = WebSocket::open_relative("/ws").unwrap();
+    drop(ws);
+}